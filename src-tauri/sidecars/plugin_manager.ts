@@ -1,4 +1,5 @@
 import { readLines } from "https://deno.land/std@0.224.0/io/read_lines.ts";
+import { join, toFileUrl } from "https://deno.land/std@0.224.0/path/mod.ts";
 
 // Define the message structures on the Deno side
 interface RpcRequest {
@@ -13,6 +14,115 @@ interface RpcResponse {
   error?: string;
 }
 
+// The active vault's plugins folder, passed by the app as `--plugins-dir <path>`
+const pluginsDirIndex = Deno.args.indexOf("--plugins-dir");
+const PLUGINS_DIR: string | null = pluginsDirIndex >= 0 ? Deno.args[pluginsDirIndex + 1] ?? null : null;
+
+// The parts of a plugin's plugin.json the sidecar needs
+interface PluginManifest {
+  id: string;
+  name: string;
+  version: string;
+  main: string;
+}
+
+// What a plugin's entry module may export
+interface PluginModule {
+  test?: () => unknown;
+  runScheduled?: () => unknown;
+}
+
+interface LoadedPlugin {
+  manifest: PluginManifest;
+  module: PluginModule;
+  loadedAt: string;
+}
+
+// Plugins whose entry module has been imported, by id
+const registry = new Map<string, LoadedPlugin>();
+
+// Same rule as the host's plugin ids, so an id can't point outside PLUGINS_DIR
+function checkPluginId(pluginId: string): void {
+  if (!/^[A-Za-z0-9_-][A-Za-z0-9_.-]*$/.test(pluginId)) {
+    throw new Error(`Invalid plugin id '${pluginId}'`);
+  }
+}
+
+function requirePluginId(params: unknown): string {
+  const pluginData = params as { plugin_id?: string };
+  const pluginId = pluginData?.plugin_id;
+
+  if (!pluginId) {
+    throw new Error("plugin_id parameter is required");
+  }
+  checkPluginId(pluginId);
+  return pluginId;
+}
+
+// Imports a plugin's entry module and puts it in the registry. Every load
+// gets a fresh URL, since Deno caches modules by URL for the process lifetime.
+async function loadPlugin(pluginId: string): Promise<LoadedPlugin> {
+  if (!PLUGINS_DIR) {
+    throw new Error("No plugins directory configured");
+  }
+
+  const pluginDir = join(PLUGINS_DIR, pluginId);
+  let manifest: PluginManifest;
+  try {
+    manifest = JSON.parse(await Deno.readTextFile(join(pluginDir, "plugin.json")));
+  } catch (e) {
+    const error = e instanceof Error ? e.message : String(e);
+    throw new Error(`Plugin '${pluginId}' not found: ${error}`);
+  }
+
+  const url = toFileUrl(join(pluginDir, manifest.main));
+  url.searchParams.set("v", `${Date.now()}-${crypto.randomUUID()}`);
+  let module: PluginModule;
+  try {
+    module = await import(url.href);
+  } catch (e) {
+    const error = e instanceof Error ? e.message : String(e);
+    throw new Error(`Failed to load plugin '${pluginId}': ${error}`);
+  }
+
+  const loaded = { manifest, module, loadedAt: new Date().toISOString() };
+  registry.set(pluginId, loaded);
+  return loaded;
+}
+
+// Loads every plugin folder at startup; one broken plugin doesn't stop the rest
+async function loadAllPlugins(): Promise<void> {
+  if (!PLUGINS_DIR) {
+    return;
+  }
+
+  try {
+    for await (const entry of Deno.readDir(PLUGINS_DIR)) {
+      if (!entry.isDirectory || entry.name.startsWith(".")) {
+        continue;
+      }
+      try {
+        await loadPlugin(entry.name);
+        console.error(`[INFO] Loaded plugin '${entry.name}'`);
+      } catch (e) {
+        const error = e instanceof Error ? e.message : String(e);
+        console.error(`[ERROR] ${error}`);
+      }
+    }
+  } catch (e) {
+    const error = e instanceof Error ? e.message : String(e);
+    console.error(`[ERROR] Failed to read plugins directory: ${error}`);
+  }
+}
+
+function loadedPlugin(pluginId: string): LoadedPlugin {
+  const loaded = registry.get(pluginId);
+  if (!loaded) {
+    throw new Error(`Plugin '${pluginId}' not found`);
+  }
+  return loaded;
+}
+
 // --- Command Handlers ---
 // A simple test handler to verify communication.
 async function handlePing(): Promise<string> {
@@ -27,55 +137,100 @@ async function handleGetInfo(): Promise<Record<string, unknown>> {
     denoVersion: Deno.version.deno,
     typescriptVersion: Deno.version.typescript,
     v8Version: Deno.version.v8,
-    capabilities: ["ping", "get_info", "list_plugins", "test_plugin"],
+    capabilities: ["ping", "get_info", "list_plugins", "test_plugin", "reload_plugin", "run_scheduled"],
+    pluginsDir: PLUGINS_DIR,
     timestamp: new Date().toISOString(),
   };
 }
 
-// List available plugins (for future expansion)
+// Ids of the plugins currently loaded
 async function handleListPlugins(): Promise<string[]> {
-  // In the future, this would scan for available plugin files
-  return ["core-ping", "example-plugin"];
+  return [...registry.keys()].sort();
 }
 
-// Test a specific plugin
+// Test a specific plugin by calling its exported test(), if it has one
 async function handleTestPlugin(params: unknown): Promise<Record<string, unknown>> {
-  const pluginData = params as { plugin_id?: string };
-  const pluginId = pluginData?.plugin_id;
-  
-  if (!pluginId) {
-    throw new Error("plugin_id parameter is required");
+  const pluginId = requirePluginId(params);
+  const loaded = loadedPlugin(pluginId);
+
+  if (typeof loaded.module.test === "function") {
+    await loaded.module.test();
   }
-  
-  // For now, simulate plugin testing
-  // In a real implementation, this would load and execute the plugin
-  if (pluginId === "test-plugin") {
-    return {
-      plugin_id: pluginId,
-      status: "active",
-      message: "Test plugin responded successfully",
-      timestamp: new Date().toISOString()
-    };
-  } else {
-    throw new Error(`Plugin '${pluginId}' not found`);
+  return {
+    plugin_id: pluginId,
+    status: "active",
+    message: `${loaded.manifest.name} ${loaded.manifest.version} responded successfully`,
+    timestamp: new Date().toISOString()
+  };
+}
+
+// Reload a plugin's module so edits take effect without restarting the app.
+// A plugin that fails to load again is dropped from the registry.
+async function handleReloadPlugin(params: unknown): Promise<Record<string, unknown>> {
+  const pluginId = requirePluginId(params);
+
+  let loaded: LoadedPlugin;
+  try {
+    loaded = await loadPlugin(pluginId);
+  } catch (e) {
+    registry.delete(pluginId);
+    throw e;
+  }
+  return {
+    plugin_id: pluginId,
+    status: "active",
+    message: `${loaded.manifest.name} ${loaded.manifest.version} reloaded`,
+    timestamp: loaded.loadedAt
+  };
+}
+
+// Run the periodic job a plugin declared with "schedule" in its plugin.json.
+// Whatever the plugin's runScheduled() throws is sent back as the error.
+async function handleRunScheduled(params: unknown): Promise<Record<string, unknown>> {
+  const pluginId = requirePluginId(params);
+  const loaded = loadedPlugin(pluginId);
+
+  if (typeof loaded.module.runScheduled !== "function") {
+    throw new Error(`Plugin '${pluginId}' does not export runScheduled()`);
   }
+  await loaded.module.runScheduled();
+  return {
+    plugin_id: pluginId,
+    status: "active",
+    message: `${loaded.manifest.name} ran its scheduled job`,
+    timestamp: new Date().toISOString()
+  };
 }
 
 // --- Main Loop ---
 async function main() {
   console.error("Deno plugin manager starting up...");
+  await loadAllPlugins();
   
   for await (const line of readLines(Deno.stdin)) {
     console.error(`[DEBUG] Received line: ${line}`);
     
+    let request: RpcRequest;
     try {
-      const request: RpcRequest = JSON.parse(line);
+      request = JSON.parse(line);
       console.error(`[DEBUG] Parsed request: ${JSON.stringify(request)}`);
-      
-      let result: unknown = null;
-      let error: string | undefined = undefined;
+    } catch (e) {
+      const error = e instanceof Error ? e.message : String(e);
+      console.error(`[ERROR] Failed to parse request: ${error}`);
+      const errorResponse: RpcResponse = {
+        id: -1, // No ID if request is unparseable
+        error: `Failed to parse request: ${error}`,
+      };
+      console.log(JSON.stringify(errorResponse));
+      continue;
+    }
 
-      // Route the request to the correct handler
+    let result: unknown = null;
+    let error: string | undefined = undefined;
+
+    // Route the request to the correct handler; a handler that throws
+    // answers this request with the error
+    try {
       switch (request.method) {
         case "ping":
           result = await handlePing();
@@ -93,33 +248,35 @@ async function main() {
           result = await handleTestPlugin(request.params);
           console.error(`[DEBUG] Test plugin handled, result: ${JSON.stringify(result)}`);
           break;
+        case "reload_plugin":
+          result = await handleReloadPlugin(request.params);
+          console.error(`[DEBUG] Reload plugin handled, result: ${JSON.stringify(result)}`);
+          break;
+        case "run_scheduled":
+          result = await handleRunScheduled(request.params);
+          console.error(`[DEBUG] Run scheduled handled, result: ${JSON.stringify(result)}`);
+          break;
         // Future methods like "initialize" or "execute_plugin" go here
         default:
           error = `Unknown method: ${request.method}`;
           console.error(`[DEBUG] Unknown method: ${request.method}`);
       }
-
-      const response: RpcResponse = { id: request.id };
-      if (error) {
-        response.error = error;
-      } else {
-        response.result = result;
-      }
-
-      // Write the response back to stdout for Rust to read
-      const responseJson = JSON.stringify(response);
-      console.log(responseJson);
-      console.error(`[DEBUG] Sent response: ${responseJson}`);
-
     } catch (e) {
-      const error = e instanceof Error ? e.message : String(e);
-      console.error(`[ERROR] Failed to parse request: ${error}`);
-      const errorResponse: RpcResponse = {
-        id: -1, // No ID if request is unparseable
-        error: `Failed to parse request: ${error}`,
-      };
-      console.log(JSON.stringify(errorResponse));
+      error = e instanceof Error ? e.message : String(e);
+      console.error(`[ERROR] ${request.method} failed: ${error}`);
     }
+
+    const response: RpcResponse = { id: request.id };
+    if (error) {
+      response.error = error;
+    } else {
+      response.result = result;
+    }
+
+    // Write the response back to stdout for Rust to read
+    const responseJson = JSON.stringify(response);
+    console.log(responseJson);
+    console.error(`[DEBUG] Sent response: ${responseJson}`);
   }
   
   console.error("Deno plugin manager shutting down...");
@@ -0,0 +1,121 @@
+use rusqlite::params;
+
+use crate::database::Database;
+use crate::error::Result;
+use crate::models::{AiContext, Permissions};
+
+// Rough size of a token; good enough for budgeting without a tokenizer
+const CHARS_PER_TOKEN: usize = 4;
+pub const DEFAULT_MAX_TOKENS: usize = 4000;
+
+impl Database {
+    /// Collects the content of every object shared with AI whose permission
+    /// hasn't expired, newest first, stopping before the budget is exceeded.
+    pub async fn build_ai_context(&self, max_tokens: usize) -> Result<AiContext> {
+        let budget = max_tokens.saturating_mul(CHARS_PER_TOKEN);
+
+        let rows: Vec<(i64, String, String, Option<String>)> = {
+            let conn = self.lock_connection().await?;
+            let mut stmt = conn.prepare(
+                "SELECT do.id, s.schema_name, oc.content_json, op.expires_at
+                 FROM data_objects do
+                 JOIN schemas s ON do.schema_id = s.id
+                 JOIN object_content oc ON do.id = oc.object_id
+                 JOIN object_permissions op ON do.id = op.object_id
+                 WHERE op.share_with_ai = 1
+                 ORDER BY do.updated_at DESC, do.id DESC"
+            )?;
+            let rows = stmt.query_map(params![], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        let mut context = AiContext {
+            text: String::new(),
+            object_ids: Vec::new(),
+            truncated: false,
+        };
+
+        for (object_id, schema_name, content_json, expires_at) in rows {
+            let permissions = Permissions {
+                share_with_ai: true,
+                expires_at,
+                ..Permissions::default()
+            };
+            if permissions.is_expired() {
+                continue;
+            }
+
+            let content: serde_json::Value = serde_json::from_str(&content_json)?;
+            let section = format!(
+                "## {} {}\n{}\n\n",
+                schema_name,
+                object_id,
+                serde_json::to_string_pretty(&content)?
+            );
+
+            if context.text.len() + section.len() > budget {
+                context.truncated = true;
+                break;
+            }
+
+            context.text.push_str(&section);
+            context.object_ids.push(object_id);
+        }
+
+        log::info!(
+            "Built AI context with {} objects ({} chars{})",
+            context.object_ids.len(),
+            context.text.len(),
+            if context.truncated { ", truncated" } else { "" }
+        );
+        Ok(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Todo;
+    use crate::test_support::temp_database;
+
+    async fn save(database: &Database, name: &str, permissions: &Permissions) -> i64 {
+        // Distinct timestamps keep the newest-first order deterministic
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        database.save_object("core.todo", &Todo::new(name.into()), None, Some(permissions), None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn shared_objects_are_included_newest_first() {
+        let (_vault, database) = temp_database("ai_context_order").await;
+        let shared = Permissions { share_with_ai: true, ..Default::default() };
+        let expired = Permissions { expires_at: Some("2000-01-01T00:00:00Z".into()), ..shared.clone() };
+        let older = save(&database, "older", &shared).await;
+        save(&database, "private", &Permissions::default()).await;
+        save(&database, "expired", &expired).await;
+        let newer = save(&database, "newer", &shared).await;
+
+        let context = database.build_ai_context(1000).await.unwrap();
+
+        assert_eq!(context.object_ids, vec![newer, older]);
+        assert!(!context.truncated);
+        assert!(!context.text.contains("private") && !context.text.contains("expired"));
+    }
+
+    #[tokio::test]
+    async fn the_token_budget_truncates_the_bundle() {
+        let (_vault, database) = temp_database("ai_context_budget").await;
+        let shared = Permissions { share_with_ai: true, ..Default::default() };
+        save(&database, "older", &shared).await;
+        let newer = save(&database, "newer", &shared).await;
+        let full = database.build_ai_context(1000).await.unwrap();
+
+        let budget = full.text.len() / 2 / CHARS_PER_TOKEN + 1;
+        let context = database.build_ai_context(budget).await.unwrap();
+
+        assert_eq!(context.object_ids, vec![newer]);
+        assert!(context.truncated);
+        assert!(context.text.len() <= budget * CHARS_PER_TOKEN);
+    }
+}
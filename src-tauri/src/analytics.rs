@@ -0,0 +1,416 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use rusqlite::params;
+use sha2::{Digest, Sha256};
+
+use crate::database::{db_path, Database};
+use crate::error::Result;
+use crate::models::{FieldStats, PermissionSummary, SchemaFieldStats, SchemaStorage, StorageBreakdown};
+use crate::vault_fs;
+
+// Field stats look at no more than this many of a schema's objects
+const FIELD_STATS_SAMPLE_SIZE: usize = 1000;
+
+// Time bucket used to group timestamps
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Bucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl Bucket {
+    fn strftime_format(&self) -> &'static str {
+        match self {
+            Bucket::Day => "%Y-%m-%d",
+            Bucket::Week => "%Y-W%W",
+            Bucket::Month => "%Y-%m",
+        }
+    }
+}
+
+impl Database {
+    /// Reports how much space the database, attachments, each schema's
+    /// content, and the vault as a whole take up.
+    pub async fn storage_breakdown(&self) -> Result<StorageBreakdown> {
+        let (schemas, attachment_bytes) = {
+            let conn = self.lock_connection().await?;
+
+            let mut stmt = conn.prepare(
+                "SELECT s.schema_name, COUNT(oc.object_id), COALESCE(SUM(length(oc.content_json)), 0)
+                 FROM schemas s
+                 LEFT JOIN data_objects do ON do.schema_id = s.id
+                 LEFT JOIN object_content oc ON oc.object_id = do.id
+                 GROUP BY s.id
+                 ORDER BY s.schema_name"
+            )?;
+            let schemas = stmt
+                .query_map([], |row| {
+                    Ok(SchemaStorage {
+                        schema_name: row.get(0)?,
+                        object_count: row.get(1)?,
+                        content_bytes: row.get::<_, i64>(2)? as u64,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            // Identical attachments share one blob on disk
+            let attachment_bytes: i64 = conn.query_row(
+                "SELECT COALESCE(SUM(size_bytes), 0) FROM (
+                    SELECT MAX(size_bytes) AS size_bytes FROM attachments GROUP BY content_hash
+                 )",
+                [],
+                |row| row.get(0),
+            )?;
+
+            (schemas, attachment_bytes as u64)
+        };
+
+        let db_file = db_path(self.vault_path());
+        let mut wal_file = db_file.clone().into_os_string();
+        wal_file.push("-wal");
+        let database_bytes = [db_file.into_os_string(), wal_file]
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+
+        Ok(StorageBreakdown {
+            database_bytes,
+            attachment_bytes,
+            schemas,
+            vault_bytes: vault_fs::directory_size(self.vault_path()),
+        })
+    }
+
+    /// Counts completed objects per bucket of their `updated_at`, oldest first.
+    /// Buckets with no completions are omitted.
+    pub async fn completion_timeseries(&self, schema_name: &str, bucket: Bucket) -> Result<Vec<(String, usize)>> {
+        let conn = self.lock_connection().await?;
+
+        // Only the first 19 chars (YYYY-MM-DDTHH:MM:SS) are needed; stored timestamps are UTC
+        let mut stmt = conn.prepare(
+            "SELECT strftime(?1, substr(do.updated_at, 1, 19)) AS bucket, COUNT(*)
+             FROM data_objects do
+             JOIN schemas s ON do.schema_id = s.id
+             JOIN object_content oc ON do.id = oc.object_id
+             WHERE s.schema_name = ?2 AND json_extract(oc.content_json, '$.completed') = 1
+             GROUP BY bucket
+             ORDER BY bucket ASC"
+        )?;
+
+        let rows = stmt.query_map(params![bucket.strftime_format(), schema_name], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, usize>(1)?))
+        })?;
+
+        let mut series = Vec::new();
+        for row in rows {
+            series.push(row?);
+        }
+
+        Ok(series)
+    }
+
+    /// Per top-level content field, how many of a schema's objects give it a
+    /// non-null value and of which types. Large schemas are sampled by taking
+    /// their most recently updated objects.
+    pub async fn schema_field_stats(&self, schema_name: &str) -> Result<SchemaFieldStats> {
+        let (total_objects, sample) = {
+            let conn = self.lock_connection().await?;
+
+            let total_objects: usize = conn.query_row(
+                "SELECT COUNT(*) FROM data_objects do
+                 JOIN schemas s ON do.schema_id = s.id
+                 WHERE s.schema_name = ?1",
+                params![schema_name],
+                |row| row.get(0),
+            )?;
+
+            let mut stmt = conn.prepare(
+                "SELECT oc.content_json FROM data_objects do
+                 JOIN schemas s ON do.schema_id = s.id
+                 JOIN object_content oc ON do.id = oc.object_id
+                 WHERE s.schema_name = ?1
+                 ORDER BY do.updated_at DESC, do.id DESC
+                 LIMIT ?2"
+            )?;
+            let sample = stmt
+                .query_map(params![schema_name, FIELD_STATS_SAMPLE_SIZE], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            (total_objects, sample)
+        };
+
+        // Fields that only ever appear as null are still listed, with no types
+        let mut fields: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+        for content_json in &sample {
+            let content: serde_json::Value = serde_json::from_str(content_json)?;
+            let Some(content) = content.as_object() else { continue };
+
+            for (field, value) in content {
+                let types = fields.entry(field.clone()).or_default();
+                if let Some(type_name) = json_type_name(value) {
+                    *types.entry(type_name.to_string()).or_default() += 1;
+                }
+            }
+        }
+
+        let sampled_objects = sample.len();
+        let fields = fields
+            .into_iter()
+            .map(|(field, types)| {
+                let non_null = types.values().sum();
+                FieldStats {
+                    field,
+                    non_null,
+                    fill_rate: non_null as f64 / sampled_objects as f64,
+                    types,
+                }
+            })
+            .collect();
+
+        Ok(SchemaFieldStats {
+            schema_name: schema_name.to_string(),
+            total_objects,
+            sampled_objects,
+            fields,
+        })
+    }
+
+    /// A hex digest that changes whenever any object is created, deleted or
+    /// updated: the Merkle root over every object's id, `updated_at` and
+    /// content hash, in id order. Vaults with the same rows share a fingerprint.
+    pub async fn vault_fingerprint(&self) -> Result<String> {
+        let conn = self.lock_connection().await?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, updated_at, COALESCE(content_hash, '') FROM data_objects ORDER BY id"
+        )?;
+        let leaves = stmt
+            .query_map([], |row| {
+                let (id, updated_at, hash): (i64, String, String) = (row.get(0)?, row.get(1)?, row.get(2)?);
+                Ok(Sha256::digest(format!("{}\0{}\0{}", id, updated_at, hash).as_bytes()).into())
+            })?
+            .collect::<rusqlite::Result<Vec<[u8; 32]>>>()?;
+
+        Ok(hex::encode(merkle_root(leaves)))
+    }
+
+    /// Counts objects by permission state in one aggregate pass. As with
+    /// `Permissions::is_expired`, an expiry SQLite can't parse counts as past.
+    pub async fn permission_summary(&self) -> Result<PermissionSummary> {
+        let conn = self.lock_connection().await?;
+
+        let counts: [i64; 6] = conn.query_row(
+            "WITH states AS (
+                SELECT op.share_with_ai, op.share_with_cloud, op.read_only,
+                       op.expires_at IS NOT NULL
+                           AND COALESCE(julianday(op.expires_at) <= julianday('now'), 1) AS expired
+                FROM object_permissions op
+                JOIN data_objects do ON do.id = op.object_id
+             )
+             SELECT
+                COUNT(*),
+                COALESCE(SUM(share_with_ai AND NOT expired), 0),
+                COALESCE(SUM(share_with_cloud AND NOT expired), 0),
+                COALESCE(SUM(read_only), 0),
+                COALESCE(SUM((share_with_ai OR share_with_cloud) AND expired), 0),
+                COALESCE(SUM(NOT share_with_ai AND NOT share_with_cloud), 0)
+             FROM states",
+            [],
+            |row| Ok([row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?]),
+        )?;
+        let [total_objects, shared_with_ai, shared_with_cloud, read_only, shared_but_expired, never_shared] =
+            counts.map(|count| count as usize);
+
+        Ok(PermissionSummary {
+            total_objects,
+            shared_with_ai,
+            shared_with_cloud,
+            read_only,
+            shared_but_expired,
+            never_shared,
+        })
+    }
+}
+
+// Hashes pairs of nodes level by level; an odd node out is carried up as is
+fn merkle_root(mut level: Vec<[u8; 32]>) -> [u8; 32] {
+    if level.is_empty() {
+        return Sha256::digest(b"").into();
+    }
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => Sha256::new().chain_update(left).chain_update(right).finalize().into(),
+                [node] => *node,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+fn json_type_name(value: &serde_json::Value) -> Option<&'static str> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::Bool(_) => Some("boolean"),
+        serde_json::Value::Number(_) => Some("number"),
+        serde_json::Value::String(_) => Some("string"),
+        serde_json::Value::Array(_) => Some("array"),
+        serde_json::Value::Object(_) => Some("object"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Permissions, Todo};
+    use crate::test_support::temp_database;
+
+    async fn completed_at(database: &Database, updated_at: &str) {
+        let todo = serde_json::json!({ "text": "done", "completed": true, "created_at": "2024-01-01T00:00:00Z" });
+        let id = database.save_object("core.todo", &todo, None, None, None).await.unwrap();
+        database
+            .lock_connection()
+            .await
+            .unwrap()
+            .execute("UPDATE data_objects SET updated_at = ?1 WHERE id = ?2", params![updated_at, id])
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn completions_are_bucketed_by_day_week_and_month() {
+        let (_vault, database) = temp_database("analytics_buckets").await;
+        for updated_at in [
+            "2024-03-01T10:00:00.123456+00:00",
+            "2024-03-01T23:00:00+00:00",
+            "2024-03-03T01:00:00+00:00",
+            "2024-04-15T00:00:00+00:00",
+        ] {
+            completed_at(&database, updated_at).await;
+        }
+        let open = serde_json::json!({ "text": "open", "completed": false, "created_at": "2024-01-01T00:00:00Z" });
+        database.save_object("core.todo", &open, None, None, None).await.unwrap();
+
+        let days = database.completion_timeseries("core.todo", Bucket::Day).await.unwrap();
+        assert_eq!(
+            days,
+            vec![("2024-03-01".to_string(), 2), ("2024-03-03".to_string(), 1), ("2024-04-15".to_string(), 1)]
+        );
+        let months = database.completion_timeseries("core.todo", Bucket::Month).await.unwrap();
+        assert_eq!(months, vec![("2024-03".to_string(), 3), ("2024-04".to_string(), 1)]);
+        let weeks = database.completion_timeseries("core.todo", Bucket::Week).await.unwrap();
+        assert_eq!(weeks.iter().map(|(_, count)| count).sum::<usize>(), 4);
+        assert_eq!(weeks.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn storage_breakdown_counts_content_and_attachments() {
+        let (_vault, database) = temp_database("analytics_storage").await;
+        database.save_object("core.todo", &Todo::new("a".into()), None, None, None).await.unwrap();
+        let todo_bytes = |breakdown: &StorageBreakdown| {
+            breakdown.schemas.iter().find(|schema| schema.schema_name == "core.todo").unwrap().content_bytes
+        };
+
+        let before = database.storage_breakdown().await.unwrap();
+        assert_eq!(before.schemas.iter().find(|schema| schema.schema_name == "core.todo").unwrap().object_count, 1);
+        assert!(before.database_bytes > 0 && before.vault_bytes > 0);
+
+        let id = database.save_object("core.todo", &Todo::new("x".repeat(50_000)), None, None, None).await.unwrap();
+        database.add_attachment(id, "a.bin", &[7u8; 20_000]).await.unwrap();
+        // The same bytes again are stored once
+        database.add_attachment(id, "b.bin", &[7u8; 20_000]).await.unwrap();
+
+        let after = database.storage_breakdown().await.unwrap();
+        assert!(todo_bytes(&after) >= todo_bytes(&before) + 50_000);
+        assert_eq!(after.attachment_bytes, before.attachment_bytes + 20_000);
+        assert!(after.vault_bytes > before.vault_bytes + 20_000);
+    }
+
+    #[tokio::test]
+    async fn field_stats_report_fill_rates_and_types() {
+        let (_vault, database) = temp_database("analytics_field_stats").await;
+        for i in 0..5 {
+            let mut todo = Todo::new(format!("t{}", i));
+            if i < 2 {
+                todo.due_date = Some("2024-05-01".into());
+            }
+            if i < 3 {
+                todo.tags = Some(vec!["x".into()]);
+            }
+            database.save_object("core.todo", &todo, None, None, None).await.unwrap();
+        }
+
+        let stats = database.schema_field_stats("core.todo").await.unwrap();
+        assert_eq!((stats.total_objects, stats.sampled_objects), (5, 5));
+        let field = |name: &str| stats.fields.iter().find(|f| f.field == name).cloned().unwrap();
+        assert_eq!(field("due_date").non_null, 2);
+        assert!((field("due_date").fill_rate - 0.4).abs() < 1e-9);
+        assert_eq!(field("tags").non_null, 3);
+        assert_eq!(field("tags").types.get("array"), Some(&3));
+        assert_eq!(field("text").non_null, 5);
+        assert_eq!(field("completed").types.get("boolean"), Some(&5));
+    }
+
+    #[tokio::test]
+    async fn field_stats_of_an_empty_schema_are_empty() {
+        let (_vault, database) = temp_database("analytics_field_stats_empty").await;
+        let stats = database.schema_field_stats("core.todo").await.unwrap();
+        assert_eq!((stats.total_objects, stats.fields.len()), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn fingerprints_change_with_objects_but_not_reads() {
+        let (_vault, database) = temp_database("analytics_fingerprint").await;
+        let empty = database.vault_fingerprint().await.unwrap();
+        let id = database.save_object("core.todo", &Todo::new("a".into()), None, None, None).await.unwrap();
+        let created = database.vault_fingerprint().await.unwrap();
+        assert_ne!(created, empty);
+        assert_eq!(created.len(), 64);
+
+        database.load_objects_by_schema::<Todo>("core.todo").await.unwrap();
+        database.load_object::<Todo>(id).await.unwrap();
+        assert_eq!(database.vault_fingerprint().await.unwrap(), created);
+
+        database.update_object(id, &Todo::new("changed".into())).await.unwrap();
+        let updated = database.vault_fingerprint().await.unwrap();
+        assert_ne!(updated, created);
+        database.delete_object(id).await.unwrap();
+        assert_ne!(database.vault_fingerprint().await.unwrap(), updated);
+    }
+
+    #[tokio::test]
+    async fn permission_states_are_counted() {
+        let (_vault, database) = temp_database("analytics_permission_summary").await;
+        assert_eq!(database.permission_summary().await.unwrap().total_objects, 0);
+
+        let hours = |hours: i64| Some((chrono::Utc::now() + chrono::Duration::hours(hours)).to_rfc3339());
+        let all = [
+            Permissions { share_with_ai: true, ..Default::default() },
+            Permissions { share_with_ai: true, share_with_cloud: true, expires_at: hours(5), ..Default::default() },
+            Permissions { share_with_cloud: true, expires_at: hours(-5), ..Default::default() },
+            // An unparseable expiry counts as past
+            Permissions { share_with_ai: true, expires_at: Some("garbage".into()), ..Default::default() },
+            Permissions { read_only: true, ..Default::default() },
+            Permissions { read_only: true, expires_at: hours(-1), ..Default::default() },
+            Permissions::default(),
+        ];
+        for permissions in &all {
+            database.save_object("core.todo", &Todo::new("x".into()), None, Some(permissions), None).await.unwrap();
+        }
+
+        let expected = PermissionSummary {
+            total_objects: 7,
+            shared_with_ai: 2,
+            shared_with_cloud: 1,
+            read_only: 2,
+            shared_but_expired: 2,
+            never_shared: 3,
+        };
+        assert_eq!(database.permission_summary().await.unwrap(), expected);
+    }
+}
@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+
+use crate::database::Database;
+use crate::error::{NexusError, Result};
+use crate::models::BackupInfo;
+
+const BACKUP_PREFIX: &str = "vault-";
+const BACKUP_EXTENSION: &str = ".sqlite";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+pub fn backups_dir(vault_path: &Path) -> PathBuf {
+    vault_path.join(".nexus").join("backups")
+}
+
+/// Writes a consistent snapshot of the live database to
+/// `.nexus/backups/vault-<timestamp>.sqlite` and prunes old backups.
+pub async fn create_backup(database: &Database, vault_path: &Path, keep: usize) -> Result<BackupInfo> {
+    let dir = backups_dir(vault_path);
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let name = format!(
+        "{}{}{}",
+        BACKUP_PREFIX,
+        Utc::now().format(TIMESTAMP_FORMAT),
+        BACKUP_EXTENSION
+    );
+    let path = dir.join(&name);
+    database.backup_to(&path).await?;
+
+    rotate_backups(vault_path, keep)?;
+
+    log::info!("Vault backup written to {:?}", path);
+    backup_info(&path).ok_or_else(|| NexusError::Sync(format!("Backup '{}' was not created", name)))
+}
+
+/// Lists backups newest first.
+pub fn list_backups(vault_path: &Path) -> Result<Vec<BackupInfo>> {
+    let dir = backups_dir(vault_path);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups: Vec<BackupInfo> = std::fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| backup_info(&entry.path()))
+        .collect();
+
+    // Timestamps in the name sort lexically in chronological order
+    backups.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(backups)
+}
+
+/// Deletes all but the `keep` most recent backups. The newest backup is always
+/// kept, so a `keep` of 0 can't throw away the one that was just written.
+pub fn rotate_backups(vault_path: &Path, keep: usize) -> Result<()> {
+    for stale in list_backups(vault_path)?.into_iter().skip(keep.max(1)) {
+        let path = backups_dir(vault_path).join(&stale.name);
+        if let Err(e) = std::fs::remove_file(&path) {
+            log::warn!("Failed to remove old backup {:?}: {}", path, e);
+        } else {
+            log::info!("Removed old backup: {}", stale.name);
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the live database contents with the named backup.
+pub async fn restore_backup(database: &Database, vault_path: &Path, name: &str) -> Result<()> {
+    if parse_backup_name(name).is_none() || name.contains(['/', '\\']) {
+        return Err(NexusError::PermissionDenied(format!("'{}' is not a backup name", name)));
+    }
+
+    let path = backups_dir(vault_path).join(name);
+    if !path.is_file() {
+        return Err(NexusError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("Backup '{}' not found", name),
+        )));
+    }
+
+    database.restore_from(&path).await?;
+    log::info!("Database restored from backup: {}", name);
+    Ok(())
+}
+
+/// Runs a backup every `interval_hours`. If the newest backup is younger than
+/// the interval, the first run waits out the remainder instead of firing at startup.
+pub fn spawn_backup_scheduler(
+    database: Arc<Database>,
+    vault_path: PathBuf,
+    interval_hours: u64,
+    keep: usize,
+) -> tokio::task::JoinHandle<()> {
+    let interval = Duration::from_secs(interval_hours.max(1) * 60 * 60);
+
+    tokio::spawn(async move {
+        let since_last = list_backups(&vault_path)
+            .ok()
+            .and_then(|backups| backups.into_iter().next())
+            .and_then(|latest| parse_backup_name(&latest.name))
+            .and_then(|created| (Utc::now().naive_utc() - created).to_std().ok());
+
+        let first_delay = match since_last {
+            Some(elapsed) if elapsed < interval => interval - elapsed,
+            _ => Duration::ZERO,
+        };
+        log::info!(
+            "Backup scheduler started (every {}h, next run in {}s)",
+            interval_hours,
+            first_delay.as_secs()
+        );
+
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + first_delay, interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            if let Err(e) = create_backup(&database, &vault_path, keep).await {
+                log::error!("Scheduled backup failed: {}", e);
+            }
+        }
+    })
+}
+
+fn parse_backup_name(name: &str) -> Option<NaiveDateTime> {
+    let timestamp = name.strip_prefix(BACKUP_PREFIX)?.strip_suffix(BACKUP_EXTENSION)?;
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()
+}
+
+fn backup_info(path: &Path) -> Option<BackupInfo> {
+    let name = path.file_name()?.to_str()?.to_string();
+    let created_at = parse_backup_name(&name)?.and_utc().to_rfc3339();
+    let size_bytes = std::fs::metadata(path).ok()?.len();
+
+    Some(BackupInfo {
+        name,
+        size_bytes,
+        created_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Todo;
+    use crate::test_support::temp_database;
+
+    fn write_fake_backups(vault_path: &Path, count: u32) {
+        std::fs::create_dir_all(backups_dir(vault_path)).unwrap();
+        for day in 1..=count {
+            let name = format!("vault-202001{:02}T000000Z.sqlite", day);
+            std::fs::write(backups_dir(vault_path).join(name), "").unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn rotation_keeps_only_the_newest() {
+        let (vault, database) = temp_database("backup_rotation").await;
+        write_fake_backups(&vault, 3);
+
+        let latest = create_backup(&database, &vault, 2).await.unwrap();
+
+        let names: Vec<String> = list_backups(&vault).unwrap().into_iter().map(|b| b.name).collect();
+        assert_eq!(names, vec![latest.name, "vault-20200103T000000Z.sqlite".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn keep_zero_still_keeps_the_latest_backup() {
+        let (vault, database) = temp_database("backup_keep_zero").await;
+        write_fake_backups(&vault, 2);
+
+        let latest = create_backup(&database, &vault, 0).await.unwrap();
+
+        let backups = list_backups(&vault).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].name, latest.name);
+    }
+
+    #[tokio::test]
+    async fn restore_replaces_the_live_database() {
+        let (vault, database) = temp_database("backup_restore").await;
+        let todo = Todo::new("kept".into());
+        database.save_object("core.todo", &todo, None, None, None).await.unwrap();
+        let backup = create_backup(&database, &vault, 2).await.unwrap();
+        database.save_object("core.todo", &todo, None, None, None).await.unwrap();
+        assert_eq!(database.get_sync_info().await.unwrap().0, 2);
+
+        restore_backup(&database, &vault, &backup.name).await.unwrap();
+
+        assert_eq!(database.get_sync_info().await.unwrap().0, 1);
+        assert!(restore_backup(&database, &vault, "../vault.sqlite").await.is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+use crate::database::{check_content, content_hash, plugin_source, Database, SOURCE_UI};
+use crate::error::{NexusError, Result};
+use crate::models::{ObjectLink, ObjectOp, ObjectRef, Permissions};
+use crate::plugins;
+
+// What a batch did, for the follow-up work done once it has committed
+#[derive(Default)]
+struct BatchEffects {
+    created: Vec<i64>,
+    updated: Vec<i64>,
+    // Attachment blobs of deleted objects, which may now be orphaned
+    deleted_blobs: Vec<(String, String)>,
+}
+
+impl Database {
+    /// Applies `ops` in order inside one transaction: if any of them fails,
+    /// none take effect. Returns the ids of the created objects in the order
+    /// they were created. Read-only objects can't be updated or deleted.
+    pub async fn batch_operations(&self, ops: &[ObjectOp]) -> Result<Vec<i64>> {
+        self.run_batch(ops, SOURCE_UI).await
+    }
+
+    /// Like `batch_operations`, but attributes the created objects to the
+    /// plugin, so its storage report and `clear_plugin_data` can find them.
+    pub async fn plugin_batch_operations(&self, plugin_id: &str, ops: &[ObjectOp]) -> Result<Vec<i64>> {
+        if !plugins::is_valid_plugin_id(plugin_id) {
+            return Err(NexusError::InvalidInput(format!("Invalid plugin id '{}'", plugin_id)));
+        }
+        self.run_batch(ops, &plugin_source(plugin_id)).await
+    }
+
+    async fn run_batch(&self, ops: &[ObjectOp], source: &str) -> Result<Vec<i64>> {
+        let conn = self.lock_connection().await?;
+        let tx = conn.unchecked_transaction()?;
+
+        let mut effects = BatchEffects::default();
+        for (index, op) in ops.iter().enumerate() {
+            if let Err(e) = self.apply_op(&tx, op, source, &mut effects) {
+                log::warn!("Batch rolled back at operation {}: {}", index, e);
+                return Err(e);
+            }
+        }
+        tx.commit()?;
+
+        self.remove_orphaned_blobs(&conn, &effects.deleted_blobs)?;
+        drop(conn);
+
+        for object_id in &effects.updated {
+            self.notify_changed(*object_id);
+        }
+
+        log::info!("Batch of {} operations committed", ops.len());
+        Ok(effects.created)
+    }
+
+    fn apply_op(&self, conn: &Connection, op: &ObjectOp, source: &str, effects: &mut BatchEffects) -> Result<()> {
+        let now = Utc::now().to_rfc3339();
+
+        match op {
+            ObjectOp::Create { schema_name, content, permissions } => {
+                let permissions = permissions.clone().unwrap_or_default();
+                let object_id = self.insert_object_on(conn, schema_name, content, &permissions, source)?;
+                effects.created.push(object_id);
+            }
+            ObjectOp::Update { object, content } => {
+                let object_id = resolve(object, &effects.created)?;
+                ensure_writable(conn, object_id)?;
+
+                let content_json = serde_json::to_string(content)?;
+                self.check_content_size(&content_json)?;
+                conn.execute(
+                    "UPDATE object_content SET content_json = ?1 WHERE object_id = ?2",
+                    params![content_json, object_id],
+                )?;
+                conn.execute(
+                    "UPDATE data_objects SET content_hash = ?1, updated_at = ?2 WHERE id = ?3",
+                    params![content_hash(&content_json), now, object_id],
+                )?;
+
+                effects.updated.push(object_id);
+            }
+            ObjectOp::Delete { object } => {
+                let object_id = resolve(object, &effects.created)?;
+                ensure_writable(conn, object_id)?;
+
+                let mut stmt = conn.prepare("SELECT content_hash, blob_path FROM attachments WHERE object_id = ?1")?;
+                let blobs = stmt.query_map(params![object_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                for blob in blobs {
+                    effects.deleted_blobs.push(blob?);
+                }
+                conn.execute("DELETE FROM data_objects WHERE id = ?1", params![object_id])?;
+
+                effects.updated.retain(|id| *id != object_id);
+            }
+            ObjectOp::Link { source, target, relation } => {
+                let source_id = resolve(source, &effects.created)?;
+                let target_id = resolve(target, &effects.created)?;
+                if relation.trim().is_empty() {
+                    return Err(NexusError::InvalidInput("A link needs a relation".to_string()));
+                }
+                for object_id in [source_id, target_id] {
+                    object_permissions(conn, object_id)?;
+                }
+
+                conn.execute(
+                    "INSERT OR IGNORE INTO object_links (source_id, target_id, relation, created_at)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![source_id, target_id, relation, now],
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Inserts a DB-only object using `conn`, typically a transaction the
+    /// caller commits. Returns the new object's id.
+    pub(crate) fn insert_object_on(
+        &self,
+        conn: &Connection,
+        schema_name: &str,
+        content: &Value,
+        permissions: &Permissions,
+        source: &str,
+    ) -> Result<i64> {
+        let (schema_id, definition_json): (i64, String) = conn
+            .query_row(
+                "SELECT id, definition_json FROM schemas WHERE schema_name = ?1",
+                params![schema_name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or_else(|| NexusError::SchemaNotFound(schema_name.to_string()))?;
+
+        let content_json = serde_json::to_string(content)?;
+        self.check_content_size(&content_json)?;
+        check_content(&definition_json, content)?;
+        let now = Utc::now().to_rfc3339();
+
+        conn.execute(
+            "INSERT INTO data_objects (schema_id, file_path, content_hash, source, updated_at, created_at)
+             VALUES (?1, NULL, ?2, ?3, ?4, ?4)",
+            params![schema_id, content_hash(&content_json), source, now],
+        )?;
+        let object_id = conn.last_insert_rowid();
+        conn.execute(
+            "INSERT INTO object_content (object_id, content_json) VALUES (?1, ?2)",
+            params![object_id, content_json],
+        )?;
+        conn.execute(
+            "INSERT INTO object_permissions
+             (object_id, share_with_ai, share_with_cloud, read_only, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                object_id,
+                permissions.share_with_ai,
+                permissions.share_with_cloud,
+                permissions.read_only,
+                permissions.expires_at
+            ],
+        )?;
+
+        Ok(object_id)
+    }
+
+    /// Links from or to an object, oldest first.
+    pub async fn get_object_links(&self, object_id: i64) -> Result<Vec<ObjectLink>> {
+        let conn = self.lock_connection().await?;
+
+        let mut stmt = conn.prepare(
+            "SELECT source_id, target_id, relation, created_at FROM object_links
+             WHERE source_id = ?1 OR target_id = ?1
+             ORDER BY created_at, source_id, target_id",
+        )?;
+        let links = stmt
+            .query_map(params![object_id], |row| {
+                Ok(ObjectLink {
+                    source_id: row.get(0)?,
+                    target_id: row.get(1)?,
+                    relation: row.get(2)?,
+                    created_at: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(links)
+    }
+}
+
+fn resolve(object: &ObjectRef, created: &[i64]) -> Result<i64> {
+    match *object {
+        ObjectRef::Existing(object_id) => Ok(object_id),
+        ObjectRef::Created { created: index } => created.get(index).copied().ok_or_else(|| {
+            NexusError::InvalidInput(format!(
+                "No object #{} has been created earlier in the batch ({} so far)",
+                index,
+                created.len()
+            ))
+        }),
+    }
+}
+
+fn object_permissions(conn: &Connection, object_id: i64) -> Result<Permissions> {
+    conn.query_row(
+        "SELECT share_with_ai, share_with_cloud, read_only, expires_at FROM object_permissions WHERE object_id = ?1",
+        params![object_id],
+        |row| {
+            Ok(Permissions {
+                share_with_ai: row.get(0)?,
+                share_with_cloud: row.get(1)?,
+                read_only: row.get(2)?,
+                expires_at: row.get(3)?,
+            })
+        },
+    )
+    .optional()?
+    .ok_or(NexusError::ObjectNotFound(object_id))
+}
+
+fn ensure_writable(conn: &Connection, object_id: i64) -> Result<()> {
+    if object_permissions(conn, object_id)?.read_only {
+        return Err(NexusError::PermissionDenied(format!("Object {} is read-only", object_id)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Todo;
+    use crate::test_support::temp_database;
+
+    fn ops(ops: Value) -> Vec<ObjectOp> {
+        serde_json::from_value(ops).unwrap()
+    }
+
+    async fn text(database: &Database, object_id: i64) -> String {
+        database.load_object::<Todo>(object_id).await.unwrap().content.text
+    }
+
+    #[tokio::test]
+    async fn batches_can_refer_to_objects_they_create() {
+        let (_vault, database) = temp_database("batch_apply").await;
+        let existing = database.save_object("core.todo", &Todo::new("old".into()), None, None, None).await.unwrap();
+        let gone = database.save_object("core.todo", &Todo::new("gone".into()), None, None, None).await.unwrap();
+
+        let created = database.batch_operations(&ops(serde_json::json!([
+            { "op": "create", "schema_name": "core.note", "content": { "title": "project", "body": "", "tags": [] } },
+            { "op": "create", "schema_name": "core.todo", "content": Todo::new("task".into()) },
+            { "op": "link", "source": { "created": 0 }, "target": { "created": 1 }, "relation": "subtask" },
+            { "op": "link", "source": { "created": 0 }, "target": existing, "relation": "subtask" },
+            { "op": "update", "object": existing, "content": Todo::new("renamed".into()) },
+            { "op": "delete", "object": gone },
+        ]))).await.unwrap();
+
+        assert_eq!(created.len(), 2);
+        assert_eq!(text(&database, created[1]).await, "task");
+        assert_eq!(text(&database, existing).await, "renamed");
+        assert!(database.load_object::<Todo>(gone).await.is_err());
+        let links = database.get_object_links(created[0]).await.unwrap();
+        assert_eq!(links.iter().map(|link| link.target_id).collect::<Vec<_>>(), vec![created[1], existing]);
+
+        // Deleting an object drops its links
+        database.batch_operations(&ops(serde_json::json!([{ "op": "delete", "object": created[1] }]))).await.unwrap();
+        assert_eq!(database.get_object_links(created[0]).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_failing_operation_rolls_back_the_whole_batch() {
+        let (_vault, database) = temp_database("batch_rollback").await;
+        let existing = database.save_object("core.todo", &Todo::new("old".into()), None, None, None).await.unwrap();
+        let (count, _) = database.get_sync_info().await.unwrap();
+
+        let result = database.batch_operations(&ops(serde_json::json!([
+            { "op": "create", "schema_name": "core.todo", "content": Todo::new("never".into()) },
+            { "op": "update", "object": existing, "content": Todo::new("never".into()) },
+            { "op": "delete", "object": 999999 },
+            { "op": "create", "schema_name": "core.todo", "content": Todo::new("never either".into()) },
+        ]))).await;
+
+        assert!(matches!(result, Err(NexusError::ObjectNotFound(999999))));
+        assert_eq!(database.get_sync_info().await.unwrap().0, count);
+        assert_eq!(text(&database, existing).await, "old");
+    }
+
+    #[tokio::test]
+    async fn bad_references_schemas_and_read_only_objects_are_rejected() {
+        let (_vault, database) = temp_database("batch_invalid").await;
+        let existing = database.save_object("core.todo", &Todo::new("old".into()), None, None, None).await.unwrap();
+
+        let unknown_reference = serde_json::json!([{ "op": "delete", "object": { "created": 0 } }]);
+        assert!(database.batch_operations(&ops(unknown_reference)).await.is_err());
+        let unknown_schema = serde_json::json!([{ "op": "create", "schema_name": "nope", "content": {} }]);
+        assert!(database.batch_operations(&ops(unknown_schema)).await.is_err());
+
+        let read_only = Permissions { read_only: true, ..Default::default() };
+        database.update_object_permissions(existing, &read_only).await.unwrap();
+        let delete = serde_json::json!([{ "op": "delete", "object": existing }]);
+        assert!(matches!(database.batch_operations(&ops(delete)).await, Err(NexusError::PermissionDenied(_))));
+    }
+}
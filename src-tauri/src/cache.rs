@@ -0,0 +1,241 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use rusqlite::Connection;
+use serde_json::Value;
+
+use crate::models::{AppObject, CacheStats, ObjectOrder};
+
+/// Recently read objects, keyed by id, with their content already parsed.
+/// Also remembers which ids a schema load returned, so repeating it while
+/// nothing changed needs no query. A capacity of 0 turns the cache off.
+pub struct ObjectCache {
+    capacity: usize,
+    objects: HashMap<i64, (AppObject<Value>, u64)>,
+    // Last use of each cached object, oldest first
+    recency: BTreeMap<u64, i64>,
+    schema_loads: HashMap<(String, ObjectOrder), Vec<i64>>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl ObjectCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            objects: HashMap::new(),
+            recency: BTreeMap::new(),
+            schema_loads: HashMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    pub fn get(&mut self, object_id: i64) -> Option<AppObject<Value>> {
+        let object = self.touch(object_id);
+        self.count(object.is_some());
+        object
+    }
+
+    /// The objects of a repeated schema load, or `None` if any has since been
+    /// evicted or changed.
+    pub fn get_schema(&mut self, schema_name: &str, order: ObjectOrder) -> Option<Vec<AppObject<Value>>> {
+        let ids = self.schema_loads.get(&(schema_name.to_string(), order)).cloned();
+        let objects = ids.and_then(|ids| ids.into_iter().map(|id| self.touch(id)).collect::<Option<Vec<_>>>());
+        self.count(objects.is_some());
+        objects
+    }
+
+    pub fn insert(&mut self, object: &AppObject<Value>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.clock += 1;
+        if let Some((_, used)) = self.objects.insert(object.id, (object.clone(), self.clock)) {
+            self.recency.remove(&used);
+        }
+        self.recency.insert(self.clock, object.id);
+        self.evict();
+    }
+
+    pub fn insert_schema(&mut self, schema_name: &str, order: ObjectOrder, objects: &[AppObject<Value>]) {
+        // A load bigger than the cache could never be served from it
+        if self.capacity == 0 || objects.len() > self.capacity {
+            return;
+        }
+
+        for object in objects {
+            self.insert(object);
+        }
+        self.schema_loads
+            .insert((schema_name.to_string(), order), objects.iter().map(|object| object.id).collect());
+    }
+
+    /// Drops one object, and every remembered schema load since any change
+    /// can alter which objects a schema has or their order.
+    pub fn invalidate(&mut self, object_id: i64) {
+        if let Some((_, used)) = self.objects.remove(&object_id) {
+            self.recency.remove(&used);
+        }
+        self.schema_loads.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.objects.clear();
+        self.recency.clear();
+        self.schema_loads.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            capacity: self.capacity,
+            entries: self.objects.len(),
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+
+    fn touch(&mut self, object_id: i64) -> Option<AppObject<Value>> {
+        self.clock += 1;
+        let (object, used) = self.objects.get_mut(&object_id)?;
+        self.recency.remove(used);
+        *used = self.clock;
+        self.recency.insert(self.clock, object_id);
+        Some(object.clone())
+    }
+
+    fn count(&mut self, hit: bool) {
+        if hit {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.objects.len() > self.capacity {
+            let Some((_, object_id)) = self.recency.pop_first() else { break };
+            self.objects.remove(&object_id);
+        }
+    }
+}
+
+/// Keeps the cache in step with every write made through `conn`, whether from
+/// a command, a patch or the sync service.
+pub fn invalidate_on_change(conn: &Connection, cache: &Arc<Mutex<ObjectCache>>) {
+    let cache = Arc::clone(cache);
+    conn.update_hook(Some(move |_action, _database: &str, table: &str, row_id: i64| {
+        let Ok(mut cache) = cache.lock() else { return };
+        match table {
+            // Keyed by object id, so the row id is the object's id
+            "data_objects" | "object_content" | "object_permissions" => cache.invalidate(row_id),
+            // Renamed or re-registered schemas change every object's schema_name
+            "schemas" => cache.clear(),
+            _ => {}
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{PatchOp, Todo};
+    use crate::test_support::{vault, TWO_TODOS};
+
+    fn object(id: i64) -> AppObject<Value> {
+        AppObject {
+            id,
+            schema_name: "test.schema".to_string(),
+            content: serde_json::json!(id),
+            file_path: None,
+            updated_at: String::new(),
+            created_at: String::new(),
+            permissions: Default::default(),
+            source: "unknown".to_string(),
+        }
+    }
+
+    #[test]
+    fn the_least_recently_used_object_is_evicted() {
+        let mut cache = ObjectCache::new(2);
+        cache.insert(&object(1));
+        cache.insert(&object(2));
+        assert!(cache.get(1).is_some());
+
+        cache.insert(&object(3));
+        assert!(cache.get(2).is_none());
+        assert!(cache.get(1).is_some() && cache.get(3).is_some());
+        assert_eq!(cache.stats().entries, 2);
+    }
+
+    #[test]
+    fn schema_loads_are_forgotten_once_an_object_changes() {
+        let mut cache = ObjectCache::new(10);
+        cache.insert_schema("test.schema", ObjectOrder::Created, &[object(1), object(2)]);
+        assert_eq!(cache.get_schema("test.schema", ObjectOrder::Created).unwrap().len(), 2);
+
+        cache.invalidate(2);
+        assert!(cache.get_schema("test.schema", ObjectOrder::Created).is_none());
+        assert!(cache.get(1).is_some());
+    }
+
+    #[tokio::test]
+    async fn cached_reads_follow_every_write() {
+        let (vault, database, sync_service) = vault("cache_writes", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        let id = database.load_objects_by_schema::<Todo>("core.todo").await.unwrap()[0].id;
+
+        let before = database.cache_stats();
+        let original = database.load_object::<Todo>(id).await.unwrap();
+        assert_eq!(database.load_objects_by_schema::<Todo>("core.todo").await.unwrap().len(), 2);
+        let after = database.cache_stats();
+        assert_eq!((after.hits, after.misses), (before.hits + 2, before.misses));
+
+        // A change synced from the file
+        std::fs::write(vault.join("Todo/todos.json"), TWO_TODOS.replace(r#""text":"a""#, r#""text":"aa""#)).unwrap();
+        sync_service.force_sync(Some(&["Todo/todos.json".to_string()])).await.unwrap();
+        let texts: Vec<String> = database
+            .load_objects_by_schema::<Todo>("core.todo")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|todo| todo.content.text)
+            .collect();
+        assert!(texts.contains(&"aa".to_string()), "{:?}", texts);
+
+        // A patch through the database
+        let patch = [PatchOp::Replace { path: "/text".into(), value: serde_json::json!("changed") }];
+        database.patch_object(id, &patch).await.unwrap();
+        assert_eq!(database.load_object::<Todo>(id).await.unwrap().content.text, "changed");
+        assert_ne!(original.content.text, "changed");
+
+        database.delete_object(id).await.unwrap();
+        assert!(database.load_object::<Todo>(id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn a_zero_capacity_turns_the_cache_off() {
+        let (_vault, database, sync_service) = vault("cache_disabled", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        let mut settings = database.settings();
+        settings.object_cache_size = 0;
+        database.update_settings(settings).unwrap();
+
+        let misses = database.cache_stats().misses;
+        let id = database.load_objects_by_schema::<Todo>("core.todo").await.unwrap()[0].id;
+        database.load_object::<Todo>(id).await.unwrap();
+        database.load_object::<Todo>(id).await.unwrap();
+
+        let stats = database.cache_stats();
+        assert_eq!(stats.misses, misses + 3);
+        assert_eq!((stats.capacity, stats.entries), (0, 0));
+    }
+}
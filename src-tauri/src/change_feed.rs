@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::Instant;
+
+use crate::models::{ChangeBatch, ChangeKind, ObjectChange};
+
+// What the change feed hands the frontend: one change while things are
+// quiet, a summary once a window holds more than the threshold
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedEvent {
+    Change(ObjectChange),
+    Batch(ChangeBatch),
+}
+
+impl ChangeBatch {
+    pub fn add(&mut self, change: &ObjectChange) {
+        match change.kind {
+            ChangeKind::Created => self.created += 1,
+            ChangeKind::Updated => self.updated += 1,
+            ChangeKind::Deleted => self.deleted += 1,
+        }
+        if let Err(index) = self.schemas.binary_search(&change.schema_name) {
+            self.schemas.insert(index, change.schema_name.clone());
+        }
+    }
+}
+
+/// Passes `changes` to `emit` until the sender is gone. The first `threshold`
+/// changes of each `window` go out one by one; the rest of that window's are
+/// coalesced into a batch sent when it ends. Right after a batch the next
+/// window batches from its first change, so a sustained burst stays batched.
+/// A zero threshold never batches.
+pub async fn forward_changes<F>(
+    mut changes: broadcast::Receiver<ObjectChange>,
+    threshold: usize,
+    window: Duration,
+    mut emit: F,
+) where
+    F: FnMut(FeedEvent),
+{
+    let mut window_end = Instant::now();
+    let mut sent_in_window = 0;
+    let mut batch: Option<ChangeBatch> = None;
+
+    loop {
+        let received = match batch {
+            Some(_) => tokio::select! {
+                received = changes.recv() => received,
+                _ = tokio::time::sleep_until(window_end) => {
+                    if let Some(batch) = batch.take() {
+                        emit(FeedEvent::Batch(batch));
+                    }
+                    window_end = Instant::now() + window;
+                    sent_in_window = threshold;
+                    continue;
+                }
+            },
+            None => changes.recv().await,
+        };
+
+        let change = match received {
+            Ok(change) => change,
+            Err(RecvError::Lagged(missed)) => {
+                log::warn!("Vault change feed fell behind; {} changes were not forwarded", missed);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        let now = Instant::now();
+        if now >= window_end {
+            window_end = now + window;
+            sent_in_window = 0;
+        }
+
+        match &mut batch {
+            Some(batch) => batch.add(&change),
+            None if threshold == 0 || sent_in_window < threshold => {
+                sent_in_window += 1;
+                emit(FeedEvent::Change(change));
+            }
+            None => {
+                let mut started = ChangeBatch::default();
+                started.add(&change);
+                batch = Some(started);
+            }
+        }
+    }
+
+    if let Some(batch) = batch {
+        emit(FeedEvent::Batch(batch));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn change(object_id: i64, kind: ChangeKind, schema_name: &str) -> ObjectChange {
+        ObjectChange { kind, object_id, schema_name: schema_name.to_string(), path: String::new() }
+    }
+
+    // Runs the feed into a shared list of everything it emitted
+    fn start_feed(
+        threshold: usize,
+        window: Duration,
+    ) -> (broadcast::Sender<ObjectChange>, Arc<Mutex<Vec<FeedEvent>>>, tokio::task::JoinHandle<()>) {
+        let (changes, receiver) = broadcast::channel(1024);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let feed = tokio::spawn(forward_changes(receiver, threshold, window, move |event| {
+            sink.lock().unwrap().push(event)
+        }));
+        (changes, events, feed)
+    }
+
+    #[tokio::test]
+    async fn bursts_are_summarised_in_one_batch() {
+        let (changes, events, feed) = start_feed(5, Duration::from_millis(200));
+        for i in 0..100 {
+            let kind = if i % 10 == 0 { ChangeKind::Deleted } else { ChangeKind::Created };
+            changes.send(change(i, kind, if i % 2 == 0 { "core.todo" } else { "core.note" })).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        {
+            let events = events.lock().unwrap();
+            assert_eq!(events.len(), 6, "{:?}", events);
+            assert!(events[..5].iter().all(|event| matches!(event, FeedEvent::Change(_))));
+            let summary = ChangeBatch { created: 86, updated: 0, deleted: 9, schemas: vec!["core.note".into(), "core.todo".into()] };
+            assert_eq!(events[5], FeedEvent::Batch(summary));
+        }
+
+        // Once quiet again, changes go out one by one
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        changes.send(change(500, ChangeKind::Updated, "core.todo")).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(matches!(events.lock().unwrap().last(), Some(FeedEvent::Change(c)) if c.object_id == 500));
+        assert_eq!(events.lock().unwrap().len(), 7);
+
+        drop(changes);
+        feed.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_pending_batch_is_sent_when_the_feed_closes() {
+        let (changes, events, feed) = start_feed(1, Duration::from_secs(60));
+        for i in 0..3 {
+            changes.send(change(i, ChangeKind::Updated, "core.todo")).unwrap();
+        }
+        drop(changes);
+        feed.await.unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        let summary = ChangeBatch { created: 0, updated: 2, deleted: 0, schemas: vec!["core.todo".into()] };
+        assert_eq!(events[1], FeedEvent::Batch(summary));
+    }
+
+    #[tokio::test]
+    async fn a_zero_threshold_never_batches() {
+        let (changes, events, feed) = start_feed(0, Duration::from_secs(60));
+        for i in 0..20 {
+            changes.send(change(i, ChangeKind::Created, "core.todo")).unwrap();
+        }
+        drop(changes);
+        feed.await.unwrap();
+        assert!(events.lock().unwrap().iter().all(|event| matches!(event, FeedEvent::Change(_))));
+        assert_eq!(events.lock().unwrap().len(), 20);
+    }
+}
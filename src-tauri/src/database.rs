@@ -1,31 +1,78 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex, MutexGuard};
 use rusqlite::{Connection, params, OptionalExtension};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
 
 use crate::error::{NexusError, Result};
 use crate::models::{
-    Schema, Permissions, AppObject
+    Schema, Permissions, AppObject, Attachment, SyncConflict, DateField, ValidationError, PluginSchema, BulkUpdateResult, CloneOptions,
+    CacheStats, InvalidObject, Note, ObjectOrder, VaultSettings, VaultValidationReport
 };
+use crate::cache::{self, ObjectCache};
+use crate::formats::FileFormat;
+use crate::notes;
+use crate::perf::SpanTimer;
+use crate::schema_validation;
+use crate::settings;
+use crate::sync_service;
+use crate::vault_fs;
+use crate::vault_lock::VaultLock;
 
 #[derive(Clone)]
 pub struct Database {
-    connection: Arc<Mutex<Connection>>,
+    pub(crate) connection: Arc<Mutex<Connection>>,
     vault_path: PathBuf,
+    lock: Arc<VaultLock>,
+    // Told the id of every object whose content the app edits
+    change_listener: Arc<std::sync::Mutex<Option<mpsc::UnboundedSender<i64>>>>,
+    max_content_bytes: Arc<AtomicUsize>,
+    settings: Arc<std::sync::RwLock<VaultSettings>>,
+    // Cleared when the vault directory vanishes; the open connection may then
+    // point at a deleted file, so nothing may touch it until a reconnect
+    available: Arc<AtomicBool>,
+    cache: Arc<std::sync::Mutex<ObjectCache>>,
 }
 
+// Values of data_objects.source, recording what created an object. Plugins
+// use "plugin:<id>"; see plugin_source.
+pub const SOURCE_UNKNOWN: &str = "unknown";
+pub const SOURCE_UI: &str = "ui";
+pub const SOURCE_SYNC: &str = "sync";
+pub const SOURCE_IMPORT: &str = "import";
+
+pub fn plugin_source(plugin_id: &str) -> String {
+    format!("plugin:{}", plugin_id)
+}
+
+// Large data belongs in attachments, not content_json
+pub const DEFAULT_MAX_CONTENT_BYTES: usize = 1024 * 1024;
+
 impl Database {
     pub async fn new(vault_path: &Path) -> Result<Self> {
         let nexus_dir = vault_path.join(".nexus");
         tokio::fs::create_dir_all(&nexus_dir).await?;
         
-        let db_path = nexus_dir.join("vault.sqlite");
-        let connection = Connection::open(&db_path)?;
+        // Refuse to open a vault another running instance is using
+        let lock = VaultLock::acquire(vault_path)?;
+        
+        let connection = Connection::open(db_path(vault_path))?;
+        let settings = settings::load_settings(vault_path)?.unwrap_or_default();
+        let cache = Arc::new(std::sync::Mutex::new(ObjectCache::new(settings.object_cache_size)));
+        cache::invalidate_on_change(&connection, &cache);
         
         let db = Self {
             connection: Arc::new(Mutex::new(connection)),
             vault_path: vault_path.to_path_buf(),
+            lock: Arc::new(lock),
+            change_listener: Arc::new(std::sync::Mutex::new(None)),
+            max_content_bytes: Arc::new(AtomicUsize::new(settings.max_content_bytes)),
+            settings: Arc::new(std::sync::RwLock::new(settings)),
+            available: Arc::new(AtomicBool::new(true)),
+            cache,
         };
         
         db.initialize_schema().await?;
@@ -34,8 +81,169 @@ impl Database {
         Ok(db)
     }
 
+    /// Throws away the SQLite file and starts over with an empty, freshly
+    /// initialized database. Callers are expected to rescan the vault afterwards.
+    pub async fn rebuild(&self) -> Result<()> {
+        {
+            let mut conn = self.lock_connection().await?;
+
+            // Close the current handle before deleting the file underneath it
+            let old = std::mem::replace(&mut *conn, Connection::open_in_memory()?);
+            old.close().map_err(|(_, e)| e)?;
+
+            let path = db_path(&self.vault_path);
+            for suffix in ["", "-wal", "-shm", "-journal"] {
+                let file = PathBuf::from(format!("{}{}", path.display(), suffix));
+                if file.exists() {
+                    std::fs::remove_file(&file)?;
+                }
+            }
+
+            *conn = Connection::open(&path)?;
+            cache::invalidate_on_change(&conn, &self.cache);
+            self.with_cache(|cache| cache.clear());
+        }
+
+        self.initialize_schema().await?;
+        self.register_core_schemas().await?;
+
+        log::warn!("Database rebuilt from scratch at {:?}", db_path(&self.vault_path));
+        Ok(())
+    }
+
+    pub fn vault_path(&self) -> &Path {
+        &self.vault_path
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.available.load(Ordering::SeqCst)
+    }
+
+    /// Refuses all further database access; used once the vault root is gone.
+    pub fn mark_unavailable(&self) {
+        self.available.store(false, Ordering::SeqCst);
+    }
+
+    pub(crate) async fn lock_connection(&self) -> Result<MutexGuard<'_, Connection>> {
+        if !self.is_available() {
+            return Err(NexusError::VaultUnavailable(self.vault_path.display().to_string()));
+        }
+        Ok(self.connection.lock().await)
+    }
+
+    /// Runs `operation` on the connection, re-running it while SQLite reports
+    /// the database as busy or locked, backing off exponentially with jitter.
+    /// The connection is unlocked while waiting so other tasks can get at it.
+    /// Other errors, and the last busy error once attempts run out, are
+    /// returned unchanged.
+    async fn with_retry<T>(&self, mut operation: impl FnMut(&Connection) -> rusqlite::Result<T>) -> Result<T> {
+        let mut attempt = 1;
+        loop {
+            let result = {
+                let conn = self.lock_connection().await?;
+                operation(&conn)
+            };
+
+            match result {
+                Err(e) if is_busy(&e) && attempt < BUSY_RETRY_ATTEMPTS => {
+                    let base = BUSY_RETRY_BASE_DELAY_MS << (attempt - 1);
+                    let delay = base + jitter_ms(base);
+                    log::warn!("Database busy (attempt {}/{}), retrying in {}ms", attempt, BUSY_RETRY_ATTEMPTS, delay);
+                    tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+                    attempt += 1;
+                }
+                result => return Ok(result?),
+            }
+        }
+    }
+
+    pub fn release_vault_lock(&self) {
+        self.lock.release();
+    }
+
+    /// Checkpoints the journal into the database file, refuses all further
+    /// access and releases the vault lock, then waits up to `timeout` for the
+    /// other handles to be dropped; the connection closes with the last one.
+    pub async fn close(self: Arc<Self>, timeout: std::time::Duration) -> Result<()> {
+        let checkpoint = match self.lock_connection().await {
+            Ok(conn) => {
+                // Nothing may get at the connection once it is released
+                self.mark_unavailable();
+                conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+            }
+            // An unavailable vault's connection is already out of use
+            Err(_) => Ok(()),
+        };
+        self.release_vault_lock();
+
+        // Aborted tasks drop their handles once the runtime next polls them
+        let deadline = tokio::time::Instant::now() + timeout;
+        while Arc::strong_count(&self) > 1 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let others = Arc::strong_count(&self) - 1;
+        if others > 0 {
+            log::warn!("{} handles to the database of {} are still open", others, self.vault_path.display());
+        }
+
+        checkpoint?;
+        Ok(())
+    }
+
+    pub fn set_change_listener(&self, listener: mpsc::UnboundedSender<i64>) {
+        if let Ok(mut current) = self.change_listener.lock() {
+            *current = Some(listener);
+        }
+    }
+
+    pub fn set_max_content_size(&self, max_bytes: usize) {
+        self.max_content_bytes.store(max_bytes, Ordering::Relaxed);
+    }
+
+    pub fn settings(&self) -> VaultSettings {
+        self.settings.read().map(|settings| settings.clone()).unwrap_or_default()
+    }
+
+    /// Validates and saves new settings to .nexus/settings.json, applying the
+    /// ones the database itself uses. Restarting other subsystems is up to the caller.
+    pub fn update_settings(&self, settings: VaultSettings) -> Result<()> {
+        settings::validate_settings(&settings)?;
+        settings::save_settings(&self.vault_path, &settings)?;
+
+        self.set_max_content_size(settings.max_content_bytes);
+        self.with_cache(|cache| cache.set_capacity(settings.object_cache_size));
+        if let Ok(mut current) = self.settings.write() {
+            *current = settings;
+        }
+        Ok(())
+    }
+
+    // Never call while running SQL: the update hook takes the same lock
+    fn with_cache<R>(&self, f: impl FnOnce(&mut ObjectCache) -> R) -> Option<R> {
+        self.cache.lock().ok().map(|mut cache| f(&mut cache))
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.with_cache(|cache| cache.stats()).unwrap_or(CacheStats { capacity: 0, entries: 0, hits: 0, misses: 0 })
+    }
+
+    pub(crate) fn check_content_size(&self, content_json: &str) -> Result<()> {
+        if content_json.len() > self.max_content_bytes.load(Ordering::Relaxed) {
+            return Err(NexusError::ContentTooLarge(content_json.len()));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn notify_changed(&self, object_id: i64) {
+        if let Ok(listener) = self.change_listener.lock() {
+            if let Some(listener) = listener.as_ref() {
+                let _ = listener.send(object_id);
+            }
+        }
+    }
+
     async fn initialize_schema(&self) -> Result<()> {
-        let conn = self.connection.lock().await;
+        let conn = self.lock_connection().await?;
         
         // Enable foreign keys
         conn.execute("PRAGMA foreign_keys = ON", [])?;
@@ -88,6 +296,122 @@ impl Database {
             [],
         )?;
 
+        // Create attachments table - binary blobs deduplicated by content hash
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS attachments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                object_id INTEGER NOT NULL,
+                filename TEXT NOT NULL,
+                mime_type TEXT NOT NULL,
+                content_hash TEXT NOT NULL,
+                blob_path TEXT NOT NULL,
+                size_bytes INTEGER NOT NULL,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                UNIQUE (object_id, content_hash),
+                FOREIGN KEY (object_id) REFERENCES data_objects (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // Create sync_conflicts table - divergent DB/file versions awaiting resolution
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS sync_conflicts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                object_id INTEGER NOT NULL,
+                file_path TEXT NOT NULL,
+                db_content_json TEXT NOT NULL,
+                file_content_json TEXT NOT NULL,
+                detected_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY (object_id) REFERENCES data_objects (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // Last-seen modification time of each scanned vault file
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS file_scan_state (
+                file_path TEXT PRIMARY KEY,
+                mtime_ms INTEGER NOT NULL,
+                scanned_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Create sync_snapshots table - each todos.json as of its last sync, the base of three-way merges
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS sync_snapshots (
+                file_path TEXT PRIMARY KEY,
+                content_json TEXT NOT NULL,
+                synced_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Create schema_extensions table - which schema parses files with a given extension
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS schema_extensions (
+                extension TEXT PRIMARY KEY,
+                schema_id INTEGER NOT NULL,
+                FOREIGN KEY (schema_id) REFERENCES schemas (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // Create object_meta table - free-form key/value metadata outside the schema
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS object_meta (
+                object_id INTEGER NOT NULL,
+                key TEXT NOT NULL,
+                value_json TEXT NOT NULL,
+                PRIMARY KEY (object_id, key),
+                FOREIGN KEY (object_id) REFERENCES data_objects (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // Named, directed relations between objects (e.g. a project and its tasks)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS object_links (
+                source_id INTEGER NOT NULL,
+                target_id INTEGER NOT NULL,
+                relation TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (source_id, target_id, relation),
+                FOREIGN KEY (source_id) REFERENCES data_objects (id) ON DELETE CASCADE,
+                FOREIGN KEY (target_id) REFERENCES data_objects (id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // Files whose sync keeps failing; quarantined ones are skipped by scans
+        // until they change. Rows still counting have no quarantined_at.
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS sync_quarantine (
+                file_path TEXT PRIMARY KEY,
+                failures INTEGER NOT NULL,
+                mtime_ms INTEGER NOT NULL,
+                last_error TEXT NOT NULL,
+                quarantined_at TEXT
+            )",
+            [],
+        )?;
+
+        // Full-text index over object content, kept current by triggers
+        crate::search::create_search_index(&conn)?;
+
+        // Columns added after the initial release
+        add_column_if_missing(&conn, "data_objects", "content_hash", "TEXT")?;
+        backfill_content_hashes(&conn)?;
+        // Stable identity taken from the source data, so the same logical object
+        // is recognised whatever path or row id it ends up with
+        add_column_if_missing(&conn, "data_objects", "external_id", "TEXT")?;
+        // Manual position set by reorder_objects; NULL until first reordered
+        add_column_if_missing(&conn, "data_objects", "order_index", "REAL")?;
+        // Schemas whose objects stay DB-only are left out of write-back
+        add_column_if_missing(&conn, "schemas", "file_backed", "INTEGER NOT NULL DEFAULT 1")?;
+        // What created each object; rows from before this column are "unknown"
+        add_column_if_missing(&conn, "data_objects", "source", "TEXT NOT NULL DEFAULT 'unknown'")?;
+
         // Create indexes for performance
         conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_data_objects_schema_id ON data_objects(schema_id)",
@@ -104,6 +428,26 @@ impl Database {
             [],
         )?;
 
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_data_objects_source ON data_objects(source)",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_attachments_content_hash ON attachments(content_hash)",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_data_objects_external_id ON data_objects(external_id)",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_object_permissions_expires_at ON object_permissions(expires_at)",
+            [],
+        )?;
+
         log::info!("Database schema initialized successfully");
         Ok(())
     }
@@ -119,46 +463,173 @@ impl Database {
                 "created_at": {"type": "string", "format": "date-time"},
                 "updated_at": {"type": ["string", "null"], "format": "date-time"},
                 "due_date": {"type": ["string", "null"], "format": "date-time"},
-                "priority": {"type": ["string", "null"], "enum": ["low", "medium", "high"]},
+                "priority": {"type": ["string", "null"], "enum": ["low", "medium", "high", null]},
                 "tags": {"type": ["array", "null"], "items": {"type": "string"}}
             },
             "required": ["text", "completed", "created_at"]
         });
 
         self.register_schema("core.todo", &todo_schema.to_string()).await?;
+
+        // Register the core Note schema
+        let note_schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "title": {"type": "string"},
+                "body": {"type": "string"},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            },
+            "required": ["title", "body"]
+        });
+
+        self.register_schema("core.note", &note_schema.to_string()).await?;
         
         log::info!("Core schemas registered successfully");
         Ok(())
     }
 
+    /// Registers a schema, or updates it in place if the name is taken. The
+    /// row keeps its id (which objects reference) and created_at; a changed
+    /// definition bumps the version, an unchanged one leaves the row alone.
     pub async fn register_schema(&self, schema_name: &str, definition_json: &str) -> Result<i64> {
-        let conn = self.connection.lock().await;
+        let conn = self.lock_connection().await?;
         
         // Validate JSON schema
-        serde_json::from_str::<serde_json::Value>(definition_json)
+        let definition: serde_json::Value = serde_json::from_str(definition_json)
             .map_err(|e| NexusError::InvalidSchema(e.to_string()))?;
-        
-        let now = Utc::now().to_rfc3339();
-        
-        match conn.execute(
-            "INSERT OR REPLACE INTO schemas (schema_name, definition_json, created_at) 
-             VALUES (?1, ?2, ?3)",
-            params![schema_name, definition_json, now],
-        ) {
-            Ok(_) => {
+
+        let existing: Option<(i64, String, String)> = conn.query_row(
+            "SELECT id, definition_json, version FROM schemas WHERE schema_name = ?1",
+            params![schema_name],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).optional()?;
+
+        match existing {
+            // Compared as JSON so formatting differences don't count as changes
+            Some((schema_id, current, _))
+                if serde_json::from_str::<serde_json::Value>(&current).ok().as_ref() == Some(&definition) =>
+            {
+                Ok(schema_id)
+            }
+            Some((schema_id, _, version)) => {
+                let version = next_schema_version(&version);
+                conn.execute(
+                    "UPDATE schemas SET definition_json = ?1, version = ?2 WHERE id = ?3",
+                    params![definition_json, version, schema_id],
+                )?;
+                log::info!("Schema '{}' updated to version {}", schema_name, version);
+                Ok(schema_id)
+            }
+            None => {
+                let now = Utc::now().to_rfc3339();
+                conn.execute(
+                    "INSERT INTO schemas (schema_name, definition_json, created_at) VALUES (?1, ?2, ?3)",
+                    params![schema_name, definition_json, now],
+                )?;
                 let schema_id = conn.last_insert_rowid();
                 log::info!("Schema '{}' registered with ID: {}", schema_name, schema_id);
                 Ok(schema_id)
             }
-            Err(e) => Err(NexusError::Database(e))
         }
     }
 
+    /// Routes files with the given extensions to `schema_name` during sync.
+    /// An extension already claimed by another schema is taken over.
+    pub async fn register_schema_extensions(&self, schema_name: &str, extensions: &[String]) -> Result<()> {
+        let conn = self.lock_connection().await?;
+
+        let schema_id: i64 = conn.query_row(
+            "SELECT id FROM schemas WHERE schema_name = ?1",
+            params![schema_name],
+            |row| row.get(0),
+        ).optional()?
+        .ok_or_else(|| NexusError::SchemaNotFound(schema_name.to_string()))?;
+
+        for extension in extensions {
+            let extension = normalize_extension(extension);
+            if extension.is_empty() {
+                continue;
+            }
+
+            conn.execute(
+                "INSERT INTO schema_extensions (extension, schema_id) VALUES (?1, ?2)
+                 ON CONFLICT(extension) DO UPDATE SET schema_id = excluded.schema_id",
+                params![extension, schema_id],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether app edits to a schema's objects are written back to their files.
+    /// Objects of a schema that isn't file-backed stay DB-only.
+    pub async fn set_schema_file_backed(&self, schema_name: &str, enabled: bool) -> Result<()> {
+        let conn = self.lock_connection().await?;
+
+        let updated = conn.execute(
+            "UPDATE schemas SET file_backed = ?1 WHERE schema_name = ?2",
+            params![enabled, schema_name],
+        )?;
+        if updated == 0 {
+            return Err(NexusError::SchemaNotFound(schema_name.to_string()));
+        }
+
+        log::info!("Schema {} is now {}", schema_name, if enabled { "file-backed" } else { "DB-only" });
+        Ok(())
+    }
+
+    pub async fn is_schema_file_backed(&self, schema_name: &str) -> Result<bool> {
+        let conn = self.lock_connection().await?;
+
+        conn.query_row(
+            "SELECT file_backed FROM schemas WHERE schema_name = ?1",
+            params![schema_name],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| NexusError::SchemaNotFound(schema_name.to_string()))
+    }
+
+    /// Registers each schema a plugin declares, namespaced by the plugin id.
+    pub async fn register_plugin_schemas(&self, plugin_id: &str, schemas: &[PluginSchema]) -> Result<()> {
+        for schema in schemas {
+            let schema_name = format!("{}.{}", plugin_id, schema.name);
+            self.register_schema(&schema_name, &schema.definition.to_string()).await?;
+            self.register_schema_extensions(&schema_name, &schema.file_extensions).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_schema_for_extension(&self, extension: &str) -> Result<Option<String>> {
+        let conn = self.lock_connection().await?;
+
+        let schema_name = conn.query_row(
+            "SELECT s.schema_name FROM schema_extensions se
+             JOIN schemas s ON se.schema_id = s.id
+             WHERE se.extension = ?1",
+            params![normalize_extension(extension)],
+            |row| row.get(0),
+        ).optional()?;
+
+        Ok(schema_name)
+    }
+
+    pub async fn get_registered_extensions(&self) -> Result<Vec<String>> {
+        let conn = self.lock_connection().await?;
+
+        let mut stmt = conn.prepare("SELECT extension FROM schema_extensions ORDER BY extension")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        let extensions = rows.collect::<rusqlite::Result<_>>()?;
+
+        Ok(extensions)
+    }
+
     pub async fn get_schema_by_name(&self, schema_name: &str) -> Result<Option<Schema>> {
-        let conn = self.connection.lock().await;
+        let conn = self.lock_connection().await?;
         
         let result = conn.query_row(
-            "SELECT id, schema_name, definition_json, version, created_at FROM schemas WHERE schema_name = ?1",
+            "SELECT id, schema_name, definition_json, version, created_at, file_backed FROM schemas WHERE schema_name = ?1",
             params![schema_name],
             |row| {
                 Ok(Schema {
@@ -167,6 +638,7 @@ impl Database {
                     definition_json: row.get(2)?,
                     version: row.get(3)?,
                     created_at: row.get(4)?,
+                    file_backed: row.get(5)?,
                 })
             },
         ).optional()?;
@@ -174,82 +646,367 @@ impl Database {
         Ok(result)
     }
 
-    pub async fn save_object<T: serde::Serialize>(
-        &self,
-        schema_name: &str,
-        content: &T,
-        file_path: Option<&str>,
-        permissions: Option<&Permissions>,
-    ) -> Result<i64> {
-        let conn = self.connection.lock().await;
-        
-        // Get schema ID
-        let schema_id = match conn.query_row(
+    pub async fn list_schemas(&self) -> Result<Vec<Schema>> {
+        let conn = self.lock_connection().await?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, schema_name, definition_json, version, created_at, file_backed FROM schemas ORDER BY schema_name"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Schema {
+                id: Some(row.get(0)?),
+                schema_name: row.get(1)?,
+                definition_json: row.get(2)?,
+                version: row.get(3)?,
+                created_at: row.get(4)?,
+                file_backed: row.get(5)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Removes a schema. Refuses while objects still use it unless
+    /// `delete_objects` is set, in which case they cascade away with it.
+    /// Returns the number of objects deleted.
+    pub async fn unregister_schema(&self, schema_name: &str, delete_objects: bool) -> Result<usize> {
+        if schema_name.starts_with("core.") {
+            return Err(NexusError::PermissionDenied(format!(
+                "Core schema '{}' cannot be removed",
+                schema_name
+            )));
+        }
+
+        let conn = self.lock_connection().await?;
+
+        let schema_id: i64 = conn.query_row(
             "SELECT id FROM schemas WHERE schema_name = ?1",
             params![schema_name],
-            |row| row.get::<_, i64>(0),
-        ).optional()? {
-            Some(id) => id,
-            None => return Err(NexusError::SchemaNotFound(schema_name.to_string())),
+            |row| row.get(0),
+        ).optional()?
+        .ok_or_else(|| NexusError::SchemaNotFound(schema_name.to_string()))?;
+
+        let object_count: usize = conn.query_row(
+            "SELECT COUNT(*) FROM data_objects WHERE schema_id = ?1",
+            params![schema_id],
+            |row| row.get(0),
+        )?;
+
+        if object_count > 0 && !delete_objects {
+            return Err(NexusError::SchemaInUse(schema_name.to_string(), object_count));
+        }
+
+        // Remember which blobs the cascaded objects referenced so orphans can be cleaned up
+        let blob_paths: Vec<(String, String)> = {
+            let mut stmt = conn.prepare(
+                "SELECT a.content_hash, a.blob_path FROM attachments a
+                 JOIN data_objects o ON a.object_id = o.id
+                 WHERE o.schema_id = ?1"
+            )?;
+            let rows = stmt.query_map(params![schema_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
         };
 
-        let now = Utc::now().to_rfc3339();
-        let content_json = serde_json::to_string(content)?;
+        conn.execute("DELETE FROM schemas WHERE id = ?1", params![schema_id])?;
 
-        // Insert data object
-        conn.execute(
-            "INSERT INTO data_objects (schema_id, file_path, updated_at, created_at) 
-             VALUES (?1, ?2, ?3, ?4)",
-            params![schema_id, file_path, now, now],
-        )?;
-        
-        let object_id = conn.last_insert_rowid();
+        self.remove_orphaned_blobs(&conn, &blob_paths)?;
 
-        // Insert content
-        conn.execute(
-            "INSERT INTO object_content (object_id, content_json) VALUES (?1, ?2)",
-            params![object_id, content_json],
-        )?;
+        log::info!("Schema '{}' unregistered ({} objects deleted)", schema_name, object_count);
+        Ok(object_count)
+    }
 
-        // Insert permissions
-        let default_perms = Permissions::default();
-        let perms = permissions.unwrap_or(&default_perms);
-        conn.execute(
-            "INSERT INTO object_permissions 
-             (object_id, share_with_ai, share_with_cloud, read_only, expires_at) 
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![
-                object_id,
-                perms.share_with_ai,
-                perms.share_with_cloud,
-                perms.read_only,
-                perms.expires_at
-            ],
+    pub async fn get_schema_names_with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        let conn = self.lock_connection().await?;
+
+        let mut stmt = conn.prepare(
+            "SELECT schema_name FROM schemas WHERE substr(schema_name, 1, length(?1)) = ?1 ORDER BY schema_name"
         )?;
+        let rows = stmt.query_map(params![prefix], |row| row.get(0))?;
 
-        log::info!("Object saved with ID: {} for schema: {}", object_id, schema_name);
-        Ok(object_id)
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
     }
 
-    pub async fn load_object<T>(&self, object_id: i64) -> Result<AppObject<T>>
-    where
-        T: serde::de::DeserializeOwned,
-    {
-        let conn = self.connection.lock().await;
-        
-        let result = conn.query_row(
-            "SELECT 
-                do.id, s.schema_name, oc.content_json, do.file_path, do.updated_at, do.created_at,
-                op.share_with_ai, op.share_with_cloud, op.read_only, op.expires_at
-             FROM data_objects do
-             JOIN schemas s ON do.schema_id = s.id
-             JOIN object_content oc ON do.id = oc.object_id
-             JOIN object_permissions op ON do.id = op.object_id
-             WHERE do.id = ?1",
-            params![object_id],
-            |row| {
+    /// Checks a document against a registered schema without saving it.
+    pub async fn validate_against_schema(&self, schema_name: &str, document: &serde_json::Value) -> Result<Vec<ValidationError>> {
+        let schema = self
+            .get_schema_by_name(schema_name)
+            .await?
+            .ok_or_else(|| NexusError::SchemaNotFound(schema_name.to_string()))?;
+
+        let definition: serde_json::Value = serde_json::from_str(&schema.definition_json)
+            .map_err(|e| NexusError::InvalidSchema(e.to_string()))?;
+
+        Ok(schema_validation::validate(&definition, document))
+    }
+
+    /// Checks every object against its schema's current definition without
+    /// changing anything, reporting the objects that no longer conform.
+    pub async fn validate_vault(&self) -> Result<VaultValidationReport> {
+        let conn = self.lock_connection().await?;
+
+        // Each schema is parsed once; a broken definition fails all its objects
+        let mut definitions: HashMap<String, std::result::Result<serde_json::Value, String>> = HashMap::new();
+        let mut stmt = conn.prepare("SELECT schema_name, definition_json FROM schemas")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (schema_name, definition_json) = row?;
+            let definition = serde_json::from_str(&definition_json)
+                .map_err(|e| format!("Schema definition is not valid JSON: {}", e));
+            definitions.insert(schema_name, definition);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT do.id, s.schema_name, do.file_path, oc.content_json
+             FROM data_objects do
+             JOIN schemas s ON do.schema_id = s.id
+             JOIN object_content oc ON do.id = oc.object_id
+             ORDER BY do.id",
+        )?;
+        let mut rows = stmt.query([])?;
+
+        let mut report = VaultValidationReport { checked: 0, invalid: Vec::new() };
+        while let Some(row) = rows.next()? {
+            let schema_name: String = row.get(1)?;
+            let content_json: String = row.get(3)?;
+            report.checked += 1;
+
+            let errors = match (&definitions[&schema_name], serde_json::from_str::<serde_json::Value>(&content_json)) {
+                (Err(message), _) => vec![ValidationError { path: String::new(), message: message.clone() }],
+                (_, Err(e)) => vec![ValidationError {
+                    path: String::new(),
+                    message: format!("Content is not valid JSON: {}", e),
+                }],
+                (Ok(definition), Ok(content)) => schema_validation::validate(definition, &content),
+            };
+            if !errors.is_empty() {
+                report.invalid.push(InvalidObject {
+                    object_id: row.get(0)?,
+                    schema_name,
+                    file_path: row.get(2)?,
+                    errors,
+                });
+            }
+        }
+
+        log::info!("Validated {} objects, {} invalid", report.checked, report.invalid.len());
+        Ok(report)
+    }
+
+    #[tracing::instrument(skip_all, fields(schema = schema_name, elapsed_us = tracing::field::Empty, objects = tracing::field::Empty))]
+    pub async fn save_object<T: serde::Serialize>(
+        &self,
+        schema_name: &str,
+        content: &T,
+        file_path: Option<&str>,
+        permissions: Option<&Permissions>,
+        source: Option<&str>,
+    ) -> Result<i64> {
+        let mut timer = SpanTimer::start("save_object");
+        let source = source.unwrap_or(SOURCE_UNKNOWN);
+        let object_id = self.insert_object(schema_name, content, file_path, permissions, source).await?;
+        timer.set_objects(1);
+        Ok(object_id)
+    }
+
+    /// Saves an object identified by `external_id`. If an object with that id
+    /// already exists it is moved to `file_path` and given the new content
+    /// instead of inserting a duplicate, keeping its original source. The
+    /// lookup and the write share one transaction, and the content must
+    /// satisfy `schema_name` either way. Returns the object's id.
+    pub async fn save_object_with_external_id<T: serde::Serialize>(
+        &self,
+        schema_name: &str,
+        content: &T,
+        file_path: Option<&str>,
+        external_id: &str,
+        source: &str,
+    ) -> Result<i64> {
+        let content_json = serde_json::to_string(content)?;
+        self.check_content_size(&content_json)?;
+        let document: serde_json::Value = serde_json::from_str(&content_json)?;
+        let hash = content_hash(&content_json);
+        let now = Utc::now().to_rfc3339();
+
+        // Schema and validation failures come back as the inner error, so
+        // the transaction is dropped and rolled back
+        let saved = self.with_retry(|conn| {
+            let tx = conn.unchecked_transaction()?;
+
+            let (schema_id, definition_json): (i64, String) = match tx.query_row(
+                "SELECT id, definition_json FROM schemas WHERE schema_name = ?1",
+                params![schema_name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ).optional()? {
+                Some(schema) => schema,
+                None => return Ok(Err(NexusError::SchemaNotFound(schema_name.to_string()))),
+            };
+            if let Err(e) = check_content(&definition_json, &document) {
+                return Ok(Err(e));
+            }
+
+            let existing: Option<i64> = tx.query_row(
+                "SELECT id FROM data_objects WHERE external_id = ?1",
+                params![external_id],
+                |row| row.get(0),
+            ).optional()?;
+
+            let saved = match existing {
+                Some(object_id) => {
+                    tx.execute(
+                        "UPDATE data_objects SET file_path = ?1, content_hash = ?2, updated_at = ?3
+                         WHERE id = ?4 AND (file_path IS NOT ?1 OR content_hash IS NOT ?2)",
+                        params![file_path, hash, now, object_id],
+                    )?;
+                    tx.execute(
+                        "UPDATE object_content SET content_json = ?1 WHERE object_id = ?2",
+                        params![content_json, object_id],
+                    )?;
+                    (object_id, true)
+                }
+                None => {
+                    let permissions = Permissions::default();
+                    tx.execute(
+                        "INSERT INTO data_objects (schema_id, file_path, content_hash, external_id, source, updated_at, created_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+                        params![schema_id, file_path, hash, external_id, source, now],
+                    )?;
+                    let object_id = tx.last_insert_rowid();
+                    tx.execute(
+                        "INSERT INTO object_content (object_id, content_json) VALUES (?1, ?2)",
+                        params![object_id, content_json],
+                    )?;
+                    tx.execute(
+                        "INSERT INTO object_permissions
+                         (object_id, share_with_ai, share_with_cloud, read_only, expires_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5)",
+                        params![
+                            object_id,
+                            permissions.share_with_ai,
+                            permissions.share_with_cloud,
+                            permissions.read_only,
+                            permissions.expires_at
+                        ],
+                    )?;
+                    (object_id, false)
+                }
+            };
+
+            tx.commit()?;
+            Ok(Ok(saved))
+        }).await?;
+
+        let (object_id, matched) = saved?;
+        if matched {
+            log::info!("Matched object {} by external id {}", object_id, external_id);
+        } else {
+            log::info!("Object saved with ID: {} for schema: {}", object_id, schema_name);
+        }
+        Ok(object_id)
+    }
+
+    pub async fn object_id_for_external_id(&self, external_id: &str) -> Result<Option<i64>> {
+        let conn = self.lock_connection().await?;
+
+        let object_id = conn.query_row(
+            "SELECT id FROM data_objects WHERE external_id = ?1",
+            params![external_id],
+            |row| row.get(0),
+        ).optional()?;
+
+        Ok(object_id)
+    }
+
+    async fn insert_object<T: serde::Serialize>(
+        &self,
+        schema_name: &str,
+        content: &T,
+        file_path: Option<&str>,
+        permissions: Option<&Permissions>,
+        source: &str,
+    ) -> Result<i64> {
+        // Get schema ID
+        let (schema_id, definition_json) = match self.with_retry(|conn| {
+            conn.query_row(
+                "SELECT id, definition_json FROM schemas WHERE schema_name = ?1",
+                params![schema_name],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+            ).optional()
+        }).await? {
+            Some(schema) => schema,
+            None => return Err(NexusError::SchemaNotFound(schema_name.to_string())),
+        };
+
+        let now = Utc::now().to_rfc3339();
+        let content_json = serde_json::to_string(content)?;
+        self.check_content_size(&content_json)?;
+        check_content(&definition_json, &serde_json::from_str(&content_json)?)?;
+        let hash = content_hash(&content_json);
+        let default_perms = Permissions::default();
+        let perms = permissions.unwrap_or(&default_perms);
+
+        let object_id = self.with_retry(|conn| {
+            let tx = conn.unchecked_transaction()?;
+
+            // Insert data object
+            tx.execute(
+                "INSERT INTO data_objects (schema_id, file_path, content_hash, source, updated_at, created_at) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![schema_id, file_path, hash, source, now, now],
+            )?;
+            
+            let object_id = tx.last_insert_rowid();
+
+            // Insert content
+            tx.execute(
+                "INSERT INTO object_content (object_id, content_json) VALUES (?1, ?2)",
+                params![object_id, content_json],
+            )?;
+
+            // Insert permissions
+            tx.execute(
+                "INSERT INTO object_permissions 
+                 (object_id, share_with_ai, share_with_cloud, read_only, expires_at) 
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    object_id,
+                    perms.share_with_ai,
+                    perms.share_with_cloud,
+                    perms.read_only,
+                    perms.expires_at
+                ],
+            )?;
+
+            tx.commit()?;
+            Ok(object_id)
+        }).await?;
+
+        log::info!("Object saved with ID: {} for schema: {}", object_id, schema_name);
+        Ok(object_id)
+    }
+
+    pub async fn load_object<T>(&self, object_id: i64) -> Result<AppObject<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        if let Some(object) = self.with_cache(|cache| cache.get(object_id)).flatten() {
+            return typed_object(object);
+        }
+
+        let conn = self.lock_connection().await?;
+        
+        let result = conn.query_row(
+            "SELECT 
+                do.id, s.schema_name, oc.content_json, do.file_path, do.updated_at, do.created_at,
+                op.share_with_ai, op.share_with_cloud, op.read_only, op.expires_at, do.source
+             FROM data_objects do
+             JOIN schemas s ON do.schema_id = s.id
+             JOIN object_content oc ON do.id = oc.object_id
+             JOIN object_permissions op ON do.id = op.object_id
+             WHERE do.id = ?1",
+            params![object_id],
+            |row| {
                 let content_json: String = row.get(2)?;
-                let content: T = serde_json::from_str(&content_json)
+                let content: serde_json::Value = serde_json::from_str(&content_json)
                     .map_err(|e| rusqlite::Error::InvalidColumnType(
                         2, 
                         format!("JSON deserialization error: {}", e).into(), 
@@ -269,34 +1026,55 @@ impl Database {
                         read_only: row.get(8)?,
                         expires_at: row.get(9)?,
                     },
+                    source: row.get(10)?,
                 })
             },
         ).optional()?;
 
-        result.ok_or(NexusError::ObjectNotFound(object_id))
+        // Cached while the connection is still held, so no write can slip in between
+        let object = result.ok_or(NexusError::ObjectNotFound(object_id))?;
+        self.with_cache(|cache| cache.insert(&object));
+        drop(conn);
+
+        typed_object(object)
     }
 
     pub async fn load_objects_by_schema<T>(&self, schema_name: &str) -> Result<Vec<AppObject<T>>>
     where
         T: serde::de::DeserializeOwned,
     {
-        let conn = self.connection.lock().await;
+        self.load_objects_by_schema_ordered(schema_name, ObjectOrder::Created).await
+    }
+
+    #[tracing::instrument(name = "load_objects_by_schema", skip_all, fields(schema = schema_name, elapsed_us = tracing::field::Empty, objects = tracing::field::Empty))]
+    pub async fn load_objects_by_schema_ordered<T>(&self, schema_name: &str, order: ObjectOrder) -> Result<Vec<AppObject<T>>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut timer = SpanTimer::start("load_objects_by_schema");
+        if let Some(objects) = self.with_cache(|cache| cache.get_schema(schema_name, order)).flatten() {
+            timer.set_objects(objects.len());
+            return objects.into_iter().map(typed_object).collect();
+        }
+
+        let conn = self.lock_connection().await?;
         
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare(&format!(
             "SELECT 
                 do.id, s.schema_name, oc.content_json, do.file_path, do.updated_at, do.created_at,
-                op.share_with_ai, op.share_with_cloud, op.read_only, op.expires_at
+                op.share_with_ai, op.share_with_cloud, op.read_only, op.expires_at, do.source
              FROM data_objects do
              JOIN schemas s ON do.schema_id = s.id
              JOIN object_content oc ON do.id = oc.object_id
              JOIN object_permissions op ON do.id = op.object_id
              WHERE s.schema_name = ?1
-             ORDER BY do.created_at DESC"
-        )?;
+             ORDER BY {}",
+            order.order_by()
+        ))?;
 
         let rows = stmt.query_map(params![schema_name], |row| {
             let content_json: String = row.get(2)?;
-            let content: T = serde_json::from_str(&content_json)
+            let content: serde_json::Value = serde_json::from_str(&content_json)
                 .map_err(|e| rusqlite::Error::InvalidColumnType(
                     2, 
                     format!("JSON deserialization error: {}", e).into(), 
@@ -316,6 +1094,7 @@ impl Database {
                     read_only: row.get(8)?,
                     expires_at: row.get(9)?,
                 },
+                source: row.get(10)?,
             })
         })?;
 
@@ -323,91 +1102,885 @@ impl Database {
         for row in rows {
             objects.push(row?);
         }
+        self.with_cache(|cache| cache.insert_schema(schema_name, order, &objects));
+        drop(stmt);
+        drop(conn);
+
+        timer.set_objects(objects.len());
+        objects.into_iter().map(typed_object).collect()
+    }
+
+    /// Loads the objects of every registered schema, most recently updated first.
+    pub async fn load_all_objects(&self) -> Result<Vec<AppObject<serde_json::Value>>> {
+        let mut objects = Vec::new();
+        for schema in self.list_schemas().await? {
+            objects.extend(self.load_objects_by_schema::<serde_json::Value>(&schema.schema_name).await?);
+        }
 
+        objects.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
         Ok(objects)
     }
 
-    pub async fn update_object_permissions(
+    /// Loads objects whose `field` timestamp falls within `from..=to` (RFC 3339),
+    /// optionally restricted to one schema.
+    pub async fn load_objects_in_range<T>(
         &self,
-        object_id: i64,
-        permissions: &Permissions,
-    ) -> Result<()> {
-        let conn = self.connection.lock().await;
+        schema_name: Option<&str>,
+        field: DateField,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<AppObject<T>>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        // Stored timestamps are UTC `to_rfc3339` strings, so normalized bounds compare lexically
+        let from = normalize_rfc3339(from)?;
+        let to = normalize_rfc3339(to)?;
+
+        let conn = self.lock_connection().await?;
         
-        let updated = conn.execute(
-            "UPDATE object_permissions 
-             SET share_with_ai = ?1, share_with_cloud = ?2, read_only = ?3, expires_at = ?4
-             WHERE object_id = ?5",
-            params![
-                permissions.share_with_ai,
-                permissions.share_with_cloud,
-                permissions.read_only,
-                permissions.expires_at,
-                object_id
-            ],
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT 
+                do.id, s.schema_name, oc.content_json, do.file_path, do.updated_at, do.created_at,
+                op.share_with_ai, op.share_with_cloud, op.read_only, op.expires_at, do.source
+             FROM data_objects do
+             JOIN schemas s ON do.schema_id = s.id
+             JOIN object_content oc ON do.id = oc.object_id
+             JOIN object_permissions op ON do.id = op.object_id
+             WHERE (?1 IS NULL OR s.schema_name = ?1) AND do.{column} BETWEEN ?2 AND ?3
+             ORDER BY do.{column} ASC",
+            column = field.column()
+        ))?;
 
-        if updated == 0 {
-            return Err(NexusError::ObjectNotFound(object_id));
+        let rows = stmt.query_map(params![schema_name, from, to], |row| {
+            let content_json: String = row.get(2)?;
+            let content: T = serde_json::from_str(&content_json)
+                .map_err(|e| rusqlite::Error::InvalidColumnType(
+                    2, 
+                    format!("JSON deserialization error: {}", e), 
+                    rusqlite::types::Type::Text
+                ))?;
+
+            Ok(AppObject {
+                id: row.get(0)?,
+                schema_name: row.get(1)?,
+                content,
+                file_path: row.get(3)?,
+                updated_at: row.get(4)?,
+                created_at: row.get(5)?,
+                permissions: Permissions {
+                    share_with_ai: row.get(6)?,
+                    share_with_cloud: row.get(7)?,
+                    read_only: row.get(8)?,
+                    expires_at: row.get(9)?,
+                },
+                source: row.get(10)?,
+            })
+        })?;
+
+        let mut objects = Vec::new();
+        for row in rows {
+            objects.push(row?);
         }
 
-        // Update the object's timestamp
-        let now = Utc::now().to_rfc3339();
-        conn.execute(
-            "UPDATE data_objects SET updated_at = ?1 WHERE id = ?2",
-            params![now, object_id],
+        Ok(objects)
+    }
+
+    /// Loads the objects created by `source` (e.g. "sync" or "plugin:<id>"),
+    /// optionally restricted to one schema, oldest first.
+    pub async fn load_objects_by_source<T>(&self, source: &str, schema_name: Option<&str>) -> Result<Vec<AppObject<T>>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let conn = self.lock_connection().await?;
+
+        let mut stmt = conn.prepare(
+            "SELECT
+                do.id, s.schema_name, oc.content_json, do.file_path, do.updated_at, do.created_at,
+                op.share_with_ai, op.share_with_cloud, op.read_only, op.expires_at, do.source
+             FROM data_objects do
+             JOIN schemas s ON do.schema_id = s.id
+             JOIN object_content oc ON do.id = oc.object_id
+             JOIN object_permissions op ON do.id = op.object_id
+             WHERE do.source = ?1 AND (?2 IS NULL OR s.schema_name = ?2)
+             ORDER BY do.created_at, do.id"
         )?;
 
-        log::info!("Permissions updated for object ID: {}", object_id);
-        Ok(())
+        let rows = stmt.query_map(params![source, schema_name], |row| {
+            let content_json: String = row.get(2)?;
+            let content: T = serde_json::from_str(&content_json)
+                .map_err(|e| rusqlite::Error::InvalidColumnType(
+                    2,
+                    format!("JSON deserialization error: {}", e),
+                    rusqlite::types::Type::Text
+                ))?;
+
+            Ok(AppObject {
+                id: row.get(0)?,
+                schema_name: row.get(1)?,
+                content,
+                file_path: row.get(3)?,
+                updated_at: row.get(4)?,
+                created_at: row.get(5)?,
+                permissions: Permissions {
+                    share_with_ai: row.get(6)?,
+                    share_with_cloud: row.get(7)?,
+                    read_only: row.get(8)?,
+                    expires_at: row.get(9)?,
+                },
+                source: row.get(10)?,
+            })
+        })?;
+
+        let objects = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(objects)
     }
 
-    pub async fn delete_object(&self, object_id: i64) -> Result<()> {
-        let conn = self.connection.lock().await;
-        
-        let deleted = conn.execute(
-            "DELETE FROM data_objects WHERE id = ?1",
-            params![object_id],
-        )?;
+    pub async fn update_object<T: serde::Serialize>(&self, object_id: i64, content: &T) -> Result<()> {
+        let content_json = serde_json::to_string(content)?;
+        self.check_content_size(&content_json)?;
+        let hash = content_hash(&content_json);
+        let now = Utc::now().to_rfc3339();
 
-        if deleted == 0 {
+        let updated = self.with_retry(|conn| {
+            let tx = conn.unchecked_transaction()?;
+
+            let updated = tx.execute(
+                "UPDATE object_content SET content_json = ?1 WHERE object_id = ?2",
+                params![content_json, object_id],
+            )?;
+
+            tx.execute(
+                "UPDATE data_objects SET content_hash = ?1, updated_at = ?2 WHERE id = ?3",
+                params![hash, now, object_id],
+            )?;
+
+            tx.commit()?;
+            Ok(updated)
+        }).await?;
+
+        if updated == 0 {
             return Err(NexusError::ObjectNotFound(object_id));
         }
 
-        log::info!("Object deleted with ID: {}", object_id);
+        self.notify_changed(object_id);
+        log::info!("Content updated for object ID: {}", object_id);
         Ok(())
     }
 
-    pub async fn update_object_from_file_path(&self, file_path: &str) -> Result<Option<i64>> {
-        let conn = self.connection.lock().await;
-        
-        // Find the object by file path
-        let object_id: Option<i64> = conn.query_row(
+    /// Returns the id and stored content hash of the object backed by `file_path`.
+    pub async fn find_object_by_path(&self, file_path: &str) -> Result<Option<(i64, Option<String>)>> {
+        let conn = self.lock_connection().await?;
+
+        let result = conn.query_row(
+            "SELECT id, content_hash FROM data_objects WHERE file_path = ?1",
+            params![file_path],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional()?;
+
+        Ok(result)
+    }
+
+    pub async fn object_id_for_path(&self, file_path: &str) -> Result<Option<i64>> {
+        let conn = self.lock_connection().await?;
+
+        let object_id = conn.query_row(
             "SELECT id FROM data_objects WHERE file_path = ?1",
             params![file_path],
             |row| row.get(0),
         ).optional()?;
 
-        if let Some(id) = object_id {
-            let now = Utc::now().to_rfc3339();
-            conn.execute(
-                "UPDATE data_objects SET updated_at = ?1 WHERE id = ?2",
-                params![now, id],
-            )?;
-            log::info!("Updated timestamp for object at path: {}", file_path);
-        }
-
         Ok(object_id)
     }
 
-    pub async fn get_sync_info(&self) -> Result<(usize, String)> {
-        let conn = self.connection.lock().await;
-        
-        let count: usize = conn.query_row(
-            "SELECT COUNT(*) FROM data_objects",
-            [],
-            |row| row.get::<_, i64>(0).map(|n| n as usize),
-        )?;
+    pub async fn object_exists_for_path(&self, file_path: &str) -> Result<bool> {
+        Ok(self.object_id_for_path(file_path).await?.is_some())
+    }
+
+    pub async fn find_object_by_hash(&self, schema_name: &str, hash: &str) -> Result<Option<i64>> {
+        let conn = self.lock_connection().await?;
+
+        let result = conn.query_row(
+            "SELECT do.id FROM data_objects do
+             JOIN schemas s ON do.schema_id = s.id
+             WHERE s.schema_name = ?1 AND do.content_hash = ?2
+             LIMIT 1",
+            params![schema_name, hash],
+            |row| row.get(0),
+        ).optional()?;
+
+        Ok(result)
+    }
+
+    /// Moves an object's backing file into `new_relative_dir` (relative to the
+    /// vault root) and repoints `file_path`. Objects without a file only have
+    /// their DB target updated. Returns the new file path.
+    pub async fn move_object(&self, object_id: i64, new_relative_dir: &str) -> Result<String> {
+        let conn = self.lock_connection().await?;
+
+        let file_path: Option<String> = conn.query_row(
+            "SELECT file_path FROM data_objects WHERE id = ?1",
+            params![object_id],
+            |row| row.get(0),
+        ).optional()?
+        .ok_or(NexusError::ObjectNotFound(object_id))?;
+
+        let destination_dir = vault_fs::resolve_vault_path(&self.vault_path, new_relative_dir)?;
+
+        let (source, file_name) = match &file_path {
+            Some(path) if sync_service::split_entry_path(path).1.is_some() => {
+                return Err(NexusError::InvalidInput(format!(
+                    "Object {} is an entry in a shared file and cannot be moved on its own",
+                    object_id
+                )));
+            }
+            Some(path) => {
+                let source = PathBuf::from(path);
+                let file_name = source.file_name().map(|n| n.to_os_string()).ok_or_else(|| {
+                    NexusError::InvalidInput(format!("'{}' has no file name", path))
+                })?;
+                (Some(source), file_name)
+            }
+            None => (None, format!("{}.json", object_id).into()),
+        };
+
+        let destination = destination_dir.join(file_name);
+        let destination_str = destination.to_string_lossy().to_string();
+
+        if source.as_deref() != Some(destination.as_path()) && destination.exists() {
+            return Err(NexusError::InvalidInput(format!("'{}' already exists", destination_str)));
+        }
+
+        let moved_on_disk = match &source {
+            Some(source) if source.exists() && source != &destination => {
+                std::fs::create_dir_all(&destination_dir)?;
+                std::fs::rename(source, &destination)?;
+                true
+            }
+            _ => false,
+        };
+
+        let now = Utc::now().to_rfc3339();
+        let update = (|| -> rusqlite::Result<()> {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "UPDATE data_objects SET file_path = ?1, updated_at = ?2 WHERE id = ?3",
+                params![destination_str, now, object_id],
+            )?;
+            tx.commit()
+        })();
+
+        if let Err(e) = update {
+            // Put the file back so disk and DB still agree
+            if let Some(source) = source.filter(|_| moved_on_disk) {
+                if let Err(rollback) = std::fs::rename(&destination, &source) {
+                    log::error!("Failed to move {:?} back after DB error: {}", destination, rollback);
+                }
+            }
+            return Err(e.into());
+        }
+
+        log::info!("Object {} moved to {}", object_id, destination_str);
+        Ok(destination_str)
+    }
+
+    /// Copies an object into a new one with default permissions. With
+    /// `new_relative_path` the copy is also written to that vault file.
+    pub async fn clone_object(
+        &self,
+        object_id: i64,
+        new_relative_path: Option<&str>,
+        options: &CloneOptions,
+    ) -> Result<AppObject<serde_json::Value>> {
+        let source: AppObject<serde_json::Value> = self.load_object(object_id).await?;
+
+        let mut content = source.content;
+        if !options.copy_tags {
+            if let Some(tags) = content.get_mut("tags").filter(|tags| tags.is_array()) {
+                *tags = serde_json::Value::Array(Vec::new());
+            }
+        }
+
+        let file_path = match new_relative_path {
+            Some(relative_path) => {
+                let destination = vault_fs::resolve_vault_path(&self.vault_path, relative_path)?;
+                if destination.exists() {
+                    return Err(NexusError::InvalidInput(format!("'{}' already exists", relative_path)));
+                }
+                let rendered = sync_service::render_file(&destination, &content)?;
+                let written = vault_fs::write_vault_file(&self.vault_path, relative_path, rendered.as_bytes())?;
+                Some(written.to_string_lossy().to_string())
+            }
+            None => None,
+        };
+
+        let inserted = {
+            let conn = self.lock_connection().await?;
+            self.insert_clone(&conn, &source.schema_name, object_id, &content, file_path.as_deref(), options)
+        };
+        let clone_id = match inserted {
+            Ok(clone_id) => clone_id,
+            Err(e) => {
+                // Don't leave a file behind that no object points to
+                if let Some(path) = &file_path {
+                    let _ = std::fs::remove_file(path);
+                }
+                return Err(e);
+            }
+        };
+
+        log::info!("Object {} cloned as {}", object_id, clone_id);
+        self.load_object(clone_id).await
+    }
+
+    /// The clone's rows and whatever `options` copies from `object_id`, in one
+    /// transaction so a failure leaves no partial clone.
+    fn insert_clone(
+        &self,
+        conn: &Connection,
+        schema_name: &str,
+        object_id: i64,
+        content: &serde_json::Value,
+        file_path: Option<&str>,
+        options: &CloneOptions,
+    ) -> Result<i64> {
+        let tx = conn.unchecked_transaction()?;
+        let clone_id = self.insert_object_on(&tx, schema_name, content, &Permissions::default(), SOURCE_UI)?;
+        if file_path.is_some() {
+            tx.execute("UPDATE data_objects SET file_path = ?1 WHERE id = ?2", params![file_path, clone_id])?;
+        }
+
+        if options.copy_meta {
+            tx.execute(
+                "INSERT INTO object_meta (object_id, key, value_json)
+                 SELECT ?1, key, value_json FROM object_meta WHERE object_id = ?2",
+                params![clone_id, object_id],
+            )?;
+        }
+        if options.copy_links {
+            let now = Utc::now().to_rfc3339();
+            tx.execute(
+                "INSERT INTO object_links (source_id, target_id, relation, created_at)
+                 SELECT ?1, target_id, relation, ?3 FROM object_links WHERE source_id = ?2",
+                params![clone_id, object_id, now],
+            )?;
+            // Self-links were copied as outgoing links above
+            tx.execute(
+                "INSERT INTO object_links (source_id, target_id, relation, created_at)
+                 SELECT source_id, ?1, relation, ?3 FROM object_links WHERE target_id = ?2 AND source_id != ?2",
+                params![clone_id, object_id, now],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(clone_id)
+    }
+
+    /// Objects that exist only in the database, oldest first. They never reach
+    /// disk, so external tools don't see them and a rebuild loses them.
+    pub async fn list_fileless_objects(&self) -> Result<Vec<AppObject<serde_json::Value>>> {
+        let object_ids: Vec<i64> = {
+            let conn = self.lock_connection().await?;
+            let mut stmt = conn.prepare("SELECT id FROM data_objects WHERE file_path IS NULL ORDER BY created_at, id")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        let mut objects = Vec::with_capacity(object_ids.len());
+        for object_id in object_ids {
+            objects.push(self.load_object(object_id).await?);
+        }
+        Ok(objects)
+    }
+
+    /// Writes a database-only object to a new vault file and makes that file
+    /// its backing file, so later edits on either side sync as usual.
+    pub async fn materialize_object(&self, object_id: i64, relative_path: &str) -> Result<AppObject<serde_json::Value>> {
+        let object: AppObject<serde_json::Value> = self.load_object(object_id).await?;
+        if let Some(file_path) = &object.file_path {
+            return Err(NexusError::InvalidInput(format!(
+                "Object {} is already backed by '{}'",
+                object_id, file_path
+            )));
+        }
+
+        let destination = vault_fs::resolve_vault_path(&self.vault_path, relative_path)?;
+        if destination.file_name().and_then(|n| n.to_str()) == Some("todos.json") {
+            return Err(NexusError::InvalidInput("todos.json holds a todo list, not a single object".to_string()));
+        }
+        if destination.exists() {
+            return Err(NexusError::InvalidInput(format!("'{}' already exists", relative_path)));
+        }
+
+        // Written the way write-back would write it, so the sync reads it back unchanged
+        let serialized = if destination.extension().and_then(|s| s.to_str()) == Some("md") {
+            let note: Note = serde_json::from_value(object.content.clone())?;
+            notes::render_note(&note)
+        } else {
+            FileFormat::from_path(&destination).unwrap_or(FileFormat::Json).render(&object.content)?
+        };
+        let written = vault_fs::write_vault_file(&self.vault_path, relative_path, serialized.as_bytes())?;
+        let file_path = written.to_string_lossy().to_string();
+
+        let now = Utc::now().to_rfc3339();
+        let updated = {
+            let conn = self.lock_connection().await?;
+            conn.execute(
+                "UPDATE data_objects SET file_path = ?1, updated_at = ?2 WHERE id = ?3 AND file_path IS NULL",
+                params![file_path, now, object_id],
+            )
+        };
+
+        match updated {
+            Ok(1) => {}
+            // Don't leave a file behind that no object points to
+            Ok(_) => {
+                let _ = std::fs::remove_file(&written);
+                return Err(NexusError::InvalidInput(format!("Object {} was given a file concurrently", object_id)));
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&written);
+                return Err(e.into());
+            }
+        }
+
+        log::info!("Object {} materialized to {}", object_id, file_path);
+        self.load_object(object_id).await
+    }
+
+    pub async fn update_object_permissions(
+        &self,
+        object_id: i64,
+        permissions: &Permissions,
+    ) -> Result<()> {
+        let conn = self.lock_connection().await?;
+        
+        let updated = conn.execute(
+            "UPDATE object_permissions 
+             SET share_with_ai = ?1, share_with_cloud = ?2, read_only = ?3, expires_at = ?4
+             WHERE object_id = ?5",
+            params![
+                permissions.share_with_ai,
+                permissions.share_with_cloud,
+                permissions.read_only,
+                permissions.expires_at,
+                object_id
+            ],
+        )?;
+
+        if updated == 0 {
+            return Err(NexusError::ObjectNotFound(object_id));
+        }
+
+        // Update the object's timestamp
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE data_objects SET updated_at = ?1 WHERE id = ?2",
+            params![now, object_id],
+        )?;
+
+        log::info!("Permissions updated for object ID: {}", object_id);
+        Ok(())
+    }
+
+    /// Applies the same permissions to many objects in one transaction.
+    pub async fn update_permissions_bulk(
+        &self,
+        object_ids: &[i64],
+        permissions: &Permissions,
+    ) -> Result<BulkUpdateResult> {
+        let now = Utc::now().to_rfc3339();
+
+        let result = self.with_retry(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            let mut result = BulkUpdateResult { updated: 0, missing_ids: Vec::new() };
+
+            for &object_id in object_ids {
+                let updated = tx.execute(
+                    "UPDATE object_permissions 
+                     SET share_with_ai = ?1, share_with_cloud = ?2, read_only = ?3, expires_at = ?4
+                     WHERE object_id = ?5",
+                    params![
+                        permissions.share_with_ai,
+                        permissions.share_with_cloud,
+                        permissions.read_only,
+                        permissions.expires_at,
+                        object_id
+                    ],
+                )?;
+
+                if updated == 0 {
+                    result.missing_ids.push(object_id);
+                    continue;
+                }
+
+                tx.execute(
+                    "UPDATE data_objects SET updated_at = ?1 WHERE id = ?2",
+                    params![now, object_id],
+                )?;
+                result.updated += 1;
+            }
+
+            tx.commit()?;
+            Ok(result)
+        }).await?;
+
+        log::info!("Permissions updated for {} objects ({} missing)", result.updated, result.missing_ids.len());
+        Ok(result)
+    }
+
+    pub async fn set_meta(&self, object_id: i64, key: &str, value: &serde_json::Value) -> Result<()> {
+        let conn = self.lock_connection().await?;
+
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM data_objects WHERE id = ?1)",
+            params![object_id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(NexusError::ObjectNotFound(object_id));
+        }
+
+        conn.execute(
+            "INSERT INTO object_meta (object_id, key, value_json) VALUES (?1, ?2, ?3)
+             ON CONFLICT(object_id, key) DO UPDATE SET value_json = excluded.value_json",
+            params![object_id, key, serde_json::to_string(value)?],
+        )?;
+
+        Ok(())
+    }
+
+    pub async fn get_meta(&self, object_id: i64, key: &str) -> Result<Option<serde_json::Value>> {
+        let conn = self.lock_connection().await?;
+
+        let value_json: Option<String> = conn.query_row(
+            "SELECT value_json FROM object_meta WHERE object_id = ?1 AND key = ?2",
+            params![object_id, key],
+            |row| row.get(0),
+        ).optional()?;
+
+        Ok(value_json.map(|v| serde_json::from_str(&v)).transpose()?)
+    }
+
+    pub async fn get_all_meta(&self, object_id: i64) -> Result<HashMap<String, serde_json::Value>> {
+        let conn = self.lock_connection().await?;
+
+        let mut stmt = conn.prepare(
+            "SELECT key, value_json FROM object_meta WHERE object_id = ?1"
+        )?;
+        let rows = stmt.query_map(params![object_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut meta = HashMap::new();
+        for row in rows {
+            let (key, value_json) = row?;
+            meta.insert(key, serde_json::from_str(&value_json)?);
+        }
+
+        Ok(meta)
+    }
+
+    pub async fn delete_object(&self, object_id: i64) -> Result<()> {
+        // Remember which blobs this object referenced so orphans can be cleaned up
+        let (deleted, blob_paths) = self.with_retry(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            let blob_paths: Vec<(String, String)> = {
+                let mut stmt = tx.prepare(
+                    "SELECT content_hash, blob_path FROM attachments WHERE object_id = ?1"
+                )?;
+                let rows = stmt.query_map(params![object_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<rusqlite::Result<_>>()?
+            };
+
+            let deleted = tx.execute(
+                "DELETE FROM data_objects WHERE id = ?1",
+                params![object_id],
+            )?;
+
+            tx.commit()?;
+            Ok((deleted, blob_paths))
+        }).await?;
+
+        if deleted == 0 {
+            return Err(NexusError::ObjectNotFound(object_id));
+        }
+
+        let conn = self.lock_connection().await?;
+        self.remove_orphaned_blobs(&conn, &blob_paths)?;
+
+        log::info!("Object deleted with ID: {}", object_id);
+        Ok(())
+    }
+
+    pub(crate) fn remove_orphaned_blobs(&self, conn: &Connection, blobs: &[(String, String)]) -> Result<()> {
+        for (content_hash, blob_path) in blobs {
+            let still_referenced: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM attachments WHERE content_hash = ?1)",
+                params![content_hash],
+                |row| row.get(0),
+            )?;
+
+            if !still_referenced {
+                let path = self.vault_path.join(blob_path);
+                if let Err(e) = std::fs::remove_file(&path) {
+                    if e.kind() != std::io::ErrorKind::NotFound {
+                        log::warn!("Failed to remove orphaned attachment blob {:?}: {}", path, e);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn add_attachment(&self, object_id: i64, filename: &str, bytes: &[u8]) -> Result<Attachment> {
+        let conn = self.lock_connection().await?;
+
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM data_objects WHERE id = ?1)",
+            params![object_id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(NexusError::ObjectNotFound(object_id));
+        }
+
+        let content_hash = hex::encode(Sha256::digest(bytes));
+        let blob_path = format!(".nexus/attachments/{}", content_hash);
+
+        // Identical bytes share a single blob on disk
+        let absolute_blob_path = self.vault_path.join(&blob_path);
+        if !absolute_blob_path.exists() {
+            if let Some(parent) = absolute_blob_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            vault_fs::atomic_write(&absolute_blob_path, bytes)?;
+        }
+
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT OR IGNORE INTO attachments 
+             (object_id, filename, mime_type, content_hash, blob_path, size_bytes, created_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                object_id,
+                filename,
+                guess_mime_type(filename),
+                content_hash,
+                blob_path,
+                bytes.len() as i64,
+                now
+            ],
+        )?;
+
+        let attachment = conn.query_row(
+            "SELECT id, object_id, filename, mime_type, content_hash, blob_path, size_bytes, created_at
+             FROM attachments WHERE object_id = ?1 AND content_hash = ?2",
+            params![object_id, content_hash],
+            attachment_from_row,
+        )?;
+
+        log::info!("Attachment '{}' added to object ID: {}", filename, object_id);
+        Ok(attachment)
+    }
+
+    pub async fn get_attachments(&self, object_id: i64) -> Result<Vec<Attachment>> {
+        let conn = self.lock_connection().await?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, object_id, filename, mime_type, content_hash, blob_path, size_bytes, created_at
+             FROM attachments WHERE object_id = ?1
+             ORDER BY created_at ASC"
+        )?;
+
+        let rows = stmt.query_map(params![object_id], attachment_from_row)?;
+
+        let mut attachments = Vec::new();
+        for row in rows {
+            attachments.push(row?);
+        }
+
+        Ok(attachments)
+    }
+
+    /// Stores new content for the object backed by `file_path`, skipping the
+    /// write entirely when the content hash hasn't changed.
+    pub async fn update_object_from_file_path(&self, file_path: &str, content_json: &str) -> Result<Option<i64>> {
+        self.check_content_size(content_json)?;
+        let conn = self.lock_connection().await?;
+        
+        // Find the object by file path
+        let existing: Option<(i64, Option<String>)> = conn.query_row(
+            "SELECT id, content_hash FROM data_objects WHERE file_path = ?1",
+            params![file_path],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional()?;
+
+        let (id, stored_hash) = match existing {
+            Some(existing) => existing,
+            None => return Ok(None),
+        };
+
+        let hash = content_hash(content_json);
+        if stored_hash.as_deref() == Some(hash.as_str()) {
+            log::debug!("Content unchanged for object at path: {}", file_path);
+            return Ok(Some(id));
+        }
+
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE object_content SET content_json = ?1 WHERE object_id = ?2",
+            params![content_json, id],
+        )?;
+        conn.execute(
+            "UPDATE data_objects SET content_hash = ?1, updated_at = ?2 WHERE id = ?3",
+            params![hash, now, id],
+        )?;
+        log::info!("Updated content for object at path: {}", file_path);
+
+        Ok(Some(id))
+    }
+
+    pub async fn touch_object_by_path(&self, file_path: &str) -> Result<Option<i64>> {
+        let conn = self.lock_connection().await?;
+        
+        // Find the object by file path
+        let object_id: Option<i64> = conn.query_row(
+            "SELECT id FROM data_objects WHERE file_path = ?1",
+            params![file_path],
+            |row| row.get(0),
+        ).optional()?;
+
+        if let Some(id) = object_id {
+            let now = Utc::now().to_rfc3339();
+            conn.execute(
+                "UPDATE data_objects SET updated_at = ?1 WHERE id = ?2",
+                params![now, id],
+            )?;
+            log::info!("Updated timestamp for object at path: {}", file_path);
+        }
+
+        Ok(object_id)
+    }
+
+    pub async fn get_file_mtime(&self, file_path: &str) -> Result<Option<i64>> {
+        let conn = self.lock_connection().await?;
+
+        let mtime = conn.query_row(
+            "SELECT mtime_ms FROM file_scan_state WHERE file_path = ?1",
+            params![file_path],
+            |row| row.get(0),
+        ).optional()?;
+
+        Ok(mtime)
+    }
+
+    pub async fn set_file_mtime(&self, file_path: &str, mtime_ms: i64) -> Result<()> {
+        let conn = self.lock_connection().await?;
+        let now = Utc::now().to_rfc3339();
+
+        conn.execute(
+            "INSERT INTO file_scan_state (file_path, mtime_ms, scanned_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(file_path) DO UPDATE SET mtime_ms = excluded.mtime_ms, scanned_at = excluded.scanned_at",
+            params![file_path, mtime_ms, now],
+        )?;
+
+        Ok(())
+    }
+
+    /// Records a DB/file divergence unless the same one is already pending.
+    pub async fn record_conflict(
+        &self,
+        object_id: i64,
+        file_path: &str,
+        db_content_json: &str,
+        file_content_json: &str,
+    ) -> Result<Option<i64>> {
+        let conn = self.lock_connection().await?;
+
+        let already_recorded: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sync_conflicts WHERE object_id = ?1 AND file_content_json = ?2)",
+            params![object_id, file_content_json],
+            |row| row.get(0),
+        )?;
+        if already_recorded {
+            return Ok(None);
+        }
+
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO sync_conflicts (object_id, file_path, db_content_json, file_content_json, detected_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![object_id, file_path, db_content_json, file_content_json, now],
+        )?;
+
+        let conflict_id = conn.last_insert_rowid();
+        log::warn!("Sync conflict {} recorded for object ID: {} ({})", conflict_id, object_id, file_path);
+        Ok(Some(conflict_id))
+    }
+
+    pub async fn get_conflicts(&self) -> Result<Vec<SyncConflict>> {
+        let conn = self.lock_connection().await?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, object_id, file_path, db_content_json, file_content_json, detected_at
+             FROM sync_conflicts ORDER BY detected_at ASC"
+        )?;
+
+        let rows = stmt.query_map([], conflict_from_row)?;
+
+        let mut conflicts = Vec::new();
+        for row in rows {
+            conflicts.push(row?);
+        }
+
+        Ok(conflicts)
+    }
+
+    pub async fn get_conflict(&self, conflict_id: i64) -> Result<Option<SyncConflict>> {
+        let conn = self.lock_connection().await?;
+
+        let conflict = conn.query_row(
+            "SELECT id, object_id, file_path, db_content_json, file_content_json, detected_at
+             FROM sync_conflicts WHERE id = ?1",
+            params![conflict_id],
+            conflict_from_row,
+        ).optional()?;
+
+        Ok(conflict)
+    }
+
+    pub async fn delete_conflict(&self, conflict_id: i64) -> Result<()> {
+        let conn = self.lock_connection().await?;
+        conn.execute("DELETE FROM sync_conflicts WHERE id = ?1", params![conflict_id])?;
+        Ok(())
+    }
+
+    /// Copies the live database to `destination` using SQLite's online backup API.
+    pub async fn backup_to(&self, destination: &Path) -> Result<()> {
+        let conn = self.lock_connection().await?;
+        conn.backup(rusqlite::DatabaseName::Main, destination, None)?;
+        Ok(())
+    }
+
+    /// Overwrites the live database with the contents of the database at `source`.
+    pub async fn restore_from(&self, source: &Path) -> Result<()> {
+        let mut conn = self.lock_connection().await?;
+        conn.restore(rusqlite::DatabaseName::Main, source, None::<fn(rusqlite::backup::Progress)>)?;
+        // Restoring replaces pages wholesale, without firing the update hook
+        self.with_cache(|cache| cache.clear());
+        Ok(())
+    }
+
+    pub async fn get_sync_info(&self) -> Result<(usize, String)> {
+        let conn = self.lock_connection().await?;
+        
+        let count: usize = conn.query_row(
+            "SELECT COUNT(*) FROM data_objects",
+            [],
+            |row| row.get::<_, i64>(0).map(|n| n as usize),
+        )?;
 
         let last_updated: String = conn.query_row(
             "SELECT MAX(updated_at) FROM data_objects",
@@ -418,3 +1991,765 @@ impl Database {
         Ok((count, last_updated))
     }
 }
+
+// Objects are read and cached as JSON values, then converted to the caller's type
+fn typed_object<T: serde::de::DeserializeOwned>(object: AppObject<serde_json::Value>) -> Result<AppObject<T>> {
+    Ok(AppObject {
+        id: object.id,
+        schema_name: object.schema_name,
+        content: serde_json::from_value(object.content)?,
+        file_path: object.file_path,
+        updated_at: object.updated_at,
+        created_at: object.created_at,
+        permissions: object.permissions,
+        source: object.source,
+    })
+}
+
+pub(crate) fn db_path(vault_path: &Path) -> PathBuf {
+    vault_path.join(".nexus").join("vault.sqlite")
+}
+
+const BUSY_RETRY_ATTEMPTS: u32 = 5;
+const BUSY_RETRY_BASE_DELAY_MS: u64 = 20;
+
+fn is_busy(error: &rusqlite::Error) -> bool {
+    matches!(
+        error,
+        rusqlite::Error::SqliteFailure(e, _)
+            if matches!(e.code, rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+fn jitter_ms(max: u64) -> u64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or(0);
+    nanos % (max + 1)
+}
+
+fn normalize_rfc3339(timestamp: &str) -> Result<String> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|dt| dt.with_timezone(&Utc).to_rfc3339())
+        .map_err(|e| NexusError::InvalidInput(format!("'{}' is not an RFC 3339 timestamp: {}", timestamp, e)))
+}
+
+// "1.2.0" becomes "1.3.0"; versions that aren't major.minor.patch get ".1" appended
+fn next_schema_version(version: &str) -> String {
+    let parts: Vec<u64> = version.split('.').filter_map(|part| part.parse().ok()).collect();
+    match parts.as_slice() {
+        [major, minor, _] if version.split('.').count() == 3 => format!("{}.{}.0", major, minor + 1),
+        _ => format!("{}.1", version),
+    }
+}
+
+/// Fails with the first way `document` breaks the schema defined by
+/// `definition_json`; the check every save path runs.
+pub(crate) fn check_content(definition_json: &str, document: &serde_json::Value) -> Result<()> {
+    let definition: serde_json::Value = serde_json::from_str(definition_json)
+        .map_err(|e| NexusError::InvalidSchema(e.to_string()))?;
+    match schema_validation::validate(&definition, document).first() {
+        Some(error) => Err(NexusError::InvalidInput(format!(
+            "Content is invalid at '{}': {}",
+            error.path, error.message
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// SHA-256 of an object's content in canonical (key-sorted) JSON form, so the
+/// same logical content hashes identically regardless of how it was serialized.
+pub fn content_hash(content_json: &str) -> String {
+    let canonical = serde_json::from_str::<serde_json::Value>(content_json)
+        .map(|value| canonicalize_json(&value).to_string())
+        .unwrap_or_else(|_| content_json.to_string());
+    hex::encode(Sha256::digest(canonical.as_bytes()))
+}
+
+fn canonicalize_json(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            serde_json::Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonicalize_json(value)))
+                    .collect(),
+            )
+        }
+        serde_json::Value::Array(items) => serde_json::Value::Array(items.iter().map(canonicalize_json).collect()),
+        other => other.clone(),
+    }
+}
+
+// Extensions are stored lowercase without the leading dot
+fn normalize_extension(extension: &str) -> String {
+    extension.trim().trim_start_matches('.').to_lowercase()
+}
+
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    if !columns.iter().any(|c| c == column) {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+        log::info!("Added column {}.{}", table, column);
+    }
+
+    Ok(())
+}
+
+fn backfill_content_hashes(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT do.id, oc.content_json FROM data_objects do
+         JOIN object_content oc ON do.id = oc.object_id
+         WHERE do.content_hash IS NULL"
+    )?;
+    let missing = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (object_id, content_json) in missing {
+        conn.execute(
+            "UPDATE data_objects SET content_hash = ?1 WHERE id = ?2",
+            params![content_hash(&content_json), object_id],
+        )?;
+    }
+
+    Ok(())
+}
+
+fn conflict_from_row(row: &rusqlite::Row) -> rusqlite::Result<SyncConflict> {
+    let db_content: String = row.get(3)?;
+    let file_content: String = row.get(4)?;
+
+    Ok(SyncConflict {
+        id: row.get(0)?,
+        object_id: row.get(1)?,
+        file_path: row.get(2)?,
+        db_content: serde_json::from_str(&db_content).unwrap_or(serde_json::Value::String(db_content)),
+        file_content: serde_json::from_str(&file_content).unwrap_or(serde_json::Value::String(file_content)),
+        detected_at: row.get(5)?,
+    })
+}
+
+fn attachment_from_row(row: &rusqlite::Row) -> rusqlite::Result<Attachment> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        object_id: row.get(1)?,
+        filename: row.get(2)?,
+        mime_type: row.get(3)?,
+        content_hash: row.get(4)?,
+        blob_path: row.get(5)?,
+        size_bytes: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+fn guess_mime_type(filename: &str) -> &'static str {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "json" => "application/json",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AppObject, CloneOptions, DateField, ObjectLink, ObjectOp, ObjectRef, Todo};
+    use crate::test_support::{temp_database, todo_path, vault, TWO_TODOS};
+
+    #[tokio::test]
+    async fn attachments_are_deduplicated_by_hash() {
+        let (vault, database) = temp_database("db_attachment_dedup").await;
+        let todo = Todo::new("with picture".into());
+        let first = database.save_object("core.todo", &todo, None, None, None).await.unwrap();
+        let second = database.save_object("core.todo", &todo, None, None, None).await.unwrap();
+
+        let attachment = database.add_attachment(first, "p.png", b"abc").await.unwrap();
+        database.add_attachment(first, "p.png", b"abc").await.unwrap();
+        database.add_attachment(second, "q.png", b"abc").await.unwrap();
+
+        assert_eq!(attachment.mime_type, "image/png");
+        assert_eq!(database.get_attachments(first).await.unwrap().len(), 1);
+        let blobs = std::fs::read_dir(vault.join(".nexus/attachments")).unwrap().count();
+        assert_eq!(blobs, 1);
+    }
+
+    #[tokio::test]
+    async fn deleting_objects_cleans_up_orphaned_blobs() {
+        let (vault, database) = temp_database("db_attachment_cascade").await;
+        let todo = Todo::new("with picture".into());
+        let first = database.save_object("core.todo", &todo, None, None, None).await.unwrap();
+        let second = database.save_object("core.todo", &todo, None, None, None).await.unwrap();
+        let attachment = database.add_attachment(first, "p.png", b"abc").await.unwrap();
+        database.add_attachment(second, "q.png", b"abc").await.unwrap();
+
+        database.delete_object(first).await.unwrap();
+        assert!(database.get_attachments(first).await.unwrap().is_empty());
+        assert!(vault.join(&attachment.blob_path).exists(), "blob still referenced");
+
+        database.delete_object(second).await.unwrap();
+        assert!(!vault.join(&attachment.blob_path).exists());
+    }
+
+    #[tokio::test]
+    async fn unchanged_file_content_is_not_rewritten() {
+        let (vault, database, sync_service) = vault("db_noop_touch", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        let path = todo_path(&vault, 1);
+        let (id, _) = database.find_object_by_path(&path).await.unwrap().unwrap();
+        let before: AppObject<Todo> = database.load_object(id).await.unwrap();
+        let json = serde_json::to_string(&before.content).unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(database.update_object_from_file_path(&path, &json).await.unwrap(), Some(id));
+        let after: AppObject<Todo> = database.load_object(id).await.unwrap();
+        assert_eq!(before.updated_at, after.updated_at);
+
+        let changed = json.replace(r#""a""#, r#""z""#);
+        database.update_object_from_file_path(&path, &changed).await.unwrap();
+        let after: AppObject<Todo> = database.load_object(id).await.unwrap();
+        assert_ne!(before.updated_at, after.updated_at);
+        assert_eq!(after.content.text, "z");
+    }
+
+    #[tokio::test]
+    async fn core_schemas_cannot_be_unregistered() {
+        let (_vault, database) = temp_database("db_unregister_core").await;
+
+        assert!(matches!(
+            database.unregister_schema("core.todo", true).await,
+            Err(NexusError::PermissionDenied(_))
+        ));
+        assert!(database.get_schema_by_name("core.todo").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn unregistering_cascades_only_when_asked() {
+        let (_vault, database) = temp_database("db_unregister_cascade").await;
+        database.register_schema("plugin.note", "{}").await.unwrap();
+        let id = database.save_object("plugin.note", &Todo::new("x".into()), None, None, None).await.unwrap();
+
+        assert!(matches!(
+            database.unregister_schema("plugin.note", false).await,
+            Err(NexusError::SchemaInUse(_, 1))
+        ));
+        assert!(database.load_object::<Todo>(id).await.is_ok());
+
+        assert_eq!(database.unregister_schema("plugin.note", true).await.unwrap(), 1);
+        assert!(database.load_object::<Todo>(id).await.is_err());
+        assert!(database.get_schema_names_with_prefix("plugin.").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn date_ranges_include_their_bounds() {
+        let (_vault, database) = temp_database("db_date_ranges").await;
+        database.save_object("core.todo", &Todo::new("x".into()), None, None, None).await.unwrap();
+        let all: Vec<AppObject<serde_json::Value>> = database.load_objects_by_schema("core.todo").await.unwrap();
+        let created = all[0].created_at.clone();
+
+        let hits: Vec<AppObject<serde_json::Value>> = database
+            .load_objects_in_range(Some("core.todo"), DateField::CreatedAt, &created, &created)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+
+        let hits: Vec<AppObject<serde_json::Value>> = database
+            .load_objects_in_range(None, DateField::UpdatedAt, "2000-01-01T00:00:00Z", "2000-01-02T00:00:00+02:00")
+            .await
+            .unwrap();
+        assert!(hits.is_empty());
+    }
+
+    #[tokio::test]
+    async fn date_ranges_reject_non_rfc3339_bounds() {
+        let (_vault, database) = temp_database("db_date_ranges_invalid").await;
+
+        let result = database
+            .load_objects_in_range::<serde_json::Value>(None, DateField::UpdatedAt, "yesterday", "2000-01-02T00:00:00Z")
+            .await;
+        assert!(matches!(result, Err(NexusError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn busy_writes_are_retried_until_the_lock_clears() {
+        let (vault, database) = temp_database("db_busy_retry").await;
+        // Surface contention as SQLITE_BUSY straight away instead of in SQLite's own busy handler
+        database.connection.lock().await.busy_timeout(std::time::Duration::from_millis(1)).unwrap();
+
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let db_file = db_path(&vault);
+        let blocker = std::thread::spawn(move || {
+            let conn = Connection::open(db_file).unwrap();
+            conn.execute_batch("BEGIN EXCLUSIVE").unwrap();
+            locked_tx.send(()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            conn.execute_batch("COMMIT").unwrap();
+        });
+        locked_rx.recv().unwrap();
+
+        let started = std::time::Instant::now();
+        let id = database.save_object("core.todo", &Todo::new("x".into()), None, None, None).await.unwrap();
+
+        assert!(started.elapsed() >= std::time::Duration::from_millis(50));
+        assert!(database.load_object::<Todo>(id).await.is_ok());
+        blocker.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn objects_move_into_vault_subfolders() {
+        let (vault, database) = temp_database("db_move_object").await;
+        let file = vault.join("note.json");
+        std::fs::write(&file, "{}").unwrap();
+        let id = database
+            .save_object("core.todo", &Todo::new("a".into()), Some(&file.to_string_lossy()), None, None)
+            .await
+            .unwrap();
+
+        let moved = database.move_object(id, "Notes/2024").await.unwrap();
+
+        assert!(!file.exists());
+        assert!(Path::new(&moved).is_file());
+        assert!(moved.ends_with("Notes/2024/note.json"));
+        let (found, _) = database.find_object_by_path(&moved).await.unwrap().unwrap();
+        assert_eq!(found, id);
+
+        let fileless = database.save_object("core.todo", &Todo::new("b".into()), None, None, None).await.unwrap();
+        let target = database.move_object(fileless, "Notes").await.unwrap();
+        assert!(target.ends_with(&format!("Notes/{}.json", fileless)));
+    }
+
+    #[tokio::test]
+    async fn moves_outside_the_vault_are_refused() {
+        let (vault, database) = temp_database("db_move_escape").await;
+        let file = vault.join("note.json");
+        std::fs::write(&file, "{}").unwrap();
+        let id = database
+            .save_object("core.todo", &Todo::new("a".into()), Some(&file.to_string_lossy()), None, None)
+            .await
+            .unwrap();
+
+        assert!(matches!(database.move_object(id, "../outside").await, Err(NexusError::PermissionDenied(_))));
+        assert!(file.is_file());
+        assert_eq!(database.find_object_by_path(&file.to_string_lossy()).await.unwrap().unwrap().0, id);
+    }
+
+    fn valid_todo() -> serde_json::Value {
+        serde_json::json!({
+            "text": "a", "completed": false, "priority": null, "tags": [],
+            "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z"
+        })
+    }
+
+    #[tokio::test]
+    async fn documents_are_validated_against_registered_schemas() {
+        let (_vault, database) = temp_database("db_validate_schema").await;
+
+        assert!(database.validate_against_schema("core.todo", &valid_todo()).await.unwrap().is_empty());
+
+        let mut wrong_type = valid_todo();
+        wrong_type["text"] = serde_json::json!(5);
+        let errors = database.validate_against_schema("core.todo", &wrong_type).await.unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "/text");
+
+        let mut missing = valid_todo();
+        missing.as_object_mut().unwrap().remove("text");
+        let errors = database.validate_against_schema("core.todo", &missing).await.unwrap();
+        assert_eq!(errors[0].path, "");
+        assert!(errors[0].message.contains("text"));
+    }
+
+    #[tokio::test]
+    async fn validating_against_an_unknown_schema_fails() {
+        let (_vault, database) = temp_database("db_validate_unknown").await;
+
+        assert!(matches!(
+            database.validate_against_schema("nope.x", &valid_todo()).await,
+            Err(NexusError::SchemaNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn object_meta_is_overwritten_and_deleted_with_its_object() {
+        let (_vault, database) = temp_database("db_object_meta").await;
+        let id = database.save_object("core.todo", &Todo::new("x".into()), None, None, None).await.unwrap();
+
+        database.set_meta(id, "color", &serde_json::json!("red")).await.unwrap();
+        database.set_meta(id, "color", &serde_json::json!("blue")).await.unwrap();
+        database.set_meta(id, "pinned", &serde_json::json!(true)).await.unwrap();
+
+        assert_eq!(database.get_meta(id, "color").await.unwrap(), Some(serde_json::json!("blue")));
+        assert_eq!(database.get_meta(id, "missing").await.unwrap(), None);
+        assert_eq!(database.get_all_meta(id).await.unwrap().len(), 2);
+        assert!(database.set_meta(9999, "x", &serde_json::json!(1)).await.is_err());
+
+        database.delete_object(id).await.unwrap();
+        assert!(database.get_all_meta(id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn bulk_permission_updates_report_missing_ids() {
+        let (_vault, database) = temp_database("db_bulk_permissions").await;
+        let first = database.save_object("core.todo", &Todo::new("a".into()), None, None, None).await.unwrap();
+        let second = database.save_object("core.todo", &Todo::new("b".into()), None, None, None).await.unwrap();
+        let permissions = Permissions { share_with_ai: true, ..Default::default() };
+
+        let result = database.update_permissions_bulk(&[first, 9999, second, -1], &permissions).await.unwrap();
+
+        assert_eq!(result.updated, 2);
+        assert_eq!(result.missing_ids, vec![9999, -1]);
+        assert!(database.load_object::<Todo>(second).await.unwrap().permissions.share_with_ai);
+    }
+
+    #[tokio::test]
+    async fn clones_get_fresh_ids_and_only_what_was_asked_for() {
+        let (vault, database) = temp_database("db_clone_object").await;
+        let mut template = Todo::new("template".into());
+        template.tags = Some(vec!["x".into()]);
+        let shared = Permissions { share_with_ai: true, ..Default::default() };
+        let id = database.save_object("core.todo", &template, None, Some(&shared), None).await.unwrap();
+        database.set_meta(id, "color", &serde_json::json!("red")).await.unwrap();
+
+        let plain = database.clone_object(id, None, &CloneOptions::default()).await.unwrap();
+        assert_ne!(plain.id, id);
+        assert_eq!(plain.content["text"], "template");
+        assert_eq!(plain.content["tags"], serde_json::json!([]));
+        assert!(!plain.permissions.share_with_ai);
+        assert!(database.get_all_meta(plain.id).await.unwrap().is_empty());
+
+        let options = CloneOptions { copy_tags: true, copy_meta: true, ..Default::default() };
+        let full = database.clone_object(id, Some("Notes/copy.json"), &options).await.unwrap();
+        assert_eq!(full.content["tags"], serde_json::json!(["x"]));
+        assert_eq!(database.get_meta(full.id, "color").await.unwrap(), Some(serde_json::json!("red")));
+        assert!(vault.join("Notes/copy.json").exists());
+        assert!(database.clone_object(id, Some("Notes/copy.json"), &CloneOptions::default()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn clones_are_written_in_the_destination_format() {
+        let (vault, database) = temp_database("db_clone_format").await;
+        let note = database.add_note("Plan", "Step one", vec![]).await.unwrap();
+        let todo = database.save_object("core.todo", &Todo::new("ship".into()), None, None, None).await.unwrap();
+
+        database.clone_object(note.id, Some("Archive/plan.md"), &CloneOptions::default()).await.unwrap();
+        let markdown = std::fs::read_to_string(vault.join("Archive/plan.md")).unwrap();
+        assert!(markdown.contains("Step one") && !markdown.trim_start().starts_with('{'), "{}", markdown);
+
+        database.clone_object(todo, Some("Tasks/ship.yaml"), &CloneOptions::default()).await.unwrap();
+        let yaml = std::fs::read_to_string(vault.join("Tasks/ship.yaml")).unwrap();
+        assert_eq!(FileFormat::Yaml.parse(&yaml).unwrap()["text"], "ship");
+        assert!(!yaml.trim_start().starts_with('{'), "{}", yaml);
+    }
+
+    #[tokio::test]
+    async fn clones_take_the_source_links_when_asked() {
+        let (_vault, database) = temp_database("db_clone_links").await;
+        let source = database.save_object("core.todo", &Todo::new("source".into()), None, None, None).await.unwrap();
+        let parent = database.save_object("core.todo", &Todo::new("parent".into()), None, None, None).await.unwrap();
+        let blocked = database.save_object("core.todo", &Todo::new("blocked".into()), None, None, None).await.unwrap();
+        database
+            .batch_operations(&[
+                ObjectOp::Link { source: ObjectRef::Existing(parent), target: ObjectRef::Existing(source), relation: "parent".into() },
+                ObjectOp::Link { source: ObjectRef::Existing(source), target: ObjectRef::Existing(blocked), relation: "blocks".into() },
+            ])
+            .await
+            .unwrap();
+        let pairs = |links: Vec<ObjectLink>| {
+            links.into_iter().map(|link| (link.source_id, link.target_id, link.relation)).collect::<Vec<_>>()
+        };
+
+        let plain = database.clone_object(source, None, &CloneOptions::default()).await.unwrap();
+        assert!(database.get_object_links(plain.id).await.unwrap().is_empty());
+
+        let options = CloneOptions { copy_links: true, ..Default::default() };
+        let linked = database.clone_object(source, None, &options).await.unwrap();
+        let mut links = pairs(database.get_object_links(linked.id).await.unwrap());
+        links.sort();
+        let mut expected = vec![(parent, linked.id, "parent".to_string()), (linked.id, blocked, "blocks".to_string())];
+        expected.sort();
+        assert_eq!(links, expected);
+        assert_eq!(database.get_object_links(source).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn writes_over_the_content_limit_are_rejected() {
+        let (_vault, database) = temp_database("db_content_limit").await;
+        database.register_schema("x.blob", "{}").await.unwrap();
+        database.set_max_content_size(100);
+
+        let overhead = serde_json::to_string(&serde_json::json!({"t": ""})).unwrap().len();
+        let at_limit = serde_json::json!({"t": "a".repeat(100 - overhead)});
+        let over_limit = serde_json::json!({"t": "a".repeat(101 - overhead)});
+
+        let id = database.save_object("x.blob", &at_limit, None, None, None).await.unwrap();
+        assert!(matches!(
+            database.save_object("x.blob", &over_limit, None, None, None).await,
+            Err(NexusError::ContentTooLarge(101))
+        ));
+        assert!(matches!(database.update_object(id, &over_limit).await, Err(NexusError::ContentTooLarge(101))));
+        assert_eq!(database.load_object::<serde_json::Value>(id).await.unwrap().content, at_limit);
+    }
+
+    #[tokio::test]
+    async fn file_updates_over_the_content_limit_are_rejected() {
+        let (vault, database) = temp_database("db_file_content_limit").await;
+        database.register_schema("x.blob", "{}").await.unwrap();
+        let path = vault.join("blob.json").to_string_lossy().to_string();
+        let id = database.save_object("x.blob", &serde_json::json!({"t": ""}), Some(&path), None, None).await.unwrap();
+        database.set_max_content_size(100);
+
+        let over_limit = serde_json::json!({"t": "a".repeat(100)}).to_string();
+        assert!(matches!(
+            database.update_object_from_file_path(&path, &over_limit).await,
+            Err(NexusError::ContentTooLarge(size)) if size == over_limit.len()
+        ));
+        assert_eq!(database.load_object::<serde_json::Value>(id).await.unwrap().content, serde_json::json!({"t": ""}));
+    }
+
+    #[tokio::test]
+    async fn objects_are_found_by_path_and_hash() {
+        let (vault, database) = temp_database("db_path_lookup").await;
+        let path = todo_path(&vault, 1);
+        let todo = Todo::new("a".to_string());
+        let id = database.save_object("core.todo", &todo, Some(&path), None, None).await.unwrap();
+
+        assert_eq!(database.object_id_for_path(&path).await.unwrap(), Some(id));
+        assert!(database.object_exists_for_path(&path).await.unwrap());
+        assert_eq!(database.object_id_for_path(&todo_path(&vault, 2)).await.unwrap(), None);
+        assert!(!database.object_exists_for_path(&todo_path(&vault, 2)).await.unwrap());
+
+        let hash = content_hash(&serde_json::to_string(&todo).unwrap());
+        assert_eq!(database.find_object_by_hash("core.todo", &hash).await.unwrap(), Some(id));
+        assert_eq!(database.find_object_by_hash("core.note", &hash).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn all_objects_span_schemas_newest_first() {
+        let (_vault, database) = temp_database("db_all_objects").await;
+        database.register_schema("x.blob", "{}").await.unwrap();
+        let todo = database.save_object("core.todo", &Todo::new("a".to_string()), None, None, None).await.unwrap();
+        database.save_object("x.blob", &serde_json::json!({"n": 1}), None, None, None).await.unwrap();
+        database.update_object(todo, &Todo::new("b".to_string())).await.unwrap();
+
+        let all = database.load_all_objects().await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].id, todo);
+        assert!(all.windows(2).all(|pair| pair[0].updated_at >= pair[1].updated_at));
+        assert!(all.iter().any(|object| object.schema_name == "x.blob"));
+    }
+
+    #[tokio::test]
+    async fn external_ids_match_instead_of_duplicating() {
+        let (vault, database) = temp_database("db_external_id").await;
+        let first_path = todo_path(&vault, 1);
+        let moved_path = todo_path(&vault, 7);
+
+        let id = database
+            .save_object_with_external_id("core.todo", &Todo::new("a".to_string()), Some(&first_path), "ext-1", SOURCE_SYNC)
+            .await
+            .unwrap();
+        let matched = database
+            .save_object_with_external_id("core.todo", &Todo::new("b".to_string()), Some(&moved_path), "ext-1", SOURCE_UI)
+            .await
+            .unwrap();
+
+        assert_eq!(matched, id);
+        assert_eq!(database.object_id_for_external_id("ext-1").await.unwrap(), Some(id));
+        let object: AppObject<Todo> = database.load_object(id).await.unwrap();
+        assert_eq!(object.content.text, "b");
+        assert_eq!(object.file_path.as_deref(), Some(moved_path.as_str()));
+        assert_eq!(object.source, SOURCE_SYNC);
+        assert_eq!(database.load_objects_by_schema::<Todo>("core.todo").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn external_id_saves_are_validated_on_insert_and_update() {
+        let (_vault, database) = temp_database("db_external_id_invalid").await;
+        let invalid = serde_json::json!({"text": 3, "completed": false});
+
+        assert!(matches!(
+            database.save_object_with_external_id("core.todo", &invalid, None, "ext-1", SOURCE_SYNC).await,
+            Err(NexusError::InvalidInput(_))
+        ));
+        assert_eq!(database.object_id_for_external_id("ext-1").await.unwrap(), None);
+
+        let id = database.save_object_with_external_id("core.todo", &valid_todo(), None, "ext-1", SOURCE_SYNC).await.unwrap();
+        assert!(matches!(
+            database.save_object_with_external_id("core.todo", &invalid, None, "ext-1", SOURCE_SYNC).await,
+            Err(NexusError::InvalidInput(_))
+        ));
+        assert_eq!(database.load_object::<serde_json::Value>(id).await.unwrap().content, valid_todo());
+        assert!(matches!(
+            database.save_object_with_external_id("nope.x", &valid_todo(), None, "ext-2", SOURCE_SYNC).await,
+            Err(NexusError::SchemaNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn saves_and_batch_creates_reject_content_breaking_the_schema() {
+        let (_vault, database) = temp_database("db_save_invalid").await;
+        let invalid = serde_json::json!({"text": "a"});
+
+        let saved = database.save_object("core.todo", &invalid, None, None, None).await;
+        assert!(matches!(saved, Err(NexusError::InvalidInput(ref message)) if message.contains("completed")));
+        let created = database
+            .batch_operations(&[
+                ObjectOp::Create { schema_name: "core.todo".into(), content: valid_todo(), permissions: None },
+                ObjectOp::Create { schema_name: "core.todo".into(), content: invalid, permissions: None },
+            ])
+            .await;
+        assert!(matches!(created, Err(NexusError::InvalidInput(_))));
+        assert!(database.load_objects_by_schema::<serde_json::Value>("core.todo").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn fileless_objects_can_be_given_a_file() {
+        let (vault, database, sync_service) = vault("db_fileless", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        assert!(database.list_fileless_objects().await.unwrap().is_empty());
+
+        let id = database.save_object("core.todo", &Todo::new("db only".into()), None, None, None).await.unwrap();
+        let fileless = database.list_fileless_objects().await.unwrap();
+        assert_eq!(fileless.iter().map(|o| o.id).collect::<Vec<_>>(), vec![id]);
+
+        let materialized = database.materialize_object(id, "Todo/extra.json").await.unwrap();
+        let path = vault.join("Todo/extra.json");
+        assert_eq!(materialized.file_path, Some(path.to_string_lossy().to_string()));
+        let written: Todo = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written.text, "db only");
+        assert!(database.list_fileless_objects().await.unwrap().is_empty());
+
+        // Syncing the new file back changes nothing
+        let before = database.load_object::<serde_json::Value>(id).await.unwrap().updated_at;
+        sync_service.force_sync(Some(&["Todo/extra.json".to_string()])).await.unwrap();
+        assert_eq!(database.load_object::<serde_json::Value>(id).await.unwrap().updated_at, before);
+    }
+
+    #[tokio::test]
+    async fn materializing_rejects_todo_lists_escapes_and_backed_objects() {
+        let (_vault, database) = temp_database("db_materialize_invalid").await;
+        let id = database.save_object("core.todo", &Todo::new("db only".into()), None, None, None).await.unwrap();
+
+        assert!(matches!(database.materialize_object(id, "Todo/todos.json").await, Err(NexusError::InvalidInput(_))));
+        assert!(database.materialize_object(id, "../outside.json").await.is_err());
+        database.materialize_object(id, "Todo/extra.json").await.unwrap();
+        assert!(matches!(database.materialize_object(id, "Todo/again.json").await, Err(NexusError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn reopening_keeps_the_core_schemas_as_they_were() {
+        let (vault, database) = temp_database("db_schema_reregistration").await;
+        let before = database.get_schema_by_name("core.todo").await.unwrap().unwrap();
+        let id = database.save_object("core.todo", &Todo::new("keep".into()), None, None, None).await.unwrap();
+        drop(database);
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        let database = Database::new(&vault).await.unwrap();
+        let after = database.get_schema_by_name("core.todo").await.unwrap().unwrap();
+        assert_eq!((after.id, &after.created_at, &after.version), (before.id, &before.created_at, &before.version));
+        assert!(database.load_object::<Todo>(id).await.is_ok());
+
+        // The same definition formatted differently is not a change
+        let definition: serde_json::Value = serde_json::from_str(&before.definition_json).unwrap();
+        let pretty = serde_json::to_string_pretty(&definition).unwrap();
+        assert_eq!(database.register_schema("core.todo", &pretty).await.unwrap(), before.id.unwrap());
+        assert_eq!(database.get_schema_by_name("core.todo").await.unwrap().unwrap().version, before.version);
+    }
+
+    #[tokio::test]
+    async fn changed_schemas_are_updated_in_place() {
+        let (_vault, database) = temp_database("db_schema_update").await;
+        let id = database.register_schema("test.schema", r#"{"type":"object"}"#).await.unwrap();
+        assert_eq!(database.register_schema("test.schema", r#"{"type": "object"}"#).await.unwrap(), id);
+
+        assert_eq!(database.register_schema("test.schema", r#"{"type":"object","required":["a"]}"#).await.unwrap(), id);
+        let schema = database.get_schema_by_name("test.schema").await.unwrap().unwrap();
+        assert_eq!(schema.version, "1.1.0");
+        assert!(schema.definition_json.contains("required"));
+    }
+
+    #[tokio::test]
+    async fn validating_the_vault_reports_without_changing_anything() {
+        let (_vault, database, sync_service) = vault("db_validate_vault", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        // The bad object predates the schema tightening, as saves are checked
+        database.register_schema("ext.p", r#"{"type":"object"}"#).await.unwrap();
+        let bad = database.save_object("ext.p", &serde_json::json!({ "name": 5 }), None, None, None).await.unwrap();
+        let definition = r#"{"type":"object","required":["name"],"properties":{"name":{"type":"string"}}}"#;
+        database.register_schema("ext.p", definition).await.unwrap();
+        database.save_object("ext.p", &serde_json::json!({ "name": "ok" }), None, None, None).await.unwrap();
+        database.add_note("n", "b", vec![]).await.unwrap();
+        let timestamps = |objects: Vec<AppObject<serde_json::Value>>| {
+            objects.into_iter().map(|object| object.updated_at).collect::<Vec<_>>()
+        };
+        let before = timestamps(database.load_all_objects().await.unwrap());
+
+        let report = database.validate_vault().await.unwrap();
+        assert_eq!(report.checked, 5);
+        assert_eq!(report.invalid.len(), 1);
+        assert_eq!((report.invalid[0].object_id, report.invalid[0].schema_name.as_str()), (bad, "ext.p"));
+        assert_eq!(report.invalid[0].errors[0].path, "/name");
+        assert_eq!(timestamps(database.load_all_objects().await.unwrap()), before);
+    }
+
+    #[tokio::test]
+    async fn the_file_backed_flag_survives_re_registration() {
+        let (_vault, database) = temp_database("db_file_backed").await;
+        database.register_schema("ext.c", r#"{"type":"object"}"#).await.unwrap();
+        assert!(database.is_schema_file_backed("ext.c").await.unwrap());
+        assert!(matches!(database.set_schema_file_backed("ext.nope", false).await, Err(NexusError::SchemaNotFound(_))));
+
+        database.set_schema_file_backed("ext.c", false).await.unwrap();
+        assert!(!database.get_schema_by_name("ext.c").await.unwrap().unwrap().file_backed);
+        database.register_schema("ext.c", r#"{"type":"object","title":"c"}"#).await.unwrap();
+        assert!(!database.is_schema_file_backed("ext.c").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn objects_remember_where_they_came_from() {
+        let (vault, database, sync_service) = vault("db_object_source", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        let ui = database.save_object("core.todo", &Todo::new("ui".into()), None, None, Some(SOURCE_UI)).await.unwrap();
+        let plugin = database.save_object("core.todo", &Todo::new("p".into()), None, None, Some("plugin:habits")).await.unwrap();
+        let unknown = database.save_object("core.todo", &Todo::new("u".into()), None, None, None).await.unwrap();
+        assert_eq!(database.load_object::<serde_json::Value>(unknown).await.unwrap().source, SOURCE_UNKNOWN);
+        assert_eq!(database.load_object::<serde_json::Value>(plugin).await.unwrap().source, "plugin:habits");
+
+        let synced = database.load_objects_by_source::<Todo>(SOURCE_SYNC, Some("core.todo")).await.unwrap();
+        assert_eq!(synced.iter().map(|t| t.content.text.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+        let from_ui = database.load_objects_by_source::<serde_json::Value>(SOURCE_UI, None).await.unwrap();
+        assert_eq!(from_ui.iter().map(|o| o.id).collect::<Vec<_>>(), vec![ui]);
+        assert!(database.load_objects_by_source::<serde_json::Value>(SOURCE_SYNC, Some("core.note")).await.unwrap().is_empty());
+
+        // The source survives reopening the vault
+        drop(sync_service);
+        database.release_vault_lock();
+        drop(database);
+        let database = Database::new(&vault).await.unwrap();
+        let todos = database.load_objects_by_schema::<serde_json::Value>("core.todo").await.unwrap();
+        let mut sources: Vec<&str> = todos.iter().map(|o| o.source.as_str()).collect();
+        sources.sort();
+        assert_eq!(sources, vec!["plugin:habits", "sync", "sync", "ui", "unknown"]);
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::database::Database;
+use crate::error::{NexusError, Result};
+use crate::models::DeduplicationReport;
+
+impl Database {
+    /// Collapses objects of `schema_name` with identical content that come
+    /// from the same file (or from no file), keeping the oldest of each group.
+    /// Links, metadata and attachments of the removed duplicates move to the
+    /// kept object first; tags are content, so they are already identical.
+    /// With `dry_run` nothing changes and the report shows what would.
+    pub async fn deduplicate_schema(&self, schema_name: &str, dry_run: bool) -> Result<DeduplicationReport> {
+        let conn = self.lock_connection().await?;
+
+        let schema_id: i64 = conn
+            .query_row("SELECT id FROM schemas WHERE schema_name = ?1", params![schema_name], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| NexusError::SchemaNotFound(schema_name.to_string()))?;
+
+        // Oldest first, so the first object seen in a group is the one kept
+        let objects: Vec<(i64, Option<String>, Option<String>)> = {
+            let mut stmt = conn.prepare(
+                "SELECT id, file_path, content_hash FROM data_objects
+                 WHERE schema_id = ?1
+                 ORDER BY created_at, id",
+            )?;
+            let rows = stmt.query_map(params![schema_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        let mut kept: HashMap<(String, Option<String>), i64> = HashMap::new();
+        let mut duplicates: Vec<(i64, i64)> = Vec::new();
+        for (object_id, file_path, hash) in objects {
+            let Some(hash) = hash else { continue };
+            let key = (hash, file_path.as_deref().map(source_file).map(str::to_string));
+            match kept.get(&key) {
+                Some(&keep_id) => duplicates.push((object_id, keep_id)),
+                None => {
+                    kept.insert(key, object_id);
+                }
+            }
+        }
+
+        let mut groups: Vec<i64> = duplicates.iter().map(|(_, keep_id)| *keep_id).collect();
+        groups.sort_unstable();
+        groups.dedup();
+        let report = DeduplicationReport {
+            duplicate_groups: groups.len(),
+            merged: duplicates.len(),
+            removed_ids: duplicates.iter().map(|(duplicate_id, _)| *duplicate_id).collect(),
+            dry_run,
+        };
+
+        if dry_run || duplicates.is_empty() {
+            return Ok(report);
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        let mut blobs: Vec<(String, String)> = Vec::new();
+        for (duplicate_id, keep_id) in &duplicates {
+            blobs.extend(move_object_rows(&tx, *duplicate_id, *keep_id)?);
+            tx.execute("DELETE FROM data_objects WHERE id = ?1", params![duplicate_id])?;
+        }
+        tx.commit()?;
+
+        self.remove_orphaned_blobs(&conn, &blobs)?;
+
+        log::info!(
+            "Merged {} duplicate objects of {} into {} objects",
+            report.merged, schema_name, report.duplicate_groups
+        );
+        Ok(report)
+    }
+}
+
+/// Moves the links, metadata and attachments of `from_id` onto `to_id`. The
+/// target's own rows win where both have one, and links between the two are
+/// left to be deleted with `from_id`. Returns the attachment blobs `from_id`
+/// still references, for cleanup once it is gone.
+pub(crate) fn move_object_rows(conn: &Connection, from_id: i64, to_id: i64) -> Result<Vec<(String, String)>> {
+    conn.execute(
+        "UPDATE OR IGNORE object_links SET source_id = ?2 WHERE source_id = ?1 AND target_id != ?2",
+        params![from_id, to_id],
+    )?;
+    conn.execute(
+        "UPDATE OR IGNORE object_links SET target_id = ?2 WHERE target_id = ?1 AND source_id != ?2",
+        params![from_id, to_id],
+    )?;
+    conn.execute(
+        "UPDATE OR IGNORE object_meta SET object_id = ?2 WHERE object_id = ?1",
+        params![from_id, to_id],
+    )?;
+    conn.execute(
+        "UPDATE OR IGNORE attachments SET object_id = ?2 WHERE object_id = ?1",
+        params![from_id, to_id],
+    )?;
+
+    let mut stmt = conn.prepare("SELECT content_hash, blob_path FROM attachments WHERE object_id = ?1")?;
+    let rows = stmt.query_map(params![from_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+// The file an object was read from; todos are tracked per entry as
+// "<todos.json>#<entry>", and duplicate entries of one file still collapse
+fn source_file(file_path: &str) -> &str {
+    match file_path.rsplit_once('#') {
+        Some((path, _)) if path.ends_with("todos.json") => path,
+        _ => file_path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ObjectOp, ObjectRef, Todo};
+    use crate::test_support::temp_database;
+
+    #[tokio::test]
+    async fn duplicates_collapse_into_the_oldest_keeping_links_and_meta() {
+        let (_vault, database) = temp_database("dedup").await;
+        let same = serde_json::json!({ "text": "dup", "completed": false, "created_at": "2024-01-01T00:00:00Z" });
+        let keep = database.save_object("core.todo", &same, None, None, None).await.unwrap();
+        let first_duplicate = database.save_object("core.todo", &same, None, None, None).await.unwrap();
+        let second_duplicate = database.save_object("core.todo", &same, None, None, None).await.unwrap();
+        let other = database.save_object("core.todo", &Todo::new("other".into()), None, None, None).await.unwrap();
+        database
+            .batch_operations(&[
+                ObjectOp::Link { source: ObjectRef::Existing(first_duplicate), target: ObjectRef::Existing(other), relation: "blocks".into() },
+                ObjectOp::Link { source: ObjectRef::Existing(other), target: ObjectRef::Existing(second_duplicate), relation: "parent".into() },
+            ])
+            .await
+            .unwrap();
+        database.set_meta(second_duplicate, "color", &serde_json::json!("red")).await.unwrap();
+
+        let dry_run = database.deduplicate_schema("core.todo", true).await.unwrap();
+        assert!(dry_run.dry_run);
+        assert_eq!((dry_run.merged, dry_run.duplicate_groups), (2, 1));
+        assert_eq!(dry_run.removed_ids, vec![first_duplicate, second_duplicate]);
+        assert_eq!(database.load_objects_by_schema::<serde_json::Value>("core.todo").await.unwrap().len(), 4);
+
+        let report = database.deduplicate_schema("core.todo", false).await.unwrap();
+        assert_eq!((report.merged, report.duplicate_groups), (2, 1));
+        let ids: Vec<i64> = database
+            .load_objects_by_schema::<serde_json::Value>("core.todo")
+            .await
+            .unwrap()
+            .iter()
+            .map(|object| object.id)
+            .collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&keep) && ids.contains(&other));
+
+        let links: Vec<_> = database
+            .get_object_links(keep)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|link| (link.source_id, link.target_id, link.relation))
+            .collect();
+        assert_eq!(links, vec![(keep, other, "blocks".to_string()), (other, keep, "parent".to_string())]);
+        assert_eq!(database.get_meta(keep, "color").await.unwrap(), Some(serde_json::json!("red")));
+        assert_eq!(database.deduplicate_schema("core.todo", false).await.unwrap().merged, 0);
+    }
+
+    #[tokio::test]
+    async fn unknown_schemas_are_rejected() {
+        let (_vault, database) = temp_database("dedup_unknown").await;
+        assert!(matches!(database.deduplicate_schema("nope", true).await, Err(NexusError::SchemaNotFound(_))));
+    }
+}
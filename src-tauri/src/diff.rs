@@ -0,0 +1,187 @@
+use std::path::Path;
+
+use rusqlite::{params, OptionalExtension};
+use serde_json::Value;
+
+use crate::database::{content_hash, Database};
+use crate::error::{NexusError, Result};
+use crate::formats::FileFormat;
+use crate::merge;
+use crate::models::{FieldDiff, Todo};
+use crate::notes;
+
+impl Database {
+    /// How an object's content differs from what its file currently parses
+    /// to, one entry per differing field; empty when the two are in sync. A
+    /// deleted file (or todo entry) shows up as a single diff at the root.
+    pub async fn diff_object_file(&self, object_id: i64) -> Result<Vec<FieldDiff>> {
+        let (file_path, stored_hash): (Option<String>, String) = {
+            let conn = self.lock_connection().await?;
+            conn.query_row(
+                "SELECT file_path, content_hash FROM data_objects WHERE id = ?1",
+                params![object_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or(NexusError::ObjectNotFound(object_id))?
+        };
+        let file_path = file_path
+            .ok_or_else(|| NexusError::InvalidInput(format!("Object {} is not backed by a file", object_id)))?;
+
+        let object = self.load_object::<Value>(object_id).await?;
+        let file_content = match self.read_file_content(&file_path).await? {
+            Some(file_content) => file_content,
+            None => {
+                return Ok(vec![FieldDiff {
+                    path: String::new(),
+                    db_value: Some(object.content),
+                    file_value: None,
+                }])
+            }
+        };
+
+        // The sync stores exactly this serialization, so matching hashes mean no diff
+        if content_hash(&file_content.to_string()) == stored_hash {
+            return Ok(Vec::new());
+        }
+
+        let mut diffs = Vec::new();
+        diff_values("", Some(&object.content), Some(&file_content), &mut diffs);
+        Ok(diffs)
+    }
+
+    // A tracked file's content parsed the way the sync service parses it, or
+    // None once the file (or its todo entry) is gone
+    async fn read_file_content(&self, file_path: &str) -> Result<Option<Value>> {
+        // Todos are tracked as "<todos.json>#<todo id>" or "#idx<n>"
+        if let Some((todos_path, entry)) = file_path.rsplit_once('#').filter(|(path, _)| path.ends_with("todos.json")) {
+            let content = match tokio::fs::read_to_string(todos_path).await {
+                Ok(content) => content,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            let todo_list: Value = serde_json::from_str(&content)?;
+            let todos = todo_list.get("todos").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+
+            let todo = match entry.strip_prefix("idx") {
+                Some(index) => index.parse::<usize>().ok().and_then(|i| todos.get(i)),
+                None => merge::find_todo(&todos, entry),
+            };
+            return match todo {
+                Some(todo) => Ok(Some(serde_json::to_value(serde_json::from_value::<Todo>(todo.clone())?)?)),
+                None => Ok(None),
+            };
+        }
+
+        let path = Path::new(file_path);
+        let content = match tokio::fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        if notes::is_note_file(self.vault_path(), path) {
+            let title = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            return Ok(Some(serde_json::to_value(notes::parse_note(&content, &title))?));
+        }
+
+        // Custom extensions are read as JSON, as during sync
+        let format = FileFormat::from_path(path).unwrap_or(FileFormat::Json);
+        Ok(Some(format.parse(&content)?))
+    }
+}
+
+/// Appends the differences between two JSON values to `diffs`, keyed by JSON
+/// Pointer. Objects and arrays are compared member by member; a member only
+/// one side has is reported with `None` on the other.
+pub fn diff_values(path: &str, db: Option<&Value>, file: Option<&Value>, diffs: &mut Vec<FieldDiff>) {
+    match (db, file) {
+        (Some(Value::Object(db)), Some(Value::Object(file))) => {
+            let mut keys: Vec<&String> = db.keys().chain(file.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                diff_values(&child, db.get(key), file.get(key), diffs);
+            }
+        }
+        (Some(Value::Array(db)), Some(Value::Array(file))) => {
+            for i in 0..db.len().max(file.len()) {
+                diff_values(&format!("{}/{}", path, i), db.get(i), file.get(i), diffs);
+            }
+        }
+        (db, file) if db != file => diffs.push(FieldDiff {
+            path: path.to_string(),
+            db_value: db.cloned(),
+            file_value: file.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::test_support::{todo_path, vault, write_file, TWO_TODOS};
+
+    fn changes(diff: &[FieldDiff]) -> Vec<(&str, Option<Value>, Option<Value>)> {
+        diff.iter().map(|field| (field.path.as_str(), field.db_value.clone(), field.file_value.clone())).collect()
+    }
+
+    #[tokio::test]
+    async fn field_changes_on_disk_are_listed() {
+        let (vault, database, sync_service) = vault("diff_fields", TWO_TODOS).await;
+        database.register_schema("ext.c", r#"{"type":"object"}"#).await.unwrap();
+        database.register_schema_extensions("ext.c", &["json".into()]).await.unwrap();
+        write_file(&vault, "c.json", r#"{"name":"x","tags":["a","b"],"meta":{"n":1}}"#);
+        sync_service.force_sync(None).await.unwrap();
+        let object = database.object_id_for_path(&vault.join("c.json").to_string_lossy()).await.unwrap().unwrap();
+        let todo = database.object_id_for_path(&todo_path(&vault, 1)).await.unwrap().unwrap();
+        assert!(database.diff_object_file(object).await.unwrap().is_empty());
+        assert!(database.diff_object_file(todo).await.unwrap().is_empty());
+
+        write_file(&vault, "c.json", r#"{"name":"y","tags":["a"],"meta":{"n":1,"m":true}}"#);
+        assert_eq!(changes(&database.diff_object_file(object).await.unwrap()), vec![
+            ("/meta/m", None, Some(json!(true))),
+            ("/name", Some(json!("x")), Some(json!("y"))),
+            ("/tags/1", Some(json!("b")), None),
+        ]);
+
+        // A todo is compared against its own entry in the list
+        write_file(&vault, "Todo/todos.json", &TWO_TODOS.replacen(r#""a""#, r#""A""#, 1));
+        let diff = database.diff_object_file(todo).await.unwrap();
+        assert_eq!(diff.iter().map(|field| field.path.as_str()).collect::<Vec<_>>(), vec!["/text"]);
+    }
+
+    #[tokio::test]
+    async fn notes_are_compared_by_their_parsed_fields() {
+        let (vault, database, sync_service) = vault("diff_notes", TWO_TODOS).await;
+        write_file(&vault, "Notes/n.md", "---\ntitle: \"N\"\ntags: []\n---\nbody");
+        sync_service.force_sync(None).await.unwrap();
+        let note = database.object_id_for_path(&vault.join("Notes/n.md").to_string_lossy()).await.unwrap().unwrap();
+        assert!(database.diff_object_file(note).await.unwrap().is_empty());
+
+        write_file(&vault, "Notes/n.md", "---\ntitle: \"N\"\ntags: [\"x\"]\n---\nbody");
+        assert_eq!(database.diff_object_file(note).await.unwrap()[0].path, "/tags/0");
+    }
+
+    #[tokio::test]
+    async fn missing_files_show_as_one_whole_difference() {
+        let (vault, database, sync_service) = vault("diff_missing", TWO_TODOS).await;
+        database.register_schema("ext.c", r#"{"type":"object"}"#).await.unwrap();
+        database.register_schema_extensions("ext.c", &["json".into()]).await.unwrap();
+        write_file(&vault, "c.json", r#"{"n":1}"#);
+        sync_service.force_sync(None).await.unwrap();
+        let object = database.object_id_for_path(&vault.join("c.json").to_string_lossy()).await.unwrap().unwrap();
+
+        std::fs::remove_file(vault.join("c.json")).unwrap();
+        let diff = database.diff_object_file(object).await.unwrap();
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].path, "");
+        assert!(diff[0].file_value.is_none());
+
+        let fileless = database.save_object("ext.c", &json!({"a": 1}), None, None, None).await.unwrap();
+        assert!(matches!(database.diff_object_file(fileless).await, Err(NexusError::InvalidInput(_))));
+    }
+}
@@ -17,15 +17,36 @@ pub enum NexusError {
     #[error("Vault not configured")]
     VaultNotConfigured,
     
+    #[error("Vault at {0} is unavailable. Reconnect it or choose another vault.")]
+    VaultUnavailable(String),
+    
+    #[error("Vault is already open in another instance (PID {0})")]
+    VaultLocked(u32),
+    
     #[error("Schema not found: {0}")]
     SchemaNotFound(String),
     
+    #[error("Schema '{0}' still has {1} object(s)")]
+    SchemaInUse(String, usize),
+    
     #[error("Object not found: {0}")]
     ObjectNotFound(i64),
     
     #[error("Invalid schema definition: {0}")]
     InvalidSchema(String),
     
+    #[error("Content too large: {0} bytes exceeds the vault's limit")]
+    ContentTooLarge(usize),
+    
+    #[error("Unsafe vault location: {0}")]
+    UnsafeVaultPath(String),
+    
+    #[error("Could not parse file: {0}")]
+    Format(String),
+    
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    
     #[error("Sync error: {0}")]
     Sync(String),
     
@@ -0,0 +1,121 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::params;
+use serde_json::Value;
+
+use crate::database::Database;
+use crate::error::{NexusError, Result};
+use crate::models::{AppObject, Permissions};
+
+impl Database {
+    /// Objects shared with AI or the cloud whose share lapses within the next
+    /// `within_hours`, soonest first. Shares that have already lapsed aren't
+    /// included.
+    pub async fn get_expiring_shares(&self, within_hours: i64) -> Result<Vec<AppObject<Value>>> {
+        if within_hours < 0 {
+            return Err(NexusError::InvalidInput("The window can't be negative".to_string()));
+        }
+        let now = Utc::now();
+        let until = now + Duration::hours(within_hours.min(24 * 365 * 100));
+
+        // Expiries are RFC 3339 strings that may carry any UTC offset, so the
+        // indexed range is widened by a day and the exact window checked below
+        let lower = (now - Duration::days(1)).to_rfc3339();
+        let upper = (until + Duration::days(1)).to_rfc3339();
+
+        let conn = self.lock_connection().await?;
+        let mut stmt = conn.prepare(
+            "SELECT
+                do.id, s.schema_name, oc.content_json, do.file_path, do.updated_at, do.created_at,
+                op.share_with_ai, op.share_with_cloud, op.read_only, op.expires_at, do.source
+             FROM object_permissions op
+             JOIN data_objects do ON do.id = op.object_id
+             JOIN schemas s ON do.schema_id = s.id
+             JOIN object_content oc ON do.id = oc.object_id
+             WHERE op.expires_at BETWEEN ?1 AND ?2
+               AND (op.share_with_ai = 1 OR op.share_with_cloud = 1)",
+        )?;
+        let rows = stmt.query_map(params![lower, upper], |row| {
+            let content_json: String = row.get(2)?;
+            let content: Value = serde_json::from_str(&content_json).map_err(|e| {
+                rusqlite::Error::InvalidColumnType(
+                    2,
+                    format!("JSON deserialization error: {}", e),
+                    rusqlite::types::Type::Text,
+                )
+            })?;
+
+            Ok(AppObject {
+                id: row.get(0)?,
+                schema_name: row.get(1)?,
+                content,
+                file_path: row.get(3)?,
+                updated_at: row.get(4)?,
+                created_at: row.get(5)?,
+                permissions: Permissions {
+                    share_with_ai: row.get(6)?,
+                    share_with_cloud: row.get(7)?,
+                    read_only: row.get(8)?,
+                    expires_at: row.get(9)?,
+                },
+                source: row.get(10)?,
+            })
+        })?;
+
+        let mut expiring: Vec<(DateTime<Utc>, AppObject<Value>)> = Vec::new();
+        for object in rows {
+            let object = object?;
+            let expires_at = object
+                .permissions
+                .expires_at
+                .as_deref()
+                .and_then(|expires_at| DateTime::parse_from_rfc3339(expires_at).ok())
+                .map(|expires_at| expires_at.with_timezone(&Utc));
+            if let Some(expires_at) = expires_at.filter(|expires_at| *expires_at > now && *expires_at <= until) {
+                expiring.push((expires_at, object));
+            }
+        }
+        expiring.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.id.cmp(&b.1.id)));
+
+        Ok(expiring.into_iter().map(|(_, object)| object).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Todo;
+    use crate::test_support::temp_database;
+
+    fn expiring(share_with_ai: bool, share_with_cloud: bool, hours: i64) -> Permissions {
+        Permissions {
+            share_with_ai,
+            share_with_cloud,
+            read_only: false,
+            expires_at: Some((Utc::now() + Duration::hours(hours)).to_rfc3339()),
+        }
+    }
+
+    #[tokio::test]
+    async fn shares_lapsing_inside_the_window_are_listed_soonest_first() {
+        let (_vault, database) = temp_database("expiring_shares").await;
+        let save = |text: &'static str, permissions: Permissions| {
+            let database = &database;
+            async move {
+                database.save_object("core.todo", &Todo::new(text.into()), None, Some(&permissions), None).await.unwrap()
+            }
+        };
+        let soon = save("soon", expiring(true, false, 2)).await;
+        let sooner = save("sooner", expiring(false, true, 1)).await;
+        // In another offset, still inside the window
+        let offset = Utc::now().with_timezone(&chrono::FixedOffset::east_opt(5 * 3600).unwrap()) + Duration::hours(3);
+        let shifted = save("shifted", Permissions { share_with_ai: true, expires_at: Some(offset.to_rfc3339()), ..Default::default() }).await;
+        save("later", expiring(true, true, 48)).await;
+        save("private", expiring(false, false, 1)).await;
+        save("lapsed", expiring(true, false, -1)).await;
+
+        let ids: Vec<i64> = database.get_expiring_shares(24).await.unwrap().iter().map(|object| object.id).collect();
+        assert_eq!(ids, vec![sooner, soon, shifted]);
+        assert!(database.get_expiring_shares(0).await.unwrap().is_empty());
+        assert!(matches!(database.get_expiring_shares(-1).await, Err(NexusError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,400 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::database::{content_hash, Database, SOURCE_IMPORT};
+use crate::error::{NexusError, Result};
+use crate::models::{AppObject, ExportFormat, ImportFailure, ImportReport, Note, Permissions, Todo};
+use crate::notes;
+use crate::schema_validation;
+
+// Lines imported per transaction
+const IMPORT_BATCH_SIZE: usize = 500;
+
+// One line of an NDJSON import: an exported object, or anything shaped like
+// one. Ids, paths and timestamps of an export are left behind.
+#[derive(Deserialize)]
+struct ImportLine {
+    #[serde(default)]
+    schema_name: Option<String>,
+    content: Value,
+    #[serde(default)]
+    permissions: Option<Permissions>,
+}
+
+impl Database {
+    /// Writes objects (all, or those of one schema) to `dest` as newline-delimited
+    /// JSON, one object per line, without collecting them in memory first.
+    /// Returns the number of objects written.
+    pub async fn export_objects_ndjson(&self, schema_name: Option<&str>, dest: &Path) -> Result<usize> {
+        let conn = self.lock_connection().await?;
+
+        if let Some(schema_name) = schema_name {
+            conn.query_row(
+                "SELECT id FROM schemas WHERE schema_name = ?1",
+                params![schema_name],
+                |row| row.get::<_, i64>(0),
+            ).optional()?
+            .ok_or_else(|| NexusError::SchemaNotFound(schema_name.to_string()))?;
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT 
+                do.id, s.schema_name, oc.content_json, do.file_path, do.updated_at, do.created_at,
+                op.share_with_ai, op.share_with_cloud, op.read_only, op.expires_at, do.source
+             FROM data_objects do
+             JOIN schemas s ON do.schema_id = s.id
+             JOIN object_content oc ON do.id = oc.object_id
+             JOIN object_permissions op ON do.id = op.object_id
+             WHERE ?1 IS NULL OR s.schema_name = ?1
+             ORDER BY do.id"
+        )?;
+        let mut rows = stmt.query(params![schema_name])?;
+
+        // Write next to the destination and rename, so a failed export never
+        // leaves a truncated file behind
+        let mut temp = dest.as_os_str().to_owned();
+        temp.push(".tmp");
+        let temp = std::path::PathBuf::from(temp);
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        let mut count = 0;
+
+        let written: Result<()> = (|| {
+            while let Some(row) = rows.next()? {
+                let content_json: String = row.get(2)?;
+                let object = AppObject {
+                    id: row.get(0)?,
+                    schema_name: row.get(1)?,
+                    content: serde_json::from_str::<serde_json::Value>(&content_json)?,
+                    file_path: row.get(3)?,
+                    updated_at: row.get(4)?,
+                    created_at: row.get(5)?,
+                    permissions: Permissions {
+                        share_with_ai: row.get(6)?,
+                        share_with_cloud: row.get(7)?,
+                        read_only: row.get(8)?,
+                        expires_at: row.get(9)?,
+                    },
+                    source: row.get(10)?,
+                };
+
+                serde_json::to_writer(&mut writer, &object)?;
+                writer.write_all(b"\n")?;
+                count += 1;
+            }
+            writer.flush()?;
+            Ok(())
+        })();
+
+        drop(writer);
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp);
+            return Err(e);
+        }
+        fs::rename(&temp, dest)?;
+
+        log::info!("Exported {} object(s) to {:?}", count, dest);
+        Ok(count)
+    }
+
+    /// Renders one object as a standalone document for sharing outside the
+    /// vault, also writing it to `dest` when given.
+    pub async fn export_object(&self, object_id: i64, format: ExportFormat, dest: Option<&Path>) -> Result<String> {
+        let object = self.load_object::<Value>(object_id).await?;
+        let rendered = render_object(&object.schema_name, &object.content, format)?;
+
+        if let Some(dest) = dest {
+            fs::write(dest, &rendered)?;
+            log::info!("Exported object {} to {:?}", object_id, dest);
+        }
+        Ok(rendered)
+    }
+
+    /// Imports DB-only objects from an NDJSON file in the format
+    /// `export_objects_ndjson` writes, into `schema_name` or else each line's
+    /// own schema. Each line is validated against its schema; a bad line is
+    /// reported with its number and the rest still import. Blank lines and
+    /// objects whose content already exists in the schema are skipped.
+    pub async fn import_objects_ndjson(&self, source: &Path, schema_name: Option<&str>) -> Result<ImportReport> {
+        // Read before locking so a slow disk doesn't hold up other database users
+        let content = tokio::fs::read(source).await?;
+        let mut lines: Vec<&[u8]> = content.split(|&byte| byte == b'\n').collect();
+        if lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+
+        let conn = self.lock_connection().await?;
+
+        if let Some(schema_name) = schema_name {
+            conn.query_row(
+                "SELECT id FROM schemas WHERE schema_name = ?1",
+                params![schema_name],
+                |row| row.get::<_, i64>(0),
+            ).optional()?
+            .ok_or_else(|| NexusError::SchemaNotFound(schema_name.to_string()))?;
+        }
+
+        let mut report = ImportReport { imported: 0, skipped: 0, failed: Vec::new() };
+        let mut definitions = HashMap::new();
+        for (batch, batch_lines) in lines.chunks(IMPORT_BATCH_SIZE).enumerate() {
+            let mut tx = conn.unchecked_transaction()?;
+            for (offset, line) in batch_lines.iter().enumerate() {
+                let index = batch * IMPORT_BATCH_SIZE + offset;
+                let line = match std::str::from_utf8(line.strip_suffix(b"\r").unwrap_or(line)) {
+                    Ok(line) if line.trim().is_empty() => {
+                        report.skipped += 1;
+                        continue;
+                    }
+                    Ok(line) => line,
+                    Err(e) => {
+                        report.failed.push(ImportFailure { line: index + 1, message: e.to_string() });
+                        continue;
+                    }
+                };
+
+                // A savepoint per line, so a line failing halfway leaves nothing behind
+                let savepoint = tx.savepoint()?;
+                match self.import_line(&savepoint, line, schema_name, &mut definitions) {
+                    Ok(true) => {
+                        savepoint.commit()?;
+                        report.imported += 1;
+                    }
+                    Ok(false) => report.skipped += 1,
+                    Err(e) => report.failed.push(ImportFailure { line: index + 1, message: e.to_string() }),
+                }
+            }
+            tx.commit()?;
+        }
+
+        log::info!(
+            "Imported {} object(s) from {:?}; {} skipped, {} failed",
+            report.imported,
+            source,
+            report.skipped,
+            report.failed.len()
+        );
+        Ok(report)
+    }
+
+    // Imports one line, returning false for content the schema already has
+    fn import_line(
+        &self,
+        conn: &Connection,
+        line: &str,
+        schema_name: Option<&str>,
+        definitions: &mut HashMap<String, Value>,
+    ) -> Result<bool> {
+        let line: ImportLine = serde_json::from_str(line)?;
+        let schema_name = schema_name
+            .map(str::to_string)
+            .or(line.schema_name)
+            .ok_or_else(|| NexusError::InvalidInput("No schema_name given for the line".to_string()))?;
+
+        if !definitions.contains_key(&schema_name) {
+            let definition_json: String = conn
+                .query_row(
+                    "SELECT definition_json FROM schemas WHERE schema_name = ?1",
+                    params![schema_name],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .ok_or_else(|| NexusError::SchemaNotFound(schema_name.clone()))?;
+            let definition = serde_json::from_str(&definition_json)
+                .map_err(|e| NexusError::InvalidSchema(e.to_string()))?;
+            definitions.insert(schema_name.clone(), definition);
+        }
+
+        let errors = schema_validation::validate(&definitions[&schema_name], &line.content);
+        if let Some(error) = errors.first() {
+            return Err(NexusError::InvalidInput(format!(
+                "Content is invalid at '{}': {}",
+                error.path, error.message
+            )));
+        }
+
+        let hash = content_hash(&serde_json::to_string(&line.content)?);
+        let existing: Option<i64> = conn.query_row(
+            "SELECT do.id FROM data_objects do
+             JOIN schemas s ON do.schema_id = s.id
+             WHERE s.schema_name = ?1 AND do.content_hash = ?2
+             LIMIT 1",
+            params![schema_name, hash],
+            |row| row.get(0),
+        ).optional()?;
+        if existing.is_some() {
+            return Ok(false);
+        }
+
+        let permissions = line.permissions.unwrap_or_default();
+        self.insert_object_on(conn, &schema_name, &line.content, &permissions, SOURCE_IMPORT)?;
+        Ok(true)
+    }
+}
+
+/// Renders object content as `format`. As markdown, todos become a checklist
+/// item and notes get their frontmatter back; other schemas, and JSON
+/// exports, are pretty-printed JSON.
+pub fn render_object(schema_name: &str, content: &Value, format: ExportFormat) -> Result<String> {
+    match (format, schema_name) {
+        (ExportFormat::Markdown, "core.todo") => {
+            let todo: Todo = serde_json::from_value(content.clone())?;
+            Ok(render_todo(&todo))
+        }
+        (ExportFormat::Markdown, "core.note") => {
+            let note: Note = serde_json::from_value(content.clone())?;
+            Ok(notes::render_note(&note))
+        }
+        _ => Ok(serde_json::to_string_pretty(content)?),
+    }
+}
+
+// A todo as one markdown checklist line, e.g.
+// "- [ ] Call Sam (due: 2024-05-01, priority: high) #work"
+fn render_todo(todo: &Todo) -> String {
+    let mut line = format!("- [{}] {}", if todo.completed { "x" } else { " " }, todo.text);
+
+    let details: Vec<String> = [("due", &todo.due_date), ("priority", &todo.priority)]
+        .into_iter()
+        .filter_map(|(label, value)| value.as_ref().map(|value| format!("{}: {}", label, value)))
+        .collect();
+    if !details.is_empty() {
+        line.push_str(&format!(" ({})", details.join(", ")));
+    }
+    for tag in todo.tags.iter().flatten() {
+        line.push_str(&format!(" #{}", tag));
+    }
+
+    line.push('\n');
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_database, temp_dir};
+
+    async fn two_todos(database: &Database) {
+        for text in ["a", "b"] {
+            database.save_object("core.todo", &Todo::new(text.to_string()), None, None, None).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn ndjson_export_writes_one_object_per_line() {
+        let (_vault, database) = temp_database("export_ndjson").await;
+        two_todos(&database).await;
+        let dest = temp_dir("export_ndjson_out").join("objects.ndjson");
+
+        assert_eq!(database.export_objects_ndjson(Some("core.todo"), &dest).await.unwrap(), 2);
+        let text = fs::read_to_string(&dest).unwrap();
+        let lines: Vec<Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["content"]["text"], "a");
+        assert_eq!(lines[1]["schema_name"], "core.todo");
+        assert!(lines.iter().all(|line| line["permissions"]["read_only"].is_boolean() && line["created_at"].is_string()));
+        assert!(!dest.with_extension("ndjson.tmp").exists());
+
+        assert_eq!(database.export_objects_ndjson(Some("core.note"), &dest).await.unwrap(), 0);
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn ndjson_export_of_an_unknown_schema_leaves_the_destination_alone() {
+        let (_vault, database) = temp_database("export_ndjson_unknown").await;
+        let dest = temp_dir("export_ndjson_unknown_out").join("objects.ndjson");
+        fs::write(&dest, "previous").unwrap();
+
+        assert!(matches!(
+            database.export_objects_ndjson(Some("nope.x"), &dest).await,
+            Err(NexusError::SchemaNotFound(_))
+        ));
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "previous");
+    }
+
+    #[tokio::test]
+    async fn exported_objects_import_once_into_another_vault() {
+        let (source_vault, source) = temp_database("export_ndjson_source").await;
+        source.register_schema("ext.p", r#"{"type":"object","required":["name"]}"#).await.unwrap();
+        source.save_object("ext.p", &serde_json::json!({ "name": "a" }), None, None, None).await.unwrap();
+        let shared = Permissions { share_with_ai: true, ..Default::default() };
+        source.save_object("ext.p", &serde_json::json!({ "name": "b" }), None, Some(&shared), None).await.unwrap();
+        let exported = source_vault.join("objects.ndjson");
+        assert_eq!(source.export_objects_ndjson(Some("ext.p"), &exported).await.unwrap(), 2);
+
+        let (_vault, database) = temp_database("export_ndjson_destination").await;
+        database.register_schema("ext.p", r#"{"type":"object","required":["name"]}"#).await.unwrap();
+        let report = database.import_objects_ndjson(&exported, None).await.unwrap();
+        assert_eq!((report.imported, report.skipped, report.failed.len()), (2, 0, 0));
+        let objects: Vec<AppObject<Value>> = database.load_objects_by_schema("ext.p").await.unwrap();
+        assert!(objects.iter().any(|o| o.content["name"] == "b" && o.permissions.share_with_ai && o.file_path.is_none()));
+
+        // Content that is already there is skipped
+        let report = database.import_objects_ndjson(&exported, None).await.unwrap();
+        assert_eq!((report.imported, report.skipped), (0, 2));
+    }
+
+    #[tokio::test]
+    async fn bad_import_lines_are_reported_and_the_rest_imported() {
+        let (vault, database) = temp_database("export_ndjson_mixed").await;
+        database.register_schema("ext.p", r#"{"type":"object","required":["name"]}"#).await.unwrap();
+        let source = vault.join("mixed.ndjson");
+        let lines = [
+            r#"{"content":{"name":"c"}}"#,
+            "{not json",
+            "",
+            r#"{"content":{"other":1}}"#,
+            r#"{"schema_name":"ext.x","content":{"name":"d"}}"#,
+            "{\"content\":{\"name\":\"e\"}}\r",
+        ];
+        fs::write(&source, lines.join("\n") + "\n").unwrap();
+        assert!(matches!(database.import_objects_ndjson(&source, Some("ext.nope")).await, Err(NexusError::SchemaNotFound(_))));
+
+        let report = database.import_objects_ndjson(&source, Some("ext.p")).await.unwrap();
+        assert_eq!((report.imported, report.skipped), (3, 1));
+        assert_eq!(report.failed.iter().map(|f| f.line).collect::<Vec<_>>(), vec![2, 4]);
+
+        // Without a schema to import into, lines must name their own
+        let report = database.import_objects_ndjson(&source, None).await.unwrap();
+        assert_eq!(report.imported, 0);
+        assert_eq!(report.failed.iter().map(|f| f.line).collect::<Vec<_>>(), vec![1, 2, 4, 5, 6]);
+        assert_eq!(database.load_objects_by_schema::<Value>("ext.p").await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn single_objects_export_as_markdown_or_json() {
+        let (vault, database) = temp_database("export_single_object").await;
+        let mut todo = Todo::new("Call Sam".into());
+        todo.due_date = Some("2024-05-01".into());
+        todo.priority = Some("high".into());
+        todo.tags = Some(vec!["work".into()]);
+        let id = database.save_object("core.todo", &todo, None, None, None).await.unwrap();
+
+        let markdown = database.export_object(id, ExportFormat::Markdown, None).await.unwrap();
+        assert_eq!(markdown, "- [ ] Call Sam (due: 2024-05-01, priority: high) #work\n");
+
+        let destination = vault.join("todo.json");
+        let json = database.export_object(id, ExportFormat::Json, Some(&destination)).await.unwrap();
+        assert!(json.contains("\n  \"text\": \"Call Sam\""));
+        let written: Todo = serde_json::from_str(&fs::read_to_string(&destination).unwrap()).unwrap();
+        assert_eq!(written.text, "Call Sam");
+
+        let note = database.add_note("Hi", "body", vec!["a".into()]).await.unwrap();
+        let markdown = database.export_object(note.id, ExportFormat::Markdown, None).await.unwrap();
+        assert_eq!(markdown, "---\ntitle: \"Hi\"\ntags: [\"a\"]\n---\nbody");
+    }
+
+    #[test]
+    fn completed_todos_and_other_schemas_render_too() {
+        let done = serde_json::json!({
+            "text": "x", "completed": true, "created_at": "", "updated_at": null, "due_date": null, "priority": null, "tags": null
+        });
+        assert_eq!(render_object("core.todo", &done, ExportFormat::Markdown).unwrap(), "- [x] x\n");
+        // Without a markdown form, other schemas fall back to JSON
+        let other = render_object("x.y", &serde_json::json!({ "a": 1 }), ExportFormat::Markdown).unwrap();
+        assert_eq!(other, "{\n  \"a\": 1\n}");
+    }
+}
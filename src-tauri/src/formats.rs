@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use crate::error::{NexusError, Result};
+
+/// Serialization formats the sync service can read structured files in.
+/// Objects are always stored as JSON; the format only matters on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl FileFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension().and_then(|s| s.to_str()).and_then(Self::from_extension)
+    }
+
+    pub fn parse(self, content: &str) -> Result<serde_json::Value> {
+        match self {
+            Self::Json => Ok(serde_json::from_str(content)?),
+            Self::Toml => toml::from_str(content).map_err(|e| NexusError::Format(format!("TOML: {}", e))),
+            Self::Yaml => serde_yaml::from_str(content).map_err(|e| NexusError::Format(format!("YAML: {}", e))),
+        }
+    }
+
+    pub fn render(self, value: &serde_json::Value) -> Result<String> {
+        match self {
+            Self::Json => Ok(serde_json::to_string_pretty(value)?),
+            // TOML has no null and needs a table at the top level, so not every object fits
+            Self::Toml => toml::to_string_pretty(value).map_err(|e| NexusError::Format(format!("TOML: {}", e))),
+            Self::Yaml => serde_yaml::to_string(value).map_err(|e| NexusError::Format(format!("YAML: {}", e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn formats_are_picked_by_extension() {
+        assert_eq!(FileFormat::from_path(Path::new("a/b.JSON")), Some(FileFormat::Json));
+        assert_eq!(FileFormat::from_path(Path::new("b.toml")), Some(FileFormat::Toml));
+        assert_eq!(FileFormat::from_path(Path::new("b.yml")), Some(FileFormat::Yaml));
+        assert_eq!(FileFormat::from_path(Path::new("b.md")), None);
+        assert_eq!(FileFormat::from_path(Path::new("json")), None);
+    }
+
+    #[test]
+    fn structured_formats_round_trip() {
+        let value = json!({"name": "alpha", "count": 3, "done": false});
+        for format in [FileFormat::Json, FileFormat::Toml, FileFormat::Yaml] {
+            let rendered = format.render(&value).unwrap();
+            assert_eq!(format.parse(&rendered).unwrap(), value, "{:?}", format);
+        }
+        assert!(FileFormat::Toml.render(&json!({"name": "x"})).unwrap().contains(r#"name = "x""#));
+    }
+
+    #[test]
+    fn toml_rejects_what_it_cannot_hold() {
+        assert!(matches!(FileFormat::Toml.render(&json!([1, 2])), Err(NexusError::Format(_))));
+        assert!(matches!(FileFormat::Toml.parse("name = "), Err(NexusError::Format(_))));
+    }
+}
@@ -0,0 +1,124 @@
+use rusqlite::Connection;
+
+use crate::database::Database;
+use crate::error::Result;
+use crate::models::{IntegrityReport, OrphanedRows};
+
+// Tables whose rows belong to an object, with the condition under which a row
+// has lost its parent (only possible if foreign keys were ever off)
+const ORPHAN_CHECKS: &[(&str, &str)] = &[
+    ("object_content", "object_id NOT IN (SELECT id FROM data_objects)"),
+    ("object_permissions", "object_id NOT IN (SELECT id FROM data_objects)"),
+    ("object_meta", "object_id NOT IN (SELECT id FROM data_objects)"),
+    ("attachments", "object_id NOT IN (SELECT id FROM data_objects)"),
+    ("sync_conflicts", "object_id NOT IN (SELECT id FROM data_objects)"),
+    (
+        "object_links",
+        "source_id NOT IN (SELECT id FROM data_objects) OR target_id NOT IN (SELECT id FROM data_objects)",
+    ),
+];
+
+impl Database {
+    /// Runs SQLite's integrity check and looks for rows whose object no longer
+    /// exists. With `repair`, those rows are deleted in one transaction.
+    /// Foreign keys are switched back on if they were found disabled.
+    pub async fn check_integrity(&self, repair: bool) -> Result<IntegrityReport> {
+        let conn = self.lock_connection().await?;
+
+        let integrity_errors: Vec<String> = {
+            let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            rows.filter(|message| !matches!(message.as_deref(), Ok("ok")))
+                .collect::<rusqlite::Result<_>>()?
+        };
+
+        let foreign_keys_enabled: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+        if !foreign_keys_enabled {
+            log::warn!("Foreign keys were disabled on the vault database, re-enabling them");
+            conn.execute("PRAGMA foreign_keys = ON", [])?;
+        }
+
+        let orphans = find_orphans(&conn)?;
+        let repaired = repair && !orphans.is_empty();
+        if repaired {
+            let orphaned_blobs: Vec<(String, String)> = {
+                let mut stmt = conn.prepare(
+                    "SELECT content_hash, blob_path FROM attachments
+                     WHERE object_id NOT IN (SELECT id FROM data_objects)",
+                )?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<rusqlite::Result<_>>()?
+            };
+
+            let tx = conn.unchecked_transaction()?;
+            for (table, orphaned) in ORPHAN_CHECKS {
+                tx.execute(&format!("DELETE FROM {} WHERE {}", table, orphaned), [])?;
+            }
+            tx.commit()?;
+
+            self.remove_orphaned_blobs(&conn, &orphaned_blobs)?;
+            log::info!(
+                "Deleted {} orphaned rows",
+                orphans.iter().map(|orphan| orphan.count).sum::<usize>()
+            );
+        }
+
+        Ok(IntegrityReport {
+            integrity_errors,
+            foreign_keys_enabled,
+            orphans,
+            repaired,
+        })
+    }
+}
+
+fn find_orphans(conn: &Connection) -> Result<Vec<OrphanedRows>> {
+    let mut orphans = Vec::new();
+    for (table, orphaned) in ORPHAN_CHECKS {
+        let count: usize = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE {}", table, orphaned),
+            [],
+            |row| row.get(0),
+        )?;
+        if count > 0 {
+            orphans.push(OrphanedRows { table: table.to_string(), count });
+        }
+    }
+    Ok(orphans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Todo;
+    use crate::test_support::{vault, TWO_TODOS};
+
+    #[tokio::test]
+    async fn orphaned_rows_are_found_and_repaired() {
+        let (_vault, database, sync_service) = vault("integrity_orphans", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        let clean = database.check_integrity(false).await.unwrap();
+        assert!(clean.integrity_errors.is_empty() && clean.foreign_keys_enabled);
+        assert!(clean.orphans.is_empty() && !clean.repaired);
+
+        {
+            let conn = database.lock_connection().await.unwrap();
+            conn.execute("PRAGMA foreign_keys = OFF", []).unwrap();
+            conn.execute("INSERT INTO object_content (object_id, content_json) VALUES (999, '{}')", []).unwrap();
+            conn.execute("INSERT INTO object_meta (object_id, key, value_json) VALUES (999, 'k', '1')", []).unwrap();
+            conn.execute("INSERT INTO object_meta (object_id, key, value_json) VALUES (998, 'k', '1')", []).unwrap();
+        }
+        let found = database.check_integrity(false).await.unwrap();
+        assert!(!found.foreign_keys_enabled && !found.repaired);
+        assert_eq!(found.orphans, vec![
+            OrphanedRows { table: "object_content".into(), count: 1 },
+            OrphanedRows { table: "object_meta".into(), count: 2 },
+        ]);
+
+        let repaired = database.check_integrity(true).await.unwrap();
+        assert!(repaired.foreign_keys_enabled && repaired.repaired);
+        assert_eq!(repaired.orphans.len(), 2);
+        assert!(database.check_integrity(false).await.unwrap().orphans.is_empty());
+        assert_eq!(database.load_objects_by_schema::<Todo>("core.todo").await.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,228 @@
+use rusqlite::{params, OptionalExtension};
+use serde_json::Value;
+
+use crate::database::{content_hash, Database};
+use crate::error::{NexusError, Result};
+use crate::models::PatchOp;
+use crate::schema_validation;
+
+impl Database {
+    /// Applies JSON Patch operations to an object's content in one transaction
+    /// and returns the new content. With `validate_patches` on, the result must
+    /// still satisfy the schema.
+    pub async fn patch_object(&self, object_id: i64, ops: &[PatchOp]) -> Result<Value> {
+        let conn = self.lock_connection().await?;
+        let tx = conn.unchecked_transaction()?;
+
+        let (content_json, definition_json, read_only): (String, String, bool) = tx.query_row(
+            "SELECT oc.content_json, s.definition_json, op.read_only
+             FROM data_objects do
+             JOIN schemas s ON do.schema_id = s.id
+             JOIN object_content oc ON do.id = oc.object_id
+             JOIN object_permissions op ON do.id = op.object_id
+             WHERE do.id = ?1",
+            params![object_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).optional()?
+        .ok_or(NexusError::ObjectNotFound(object_id))?;
+
+        if read_only {
+            return Err(NexusError::PermissionDenied(format!("Object {} is read-only", object_id)));
+        }
+
+        let mut content: Value = serde_json::from_str(&content_json)?;
+        apply_patch(&mut content, ops)?;
+
+        if self.settings().validate_patches {
+            let definition: Value = serde_json::from_str(&definition_json)
+                .map_err(|e| NexusError::InvalidSchema(e.to_string()))?;
+            let errors = schema_validation::validate(&definition, &content);
+            if let Some(error) = errors.first() {
+                return Err(NexusError::InvalidInput(format!(
+                    "Patched content is invalid at '{}': {}",
+                    error.path, error.message
+                )));
+            }
+        }
+
+        let content_json = serde_json::to_string(&content)?;
+        self.check_content_size(&content_json)?;
+        let now = chrono::Utc::now().to_rfc3339();
+        tx.execute(
+            "UPDATE object_content SET content_json = ?1 WHERE object_id = ?2",
+            params![content_json, object_id],
+        )?;
+        tx.execute(
+            "UPDATE data_objects SET content_hash = ?1, updated_at = ?2 WHERE id = ?3",
+            params![content_hash(&content_json), now, object_id],
+        )?;
+        tx.commit()?;
+
+        self.notify_changed(object_id);
+        log::info!("Applied {} patch operation(s) to object {}", ops.len(), object_id);
+        Ok(content)
+    }
+}
+
+/// Applies the operations in order; the document is left untouched if any fails.
+pub fn apply_patch(document: &mut Value, ops: &[PatchOp]) -> Result<()> {
+    let mut patched = document.clone();
+    for op in ops {
+        match op {
+            PatchOp::Add { path, value } => add(&mut patched, path, value.clone())?,
+            PatchOp::Replace { path, value } => *pointer_mut(&mut patched, path)? = value.clone(),
+            PatchOp::Remove { path } => {
+                remove(&mut patched, path)?;
+            }
+        }
+    }
+
+    *document = patched;
+    Ok(())
+}
+
+fn add(document: &mut Value, path: &str, value: Value) -> Result<()> {
+    let (parent_path, key) = match split_pointer(path)? {
+        Some(split) => split,
+        // An empty path replaces the whole document
+        None => {
+            *document = value;
+            return Ok(());
+        }
+    };
+
+    match pointer_mut(document, parent_path)? {
+        Value::Object(object) => {
+            object.insert(key, value);
+            Ok(())
+        }
+        Value::Array(array) => {
+            let index = if key == "-" { array.len() } else { array_index(&key, array.len() + 1, path)? };
+            array.insert(index, value);
+            Ok(())
+        }
+        _ => Err(invalid_pointer(path)),
+    }
+}
+
+fn remove(document: &mut Value, path: &str) -> Result<Value> {
+    let (parent_path, key) = split_pointer(path)?.ok_or_else(|| invalid_pointer(path))?;
+
+    match pointer_mut(document, parent_path)? {
+        Value::Object(object) => object.remove(&key).ok_or_else(|| invalid_pointer(path)),
+        Value::Array(array) => {
+            let index = array_index(&key, array.len(), path)?;
+            Ok(array.remove(index))
+        }
+        _ => Err(invalid_pointer(path)),
+    }
+}
+
+fn pointer_mut<'a>(document: &'a mut Value, path: &str) -> Result<&'a mut Value> {
+    if !path.is_empty() && !path.starts_with('/') {
+        return Err(invalid_pointer(path));
+    }
+    document.pointer_mut(path).ok_or_else(|| invalid_pointer(path))
+}
+
+// Splits "/a/b" into the parent pointer "/a" and the unescaped last token "b"
+fn split_pointer(path: &str) -> Result<Option<(&str, String)>> {
+    if path.is_empty() {
+        return Ok(None);
+    }
+    if !path.starts_with('/') {
+        return Err(invalid_pointer(path));
+    }
+
+    let (parent, token) = path.rsplit_once('/').unwrap_or_default();
+    Ok(Some((parent, token.replace("~1", "/").replace("~0", "~"))))
+}
+
+// Indexes must be plain decimal numbers below `bound`
+fn array_index(token: &str, bound: usize, path: &str) -> Result<usize> {
+    if token.is_empty() || !token.chars().all(|c| c.is_ascii_digit()) || (token.len() > 1 && token.starts_with('0')) {
+        return Err(invalid_pointer(path));
+    }
+    token
+        .parse::<usize>()
+        .ok()
+        .filter(|index| *index < bound)
+        .ok_or_else(|| invalid_pointer(path))
+}
+
+fn invalid_pointer(path: &str) -> NexusError {
+    NexusError::InvalidInput(format!("Invalid JSON pointer '{}'", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AppObject, Todo};
+    use crate::test_support::temp_database;
+    use serde_json::json;
+
+    async fn tagged_todo(database: &Database) -> i64 {
+        let mut todo = Todo::new("a".to_string());
+        todo.tags = Some(vec!["x".to_string()]);
+        database.save_object("core.todo", &todo, None, None, None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn patch_replaces_and_appends_in_one_update() {
+        let (_vault, database) = temp_database("patch_apply").await;
+        let id = tagged_todo(&database).await;
+
+        let content = database.patch_object(id, &[
+            PatchOp::Replace { path: "/completed".to_string(), value: json!(true) },
+            PatchOp::Add { path: "/tags/-".to_string(), value: json!("y") },
+        ]).await.unwrap();
+        assert_eq!(content["completed"], true);
+        assert_eq!(content["tags"], json!(["x", "y"]));
+
+        let stored: AppObject<Todo> = database.load_object(id).await.unwrap();
+        assert!(stored.content.completed);
+    }
+
+    #[tokio::test]
+    async fn invalid_pointers_leave_the_object_untouched() {
+        let (_vault, database) = temp_database("patch_pointer").await;
+        let id = tagged_todo(&database).await;
+
+        for ops in [
+            vec![PatchOp::Replace { path: "/nope/x".to_string(), value: json!(1) }],
+            vec![PatchOp::Remove { path: "/tags/5".to_string() }],
+            vec![PatchOp::Add { path: "/tags/-".to_string(), value: json!("y") }, PatchOp::Remove { path: "tags".to_string() }],
+        ] {
+            assert!(matches!(database.patch_object(id, &ops).await, Err(NexusError::InvalidInput(_))));
+        }
+
+        let stored: AppObject<Todo> = database.load_object(id).await.unwrap();
+        assert_eq!(stored.content.tags, Some(vec!["x".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn schema_is_only_enforced_when_validation_is_enabled() {
+        let (_vault, database) = temp_database("patch_validation").await;
+        let id = tagged_todo(&database).await;
+        let wrong_type = [PatchOp::Replace { path: "/tags".to_string(), value: json!("x") }];
+
+        let mut settings = database.settings();
+        settings.validate_patches = true;
+        database.update_settings(settings.clone()).unwrap();
+        assert!(matches!(database.patch_object(id, &wrong_type).await, Err(NexusError::InvalidInput(_))));
+
+        settings.validate_patches = false;
+        database.update_settings(settings).unwrap();
+        assert_eq!(database.patch_object(id, &wrong_type).await.unwrap()["tags"], "x");
+    }
+
+    #[test]
+    fn escaped_tokens_address_keys_with_slashes() {
+        let mut document = json!({"a/b": [1]});
+        apply_patch(&mut document, &[
+            PatchOp::Add { path: "/a~1b/0".to_string(), value: json!(0) },
+            PatchOp::Remove { path: "/a~1b/1".to_string() },
+        ]).unwrap();
+        assert_eq!(document, json!({"a/b": [0]}));
+    }
+}
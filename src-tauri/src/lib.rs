@@ -1,24 +1,70 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::Mutex;
 
 // Import our new modules
 mod error;
 mod models;
+mod perf;
 mod database;
 mod sync_service;
 mod sidecar;
+mod sidecar_protocol;
+mod plugins;
+mod plugin_permissions;
+mod registry;
+mod scheduler;
+mod backup;
+mod batch;
+mod diff;
+mod cache;
+mod vault_fs;
+mod write_back;
+mod schema_validation;
+mod settings;
+mod vault_lock;
+mod analytics;
+mod export;
+mod formats;
+mod subtasks;
+mod todos;
+mod notes;
+mod json_patch;
+mod merge;
+mod ordering;
+mod query;
+mod ai_context;
+mod tags;
+mod plugin_data;
+mod integrity;
+mod dedup;
+mod object_merge;
+mod reclassify;
+mod expiring_shares;
+mod search;
+mod relocate;
+mod change_feed;
+mod quarantine;
+mod todo_snapshots;
+#[cfg(test)]
+mod test_support;
 
-use models::{VaultConfig, VaultInfo, Todo, Permissions, PluginMetadata, InstalledPlugin, PluginStatus};
+use models::{VaultConfig, VaultSettings, VaultInfo, VaultFileContents, BackupInfo, CacheStats, OperationStats, SchemaFieldStats, StorageBreakdown, Todo, TodoPatch, TodoOrder, ObjectOrder, Note, NotePatch, ObjectLink, ObjectOp, Permissions, BulkUpdateResult, CloneOptions, PatchOp, AiContext, Attachment, SyncConflict, SyncError, Keep, DateField, ValidationError, PluginMetadata, InstalledPlugin, PluginLogLine, PluginManifestError, PluginStatus, PluginStatusSummary, PluginVerification, ScheduledJob, FieldDiff, TagFilter, VaultValidationReport, ImportReport, PluginSchemaInfo, IntegrityReport, ExportFormat, PluginStorage, ClearedPluginData, DeduplicationReport, QuarantinedFile, PluginPermissionReport, TodoSnapshotInfo, MergeStrategy, PermissionSummary};
 
 // Application state for managing the database and sync service
 pub struct AppState {
     database: Option<Arc<database::Database>>,
     sync_service: Option<Arc<Mutex<sync_service::SyncService>>>,
     sidecar_manager: Option<Arc<sidecar::SidecarManager>>,
+    backup_task: Option<tokio::task::JoinHandle<()>>,
+    plugin_scheduler: Option<Arc<Mutex<scheduler::PluginScheduler>>>,
+    scheduler_task: Option<tokio::task::JoinHandle<()>>,
+    // Tasks forwarding one schema's changes, by schema name
+    schema_watches: HashMap<String, tokio::task::JoinHandle<()>>,
 }
 
 impl AppState {
@@ -27,6 +73,10 @@ impl AppState {
             database: None,
             sync_service: None,
             sidecar_manager: None,
+            backup_task: None,
+            plugin_scheduler: None,
+            scheduler_task: None,
+            schema_watches: HashMap::new(),
         }
     }
 }
@@ -39,9 +89,12 @@ pub struct TodoList {
 
 // Initialize logging
 fn init_logging() {
+    // The logger accepts everything; the effective level comes from the
+    // vault's settings once one is opened
     env_logger::Builder::from_default_env()
-        .filter_level(log::LevelFilter::Info)
+        .filter_level(log::LevelFilter::Trace)
         .init();
+    log::set_max_level(log::LevelFilter::Info);
 }
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
@@ -67,7 +120,7 @@ async fn get_vault_config(app: AppHandle) -> Result<Option<VaultConfig>, String>
 }
 
 #[tauri::command]
-async fn set_vault_path(app: AppHandle, vault_path: String) -> Result<VaultConfig, String> {
+async fn set_vault_path(app: AppHandle, vault_path: String, force: bool) -> Result<VaultConfig, String> {
     let path = Path::new(&vault_path);
     
     if !path.exists() {
@@ -78,21 +131,24 @@ async fn set_vault_path(app: AppHandle, vault_path: String) -> Result<VaultConfi
         return Err("Selected path is not a directory".to_string());
     }
     
+    if !force {
+        // Counting files can take a while on a large folder
+        let home = app.path().home_dir().ok();
+        let location = path.to_path_buf();
+        tokio::task::spawn_blocking(move || vault_fs::check_vault_location(&location, home.as_deref()))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+    }
+    
     // Create vault config
     let config = VaultConfig {
         vault_path: vault_path.clone(),
-        created_at: chrono::Utc::now().to_rfc3339(),
-        version: "1.0.0".to_string(),
-        encryption_enabled: false,
+        ..VaultConfig::default()
     };
     
     // Save config to app data
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    fs::create_dir_all(&app_dir).map_err(|e| e.to_string())?;
-    
-    let config_file = app_dir.join("vault_config.json");
-    let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
-    fs::write(&config_file, content).map_err(|e| e.to_string())?;
+    save_vault_config_sync(&app, &config)?;
     
     // Create vault structure
     create_vault_structure(&vault_path)?;
@@ -115,24 +171,356 @@ async fn set_vault_path(app: AppHandle, vault_path: String) -> Result<VaultConfi
 async fn initialize_vault_backend(app: &AppHandle, vault_path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let vault_path = Path::new(vault_path);
     
+    // Vaults without settings.json take their settings from the app-level config
+    if settings::load_settings(vault_path)?.is_none() {
+        let config_file = app.path().app_data_dir()?.join("vault_config.json");
+        let legacy = match fs::read_to_string(&config_file) {
+            Ok(content) => settings::settings_from_legacy_config(&content)?,
+            Err(_) => VaultSettings::default(),
+        };
+        settings::save_settings(vault_path, &legacy)?;
+    }
+    
     // Create database
     let database = Arc::new(database::Database::new(vault_path).await?);
+    let settings = database.settings();
+    settings::apply_log_level(&settings);
     
     // Create sync service
     let mut sync_service = sync_service::SyncService::new(Arc::clone(&database), vault_path).await?;
+    let progress_handle = app.clone();
+    sync_service.set_progress_emitter(Arc::new(move |progress| {
+        if let Err(e) = progress_handle.emit("scan-progress", progress) {
+            log::warn!("Failed to emit scan progress: {}", e);
+        }
+    }));
+    spawn_change_feed(app, sync_service.subscribe_changes(), &settings);
     sync_service.start().await?;
     let sync_service = Arc::new(Mutex::new(sync_service));
     
+    // Schedule automatic backups if enabled for this vault
+    let backup_task = spawn_backups(&database, &settings);
+    
     // Store in app state
     let state = app.state::<Mutex<AppState>>();
     let mut app_state = state.lock().await;
+    if let Some(previous) = app_state.backup_task.take() {
+        previous.abort();
+    }
+    if let Some(previous) = app_state.sync_service.take() {
+        previous.lock().await.flush_write_back().await;
+    }
+    if let Some(previous) = app_state.database.take() {
+        previous.release_vault_lock();
+    }
     app_state.database = Some(database);
     app_state.sync_service = Some(sync_service);
+    app_state.backup_task = backup_task;
+    start_plugin_scheduler(&mut app_state);
+    
+    // Plugins need a vault, so the sidecar only starts (or follows) one here
+    let plugins_dir = sidecar::plugins_dir_to_spawn(app_state.sidecar_manager.as_deref(), Some(vault_path));
+    drop(app_state);
+    if let Some(plugins_dir) = plugins_dir {
+        if let Err(e) = start_sidecar(app, plugins_dir).await {
+            log::error!("Failed to initialize sidecar manager: {}", e);
+        }
+    }
     
     log::info!("Vault backend initialized for path: {}", vault_path.display());
     Ok(())
 }
 
+// Spawns a sidecar for `plugins_dir`, replacing (and stopping) any previous one
+async fn start_sidecar(app: &AppHandle, plugins_dir: PathBuf) -> Result<(), String> {
+    let settings = {
+        let state = app.state::<Mutex<AppState>>();
+        let app_state = state.lock().await;
+        app_state.database.as_ref().map(|database| database.settings()).unwrap_or_default()
+    };
+    let manager = sidecar::SidecarManager::new(app.clone(), plugins_dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut manager = manager
+        .with_max_in_flight(settings.sidecar_max_in_flight)
+        .with_handler_max_age(std::time::Duration::from_secs(settings.sidecar_handler_max_age_secs));
+    if let Some(secs) = settings.sidecar_request_timeout_secs {
+        manager = manager.with_request_timeout(std::time::Duration::from_secs(secs));
+    }
+    
+    let state = app.state::<Mutex<AppState>>();
+    let mut app_state = state.lock().await;
+    app_state.sidecar_manager = Some(Arc::new(manager));
+    start_plugin_scheduler(&mut app_state);
+    log::info!("Sidecar manager initialized successfully");
+    Ok(())
+}
+
+/// Starts emitting the changes to `schema_name`'s objects as a
+/// schema-change-<name> event, returning the event's name. Watching a schema
+/// again replaces its previous watch.
+#[tauri::command]
+async fn watch_schema(app: AppHandle, schema_name: String) -> Result<String, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let mut app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        let changes = sync_service.lock().await.subscribe_schema(&schema_name);
+        let event = sync_service::schema_change_event(&schema_name);
+        let task = spawn_schema_feed(&app, changes);
+        if let Some(previous) = app_state.schema_watches.insert(schema_name, task) {
+            previous.abort();
+        }
+        Ok(event)
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn unwatch_schema(app: AppHandle, schema_name: String) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let mut app_state = state.lock().await;
+    
+    if let Some(task) = app_state.schema_watches.remove(&schema_name) {
+        task.abort();
+    }
+    Ok(())
+}
+
+/// Re-spawns the sidecar for the active vault's plugins folder.
+#[tauri::command]
+async fn restart_sidecar(app: AppHandle) -> Result<(), String> {
+    let vault_path = {
+        let state = app.state::<Mutex<AppState>>();
+        let app_state = state.lock().await;
+        match &app_state.database {
+            Some(database) => database.vault_path().to_path_buf(),
+            None => return Err(error::NexusError::VaultNotConfigured.to_string()),
+        }
+    };
+    
+    start_sidecar(&app, vault_path.join("plugins")).await
+}
+
+/// Reopens the configured vault after its directory vanished and came back,
+/// replacing the database and sync service that had gone unavailable.
+#[tauri::command]
+async fn reconnect_vault(app: AppHandle) -> Result<(), String> {
+    let config = get_vault_config_sync(&app)?.ok_or_else(|| error::NexusError::VaultNotConfigured.to_string())?;
+    if !Path::new(&config.vault_path).is_dir() {
+        return Err(error::NexusError::VaultUnavailable(config.vault_path).to_string());
+    }
+    
+    initialize_vault_backend(&app, &config.vault_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    log::info!("Reconnected vault: {}", config.vault_path);
+    Ok(())
+}
+
+/// Moves the whole vault directory to `new_path`, which must not exist or
+/// be an empty folder, and reopens it there: the config, database, sync
+/// service and sidecar all follow. If the move fails the vault is reopened
+/// where it was.
+#[tauri::command]
+async fn relocate_vault(app: AppHandle, new_path: String) -> Result<VaultConfig, String> {
+    let mut config = get_vault_config_sync(&app)?.ok_or_else(|| error::NexusError::VaultNotConfigured.to_string())?;
+    let old_path = PathBuf::from(&config.vault_path);
+    let destination = relocate::check_destination(&old_path, Path::new(&new_path)).map_err(|e| e.to_string())?;
+    
+    // Nothing may touch the vault while its files move
+    close_vault_backend(&app).await;
+    
+    let (source, target) = (old_path.clone(), destination.clone());
+    let moved = tokio::task::spawn_blocking(move || {
+        relocate::move_vault(&source, &target)?;
+        if let Err(e) = relocate::rebase_vault_paths(&target, &source) {
+            // Put the vault back rather than open it with stale paths
+            relocate::move_vault(&target, &source)?;
+            return Err(e);
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    
+    if let Err(e) = moved {
+        log::error!("Failed to relocate vault to {}: {}", destination.display(), e);
+        if old_path.is_dir() {
+            if let Err(reopen) = initialize_vault_backend(&app, &config.vault_path).await {
+                log::error!("Failed to reopen vault at {}: {}", config.vault_path, reopen);
+            }
+        }
+        return Err(e.to_string());
+    }
+    
+    config.vault_path = destination.to_string_lossy().to_string();
+    save_vault_config_sync(&app, &config)?;
+    initialize_vault_backend(&app, &config.vault_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    log::info!("Relocated vault from {} to {}", old_path.display(), config.vault_path);
+    Ok(config)
+}
+
+// Stops everything using the open vault and closes its database
+async fn close_vault_backend(app: &AppHandle) {
+    let state = app.state::<Mutex<AppState>>();
+    let mut app_state = state.lock().await;
+    
+    for task in [app_state.backup_task.take(), app_state.scheduler_task.take()].into_iter().flatten() {
+        task.abort();
+    }
+    for (_, watch) in app_state.schema_watches.drain() {
+        watch.abort();
+    }
+    app_state.plugin_scheduler = None;
+    app_state.sidecar_manager = None;
+    if let Some(sync_service) = app_state.sync_service.take() {
+        sync_service.lock().await.flush_write_back().await;
+    }
+    if let Some(database) = app_state.database.take() {
+        if let Err(e) = database.close(std::time::Duration::from_secs(5)).await {
+            log::warn!("Failed to checkpoint the vault database: {}", e);
+        }
+    }
+}
+
+fn spawn_backups(database: &Arc<database::Database>, settings: &VaultSettings) -> Option<tokio::task::JoinHandle<()>> {
+    settings.backup_interval_hours.map(|hours| {
+        backup::spawn_backup_scheduler(
+            Arc::clone(database),
+            database.vault_path().to_path_buf(),
+            hours,
+            settings.backup_keep_count,
+        )
+    })
+}
+
+// Forwards the sync service's object changes to the frontend as vault-change
+// events, or vault-changed-batch summaries during bursts; ends once the
+// service is dropped
+fn spawn_change_feed(
+    app: &AppHandle,
+    changes: tokio::sync::broadcast::Receiver<models::ObjectChange>,
+    settings: &VaultSettings,
+) {
+    let app = app.clone();
+    let window = std::time::Duration::from_millis(settings.change_batch_window_ms);
+    tokio::spawn(change_feed::forward_changes(changes, settings.change_batch_threshold, window, move |event| {
+        let emitted = match event {
+            change_feed::FeedEvent::Change(change) => app.emit("vault-change", change),
+            change_feed::FeedEvent::Batch(batch) => app.emit("vault-changed-batch", batch),
+        };
+        if let Err(e) = emitted {
+            log::warn!("Failed to emit vault change: {}", e);
+        }
+    }));
+}
+
+// Forwards one schema's changes as its schema-change-<name> event; ends
+// once the sync service is dropped
+fn spawn_schema_feed(app: &AppHandle, mut changes: sync_service::SchemaChanges) -> tokio::task::JoinHandle<()> {
+    let app = app.clone();
+    let event = sync_service::schema_change_event(changes.schema_name());
+    tokio::spawn(async move {
+        loop {
+            match changes.recv().await {
+                Ok(change) => {
+                    if let Err(e) = app.emit(&event, change) {
+                        log::warn!("Failed to emit {}: {}", event, e);
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                    log::warn!("{} fell behind; {} changes were not forwarded", event, missed);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+// Runs plugin schedules once both a vault and the sidecar are available,
+// replacing any scheduler for a previous vault
+fn start_plugin_scheduler(app_state: &mut AppState) {
+    let (Some(database), Some(manager)) = (&app_state.database, &app_state.sidecar_manager) else {
+        return;
+    };
+    if let Some(previous) = app_state.scheduler_task.take() {
+        previous.abort();
+    }
+    
+    let vault_path = database.vault_path();
+    let plugin_scheduler = Arc::new(Mutex::new(scheduler::PluginScheduler::load(
+        scheduler::schedule_state_path(vault_path),
+    )));
+    app_state.scheduler_task = Some(scheduler::spawn_plugin_scheduler(
+        Arc::clone(&plugin_scheduler),
+        Arc::clone(manager),
+        vault_path.join("plugins"),
+    ));
+    app_state.plugin_scheduler = Some(plugin_scheduler);
+}
+
+#[tauri::command]
+async fn get_settings(app: AppHandle) -> Result<VaultSettings, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        Ok(database.settings())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn update_settings(app: AppHandle, settings: VaultSettings) -> Result<VaultSettings, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let mut app_state = state.lock().await;
+    
+    let database = match &app_state.database {
+        Some(database) => Arc::clone(database),
+        None => return Err("Database not initialized. Please configure a vault first.".to_string()),
+    };
+    
+    let previous = database.settings();
+    database.update_settings(settings.clone()).map_err(|e| e.to_string())?;
+    settings::apply_log_level(&settings);
+    
+    // Restart only the subsystems whose settings changed
+    if settings.write_back_delay_ms != previous.write_back_delay_ms {
+        if let Some(sync_service) = &app_state.sync_service {
+            sync_service
+                .lock()
+                .await
+                .set_write_back_delay(std::time::Duration::from_millis(settings.write_back_delay_ms))
+                .await;
+        }
+    }
+    
+    if settings.watch_paths != previous.watch_paths {
+        if let Some(sync_service) = &app_state.sync_service {
+            sync_service.lock().await.rewatch().await.map_err(|e| e.to_string())?;
+        }
+    }
+    
+    if settings.backup_interval_hours != previous.backup_interval_hours
+        || settings.backup_keep_count != previous.backup_keep_count
+    {
+        if let Some(previous_task) = app_state.backup_task.take() {
+            previous_task.abort();
+        }
+        app_state.backup_task = spawn_backups(&database, &settings);
+    }
+    
+    log::info!("Vault settings updated");
+    Ok(settings)
+}
+
 #[tauri::command]
 async fn check_directory_info(path: String) -> Result<VaultInfo, String> {
     let dir_path = Path::new(&path);
@@ -178,6 +566,9 @@ fn create_vault_structure(vault_path: &str) -> Result<(), String> {
         fs::write(&todos_file, content).map_err(|e| e.to_string())?;
     }
     
+    // Create Notes directory
+    fs::create_dir_all(vault_dir.join(notes::NOTES_DIR)).map_err(|e| e.to_string())?;
+    
     // Create a .nexus directory for metadata
     let nexus_dir = vault_dir.join(".nexus");
     fs::create_dir_all(&nexus_dir).map_err(|e| e.to_string())?;
@@ -208,19 +599,30 @@ fn create_vault_structure(vault_path: &str) -> Result<(), String> {
     Ok(())
 }
 
-// Legacy Todo commands for backward compatibility
-fn get_vault_todos_path(app: &AppHandle) -> Result<PathBuf, String> {
+fn get_vault_root(app: &AppHandle) -> Result<PathBuf, String> {
     let config = match get_vault_config_sync(app)? {
         Some(config) => config,
         None => return Err("No vault configured. Please set up a vault first.".to_string()),
     };
     
-    let vault_path = Path::new(&config.vault_path);
+    let vault_path = PathBuf::from(&config.vault_path);
     if !vault_path.exists() {
         return Err("Vault directory no longer exists. Please reconfigure vault.".to_string());
     }
     
-    Ok(vault_path.join("Todo").join("todos.json"))
+    Ok(vault_path)
+}
+
+// Recovery for a vault left locked by an instance that didn't shut down cleanly
+#[tauri::command]
+async fn force_unlock_vault(app: AppHandle) -> Result<bool, String> {
+    let vault_path = get_vault_root(&app)?;
+    vault_lock::force_unlock(&vault_path).map_err(|e| e.to_string())
+}
+
+// Legacy Todo commands for backward compatibility
+fn get_vault_todos_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(get_vault_root(app)?.join("Todo").join("todos.json"))
 }
 
 fn get_vault_config_sync(app: &AppHandle) -> Result<Option<VaultConfig>, String> {
@@ -237,6 +639,15 @@ fn get_vault_config_sync(app: &AppHandle) -> Result<Option<VaultConfig>, String>
     Ok(Some(config))
 }
 
+fn save_vault_config_sync(app: &AppHandle, config: &VaultConfig) -> Result<(), String> {
+    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    fs::create_dir_all(&app_dir).map_err(|e| e.to_string())?;
+    
+    let config_file = app_dir.join("vault_config.json");
+    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    fs::write(&config_file, content).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn load_todos(app: AppHandle) -> Result<Vec<Todo>, String> {
     let todos_file = get_vault_todos_path(&app)?;
@@ -262,7 +673,7 @@ async fn save_todos(app: AppHandle, todos: Vec<Todo>) -> Result<(), String> {
     
     let todo_list = TodoList { todos };
     let content = serde_json::to_string_pretty(&todo_list).map_err(|e| e.to_string())?;
-    fs::write(&todos_file, content).map_err(|e| e.to_string())?;
+    vault_fs::atomic_write(&todos_file, content.as_bytes()).map_err(|e| e.to_string())?;
     
     Ok(())
 }
@@ -301,7 +712,7 @@ async fn load_todos_v2(app: AppHandle) -> Result<Vec<models::AppObject<Todo>>, S
     let app_state = state.lock().await;
     
     if let Some(database) = &app_state.database {
-        let todos = database.load_objects_by_schema("core.todo").await.map_err(|e| e.to_string())?;
+        let todos = database.load_todos_by_archived(false).await.map_err(|e| e.to_string())?;
         Ok(todos)
     } else {
         Err("Database not initialized. Please configure a vault first.".to_string())
@@ -309,242 +720,1365 @@ async fn load_todos_v2(app: AppHandle) -> Result<Vec<models::AppObject<Todo>>, S
 }
 
 #[tauri::command]
-async fn add_todo_v2(app: AppHandle, text: String) -> Result<models::AppObject<Todo>, String> {
+async fn load_todos_ordered(app: AppHandle, order: TodoOrder) -> Result<Vec<models::AppObject<Todo>>, String> {
     let state = app.state::<Mutex<AppState>>();
     let app_state = state.lock().await;
     
     if let Some(database) = &app_state.database {
-        let todo = Todo::new(text);
-        let object_id = database.save_object(
-            "core.todo",
-            &todo,
-            None, // We could specify a file path here
-            None, // Default permissions
-        ).await.map_err(|e| e.to_string())?;
-        
-        let saved_todo = database.load_object(object_id).await.map_err(|e| e.to_string())?;
-        Ok(saved_todo)
+        let mut todos = database.load_todos_by_archived(false).await.map_err(|e| e.to_string())?;
+        order.sort(&mut todos);
+        Ok(todos)
     } else {
         Err("Database not initialized. Please configure a vault first.".to_string())
     }
 }
 
 #[tauri::command]
-async fn update_todo_permissions(
-    app: AppHandle,
-    object_id: i64,
-    permissions: Permissions,
-) -> Result<(), String> {
+async fn load_archived_todos(app: AppHandle) -> Result<Vec<models::AppObject<Todo>>, String> {
     let state = app.state::<Mutex<AppState>>();
     let app_state = state.lock().await;
     
     if let Some(database) = &app_state.database {
-        database.update_object_permissions(object_id, &permissions).await.map_err(|e| e.to_string())?;
-        Ok(())
+        let todos = database.load_todos_by_archived(true).await.map_err(|e| e.to_string())?;
+        Ok(todos)
     } else {
         Err("Database not initialized. Please configure a vault first.".to_string())
     }
 }
 
 #[tauri::command]
-async fn get_sync_status(app: AppHandle) -> Result<models::SyncStatus, String> {
+async fn archive_todo(app: AppHandle, object_id: i64) -> Result<models::AppObject<Todo>, String> {
     let state = app.state::<Mutex<AppState>>();
     let app_state = state.lock().await;
     
-    if let Some(sync_service) = &app_state.sync_service {
-        let service = sync_service.lock().await;
-        let status = service.get_status().await;
-        Ok(status)
+    if let Some(database) = &app_state.database {
+        let todo = database.archive_todo(object_id).await.map_err(|e| e.to_string())?;
+        Ok(todo)
     } else {
-        Err("Sync service not initialized. Please configure a vault first.".to_string())
+        Err("Database not initialized. Please configure a vault first.".to_string())
     }
 }
 
 #[tauri::command]
-async fn get_all_vault_objects(app: AppHandle) -> Result<Vec<models::AppObject<serde_json::Value>>, String> {
+async fn set_todo_due_date(
+    app: AppHandle,
+    object_id: i64,
+    due_date: String,
+    tz: Option<String>,
+) -> Result<models::AppObject<Todo>, String> {
     let state = app.state::<Mutex<AppState>>();
     let app_state = state.lock().await;
     
     if let Some(database) = &app_state.database {
-        // Get all objects from all schemas
-        let todos: Vec<models::AppObject<serde_json::Value>> = database
-            .load_objects_by_schema("core.todo")
+        let todo = database
+            .set_todo_due_date(object_id, &due_date, tz.as_deref())
             .await
-            .map_err(|e| e.to_string())?
-            .into_iter()
-            .map(|obj: models::AppObject<Todo>| models::AppObject {
-                id: obj.id,
-                schema_name: obj.schema_name,
-                content: serde_json::to_value(&obj.content).unwrap_or_default(),
-                permissions: obj.permissions,
-                file_path: obj.file_path,
-                updated_at: obj.updated_at,
-                created_at: obj.created_at,
-            })
-            .collect();
-        
-        Ok(todos)
+            .map_err(|e| e.to_string())?;
+        Ok(todo)
     } else {
         Err("Database not initialized. Please configure a vault first.".to_string())
     }
 }
 
 #[tauri::command]
-async fn update_object_permissions(
-    app: AppHandle,
-    object_id: i64,
-    permissions: Permissions,
-) -> Result<(), String> {
+async fn get_due_todos(app: AppHandle, within_hours: i64) -> Result<models::DueTodos, String> {
     let state = app.state::<Mutex<AppState>>();
     let app_state = state.lock().await;
     
     if let Some(database) = &app_state.database {
-        database.update_object_permissions(object_id, &permissions).await.map_err(|e| e.to_string())?;
-        Ok(())
+        database.get_due_todos(within_hours).await.map_err(|e| e.to_string())
     } else {
         Err("Database not initialized. Please configure a vault first.".to_string())
     }
 }
 
 #[tauri::command]
-async fn delete_todo(app: AppHandle, id: u32) -> Result<Vec<Todo>, String> {
-    let mut todos = load_todos(app.clone()).await?;
-    todos.retain(|t| t.id != Some(id));
+async fn add_todo_v2(app: AppHandle, text: String) -> Result<models::AppObject<Todo>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
     
-    save_todos(app, todos.clone()).await?;
-    Ok(todos)
+    if let Some(database) = &app_state.database {
+        let todo = Todo::new(text);
+        let object_id = database.save_object(
+            "core.todo",
+            &todo,
+            None, // We could specify a file path here
+            None, // Default permissions
+            Some(database::SOURCE_UI),
+        ).await.map_err(|e| e.to_string())?;
+        
+        let saved_todo = database.load_object(object_id).await.map_err(|e| e.to_string())?;
+        Ok(saved_todo)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
 }
 
-// Plugin system test command
 #[tauri::command]
-async fn ping_plugins(app: AppHandle) -> Result<String, String> {
+async fn update_todo_v2(app: AppHandle, object_id: i64, patch: TodoPatch) -> Result<models::AppObject<Todo>, String> {
     let state = app.state::<Mutex<AppState>>();
     let app_state = state.lock().await;
     
-    if let Some(ref manager) = app_state.sidecar_manager {
-        match manager.send_request("ping".to_string(), serde_json::Value::Null).await {
-            Ok(response) => {
-                if let Some(error) = response.error {
-                    Err(format!("Sidecar error: {}", error))
-                } else if let Some(result) = response.result {
-                    Ok(format!("Plugin response: {}", result))
-                } else {
-                    Ok("Plugin responded successfully".to_string())
-                }
-            }
-            Err(e) => Err(format!("Failed to communicate with plugins: {}", e))
-        }
+    if let Some(database) = &app_state.database {
+        database.update_todo(object_id, patch).await.map_err(|e| e.to_string())
     } else {
-        Err("Plugin system not initialized".to_string())
+        Err("Database not initialized. Please configure a vault first.".to_string())
     }
 }
 
-// Get plugin manager information
 #[tauri::command]
-async fn get_plugin_info(app: AppHandle) -> Result<serde_json::Value, String> {
+async fn load_notes(app: AppHandle) -> Result<Vec<models::AppObject<Note>>, String> {
     let state = app.state::<Mutex<AppState>>();
     let app_state = state.lock().await;
     
-    if let Some(ref manager) = app_state.sidecar_manager {
-        match manager.send_request("get_info".to_string(), serde_json::Value::Null).await {
-            Ok(response) => {
-                if let Some(error) = response.error {
-                    Err(format!("Sidecar error: {}", error))
-                } else if let Some(result) = response.result {
-                    Ok(result)
-                } else {
-                    Err("No result from plugin manager".to_string())
-                }
-            }
-            Err(e) => Err(format!("Failed to communicate with plugins: {}", e))
-        }
+    if let Some(database) = &app_state.database {
+        let notes = database.load_objects_by_schema("core.note").await.map_err(|e| e.to_string())?;
+        Ok(notes)
     } else {
-        Err("Plugin system not initialized".to_string())
+        Err("Database not initialized. Please configure a vault first.".to_string())
     }
 }
 
-// Plugin management commands
 #[tauri::command]
-async fn discover_plugins(app: AppHandle) -> Result<Vec<InstalledPlugin>, String> {
-    let plugins_dir = get_plugins_directory(&app)?;
-    log::info!("Looking for plugins in directory: {:?}", plugins_dir);
-    let mut plugins = Vec::new();
-
-    if !plugins_dir.exists() {
-        log::info!("Plugins directory does not exist, creating it: {:?}", plugins_dir);
-        fs::create_dir_all(&plugins_dir).map_err(|e| format!("Failed to create plugins directory: {}", e))?;
-        return Ok(plugins);
-    }
-
-    let entries = fs::read_dir(&plugins_dir).map_err(|e| format!("Failed to read plugins directory: {}", e))?;
-
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-        let path = entry.path();
-        log::info!("Checking path: {:?}", path);
-
-        if path.is_dir() {
-            let plugin_json_path = path.join("plugin.json");
-            if plugin_json_path.exists() {
-                match load_plugin_metadata(&plugin_json_path) {
-                    Ok(metadata) => {
-                        let plugin = InstalledPlugin {
-                            metadata,
-                            path: path.to_string_lossy().to_string(),
-                            enabled: true, // Default to enabled
-                            installed_at: chrono::Utc::now().to_rfc3339(),
-                            last_used: None,
-                        };
-                        plugins.push(plugin);
-                    }
-                    Err(e) => {
-                        log::warn!("Failed to load plugin metadata from {:?}: {}", plugin_json_path, e);
-                    }
-                }
-            }
-        }
+async fn add_note(
+    app: AppHandle,
+    title: String,
+    body: String,
+    tags: Option<Vec<String>>,
+) -> Result<models::AppObject<Note>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let note = database
+            .add_note(&title, &body, tags.unwrap_or_default())
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(note)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
     }
-
-    Ok(plugins)
 }
 
 #[tauri::command]
-async fn test_plugin(app: AppHandle, plugin_id: String) -> Result<PluginStatus, String> {
+async fn update_note(app: AppHandle, object_id: i64, patch: NotePatch) -> Result<models::AppObject<Note>, String> {
     let state = app.state::<Mutex<AppState>>();
     let app_state = state.lock().await;
     
-    if let Some(ref manager) = app_state.sidecar_manager {
-        let params = serde_json::json!({ "plugin_id": plugin_id });
-        match manager.send_request("test_plugin".to_string(), params).await {
-            Ok(response) => {
-                if let Some(error) = response.error {
-                    Ok(PluginStatus {
-                        plugin_id: plugin_id.clone(),
-                        status: "error".to_string(),
-                        last_ping: Some(chrono::Utc::now().to_rfc3339()),
-                        error_message: Some(error),
-                    })
-                } else {
-                    Ok(PluginStatus {
-                        plugin_id: plugin_id.clone(),
-                        status: "active".to_string(),
-                        last_ping: Some(chrono::Utc::now().to_rfc3339()),
-                        error_message: None,
-                    })
-                }
-            }
-            Err(e) => Ok(PluginStatus {
-                plugin_id: plugin_id.clone(),
-                status: "error".to_string(),
-                last_ping: Some(chrono::Utc::now().to_rfc3339()),
-                error_message: Some(e.to_string()),
-            })
-        }
+    if let Some(database) = &app_state.database {
+        let note = database.update_note(object_id, patch).await.map_err(|e| e.to_string())?;
+        Ok(note)
     } else {
-        Err("Plugin system not initialized".to_string())
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn delete_note(app: AppHandle, object_id: i64) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.delete_note(object_id).await.map_err(|e| e.to_string())?;
+        Ok(())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_subtasks(app: AppHandle, parent_object_id: i64) -> Result<Vec<models::AppObject<Todo>>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let subtasks = database.get_subtasks(parent_object_id).await.map_err(|e| e.to_string())?;
+        Ok(subtasks)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_todo_progress(app: AppHandle, object_id: i64) -> Result<Option<f64>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let progress = database.subtask_progress(object_id).await.map_err(|e| e.to_string())?;
+        Ok(progress)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn complete_todo_v2(
+    app: AppHandle,
+    object_id: i64,
+    completed: bool,
+    complete_with_subtasks: Option<bool>,
+) -> Result<models::AppObject<Todo>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let todo = database
+            .set_todo_completed(object_id, completed, complete_with_subtasks.unwrap_or(false))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(todo)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn update_todo_permissions(
+    app: AppHandle,
+    object_id: i64,
+    permissions: Permissions,
+) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.update_object_permissions(object_id, &permissions).await.map_err(|e| e.to_string())?;
+        Ok(())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_sync_status(app: AppHandle) -> Result<models::SyncStatus, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        let service = sync_service.lock().await;
+        // Make sure a dead watcher shows up even between watchdog ticks
+        service.check_watcher_health().await;
+        let status = service.get_status().await;
+        Ok(status)
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn import_legacy_todos(app: AppHandle) -> Result<usize, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        let service = sync_service.lock().await;
+        service.import_legacy_todos().await.map_err(|e| e.to_string())
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_sync_errors(app: AppHandle) -> Result<Vec<SyncError>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        let service = sync_service.lock().await;
+        Ok(service.get_errors().await)
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn clear_sync_errors(app: AppHandle) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        let service = sync_service.lock().await;
+        service.clear_errors().await;
+        Ok(())
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn compact_sync_errors(app: AppHandle) -> Result<Vec<SyncError>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        let service = sync_service.lock().await;
+        Ok(service.compact_errors().await)
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn force_sync(app: AppHandle, paths: Option<Vec<String>>) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        let service = sync_service.lock().await;
+        service.force_sync(paths.as_deref()).await.map_err(|e| e.to_string())?;
+        Ok(())
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn set_watch_paths(app: AppHandle, paths: Vec<String>) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        let service = sync_service.lock().await;
+        service.set_watch_paths(paths).await.map_err(|e| e.to_string())?;
+        Ok(())
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn pause_sync(app: AppHandle) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        sync_service.lock().await.pause().await;
+        Ok(())
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn resume_sync(app: AppHandle) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        let service = sync_service.lock().await;
+        service.resume().await.map_err(|e| e.to_string())?;
+        Ok(())
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn rebuild_database(app: AppHandle) -> Result<usize, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        let service = sync_service.lock().await;
+        let reindexed = service.rebuild_index().await.map_err(|e| e.to_string())?;
+        Ok(reindexed)
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_conflicts(app: AppHandle) -> Result<Vec<SyncConflict>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let conflicts = database.get_conflicts().await.map_err(|e| e.to_string())?;
+        Ok(conflicts)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn resolve_conflict(app: AppHandle, id: i64, keep: Keep) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        let service = sync_service.lock().await;
+        service.resolve_conflict(id, keep).await.map_err(|e| e.to_string())?;
+        Ok(())
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn set_schema_file_backed(app: AppHandle, schema_name: String, enabled: bool) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database
+            .set_schema_file_backed(&schema_name, enabled)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn validate_vault(app: AppHandle) -> Result<VaultValidationReport, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.validate_vault().await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn deduplicate_schema(app: AppHandle, schema_name: String, dry_run: bool) -> Result<DeduplicationReport, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database
+            .deduplicate_schema(&schema_name, dry_run)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn merge_objects(
+    app: AppHandle,
+    primary_id: i64,
+    secondary_id: i64,
+    strategy: MergeStrategy,
+) -> Result<models::AppObject<serde_json::Value>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database
+            .merge_objects(primary_id, secondary_id, strategy)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn reclassify_object(
+    app: AppHandle,
+    object_id: i64,
+    new_schema_name: String,
+    mapping: Option<HashMap<String, String>>,
+) -> Result<models::AppObject<serde_json::Value>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database
+            .reclassify_object(object_id, &new_schema_name, mapping.as_ref())
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_expiring_shares(app: AppHandle, within_hours: i64) -> Result<Vec<models::AppObject<serde_json::Value>>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.get_expiring_shares(within_hours).await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn search_objects(
+    app: AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<models::AppObject<serde_json::Value>>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database
+            .search_objects(&query, limit.unwrap_or(50))
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn reindex_search(app: AppHandle) -> Result<usize, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.reindex_search().await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn snapshot_todos(app: AppHandle, label: String) -> Result<TodoSnapshotInfo, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.snapshot_todos(&label).await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn list_todo_snapshots(app: AppHandle) -> Result<Vec<TodoSnapshotInfo>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.list_todo_snapshots().await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn restore_todo_snapshot(app: AppHandle, label: String) -> Result<usize, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.restore_todo_snapshot(&label).await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_quarantined_files(app: AppHandle) -> Result<Vec<QuarantinedFile>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.get_quarantined_files().await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn retry_quarantined(app: AppHandle) -> Result<usize, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(sync_service) = &app_state.sync_service {
+        let service = sync_service.lock().await;
+        service.retry_quarantined().await.map_err(|e| e.to_string())
+    } else {
+        Err("Sync service not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn check_integrity(app: AppHandle, repair: bool) -> Result<IntegrityReport, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.check_integrity(repair).await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn validate_against_schema(
+    app: AppHandle,
+    schema_name: String,
+    document: serde_json::Value,
+) -> Result<Vec<ValidationError>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let errors = database
+            .validate_against_schema(&schema_name, &document)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(errors)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn unregister_schema(app: AppHandle, schema_name: String, delete_objects: Option<bool>) -> Result<usize, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let deleted = database
+            .unregister_schema(&schema_name, delete_objects.unwrap_or(false))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(deleted)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_objects_in_range(
+    app: AppHandle,
+    schema_name: Option<String>,
+    field: DateField,
+    from: String,
+    to: String,
+) -> Result<Vec<models::AppObject<serde_json::Value>>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let objects = database
+            .load_objects_in_range(schema_name.as_deref(), field, &from, &to)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(objects)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_objects_by_source(
+    app: AppHandle,
+    source: String,
+    schema_name: Option<String>,
+) -> Result<Vec<models::AppObject<serde_json::Value>>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let objects = database
+            .load_objects_by_source(&source, schema_name.as_deref())
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(objects)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_completion_stats(app: AppHandle, bucket: analytics::Bucket) -> Result<Vec<(String, usize)>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let stats = database
+            .completion_timeseries("core.todo", bucket)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(stats)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn schema_field_stats(app: AppHandle, schema_name: String) -> Result<SchemaFieldStats, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.schema_field_stats(&schema_name).await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn build_ai_context(app: AppHandle, max_tokens: Option<usize>) -> Result<AiContext, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let context = database
+            .build_ai_context(max_tokens.unwrap_or(ai_context::DEFAULT_MAX_TOKENS))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(context)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_cache_stats(app: AppHandle) -> Result<CacheStats, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        Ok(database.cache_stats())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_performance_stats() -> Result<Vec<OperationStats>, String> {
+    Ok(perf::performance_stats())
+}
+
+#[tauri::command]
+async fn vault_fingerprint(app: AppHandle) -> Result<String, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.vault_fingerprint().await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn permission_summary(app: AppHandle) -> Result<PermissionSummary, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.permission_summary().await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_storage_breakdown(app: AppHandle) -> Result<StorageBreakdown, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let breakdown = database.storage_breakdown().await.map_err(|e| e.to_string())?;
+        Ok(breakdown)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn export_objects_ndjson(app: AppHandle, schema_name: Option<String>, dest_path: String) -> Result<usize, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let count = database
+            .export_objects_ndjson(schema_name.as_deref(), Path::new(&dest_path))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(count)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn export_object(
+    app: AppHandle,
+    object_id: i64,
+    format: ExportFormat,
+    dest_path: Option<String>,
+) -> Result<String, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database
+            .export_object(object_id, format, dest_path.as_deref().map(Path::new))
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn import_objects_ndjson(
+    app: AppHandle,
+    source_path: String,
+    schema_name: Option<String>,
+) -> Result<ImportReport, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database
+            .import_objects_ndjson(Path::new(&source_path), schema_name.as_deref())
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_all_vault_objects(app: AppHandle) -> Result<Vec<models::AppObject<serde_json::Value>>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        // Get all objects from all schemas
+        let objects = database.load_all_objects().await.map_err(|e| e.to_string())?;
+        Ok(objects)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn load_objects(
+    app: AppHandle,
+    schema_name: String,
+    order: Option<ObjectOrder>,
+) -> Result<Vec<models::AppObject<serde_json::Value>>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let objects = database
+            .load_objects_by_schema_ordered(&schema_name, order.unwrap_or(ObjectOrder::Created))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(objects)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn reorder_objects(app: AppHandle, ordered_ids: Vec<i64>) -> Result<usize, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.reorder_objects(&ordered_ids).await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn update_object_permissions(
+    app: AppHandle,
+    object_id: i64,
+    permissions: Permissions,
+) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.update_object_permissions(object_id, &permissions).await.map_err(|e| e.to_string())?;
+        Ok(())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn update_permissions_bulk(
+    app: AppHandle,
+    object_ids: Vec<i64>,
+    permissions: Permissions,
+) -> Result<BulkUpdateResult, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database
+            .update_permissions_bulk(&object_ids, &permissions)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn set_object_meta(
+    app: AppHandle,
+    object_id: i64,
+    key: String,
+    value: serde_json::Value,
+) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.set_meta(object_id, &key, &value).await.map_err(|e| e.to_string())?;
+        Ok(())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+// Without a key, returns every meta entry of the object
+#[tauri::command]
+async fn get_object_meta(
+    app: AppHandle,
+    object_id: i64,
+    key: Option<String>,
+) -> Result<HashMap<String, serde_json::Value>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        match key {
+            Some(key) => {
+                let value = database.get_meta(object_id, &key).await.map_err(|e| e.to_string())?;
+                Ok(value.into_iter().map(|v| (key.clone(), v)).collect())
+            }
+            None => database.get_all_meta(object_id).await.map_err(|e| e.to_string()),
+        }
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn patch_object(app: AppHandle, object_id: i64, ops: Vec<PatchOp>) -> Result<serde_json::Value, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.patch_object(object_id, &ops).await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn clone_object(
+    app: AppHandle,
+    object_id: i64,
+    new_file_path: Option<String>,
+    options: Option<CloneOptions>,
+) -> Result<models::AppObject<serde_json::Value>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database
+            .clone_object(object_id, new_file_path.as_deref(), &options.unwrap_or_default())
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn move_object(app: AppHandle, object_id: i64, new_relative_dir: String) -> Result<String, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let file_path = database.move_object(object_id, &new_relative_dir).await.map_err(|e| e.to_string())?;
+        Ok(file_path)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn batch_operations(app: AppHandle, ops: Vec<ObjectOp>, plugin_id: Option<String>) -> Result<Vec<i64>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        // Plugins pass their id so the objects they create are attributed to them
+        let result = match &plugin_id {
+            Some(plugin_id) => database.plugin_batch_operations(plugin_id, &ops).await,
+            None => database.batch_operations(&ops).await,
+        };
+        result.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_object_links(app: AppHandle, object_id: i64) -> Result<Vec<ObjectLink>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.get_object_links(object_id).await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn query_objects(
+    app: AppHandle,
+    schema_name: String,
+    field_path: String,
+    value: serde_json::Value,
+) -> Result<Vec<models::AppObject<serde_json::Value>>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.query_objects(&schema_name, &field_path, &value).await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn list_fileless_objects(app: AppHandle) -> Result<Vec<models::AppObject<serde_json::Value>>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.list_fileless_objects().await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn tag_objects_matching(
+    app: AppHandle,
+    filter: TagFilter,
+    add_tags: Vec<String>,
+    remove_tags: Vec<String>,
+) -> Result<usize, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database
+            .tag_objects_matching(&filter, &add_tags, &remove_tags)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn diff_object_file(app: AppHandle, object_id: i64) -> Result<Vec<FieldDiff>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.diff_object_file(object_id).await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn materialize_object(
+    app: AppHandle,
+    object_id: i64,
+    relative_path: String,
+) -> Result<models::AppObject<serde_json::Value>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.materialize_object(object_id, &relative_path).await.map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn add_attachment(
+    app: AppHandle,
+    object_id: i64,
+    filename: String,
+    bytes: Vec<u8>,
+) -> Result<Attachment, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let attachment = database.add_attachment(object_id, &filename, &bytes).await.map_err(|e| e.to_string())?;
+        Ok(attachment)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn list_attachments(app: AppHandle, object_id: i64) -> Result<Vec<Attachment>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        let attachments = database.get_attachments(object_id).await.map_err(|e| e.to_string())?;
+        Ok(attachments)
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+#[tauri::command]
+async fn delete_todo(app: AppHandle, id: u32) -> Result<Vec<Todo>, String> {
+    let mut todos = load_todos(app.clone()).await?;
+    todos.retain(|t| t.id != Some(id));
+    
+    save_todos(app, todos.clone()).await?;
+    Ok(todos)
+}
+
+// Backup commands
+#[tauri::command]
+async fn list_backups(app: AppHandle) -> Result<Vec<BackupInfo>, String> {
+    let vault_root = get_vault_root(&app)?;
+    backup::list_backups(&vault_root).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn restore_backup(app: AppHandle, name: String) -> Result<(), String> {
+    let vault_root = get_vault_root(&app)?;
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        backup::restore_backup(database, &vault_root, &name).await.map_err(|e| e.to_string())?;
+        Ok(())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
+
+// Vault file access commands
+#[tauri::command]
+async fn read_vault_file(app: AppHandle, relative_path: String) -> Result<VaultFileContents, String> {
+    let vault_root = get_vault_root(&app)?;
+    vault_fs::read_vault_file(&vault_root, &relative_path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn write_vault_file(app: AppHandle, relative_path: String, contents: String) -> Result<(), String> {
+    let vault_root = get_vault_root(&app)?;
+    vault_fs::write_vault_file(&vault_root, &relative_path, contents.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Plugin system test command
+#[tauri::command]
+async fn ping_plugins(app: AppHandle) -> Result<String, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(ref manager) = app_state.sidecar_manager {
+        let reply = manager.call::<sidecar_protocol::Ping>(()).await.map_err(|e| e.to_string())?;
+        Ok(format!("Plugin response: {}", reply))
+    } else {
+        Err("Plugin system not initialized".to_string())
+    }
+}
+
+// Get plugin manager information
+#[tauri::command]
+async fn get_plugin_info(app: AppHandle) -> Result<sidecar_protocol::PluginManagerInfo, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(ref manager) = app_state.sidecar_manager {
+        manager.call::<sidecar_protocol::GetInfo>(()).await.map_err(|e| e.to_string())
+    } else {
+        Err("Plugin system not initialized".to_string())
+    }
+}
+
+// Plugin management commands
+#[tauri::command]
+async fn discover_plugins(app: AppHandle) -> Result<Vec<InstalledPlugin>, String> {
+    let plugins_dir = get_plugins_directory(&app)?;
+    log::info!("Looking for plugins in directory: {:?}", plugins_dir);
+
+    if !plugins_dir.exists() {
+        log::info!("Plugins directory does not exist, creating it: {:?}", plugins_dir);
+        fs::create_dir_all(&plugins_dir).map_err(|e| format!("Failed to create plugins directory: {}", e))?;
+        return Ok(Vec::new());
+    }
+
+    plugins::scan_plugins_dir(&plugins_dir)
+}
+
+#[tauri::command]
+async fn get_plugin_errors(app: AppHandle) -> Result<Vec<PluginManifestError>, String> {
+    let plugins_dir = get_plugins_directory(&app)?;
+
+    let errors = plugins::scan_plugins_dir(&plugins_dir)?
+        .into_iter()
+        .filter(|plugin| !plugin.errors.is_empty())
+        .map(|plugin| PluginManifestError {
+            plugin_id: plugin.metadata.id,
+            path: plugin.path,
+            errors: plugin.errors,
+        })
+        .collect();
+
+    Ok(errors)
+}
+
+#[tauri::command]
+async fn test_plugin(app: AppHandle, plugin_id: String) -> Result<PluginStatus, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(ref manager) = app_state.sidecar_manager {
+        Ok(manager.test_plugin(plugin_id).await)
+    } else {
+        Err("Plugin system not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_plugin_last_status(app: AppHandle, plugin_id: String) -> Result<Option<PluginStatus>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(ref manager) = app_state.sidecar_manager {
+        Ok(manager.last_status(&plugin_id))
+    } else {
+        Err("Plugin system not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+async fn get_plugin_logs(app: AppHandle, lines: Option<usize>) -> Result<Vec<PluginLogLine>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(ref manager) = app_state.sidecar_manager {
+        Ok(manager.plugin_logs().recent(lines.unwrap_or(usize::MAX)))
+    } else {
+        Err("Plugin system not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+async fn sidecar_pending_count(app: AppHandle) -> Result<usize, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(ref manager) = app_state.sidecar_manager {
+        Ok(manager.pending_count().await)
+    } else {
+        Err("Plugin system not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+async fn reload_plugin(app: AppHandle, plugin_id: String) -> Result<PluginStatus, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(ref manager) = app_state.sidecar_manager {
+        Ok(manager.reload_plugin(plugin_id).await)
+    } else {
+        Err("Plugin system not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+async fn list_scheduled_jobs(app: AppHandle) -> Result<Vec<ScheduledJob>, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(ref plugin_scheduler) = app_state.plugin_scheduler {
+        Ok(plugin_scheduler.lock().await.jobs())
+    } else {
+        Err("Plugin system not initialized".to_string())
     }
 }
 
+/// Runs a plugin's scheduled job right away, without waiting for its next time.
+#[tauri::command]
+async fn run_plugin_now(app: AppHandle, plugin_id: String) -> Result<PluginStatus, String> {
+    let (manager, plugin_scheduler) = {
+        let state = app.state::<Mutex<AppState>>();
+        let app_state = state.lock().await;
+        match (&app_state.sidecar_manager, &app_state.plugin_scheduler) {
+            (Some(manager), Some(plugin_scheduler)) => (Arc::clone(manager), Arc::clone(plugin_scheduler)),
+            _ => return Err("Plugin system not initialized".to_string()),
+        }
+    };
+    
+    Ok(scheduler::run_job(&plugin_scheduler, &manager, &plugin_id).await)
+}
+
+#[tauri::command]
+async fn test_all_plugins(app: AppHandle) -> Result<Vec<PluginStatus>, String> {
+    let manager = {
+        let state = app.state::<Mutex<AppState>>();
+        let app_state = state.lock().await;
+        app_state.sidecar_manager.clone().ok_or("Plugin system not initialized")?
+    };
+
+    let plugin_ids = plugins::scan_plugins_dir(&get_plugins_directory(&app)?)?
+        .into_iter()
+        .map(|plugin| plugin.metadata.id)
+        .collect();
+
+    Ok(manager.test_plugins(plugin_ids).await)
+}
+
+#[tauri::command]
+async fn get_plugin_status_summary(app: AppHandle) -> Result<Vec<PluginStatusSummary>, String> {
+    let manager = {
+        let state = app.state::<Mutex<AppState>>();
+        let app_state = state.lock().await;
+        app_state.sidecar_manager.clone()
+    };
+
+    let discovered = plugins::scan_plugins_dir(&get_plugins_directory(&app)?)?;
+
+    let loaded = match manager {
+        Some(manager) => match manager.call::<sidecar_protocol::ListPlugins>(()).await {
+            Ok(loaded) => Some(loaded),
+            Err(e) => {
+                log::warn!("Could not list loaded plugins: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    Ok(plugins::summarize_plugins(&discovered, loaded.as_deref()))
+}
+
 fn get_plugins_directory(app: &AppHandle) -> Result<PathBuf, String> {
     // Get the current vault configuration to find the vault path
     if let Some(config) = get_vault_config_sync(app)? {
@@ -562,10 +2096,8 @@ fn get_plugins_directory(app: &AppHandle) -> Result<PathBuf, String> {
     }
 }
 
-fn load_plugin_metadata(plugin_json_path: &Path) -> Result<PluginMetadata, Box<dyn std::error::Error>> {
-    let content = fs::read_to_string(plugin_json_path)?;
-    let metadata: PluginMetadata = serde_json::from_str(&content)?;
-    Ok(metadata)
+fn installed_plugin_dir(app: &AppHandle, plugin_id: &str) -> Result<PathBuf, String> {
+    plugins::installed_plugin_dir(&get_plugins_directory(app)?, plugin_id)
 }
 
 // Plugin installation commands
@@ -576,125 +2108,204 @@ async fn open_plugin_file_dialog() -> Result<Option<String>, String> {
 }
 
 #[tauri::command]
-async fn install_plugin_from_path(app: AppHandle, file_path: String) -> Result<String, String> {
+async fn validate_plugin(source: String) -> Result<PluginMetadata, String> {
+    let source = plugins::PluginSource::parse(&source)?;
+    plugins::validate_plugin(&source)
+}
+
+#[tauri::command]
+async fn inspect_plugin_schemas(source: String) -> Result<Vec<PluginSchemaInfo>, String> {
+    let source = plugins::PluginSource::parse(&source)?;
+    plugins::inspect_plugin_schemas(&source)
+}
+
+#[tauri::command]
+async fn install_plugin_from_path(app: AppHandle, file_path: String, verify_checksum: Option<bool>) -> Result<InstalledPlugin, String> {
     let plugins_dir = get_plugins_directory(&app)?;
-    
-    // Ensure plugins directory exists
-    if !plugins_dir.exists() {
-        fs::create_dir_all(&plugins_dir).map_err(|e| format!("Failed to create plugins directory: {}", e))?;
-    }
+    let staging_dir = get_vault_root(&app)?.join(".nexus").join("staging");
 
     let archive_path = Path::new(&file_path);
     if !archive_path.exists() {
         return Err("File does not exist".to_string());
     }
 
-    extract_plugin_archive(archive_path, &plugins_dir)?;
-    Ok(format!("Plugin installed from: {}", file_path))
+    let source = plugins::PluginSource::Archive(archive_path.to_path_buf());
+    let (metadata, plugin_path) =
+        plugins::install_plugin(&source, &plugins_dir, &staging_dir, verify_checksum.unwrap_or(true))?;
+    log::info!("Plugin '{}' installed from: {}", metadata.id, file_path);
+    register_plugin_schemas(&app, &metadata).await?;
+    Ok(InstalledPlugin::new(metadata, &plugin_path))
 }
 
 #[tauri::command]
-async fn install_plugin_from_github(app: AppHandle, github_url: String) -> Result<String, String> {
-    use std::process::Command;
-    
+async fn install_plugin_from_github(app: AppHandle, github_url: String, verify_checksum: Option<bool>) -> Result<InstalledPlugin, String> {
     let plugins_dir = get_plugins_directory(&app)?;
-    
-    // Ensure plugins directory exists
-    if !plugins_dir.exists() {
-        fs::create_dir_all(&plugins_dir).map_err(|e| format!("Failed to create plugins directory: {}", e))?;
-    }
+    let staging_dir = get_vault_root(&app)?.join(".nexus").join("staging");
 
     // Validate GitHub URL
-    if !github_url.starts_with("https://github.com/") && !github_url.starts_with("git@github.com:") {
+    if !plugins::is_github_url(&github_url) {
         return Err("Invalid GitHub URL. Must start with https://github.com/ or git@github.com:".to_string());
     }
 
-    // Extract repository name for the folder
-    let repo_name = github_url
-        .split('/')
-        .last()
-        .unwrap_or("unknown-plugin")
-        .replace(".git", "");
-
-    let plugin_path = plugins_dir.join(&repo_name);
-
-    // Clone the repository
-    let output = Command::new("git")
-        .args(&["clone", &github_url, plugin_path.to_str().unwrap()])
-        .output()
-        .map_err(|e| format!("Failed to execute git clone: {}", e))?;
-
-    if output.status.success() {
-        // Verify the plugin has the required files
-        let plugin_json = plugin_path.join("plugin.json");
-        if plugin_json.exists() {
-            Ok(format!("Plugin '{}' installed successfully from GitHub", repo_name))
-        } else {
-            // Clean up invalid plugin
-            let _ = fs::remove_dir_all(&plugin_path);
-            Err("Invalid plugin: plugin.json not found in repository".to_string())
-        }
-    } else {
-        Err(format!("Git clone failed: {}", String::from_utf8_lossy(&output.stderr)))
-    }
+    let source = plugins::PluginSource::GitHub(github_url);
+    let (metadata, plugin_path) =
+        plugins::install_plugin(&source, &plugins_dir, &staging_dir, verify_checksum.unwrap_or(true))?;
+    log::info!("Plugin '{}' installed successfully from GitHub", metadata.id);
+    register_plugin_schemas(&app, &metadata).await?;
+    Ok(InstalledPlugin::new(metadata, &plugin_path))
+}
+
+// The registry URL from the open vault's settings
+async fn plugin_registry_url(app: &AppHandle) -> Result<String, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    let database = app_state
+        .database
+        .as_ref()
+        .ok_or("Database not initialized. Please configure a vault first.")?;
+    database
+        .settings()
+        .plugin_registry_url
+        .ok_or_else(|| registry::RegistryError::NotConfigured.to_string())
 }
 
 #[tauri::command]
-async fn remove_plugin(app: AppHandle, plugin_id: String) -> Result<String, String> {
-    let plugins_dir = get_plugins_directory(&app)?;
-    let plugin_path = plugins_dir.join(&plugin_id);
+async fn search_plugin_registry(app: AppHandle, query: String) -> Result<Vec<registry::RegistryEntry>, String> {
+    let url = plugin_registry_url(&app).await?;
+    let entries = registry::fetch_registry(&url).await.map_err(|e| e.to_string())?;
+    Ok(registry::search_registry(entries, &query))
+}
+
+#[tauri::command]
+async fn install_plugin_from_registry(app: AppHandle, plugin_id: String, verify_checksum: Option<bool>) -> Result<InstalledPlugin, String> {
+    let url = plugin_registry_url(&app).await?;
+    let install_url = registry::resolve_install_url(&url, &plugin_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    install_plugin_from_github(app, install_url, verify_checksum).await
+}
+
+#[tauri::command]
+async fn verify_plugin(app: AppHandle, plugin_id: String) -> Result<PluginVerification, String> {
+    let plugin_dir = installed_plugin_dir(&app, &plugin_id)?;
+    let metadata = plugins::load_plugin_metadata(&plugin_dir.join("plugin.json"))?;
+    plugins::verify_plugin_files(&plugin_dir, &metadata)
+}
+
+#[tauri::command]
+async fn get_plugin_permission_report(app: AppHandle, plugin_id: String) -> Result<PluginPermissionReport, String> {
+    let config = get_vault_config_sync(&app)?.ok_or_else(|| error::NexusError::VaultNotConfigured.to_string())?;
+    let plugin_dir = installed_plugin_dir(&app, &plugin_id)?;
+    let metadata = plugins::load_plugin_metadata(&plugin_dir.join("plugin.json"))?;
+    Ok(plugin_permissions::permission_report(Path::new(&config.vault_path), &metadata))
+}
+
+#[tauri::command]
+async fn set_plugin_permission(app: AppHandle, plugin_id: String, permission: String, granted: bool) -> Result<(), String> {
+    let config = get_vault_config_sync(&app)?.ok_or_else(|| error::NexusError::VaultNotConfigured.to_string())?;
+    installed_plugin_dir(&app, &plugin_id)?;
+    plugin_permissions::set_plugin_permission(Path::new(&config.vault_path), &plugin_id, &permission, granted)
+}
+
+#[tauri::command]
+async fn get_plugin_entry_path(app: AppHandle, plugin_id: String) -> Result<String, String> {
+    let plugin_dir = installed_plugin_dir(&app, &plugin_id)?;
+    let metadata = plugins::load_plugin_metadata(&plugin_dir.join("plugin.json"))?;
+    let entry = plugins::plugin_entry_path(&plugin_dir, &metadata);
+    if !entry.is_file() {
+        return Err(format!("Plugin '{}' entry file '{}' does not exist", plugin_id, metadata.main));
+    }
+    Ok(entry.to_string_lossy().to_string())
+}
+
+// Makes a plugin's data types (and the file extensions they parse) known to sync
+async fn register_plugin_schemas(app: &AppHandle, metadata: &PluginMetadata) -> Result<(), String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+
+    if let Some(database) = &app_state.database {
+        database
+            .register_plugin_schemas(&metadata.id, &metadata.schemas)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
 
-    if plugin_path.exists() {
-        fs::remove_dir_all(&plugin_path).map_err(|e| format!("Failed to remove plugin: {}", e))?;
-        Ok(format!("Plugin '{}' removed successfully", plugin_id))
+#[tauri::command]
+async fn get_plugin_storage(app: AppHandle, plugin_id: String) -> Result<PluginStorage, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
+    
+    if let Some(database) = &app_state.database {
+        database.plugin_storage(&plugin_id).await.map_err(|e| e.to_string())
     } else {
-        Err(format!("Plugin '{}' not found", plugin_id))
+        Err("Database not initialized. Please configure a vault first.".to_string())
     }
 }
 
-fn extract_plugin_archive(archive_path: &Path, plugins_dir: &Path) -> Result<(), String> {
-    use std::process::Command;
+#[tauri::command]
+async fn clear_plugin_data(app: AppHandle, plugin_id: String, delete_objects: bool) -> Result<ClearedPluginData, String> {
+    let state = app.state::<Mutex<AppState>>();
+    let app_state = state.lock().await;
     
-    let extension = archive_path.extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("");
+    if let Some(database) = &app_state.database {
+        database
+            .clear_plugin_data(&plugin_id, delete_objects)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Database not initialized. Please configure a vault first.".to_string())
+    }
+}
 
-    match extension {
-        "zip" => {
-            // Use built-in zip extraction
-            let file = fs::File::open(archive_path).map_err(|e| format!("Failed to open archive: {}", e))?;
-            let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Failed to read zip archive: {}", e))?;
-            
-            for i in 0..archive.len() {
-                let mut file = archive.by_index(i).map_err(|e| format!("Failed to read zip entry: {}", e))?;
-                let outpath = plugins_dir.join(file.mangled_name());
+#[tauri::command]
+async fn remove_plugin(
+    app: AppHandle,
+    plugin_id: String,
+    remove_schemas: Option<bool>,
+    clear_data: Option<bool>,
+) -> Result<String, String> {
+    let plugin_path = installed_plugin_dir(&app, &plugin_id)?;
 
-                if let Some(parent) = outpath.parent() {
-                    fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
-                }
+    // The database cleanup comes first: if it fails the plugin is still there
+    // to retry with. Plugin schemas are namespaced by plugin id, e.g. "my-plugin.note"
+    if remove_schemas.unwrap_or(false) {
+        let state = app.state::<Mutex<AppState>>();
+        let app_state = state.lock().await;
 
-                if !file.name().ends_with('/') {
-                    let mut outfile = fs::File::create(&outpath).map_err(|e| format!("Failed to create file: {}", e))?;
-                    std::io::copy(&mut file, &mut outfile).map_err(|e| format!("Failed to extract file: {}", e))?;
-                }
+        if let Some(database) = &app_state.database {
+            let schema_names = database
+                .get_schema_names_with_prefix(&format!("{}.", plugin_id))
+                .await
+                .map_err(|e| e.to_string())?;
+
+            for schema_name in schema_names {
+                database
+                    .unregister_schema(&schema_name, true)
+                    .await
+                    .map_err(|e| format!("Schema '{}' could not be removed: {}", schema_name, e))?;
             }
-            Ok(())
         }
-        "rar" | "7z" => {
-            // Use 7zip for rar and 7z files
-            let output = Command::new("7z")
-                .args(&["x", archive_path.to_str().unwrap(), &format!("-o{}", plugins_dir.to_str().unwrap())])
-                .output()
-                .map_err(|e| format!("Failed to extract with 7z: {}. Make sure 7-Zip is installed.", e))?;
-
-            if output.status.success() {
-                Ok(())
-            } else {
-                Err(format!("7z extraction failed: {}", String::from_utf8_lossy(&output.stderr)))
-            }
+    }
+
+    // Clearing also deletes the objects the plugin created
+    if clear_data.unwrap_or(false) {
+        let state = app.state::<Mutex<AppState>>();
+        let app_state = state.lock().await;
+
+        if let Some(database) = &app_state.database {
+            database
+                .clear_plugin_data(&plugin_id, true)
+                .await
+                .map_err(|e| format!("Plugin data could not be cleared: {}", e))?;
         }
-        _ => Err(format!("Unsupported archive format: {}", extension))
     }
+
+    fs::remove_dir_all(&plugin_path).map_err(|e| format!("Failed to remove plugin: {}", e))?;
+
+    Ok(format!("Plugin '{}' removed successfully", plugin_id))
 }
 
 // Initialize existing vault on app startup
@@ -717,7 +2328,6 @@ pub fn run() {
         .manage(Mutex::new(AppState::new()))
         .setup(|app| {
             let app_handle = app.handle().clone();
-            let app_handle_clone = app_handle.clone();
             
             tauri::async_runtime::spawn(async move {
                 if let Err(e) = initialize_existing_vault(&app_handle).await {
@@ -725,26 +2335,19 @@ pub fn run() {
                 }
             });
 
-            // Initialize the sidecar manager
-            tauri::async_runtime::spawn(async move {
-                match sidecar::SidecarManager::new(app_handle_clone.clone()).await {
-                    Ok(manager) => {
-                        let state = app_handle_clone.state::<Mutex<AppState>>();
-                        let mut app_state = state.lock().await;
-                        app_state.sidecar_manager = Some(Arc::new(manager));
-                        log::info!("Sidecar manager initialized successfully");
-                    }
-                    Err(e) => {
-                        log::error!("Failed to initialize sidecar manager: {}", e);
-                    }
-                }
-            });
-            
+            // The sidecar is started by initialize_vault_backend once a vault is open
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             greet,
             get_vault_config,
+            get_settings,
+            reconnect_vault,
+            relocate_vault,
+            restart_sidecar,
+            watch_schema,
+            unwatch_schema,
+            update_settings,
             set_vault_path,
             check_directory_info,
             load_todos,
@@ -754,21 +2357,133 @@ pub fn run() {
             delete_todo,
             // New backend-powered commands
             load_todos_v2,
+            load_todos_ordered,
+            load_archived_todos,
+            archive_todo,
+            set_todo_due_date,
+            get_due_todos,
             add_todo_v2,
+            update_todo_v2,
+            load_notes,
+            add_note,
+            update_note,
+            delete_note,
+            get_subtasks,
+            get_todo_progress,
+            complete_todo_v2,
             update_todo_permissions,
             get_sync_status,
+            import_legacy_todos,
+            get_sync_errors,
+            clear_sync_errors,
+            compact_sync_errors,
+            force_sync,
+            set_watch_paths,
+            pause_sync,
+            resume_sync,
+            rebuild_database,
+            get_conflicts,
+            resolve_conflict,
+            validate_against_schema,
+            validate_vault,
+            check_integrity,
+            get_quarantined_files,
+            retry_quarantined,
+            deduplicate_schema,
+            merge_objects,
+            reclassify_object,
+            get_expiring_shares,
+            search_objects,
+            reindex_search,
+            snapshot_todos,
+            list_todo_snapshots,
+            restore_todo_snapshot,
+            set_schema_file_backed,
+            unregister_schema,
             get_all_vault_objects,
+            load_objects,
+            reorder_objects,
+            export_objects_ndjson,
+            export_object,
+            import_objects_ndjson,
+            get_storage_breakdown,
+            vault_fingerprint,
+            permission_summary,
+            get_performance_stats,
+            get_cache_stats,
+            get_objects_in_range,
+            get_objects_by_source,
+            get_completion_stats,
+            schema_field_stats,
+            build_ai_context,
             update_object_permissions,
+            update_permissions_bulk,
+            set_object_meta,
+            get_object_meta,
+            patch_object,
+            clone_object,
+            move_object,
+            batch_operations,
+            get_object_links,
+            query_objects,
+            list_fileless_objects,
+            materialize_object,
+            diff_object_file,
+            tag_objects_matching,
+            add_attachment,
+            list_attachments,
+            // Backup commands
+            list_backups,
+            restore_backup,
+            // Vault file access commands
+            read_vault_file,
+            write_vault_file,
             // Plugin system commands
             ping_plugins,
             get_plugin_info,
             discover_plugins,
+            get_plugin_errors,
             test_plugin,
+            test_all_plugins,
+            reload_plugin,
+            get_plugin_logs,
+            sidecar_pending_count,
+            get_plugin_last_status,
+            list_scheduled_jobs,
+            run_plugin_now,
+            get_plugin_status_summary,
             open_plugin_file_dialog,
+            validate_plugin,
+            inspect_plugin_schemas,
             install_plugin_from_path,
             install_plugin_from_github,
-            remove_plugin
+            search_plugin_registry,
+            install_plugin_from_registry,
+            verify_plugin,
+            get_plugin_entry_path,
+            get_plugin_permission_report,
+            set_plugin_permission,
+            remove_plugin,
+            get_plugin_storage,
+            clear_plugin_data,
+            force_unlock_vault
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Flush pending write-backs, then release the vault lock so the
+                // next launch doesn't have to reclaim it
+                let state = app_handle.state::<Mutex<AppState>>();
+                tauri::async_runtime::block_on(async {
+                    let app_state = state.lock().await;
+                    if let Some(sync_service) = &app_state.sync_service {
+                        sync_service.lock().await.flush_write_back().await;
+                    }
+                    if let Some(database) = &app_state.database {
+                        database.release_vault_lock();
+                    }
+                });
+            }
+        });
 }
@@ -0,0 +1,274 @@
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension};
+use serde_json::Value;
+
+use crate::database::Database;
+use crate::error::Result;
+use crate::models::Todo;
+
+/// The outcome of merging the app's edits into a todos.json.
+#[derive(Debug)]
+pub struct TodoMerge {
+    // The todos array to write
+    pub todos: Vec<Value>,
+    // The new last-synced snapshot
+    pub base: Vec<Value>,
+    // Todos edited both in the app and on disk; the disk version is kept
+    pub conflicts: Vec<EntryConflict>,
+}
+
+#[derive(Debug)]
+pub struct EntryConflict {
+    pub entry: String,
+    pub ours: Value,
+    pub theirs: Value,
+}
+
+/// Three-way merges the app's edited todos (`entries`, keyed by todo id or
+/// `idx<N>` for todos without one) into what's on `disk`, using `base`, the
+/// todos as they were at the last sync. A todo changed on only one side takes
+/// that side's version; one changed differently on both sides is a conflict.
+/// Without a base every edit simply overwrites the disk version.
+pub fn merge_todos(base: Option<&[Value]>, disk: &[Value], entries: &[(Option<&str>, &Value)]) -> TodoMerge {
+    let mut todos = disk.to_vec();
+    let mut conflicts = Vec::new();
+    // Todos the app and the file agree on once this write lands
+    let mut synced = Vec::new();
+
+    for (entry, ours) in entries {
+        let entry = entry.unwrap_or_default();
+
+        // Todos without an id can only be matched by position
+        if let Some(index) = entry.strip_prefix("idx") {
+            match index.parse::<usize>().ok().filter(|i| *i < todos.len()) {
+                Some(i) => todos[i] = (*ours).clone(),
+                None => todos.push((*ours).clone()),
+            }
+            continue;
+        }
+
+        let position = todos.iter().position(|todo| todo_key(todo).as_deref() == Some(entry));
+        let (i, base) = match (position, base) {
+            // New in the app, or deleted on disk while edited in the app
+            (None, _) => {
+                todos.push((*ours).clone());
+                synced.push(entry);
+                continue;
+            }
+            (Some(i), None) => {
+                todos[i] = (*ours).clone();
+                continue;
+            }
+            (Some(i), Some(base)) => (i, base),
+        };
+
+        let theirs = &todos[i];
+        let base_entry = find_todo(base, entry);
+        if same_todo(theirs, ours) {
+            synced.push(entry);
+        } else if base_entry.is_some_and(|base_entry| same_todo(ours, base_entry)) {
+            // Only the file changed; the sync will pick it up
+        } else if base_entry.is_some_and(|base_entry| same_todo(theirs, base_entry)) {
+            todos[i] = (*ours).clone();
+            synced.push(entry);
+        } else {
+            conflicts.push(EntryConflict {
+                entry: entry.to_string(),
+                ours: (*ours).clone(),
+                theirs: theirs.clone(),
+            });
+        }
+    }
+
+    // Todos the app didn't write may differ on disk from the database until the
+    // next sync, so only the ones both sides now agree on move the base forward
+    let unsynced: Vec<String> = match base {
+        Some(_) => todos
+            .iter()
+            .filter_map(todo_key)
+            .filter(|key| !synced.contains(&key.as_str()))
+            .collect(),
+        None => Vec::new(),
+    };
+    let unsynced: Vec<&str> = unsynced.iter().map(String::as_str).collect();
+    let base = rebase(base, &todos, &unsynced);
+    TodoMerge { todos, base, conflicts }
+}
+
+/// The snapshot after a sync of `synced`. Todos listed in `unsynced` (e.g.
+/// conflicted ones) keep their old base so they stay recognisable as changed.
+pub fn rebase(base: Option<&[Value]>, synced: &[Value], unsynced: &[&str]) -> Vec<Value> {
+    synced
+        .iter()
+        .filter_map(|todo| match todo_key(todo) {
+            Some(key) if unsynced.contains(&key.as_str()) => base.and_then(|base| find_todo(base, &key)).cloned(),
+            _ => Some(todo.clone()),
+        })
+        .collect()
+}
+
+pub fn todo_key(todo: &Value) -> Option<String> {
+    todo.get("id").and_then(|id| id.as_u64()).map(|id| id.to_string())
+}
+
+pub fn find_todo<'a>(todos: &'a [Value], key: &str) -> Option<&'a Value> {
+    todos.iter().find(|todo| todo_key(todo).as_deref() == Some(key))
+}
+
+// Compares todos as the app sees them, so fields a hand-written file leaves
+// out don't count as changes
+pub fn same_todo(a: &Value, b: &Value) -> bool {
+    let normalize = |todo: &Value| {
+        serde_json::from_value::<Todo>(todo.clone())
+            .ok()
+            .and_then(|todo| serde_json::to_value(todo).ok())
+            .unwrap_or_else(|| todo.clone())
+    };
+    normalize(a) == normalize(b)
+}
+
+impl Database {
+    /// The todos of `file_path` as of its last sync.
+    pub async fn get_sync_snapshot(&self, file_path: &str) -> Result<Option<Vec<Value>>> {
+        let conn = self.lock_connection().await?;
+
+        let content_json: Option<String> = conn.query_row(
+            "SELECT content_json FROM sync_snapshots WHERE file_path = ?1",
+            params![file_path],
+            |row| row.get(0),
+        ).optional()?;
+
+        match content_json {
+            Some(content_json) => Ok(Some(serde_json::from_str(&content_json)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_sync_snapshot(&self, file_path: &str, todos: &[Value]) -> Result<()> {
+        let conn = self.lock_connection().await?;
+        let content_json = serde_json::to_string(todos)?;
+        let now = Utc::now().to_rfc3339();
+
+        conn.execute(
+            "INSERT INTO sync_snapshots (file_path, content_json, synced_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(file_path) DO UPDATE SET content_json = excluded.content_json, synced_at = excluded.synced_at",
+            params![file_path, content_json, now],
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use crate::models::{AppObject, Keep};
+    use crate::sync_service::SyncService;
+    use crate::test_support::{vault, TWO_TODOS};
+    use crate::write_back::WriteBack;
+
+    fn todo(id: u64, text: &str) -> Value {
+        serde_json::json!({
+            "id": id, "text": text, "completed": false, "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": null, "due_date": null, "priority": null, "tags": null
+        })
+    }
+
+    fn file_texts(vault: &Path) -> Vec<String> {
+        let file: Value = serde_json::from_str(&std::fs::read_to_string(vault.join("Todo/todos.json")).unwrap()).unwrap();
+        file["todos"].as_array().unwrap().iter().map(|todo| todo["text"].as_str().unwrap().to_string()).collect()
+    }
+
+    // A synced vault holding TWO_TODOS, with its todos in file order
+    async fn synced_vault(name: &str) -> (std::path::PathBuf, std::sync::Arc<Database>, SyncService, WriteBack, Vec<i64>) {
+        let (vault, database, sync_service) = vault(name, TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        let write_back = WriteBack::spawn(&database, Duration::from_secs(60));
+        let mut todos: Vec<AppObject<Todo>> = database.load_objects_by_schema("core.todo").await.unwrap();
+        todos.sort_by_key(|todo| todo.content.id);
+        let ids = todos.iter().map(|todo| todo.id).collect();
+        (vault, database, sync_service, write_back, ids)
+    }
+
+    async fn rename(database: &Database, object_id: i64, text: &str) {
+        let mut todo = database.load_object::<Todo>(object_id).await.unwrap().content;
+        todo.text = text.to_string();
+        database.update_object(object_id, &todo).await.unwrap();
+    }
+
+    #[test]
+    fn edits_to_different_todos_are_combined() {
+        let base = vec![todo(1, "a"), todo(2, "b")];
+        let disk = vec![todo(1, "a"), todo(2, "b2")];
+        let (ours, new) = (todo(1, "a1"), todo(3, "c"));
+
+        let merged = merge_todos(Some(&base), &disk, &[(Some("1"), &ours), (Some("3"), &new)]);
+        assert_eq!(merged.todos, vec![todo(1, "a1"), todo(2, "b2"), todo(3, "c")]);
+        assert!(merged.conflicts.is_empty());
+        // Todo 2 changed only on disk, so it stays at the base until synced
+        assert_eq!(merged.base, vec![todo(1, "a1"), todo(2, "b"), todo(3, "c")]);
+    }
+
+    #[test]
+    fn edits_to_the_same_todo_conflict() {
+        let base = vec![todo(1, "a"), todo(2, "b")];
+        let disk = vec![todo(1, "a"), todo(2, "b2")];
+        let ours = todo(2, "b3");
+
+        let merged = merge_todos(Some(&base), &disk, &[(Some("2"), &ours)]);
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!(merged.todos, disk);
+        assert_eq!(merged.base, base);
+
+        // Without a snapshot the app's edit wins
+        assert_eq!(merge_todos(None, &disk, &[(Some("2"), &ours)]).todos[1], ours);
+    }
+
+    #[tokio::test]
+    async fn write_back_keeps_concurrent_disk_edits() {
+        let (vault, database, sync_service, write_back, ids) = synced_vault("merge_clean").await;
+        // The app edits todo 1 while an editor changes todo 2 on disk
+        rename(&database, ids[0], "app").await;
+        std::fs::write(vault.join("Todo/todos.json"), TWO_TODOS.replace(r#""text":"b""#, r#""text":"editor""#)).unwrap();
+
+        assert_eq!(write_back.flush().await, 1);
+        assert_eq!(file_texts(&vault), vec!["app", "editor"]);
+        assert!(database.get_conflicts().await.unwrap().is_empty());
+
+        // The disk-only edit then fast-forwards the database
+        sync_service.force_sync(Some(&["Todo/todos.json".to_string()])).await.unwrap();
+        assert!(database.get_conflicts().await.unwrap().is_empty());
+        assert_eq!(database.load_object::<Todo>(ids[1]).await.unwrap().content.text, "editor");
+        assert_eq!(database.load_object::<Todo>(ids[0]).await.unwrap().content.text, "app");
+    }
+
+    #[tokio::test]
+    async fn clashing_write_backs_are_recorded_as_conflicts() {
+        let (vault, database, sync_service, write_back, ids) = synced_vault("merge_conflict").await;
+        rename(&database, ids[0], "app").await;
+        std::fs::write(vault.join("Todo/todos.json"), TWO_TODOS.replace(r#""text":"a""#, r#""text":"editor""#)).unwrap();
+        write_back.flush().await;
+
+        // The editor's version stays on disk
+        assert_eq!(file_texts(&vault)[0], "editor");
+        let conflicts = database.get_conflicts().await.unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].object_id, ids[0]);
+        assert_eq!(conflicts[0].db_content["text"], "app");
+        assert_eq!(conflicts[0].file_content["text"], "editor");
+
+        // A rescan doesn't record it twice, and keeping the app's version writes it out
+        sync_service.force_sync(Some(&["Todo/todos.json".to_string()])).await.unwrap();
+        assert_eq!(database.get_conflicts().await.unwrap().len(), 1);
+        sync_service.resolve_conflict(conflicts[0].id, Keep::Database).await.unwrap();
+        assert_eq!(file_texts(&vault)[0], "app");
+
+        rename(&database, ids[0], "again").await;
+        write_back.flush().await;
+        assert_eq!(file_texts(&vault)[0], "again");
+        assert!(database.get_conflicts().await.unwrap().is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use chrono::Utc;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 
 // Core data structures
@@ -13,6 +14,48 @@ pub struct Todo {
     pub due_date: Option<String>,
     pub priority: Option<String>,
     pub tags: Option<Vec<String>>,
+    // Todo `id` of the parent task; omitted for top-level todos
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<u32>,
+    // Archived todos are kept but left out of the active list; omitted unless set
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub archived: bool,
+}
+
+// Partial update for a Todo; only fields that are set are applied
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TodoPatch {
+    pub text: Option<String>,
+    pub completed: Option<bool>,
+    pub due_date: Option<String>,
+    pub priority: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub parent_id: Option<u32>,
+}
+
+// Open todos past their due date, and those falling due soon; each list is
+// ordered by due date
+#[derive(Debug, Serialize)]
+pub struct DueTodos {
+    pub overdue: Vec<AppObject<Todo>>,
+    pub upcoming: Vec<AppObject<Todo>>,
+}
+
+// A markdown note; stored on disk as Notes/<name>.md with YAML frontmatter
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Note {
+    pub title: String,
+    pub body: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// Partial update for a Note; only fields that are set are applied
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct NotePatch {
+    pub title: Option<String>,
+    pub body: Option<String>,
+    pub tags: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -23,8 +66,99 @@ pub struct Permissions {
     pub expires_at: Option<String>,
 }
 
+// One step of a batch_operations call; see Database::batch_operations
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum ObjectOp {
+    Create {
+        schema_name: String,
+        content: serde_json::Value,
+        #[serde(default)]
+        permissions: Option<Permissions>,
+    },
+    Update { object: ObjectRef, content: serde_json::Value },
+    Delete { object: ObjectRef },
+    Link { source: ObjectRef, target: ObjectRef, relation: String },
+}
+
+// An existing object's id, or `{"created": n}` for the nth object created earlier in the same batch
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ObjectRef {
+    Existing(i64),
+    Created { created: usize },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ObjectLink {
+    pub source_id: i64,
+    pub target_id: i64,
+    pub relation: String,
+    pub created_at: String,
+}
+
+// One JSON Patch (RFC 6902) operation; `path` is a JSON Pointer into the content
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    Add { path: String, value: serde_json::Value },
+    Replace { path: String, value: serde_json::Value },
+    Remove { path: String },
+}
+
+// What besides the content a cloned object takes from its source.
+// Sharing flags are never copied.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CloneOptions {
+    pub copy_tags: bool,
+    pub copy_meta: bool,
+    // Links to and from the source, re-pointed at the clone
+    pub copy_links: bool,
+}
+
+// Which objects a bulk tag change applies to; every criterion given must match
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TagFilter {
+    pub schema_name: Option<String>,
+    // Vault-relative, e.g. "Projects/**/*.md"
+    pub path_glob: Option<String>,
+    // Objects already carrying this tag
+    pub tag: Option<String>,
+}
+
+// Outcome of a bulk update; ids that matched no object are reported, not fatal
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BulkUpdateResult {
+    pub updated: usize,
+    pub missing_ids: Vec<i64>,
+}
+
+// Outcome of an NDJSON import; failed lines are reported, not fatal
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: Vec<ImportFailure>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportFailure {
+    pub line: usize, // 1-based
+    pub message: String,
+}
+
+// Shared objects bundled for a model prompt
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AiContext {
+    pub text: String,
+    pub object_ids: Vec<i64>,
+    pub truncated: bool,
+}
+
 // Generic container for any object type with metadata
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone)]
 pub struct AppObject<T> {
     pub id: i64,
     pub schema_name: String,
@@ -33,6 +167,21 @@ pub struct AppObject<T> {
     pub file_path: Option<String>,
     pub updated_at: String,
     pub created_at: String,
+    // Who created the object: "ui", "sync", "import", "plugin:<id>" or "unknown"
+    pub source: String,
+}
+
+// Binary blob attached to an object, stored under .nexus/attachments/<hash>
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Attachment {
+    pub id: i64,
+    pub object_id: i64,
+    pub filename: String,
+    pub mime_type: String,
+    pub content_hash: String,
+    pub blob_path: String,
+    pub size_bytes: i64,
+    pub created_at: String,
 }
 
 // Database schema structures
@@ -43,6 +192,8 @@ pub struct Schema {
     pub definition_json: String,
     pub version: String,
     pub created_at: String,
+    // Whether edits made in the app are written back to the objects' files
+    pub file_backed: bool,
 }
 
 #[derive(Debug)]
@@ -75,6 +226,207 @@ pub struct VaultConfig {
     pub encryption_enabled: bool,
 }
 
+// Per-vault settings, stored in .nexus/settings.json so they travel with the vault
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct VaultSettings {
+    #[serde(default = "default_backup_interval_hours")]
+    pub backup_interval_hours: Option<u64>, // None disables automatic backups
+    #[serde(default = "default_backup_keep_count")]
+    pub backup_keep_count: usize,
+    #[serde(default = "default_write_back_delay_ms")]
+    pub write_back_delay_ms: u64,
+    #[serde(default = "default_max_content_bytes")]
+    pub max_content_bytes: usize,
+    // One of off, error, warn, info, debug, trace
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    // Vault-relative paths the sync service leaves alone
+    #[serde(default)]
+    pub ignore_paths: Vec<String>,
+    // Vault-relative folders the sync service is limited to; empty syncs the whole vault
+    #[serde(default)]
+    pub watch_paths: Vec<String>,
+    // JSON index of installable plugins searched by the plugin browser
+    #[serde(default)]
+    pub plugin_registry_url: Option<String>,
+    // How many parsed objects to keep in memory; 0 disables the cache
+    #[serde(default = "default_object_cache_size")]
+    pub object_cache_size: usize,
+    // Most recent sync errors kept in the sync status
+    #[serde(default = "default_max_sync_errors")]
+    pub max_sync_errors: usize,
+    // Consecutive failed syncs after which a file is quarantined; 0 never quarantines
+    #[serde(default = "default_quarantine_after_failures")]
+    pub quarantine_after_failures: u32,
+    // Object changes within one window beyond which the rest of the window's
+    // changes reach the frontend as one vault-changed-batch; 0 never batches
+    #[serde(default = "default_change_batch_threshold")]
+    pub change_batch_threshold: usize,
+    #[serde(default = "default_change_batch_window_ms")]
+    pub change_batch_window_ms: u64,
+    // Reject JSON patches whose result no longer satisfies the object's schema
+    #[serde(default)]
+    pub validate_patches: bool,
+    // Sidecar requests that may await a response at once; the rest queue.
+    // Like the other sidecar settings, read when the sidecar starts.
+    #[serde(default = "default_sidecar_max_in_flight")]
+    pub sidecar_max_in_flight: usize,
+    // None waits for sidecar responses indefinitely
+    #[serde(default)]
+    pub sidecar_request_timeout_secs: Option<u64>,
+    // How long a request may wait for a response before the periodic sweep fails it
+    #[serde(default = "default_sidecar_handler_max_age_secs")]
+    pub sidecar_handler_max_age_secs: u64,
+}
+
+impl Default for VaultSettings {
+    fn default() -> Self {
+        Self {
+            backup_interval_hours: default_backup_interval_hours(),
+            backup_keep_count: default_backup_keep_count(),
+            write_back_delay_ms: default_write_back_delay_ms(),
+            max_content_bytes: default_max_content_bytes(),
+            log_level: default_log_level(),
+            ignore_paths: Vec::new(),
+            watch_paths: Vec::new(),
+            plugin_registry_url: None,
+            object_cache_size: default_object_cache_size(),
+            max_sync_errors: default_max_sync_errors(),
+            quarantine_after_failures: default_quarantine_after_failures(),
+            change_batch_threshold: default_change_batch_threshold(),
+            change_batch_window_ms: default_change_batch_window_ms(),
+            validate_patches: false,
+            sidecar_max_in_flight: default_sidecar_max_in_flight(),
+            sidecar_request_timeout_secs: None,
+            sidecar_handler_max_age_secs: default_sidecar_handler_max_age_secs(),
+        }
+    }
+}
+
+fn default_object_cache_size() -> usize {
+    512
+}
+
+fn default_max_sync_errors() -> usize {
+    100
+}
+
+fn default_quarantine_after_failures() -> u32 {
+    3
+}
+
+fn default_change_batch_threshold() -> usize {
+    20
+}
+
+fn default_change_batch_window_ms() -> u64 {
+    250
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_backup_interval_hours() -> Option<u64> {
+    Some(24)
+}
+
+fn default_backup_keep_count() -> usize {
+    7
+}
+
+fn default_write_back_delay_ms() -> u64 {
+    500
+}
+
+fn default_max_content_bytes() -> usize {
+    crate::database::DEFAULT_MAX_CONTENT_BYTES
+}
+
+fn default_sidecar_max_in_flight() -> usize {
+    crate::sidecar::DEFAULT_MAX_IN_FLIGHT
+}
+
+fn default_sidecar_handler_max_age_secs() -> u64 {
+    crate::sidecar::DEFAULT_HANDLER_MAX_AGE.as_secs()
+}
+
+// Where a vault's bytes go, for deciding what to compact or clean up
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StorageBreakdown {
+    pub database_bytes: u64, // SQLite file plus its write-ahead log
+    pub attachment_bytes: u64, // Each distinct blob counted once
+    pub schemas: Vec<SchemaStorage>,
+    pub vault_bytes: u64, // Everything under the vault directory, .nexus included
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemaStorage {
+    pub schema_name: String,
+    pub object_count: usize,
+    pub content_bytes: u64,
+}
+
+// How often one top-level content field holds a value, and of which JSON types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FieldStats {
+    pub field: String,
+    pub non_null: usize,
+    pub fill_rate: f64,
+    // JSON type name ("string", "number", ...) to how many values had it
+    pub types: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemaFieldStats {
+    pub schema_name: String,
+    pub total_objects: usize,
+    // Objects the stats were computed from; the most recent ones when there are many
+    pub sampled_objects: usize,
+    pub fields: Vec<FieldStats>,
+}
+
+// How many objects are in each permission state. Share counts only include
+// shares that haven't expired; lapsed ones are counted apart
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PermissionSummary {
+    pub total_objects: usize,
+    pub shared_with_ai: usize,
+    pub shared_with_cloud: usize,
+    pub read_only: usize,
+    // Shared with AI or the cloud, but past expires_at
+    pub shared_but_expired: usize,
+    // Shared with neither
+    pub never_shared: usize,
+}
+
+// Hit rate of the in-memory object cache; misses are reads that went to SQLite
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CacheStats {
+    pub capacity: usize,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+// Aggregated timings of one instrumented operation
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OperationStats {
+    pub operation: String,
+    pub calls: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    pub objects: u64, // Objects saved, loaded or files scanned, summed over all calls
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupInfo {
+    pub name: String,
+    pub size_bytes: u64,
+    pub created_at: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VaultInfo {
     pub path: String,
@@ -84,6 +436,14 @@ pub struct VaultInfo {
     pub database_exists: bool,
 }
 
+// Contents of a file read from inside the vault
+#[derive(Debug, Serialize)]
+#[serde(tag = "encoding", content = "data", rename_all = "lowercase")]
+pub enum VaultFileContents {
+    Utf8(String),
+    Binary(Vec<u8>),
+}
+
 // Plugin system structures
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PluginManifest {
@@ -95,7 +455,7 @@ pub struct PluginManifest {
     pub description: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PluginSchema {
     pub name: String,
     pub version: String,
@@ -103,6 +463,200 @@ pub struct PluginSchema {
     pub file_extensions: Vec<String>,
 }
 
+// What a plugin's schema would be registered as, shown before installing
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PluginSchemaInfo {
+    pub name: String,
+    // "<plugin id>.<name>"
+    pub schema_name: String,
+    pub version: String,
+    pub file_extensions: Vec<String>,
+}
+
+// A plugin's footprint in the vault
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginStorage {
+    pub plugin_id: String,
+    // Installed files under plugins/<id>
+    pub plugin_bytes: u64,
+    pub data_dir: String,
+    pub data_bytes: u64,
+    // Objects whose source is "plugin:<id>"
+    pub object_ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ClearedPluginData {
+    pub data_bytes: u64,
+    pub objects_deleted: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+// An object a file event changed, broadcast on the vault-change feed
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ObjectChange {
+    pub kind: ChangeKind,
+    pub object_id: i64,
+    pub schema_name: String,
+    // The file (or todos.json entry) the change came from
+    pub path: String,
+}
+
+// Summary of a burst of object changes, sent as one vault-changed-batch event
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ChangeBatch {
+    pub created: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    // Schemas of the changed objects, sorted
+    pub schemas: Vec<String>,
+}
+
+// A DB object whose backing file was changed independently
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncConflict {
+    pub id: i64,
+    pub object_id: i64,
+    pub file_path: String,
+    pub db_content: serde_json::Value,
+    pub file_content: serde_json::Value,
+    pub detected_at: String,
+}
+
+// One field where an object and its file disagree; None where a side lacks the field
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FieldDiff {
+    pub path: String, // JSON Pointer, empty for the whole document
+    pub db_value: Option<serde_json::Value>,
+    pub file_value: Option<serde_json::Value>,
+}
+
+// Which side wins when resolving a sync conflict
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Keep {
+    Database,
+    File,
+}
+
+// A single JSON Schema violation; `path` is a JSON Pointer into the document
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ValidationError {
+    pub path: String,
+    pub message: String,
+}
+
+// An object whose content no longer satisfies its schema
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvalidObject {
+    pub object_id: i64,
+    pub schema_name: String,
+    pub file_path: Option<String>,
+    pub errors: Vec<ValidationError>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VaultValidationReport {
+    pub checked: usize,
+    pub invalid: Vec<InvalidObject>,
+}
+
+// Outcome of deduplicate_schema; `removed_ids` are the duplicates deleted,
+// or that would be on a dry run
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeduplicationReport {
+    pub duplicate_groups: usize,
+    pub merged: usize,
+    pub removed_ids: Vec<i64>,
+    pub dry_run: bool,
+}
+
+// A file skipped by scans after failing to sync too many times in a row
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuarantinedFile {
+    pub file_path: String,
+    pub failures: u32,
+    pub last_error: String,
+    pub quarantined_at: String,
+}
+
+// Outcome of check_integrity. `integrity_errors` holds SQLite's integrity
+// check messages and is empty for a healthy file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IntegrityReport {
+    pub integrity_errors: Vec<String>,
+    pub foreign_keys_enabled: bool,
+    pub orphans: Vec<OrphanedRows>,
+    // Whether the orphaned rows were deleted
+    pub repaired: bool,
+}
+
+// Rows of `table` whose object no longer exists
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct OrphanedRows {
+    pub table: String,
+    pub count: usize,
+}
+
+// Sort order for todo lists; completed todos always sink to the bottom
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoOrder {
+    DueDate,
+    Priority,
+    Created,
+}
+
+// Sort order for objects loaded by schema
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectOrder {
+    Created,
+    // Manual order from reorder_objects; objects never reordered come last
+    OrderIndex,
+}
+
+impl ObjectOrder {
+    pub fn order_by(&self) -> &'static str {
+        match self {
+            ObjectOrder::Created => "do.created_at DESC",
+            ObjectOrder::OrderIndex => "do.order_index IS NULL, do.order_index, do.created_at DESC",
+        }
+    }
+}
+
+// Format of a single exported object
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Json,
+    Markdown,
+}
+
+// Timestamp column used by date range queries
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DateField {
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl DateField {
+    pub fn column(&self) -> &'static str {
+        match self {
+            DateField::CreatedAt => "created_at",
+            DateField::UpdatedAt => "updated_at",
+        }
+    }
+}
+
 // Sync service structures
 #[derive(Debug)]
 pub enum SyncEvent {
@@ -118,10 +672,82 @@ pub struct SyncStatus {
     pub is_syncing: bool,
     pub last_sync: Option<String>,
     pub pending_changes: usize,
-    pub errors: Vec<String>,
+    pub errors: Vec<SyncError>,
+    // False while the file watcher is down; cleared only once it is re-established
+    pub watcher_healthy: bool,
+    pub watcher_error: Option<String>,
+    // False once the vault directory has vanished, until reconnect_vault
+    pub vault_available: bool,
+    // True between pause_sync and resume_sync; file events are dropped meanwhile
+    pub paused: bool,
+}
+
+// A sync failure, tied to the file that caused it when there is one
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncError {
+    pub timestamp: String, // Of the latest occurrence
+    pub path: Option<String>,
+    pub message: String,
+    // How many times in a row this error occurred
+    #[serde(default = "default_repeat_count")]
+    pub repeat_count: usize,
+}
+
+impl SyncError {
+    pub fn new(path: Option<&std::path::Path>, message: impl Into<String>) -> Self {
+        Self {
+            timestamp: Utc::now().to_rfc3339(),
+            path: path.map(|p| p.to_string_lossy().to_string()),
+            message: message.into(),
+            repeat_count: 1,
+        }
+    }
+}
+
+fn default_repeat_count() -> usize {
+    1
+}
+
+impl SyncStatus {
+    /// Appends errors, folding one identical to the latest error into it, and
+    /// drops the oldest so no more than `max_errors` are kept.
+    pub fn record_errors(&mut self, errors: impl IntoIterator<Item = SyncError>, max_errors: usize) {
+        for error in errors {
+            match self.errors.last_mut() {
+                Some(last) if last.path == error.path && last.message == error.message => {
+                    last.repeat_count += error.repeat_count;
+                    last.timestamp = error.timestamp;
+                }
+                _ => self.errors.push(error),
+            }
+        }
+
+        let excess = self.errors.len().saturating_sub(max_errors);
+        self.errors.drain(..excess);
+    }
+}
+
+// Payload of the `scan-progress` event emitted while the vault is scanned
+#[derive(Debug, Serialize, Clone)]
+pub struct ScanProgress {
+    pub processed: usize,
+    pub total: usize,
+    pub current_path: String,
 }
 
 // Helper implementations
+impl Permissions {
+    // An unparseable expiry counts as expired so nothing is shared by accident
+    pub fn is_expired(&self) -> bool {
+        match &self.expires_at {
+            None => false,
+            Some(expires_at) => chrono::DateTime::parse_from_rfc3339(expires_at)
+                .map(|expires_at| expires_at <= Utc::now())
+                .unwrap_or(true),
+        }
+    }
+}
+
 impl Todo {
     pub fn new(text: String) -> Self {
         Self {
@@ -133,12 +759,84 @@ impl Todo {
             due_date: None,
             priority: None,
             tags: None,
+            parent_id: None,
+            archived: false,
         }
     }
 
     pub fn mark_updated(&mut self) {
         self.updated_at = Some(Utc::now().to_rfc3339());
     }
+
+    pub fn apply_patch(&mut self, patch: TodoPatch) {
+        if let Some(text) = patch.text {
+            self.text = text;
+        }
+        if let Some(completed) = patch.completed {
+            self.completed = completed;
+        }
+        if let Some(due_date) = patch.due_date {
+            self.due_date = Some(due_date);
+        }
+        if let Some(priority) = patch.priority {
+            self.priority = Some(priority);
+        }
+        if let Some(tags) = patch.tags {
+            self.tags = Some(tags);
+        }
+        if let Some(parent_id) = patch.parent_id {
+            self.parent_id = Some(parent_id);
+        }
+        self.mark_updated();
+    }
+}
+
+impl Note {
+    pub fn apply_patch(&mut self, patch: NotePatch) {
+        if let Some(title) = patch.title {
+            self.title = title;
+        }
+        if let Some(body) = patch.body {
+            self.body = body;
+        }
+        if let Some(tags) = patch.tags {
+            self.tags = tags;
+        }
+    }
+}
+
+impl TodoOrder {
+    pub fn sort(&self, todos: &mut [AppObject<Todo>]) {
+        todos.sort_by(|a, b| {
+            let (a_todo, b_todo) = (&a.content, &b.content);
+            let primary = match self {
+                // Todos without a due date go after those with one
+                TodoOrder::DueDate => a_todo
+                    .due_date
+                    .is_none()
+                    .cmp(&b_todo.due_date.is_none())
+                    .then_with(|| a_todo.due_date.cmp(&b_todo.due_date)),
+                TodoOrder::Priority => priority_rank(&a_todo.priority).cmp(&priority_rank(&b_todo.priority)),
+                TodoOrder::Created => std::cmp::Ordering::Equal,
+            };
+
+            a_todo
+                .completed
+                .cmp(&b_todo.completed)
+                .then(primary)
+                .then_with(|| b.created_at.cmp(&a.created_at))
+        });
+    }
+}
+
+// Lower sorts first; unknown or missing priorities go last
+fn priority_rank(priority: &Option<String>) -> u8 {
+    match priority.as_deref().map(|p| p.to_ascii_lowercase()).as_deref() {
+        Some("high") => 0,
+        Some("medium") => 1,
+        Some("low") => 2,
+        _ => 3,
+    }
 }
 
 impl Default for VaultConfig {
@@ -160,6 +858,7 @@ impl Schema {
             definition_json,
             version: "1.0.0".to_string(),
             created_at: Utc::now().to_rfc3339(),
+            file_backed: true,
         }
     }
 }
@@ -190,15 +889,58 @@ pub struct PluginMetadata {
     pub capabilities: Vec<String>,
     pub category: String,
     pub tags: Vec<String>,
+    // Data types the plugin provides, registered as "<plugin id>.<schema name>"
+    #[serde(default)]
+    pub schemas: Vec<PluginSchema>,
+    // SHA-256 over the plugin's files (see plugins::plugin_files_checksum)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_checksum: Option<String>,
+    // When the host should send the plugin `run_scheduled`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<PluginSchedule>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginSchedule {
+    pub cron: String, // Five-field cron expression, evaluated in local time
+    // Run once on startup if a scheduled time passed while the app was closed
+    #[serde(default)]
+    pub catch_up: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScheduledJob {
+    pub plugin_id: String,
+    pub cron: String,
+    pub catch_up: bool,
+    pub next_run: Option<String>,
+    pub last_run: Option<String>,
+    pub last_status: Option<PluginStatus>, // Outcome of the last run since the app started
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct PluginPermissions {
     pub network: bool,
     pub filesystem: bool,
     pub system: bool,
 }
 
+// A plugin's requested permissions against those it is granted
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginPermissionReport {
+    pub plugin_id: String,
+    pub requested: PluginPermissions,
+    pub granted: PluginPermissions,
+    pub mismatches: Vec<PermissionMismatch>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PermissionMismatch {
+    pub permission: String,
+    pub requested: bool,
+    pub granted: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct InstalledPlugin {
     pub metadata: PluginMetadata,
@@ -206,6 +948,15 @@ pub struct InstalledPlugin {
     pub enabled: bool,
     pub installed_at: String,
     pub last_used: Option<String>,
+    #[serde(default)]
+    pub errors: Vec<String>, // Manifest validation problems; empty when valid
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginManifestError {
+    pub plugin_id: String,
+    pub path: String,
+    pub errors: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -214,4 +965,192 @@ pub struct PluginStatus {
     pub status: String, // "active", "inactive", "error"
     pub last_ping: Option<String>,
     pub error_message: Option<String>,
+    pub latency_ms: Option<u64>,
+}
+
+// Which output stream of the sidecar a log line came from
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+// A line the sidecar or a plugin printed that wasn't an RPC response
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginLogLine {
+    pub stream: LogStream,
+    pub line: String,
+    pub timestamp: String,
+}
+
+// Result of checking an installed plugin's files against its declared checksum
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginVerification {
+    pub plugin_id: String,
+    pub verified: bool, // False when no checksum is declared
+    pub expected: Option<String>,
+    pub actual: String,
+}
+
+// Where a plugin stands on disk versus in the sidecar
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginStatusSummary {
+    pub plugin_id: String,
+    pub on_disk: bool,
+    pub loaded: bool,
+    pub enabled: bool,
+    pub status: String, // "ok", "invalid", "disabled", "not_loaded", "not_on_disk", "sidecar_unavailable"
+}
+
+impl InstalledPlugin {
+    pub fn new(metadata: PluginMetadata, path: &std::path::Path) -> Self {
+        Self {
+            metadata,
+            path: path.to_string_lossy().to_string(),
+            enabled: true, // Default to enabled
+            installed_at: Utc::now().to_rfc3339(),
+            last_used: None,
+            errors: Vec::new(),
+        }
+    }
+
+    // Placeholder entry for a plugin folder whose manifest failed validation
+    pub fn invalid(path: &std::path::Path, errors: Vec<String>) -> Self {
+        let folder_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        Self {
+            metadata: PluginMetadata {
+                name: folder_name.clone(),
+                id: folder_name,
+                version: String::new(),
+                description: String::new(),
+                author: String::new(),
+                main: String::new(),
+                permissions: PluginPermissions {
+                    network: false,
+                    filesystem: false,
+                    system: false,
+                },
+                capabilities: Vec::new(),
+                category: String::new(),
+                tags: Vec::new(),
+                schemas: Vec::new(),
+                files_checksum: None,
+                schedule: None,
+            },
+            path: path.to_string_lossy().to_string(),
+            enabled: false,
+            installed_at: Utc::now().to_rfc3339(),
+            last_used: None,
+            errors,
+        }
+    }
+}
+
+// A saved copy of the vault's todos, restorable with restore_todo_snapshot
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TodoSnapshotInfo {
+    pub label: String,
+    pub created_at: String,
+    pub todo_count: usize,
+}
+
+// How merge_objects resolves fields both objects set
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    // The primary's value wins
+    PreferPrimary,
+    // The value of whichever object was updated last wins
+    PreferNewer,
+    // Text bodies are joined, primary first; other fields as PreferPrimary
+    Concatenate,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo(id: i64, completed: bool, priority: Option<&str>, due_date: Option<&str>, created_at: &str) -> AppObject<Todo> {
+        let mut todo = Todo::new(id.to_string());
+        todo.completed = completed;
+        todo.priority = priority.map(Into::into);
+        todo.due_date = due_date.map(Into::into);
+        AppObject {
+            id,
+            schema_name: "core.todo".to_string(),
+            content: todo,
+            permissions: Permissions::default(),
+            file_path: None,
+            updated_at: created_at.to_string(),
+            created_at: created_at.to_string(),
+            source: "unknown".to_string(),
+        }
+    }
+
+    fn ids(todos: &[AppObject<Todo>]) -> Vec<i64> {
+        todos.iter().map(|todo| todo.id).collect()
+    }
+
+    #[test]
+    fn todo_orders_sink_completed_todos() {
+        let mut todos = vec![
+            todo(1, true, Some("high"), Some("2024-01-01"), "2024-01-01"),
+            todo(2, false, Some("low"), None, "2024-01-02"),
+            todo(3, false, Some("High"), Some("2024-03-01"), "2024-01-03"),
+            todo(4, false, None, Some("2024-02-01"), "2024-01-04"),
+        ];
+
+        TodoOrder::Priority.sort(&mut todos);
+        assert_eq!(ids(&todos), vec![3, 2, 4, 1]);
+        TodoOrder::DueDate.sort(&mut todos);
+        assert_eq!(ids(&todos), vec![4, 3, 2, 1]);
+        TodoOrder::Created.sort(&mut todos);
+        assert_eq!(ids(&todos), vec![4, 3, 2, 1]);
+    }
+
+    fn sync_status() -> SyncStatus {
+        SyncStatus {
+            is_syncing: false,
+            last_sync: None,
+            pending_changes: 0,
+            errors: Vec::new(),
+            watcher_healthy: true,
+            watcher_error: None,
+            vault_available: true,
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn repeated_sync_errors_are_folded_together() {
+        let mut status = sync_status();
+        let path = std::path::Path::new("/v/a.json");
+        status.record_errors([SyncError::new(Some(path), "bad"), SyncError::new(Some(path), "bad")], 10);
+        status.record_errors([SyncError::new(Some(path), "bad")], 10);
+        assert_eq!(status.errors.len(), 1);
+        assert_eq!(status.errors[0].repeat_count, 3);
+
+        // A different path or message starts a new entry
+        status.record_errors([SyncError::new(None, "bad"), SyncError::new(Some(path), "bad")], 10);
+        assert_eq!(status.errors.iter().map(|e| e.repeat_count).collect::<Vec<_>>(), vec![3, 1, 1]);
+    }
+
+    #[test]
+    fn sync_errors_are_capped_to_the_newest() {
+        let mut status = sync_status();
+        for i in 0..20 {
+            status.record_errors([SyncError::new(None, format!("e{}", i))], 5);
+        }
+        assert_eq!(status.errors.len(), 5);
+        assert_eq!((status.errors[0].message.as_str(), status.errors[4].message.as_str()), ("e15", "e19"));
+
+        // Errors saved before the count existed still load
+        let old: SyncError = serde_json::from_str(r#"{"timestamp":"t","path":null,"message":"m"}"#).unwrap();
+        assert_eq!(old.repeat_count, 1);
+    }
 }
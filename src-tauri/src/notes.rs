@@ -0,0 +1,242 @@
+use std::path::{Path, PathBuf};
+
+use crate::database::{Database, SOURCE_UI};
+use crate::error::{NexusError, Result};
+use crate::models::{AppObject, Note, NotePatch};
+use crate::vault_fs;
+
+// Vault folder holding markdown notes
+pub const NOTES_DIR: &str = "Notes";
+
+impl Database {
+    /// Creates a note and its markdown file under Notes/.
+    pub async fn add_note(&self, title: &str, body: &str, tags: Vec<String>) -> Result<AppObject<Note>> {
+        let note = Note {
+            title: title.to_string(),
+            body: body.to_string(),
+            tags,
+        };
+
+        let relative_path = unique_note_path(self.vault_path(), title);
+        let file_path = self.vault_path().join(&relative_path).to_string_lossy().to_string();
+
+        // The row goes in first so the watcher finds the new file already tracked
+        let object_id = self.save_object("core.note", &note, Some(&file_path), None, Some(SOURCE_UI)).await?;
+        if let Err(e) = vault_fs::write_vault_file(self.vault_path(), &relative_path, render_note(&note).as_bytes()) {
+            let _ = self.delete_object(object_id).await;
+            return Err(e);
+        }
+
+        self.load_object(object_id).await
+    }
+
+    /// Applies `patch` to a note. Like other app edits, the markdown file is
+    /// rewritten by the write-back once edits go quiet.
+    pub async fn update_note(&self, object_id: i64, patch: NotePatch) -> Result<AppObject<Note>> {
+        let mut object = self.load_note(object_id).await?;
+        if object.permissions.read_only {
+            return Err(NexusError::PermissionDenied(format!("Note {} is read-only", object_id)));
+        }
+
+        object.content.apply_patch(patch);
+        self.update_object(object_id, &object.content).await?;
+
+        self.load_object(object_id).await
+    }
+
+    /// Deletes a note along with its markdown file.
+    pub async fn delete_note(&self, object_id: i64) -> Result<()> {
+        let object = self.load_note(object_id).await?;
+        if object.permissions.read_only {
+            return Err(NexusError::PermissionDenied(format!("Note {} is read-only", object_id)));
+        }
+
+        if let Some(file_path) = &object.file_path {
+            match std::fs::remove_file(file_path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        self.delete_object(object_id).await
+    }
+
+    async fn load_note(&self, object_id: i64) -> Result<AppObject<Note>> {
+        let object: AppObject<serde_json::Value> = self.load_object(object_id).await?;
+        if object.schema_name != "core.note" {
+            return Err(NexusError::InvalidInput(format!("Object {} is not a note", object_id)));
+        }
+        self.load_object(object_id).await
+    }
+}
+
+/// Whether `path` is a markdown file inside the vault's Notes folder.
+pub fn is_note_file(vault_path: &Path, path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("md") && path.starts_with(vault_path.join(NOTES_DIR))
+}
+
+/// Renders a note as markdown. Frontmatter values are written as JSON, which
+/// is also valid YAML, so any title or tag survives a round trip.
+pub fn render_note(note: &Note) -> String {
+    format!(
+        "---\ntitle: {}\ntags: {}\n---\n{}",
+        serde_json::to_string(&note.title).unwrap_or_default(),
+        serde_json::to_string(&note.tags).unwrap_or_default(),
+        note.body
+    )
+}
+
+/// Parses a markdown note. Without frontmatter the whole file is the body
+/// and `fallback_title` (usually the file name) becomes the title. Line
+/// endings are normalized to `\n`.
+pub fn parse_note(content: &str, fallback_title: &str) -> Note {
+    // Files saved on Windows may use CRLF line endings
+    let content = &content.replace("\r\n", "\n");
+    let mut note = Note {
+        title: fallback_title.to_string(),
+        body: content.to_string(),
+        tags: Vec::new(),
+    };
+
+    let (header, body) = match content
+        .strip_prefix("---\n")
+        .and_then(|rest| rest.split_once("\n---\n").or_else(|| rest.strip_suffix("\n---").map(|h| (h, ""))))
+    {
+        Some(split) => split,
+        None => return note,
+    };
+
+    note.body = body.to_string();
+    for line in header.lines() {
+        match line.split_once(':') {
+            Some(("title", value)) => note.title = parse_scalar(value),
+            Some(("tags", value)) => note.tags = parse_list(value.trim()),
+            _ => {}
+        }
+    }
+
+    note
+}
+
+fn parse_scalar(value: &str) -> String {
+    let value = value.trim();
+    if value.starts_with('"') {
+        if let Ok(parsed) = serde_json::from_str::<String>(value) {
+            return parsed;
+        }
+    }
+    value.trim_matches(|c| c == '"' || c == '\'').to_string()
+}
+
+// Accepts JSON arrays as well as plain YAML flow lists like [a, b]
+fn parse_list(value: &str) -> Vec<String> {
+    if let Ok(parsed) = serde_json::from_str::<Vec<String>>(value) {
+        return parsed;
+    }
+
+    value
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .map(parse_scalar)
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+// Notes/<slug>.md, numbered when the name is taken
+fn unique_note_path(vault_path: &Path, title: &str) -> String {
+    let slug: String = title
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let slug = if slug.is_empty() { "note".to_string() } else { slug };
+
+    let notes_dir: PathBuf = vault_path.join(NOTES_DIR);
+    let mut name = format!("{}.md", slug);
+    let mut counter = 2;
+    while notes_dir.join(&name).exists() {
+        name = format!("{}-{}.md", slug, counter);
+        counter += 1;
+    }
+
+    format!("{}/{}", NOTES_DIR, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_database;
+    use crate::write_back::WriteBack;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn note(title: &str, body: &str, tags: &[&str]) -> Note {
+        Note {
+            title: title.to_string(),
+            body: body.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn rendered_notes_parse_back_unchanged() {
+        for original in [note("Plan: \"Q3\"", "# Heading\n\nbody\n", &["a, b", "c"]), note("Empty", "", &[])] {
+            let parsed = parse_note(&render_note(&original), "fallback");
+            assert_eq!(parsed.title, original.title);
+            assert_eq!(parsed.body, original.body);
+            assert_eq!(parsed.tags, original.tags);
+        }
+    }
+
+    #[test]
+    fn crlf_frontmatter_is_recognized() {
+        let parsed = parse_note("---\r\ntitle: Windows\r\ntags: [x, 'y']\r\n---\r\nline one\r\nline two", "fallback");
+        assert_eq!(parsed.title, "Windows");
+        assert_eq!(parsed.tags, vec!["x", "y"]);
+        assert_eq!(parsed.body, "line one\nline two");
+
+        let plain = parse_note("no frontmatter\r\n", "fallback");
+        assert_eq!(plain.title, "fallback");
+        assert_eq!(plain.body, "no frontmatter\n");
+    }
+
+    #[tokio::test]
+    async fn added_notes_are_tracked_at_their_file() {
+        let (vault, database) = temp_database("notes_add").await;
+
+        let first = database.add_note("My Note!", "hello", vec!["t".to_string()]).await.unwrap();
+        let second = database.add_note("my note", "again", Vec::new()).await.unwrap();
+
+        let first_path = vault.join("Notes/my-note.md");
+        assert_eq!(first.file_path.as_deref(), Some(first_path.to_string_lossy().as_ref()));
+        assert_eq!(second.file_path.unwrap(), vault.join("Notes/my-note-2.md").to_string_lossy());
+        assert_eq!(std::fs::read_to_string(&first_path).unwrap(), render_note(&first.content));
+        assert!(is_note_file(&vault, &first_path));
+    }
+
+    #[tokio::test]
+    async fn note_edits_reach_the_file_through_write_back() {
+        let (vault, database) = temp_database("notes_update").await;
+        let database = Arc::new(database);
+        let write_back = WriteBack::spawn(&database, Duration::from_secs(60));
+        let created = database.add_note("Draft", "old", Vec::new()).await.unwrap();
+
+        let patch = NotePatch { body: Some("new".to_string()), ..Default::default() };
+        let updated = database.update_note(created.id, patch).await.unwrap();
+        assert_eq!(updated.content.body, "new");
+        assert_eq!(updated.content.title, "Draft");
+
+        assert_eq!(write_back.flush().await, 1);
+        let on_disk = std::fs::read_to_string(vault.join("Notes/draft.md")).unwrap();
+        assert_eq!(parse_note(&on_disk, "draft").body, "new");
+
+        database.delete_note(created.id).await.unwrap();
+        assert!(!vault.join("Notes/draft.md").exists());
+    }
+}
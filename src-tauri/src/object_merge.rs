@@ -0,0 +1,226 @@
+use chrono::Utc;
+use rusqlite::params;
+use serde_json::Value;
+
+use crate::database::{content_hash, Database};
+use crate::dedup::move_object_rows;
+use crate::error::{NexusError, Result};
+use crate::models::{AppObject, MergeStrategy};
+
+// Content fields holding free text, joined by MergeStrategy::Concatenate
+const TEXT_BODY_FIELDS: &[&str] = &["body", "description"];
+
+impl Database {
+    /// Folds `secondary_id` into `primary_id` in one transaction: fields are
+    /// resolved by `strategy`, tags are combined, the secondary's links,
+    /// metadata and attachments move to the primary and the secondary is
+    /// deleted, along with the file backing it. Both objects must share a
+    /// schema and be writable. Returns the merged object.
+    pub async fn merge_objects(
+        &self,
+        primary_id: i64,
+        secondary_id: i64,
+        strategy: MergeStrategy,
+    ) -> Result<AppObject<Value>> {
+        if primary_id == secondary_id {
+            return Err(NexusError::InvalidInput("An object can't be merged with itself".to_string()));
+        }
+
+        let primary = self.load_object::<Value>(primary_id).await?;
+        let secondary = self.load_object::<Value>(secondary_id).await?;
+        if primary.schema_name != secondary.schema_name {
+            return Err(NexusError::InvalidInput(format!(
+                "Can't merge a {} object into a {} object",
+                secondary.schema_name, primary.schema_name
+            )));
+        }
+        for object in [&primary, &secondary] {
+            if object.permissions.read_only {
+                return Err(NexusError::PermissionDenied(format!("Object {} is read-only", object.id)));
+            }
+        }
+        // Write-back can't remove an entry from a todos.json, so the next
+        // sync would bring the secondary back
+        if secondary.file_path.as_deref().is_some_and(is_todo_entry) {
+            return Err(NexusError::InvalidInput(format!(
+                "Object {} is an entry of a todos.json and can't be merged away",
+                secondary_id
+            )));
+        }
+
+        let primary_wins = match strategy {
+            MergeStrategy::PreferPrimary | MergeStrategy::Concatenate => true,
+            MergeStrategy::PreferNewer => primary.updated_at >= secondary.updated_at,
+        };
+        let content = merge_content(
+            &primary.content,
+            &secondary.content,
+            primary_wins,
+            strategy == MergeStrategy::Concatenate,
+        );
+        let content_json = serde_json::to_string(&content)?;
+        self.check_content_size(&content_json)?;
+
+        let conn = self.lock_connection().await?;
+        let tx = conn.unchecked_transaction()?;
+
+        tx.execute(
+            "UPDATE object_content SET content_json = ?1 WHERE object_id = ?2",
+            params![content_json, primary_id],
+        )?;
+        tx.execute(
+            "UPDATE data_objects SET content_hash = ?1, updated_at = ?2 WHERE id = ?3",
+            params![content_hash(&content_json), Utc::now().to_rfc3339(), primary_id],
+        )?;
+        let blobs = move_object_rows(&tx, secondary_id, primary_id)?;
+        let deleted = tx.execute("DELETE FROM data_objects WHERE id = ?1", params![secondary_id])?;
+        if deleted == 0 {
+            return Err(NexusError::ObjectNotFound(secondary_id));
+        }
+        tx.commit()?;
+
+        self.remove_orphaned_blobs(&conn, &blobs)?;
+        drop(conn);
+
+        if let Some(file_path) = &secondary.file_path {
+            match std::fs::remove_file(file_path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.notify_changed(primary_id);
+
+        log::info!("Merged object {} into {} ({:?})", secondary_id, primary_id, strategy);
+        self.load_object(primary_id).await
+    }
+}
+
+/// Combines two contents field by field. The winner's fields are kept, the
+/// loser fills in those it lacks or has null, and `tags` become the union of
+/// both, primary first. With `concatenate`, differing text bodies are joined
+/// primary first. Non-object contents resolve to the winner's.
+pub fn merge_content(primary: &Value, secondary: &Value, primary_wins: bool, concatenate: bool) -> Value {
+    let (Value::Object(primary_fields), Value::Object(secondary_fields)) = (primary, secondary) else {
+        return if primary_wins { primary.clone() } else { secondary.clone() };
+    };
+    let (winner, loser) = if primary_wins {
+        (primary_fields, secondary_fields)
+    } else {
+        (secondary_fields, primary_fields)
+    };
+
+    let mut merged = winner.clone();
+    for (key, value) in loser {
+        if merged.get(key).is_none_or(Value::is_null) {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+
+    let mut tags: Vec<Value> = Vec::new();
+    for fields in [primary_fields, secondary_fields] {
+        for tag in fields.get("tags").and_then(|tags| tags.as_array()).into_iter().flatten() {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+    }
+    if !tags.is_empty() {
+        merged.insert("tags".to_string(), Value::Array(tags));
+    }
+
+    if concatenate {
+        for field in TEXT_BODY_FIELDS {
+            let texts = (
+                primary_fields.get(*field).and_then(|v| v.as_str()),
+                secondary_fields.get(*field).and_then(|v| v.as_str()),
+            );
+            if let (Some(first), Some(second)) = texts {
+                let joined = match (first.trim().is_empty(), second.trim().is_empty()) {
+                    (_, true) => first.to_string(),
+                    (true, false) => second.to_string(),
+                    _ if first == second => first.to_string(),
+                    _ => format!("{}\n\n{}", first.trim_end(), second.trim_start()),
+                };
+                merged.insert(field.to_string(), Value::String(joined));
+            }
+        }
+    }
+
+    Value::Object(merged)
+}
+
+/// Whether a tracked path is a todo entry, "<todos.json>#<entry>", rather
+/// than a file of its own.
+pub(crate) fn is_todo_entry(file_path: &str) -> bool {
+    file_path.rsplit_once('#').is_some_and(|(path, _)| path.ends_with("todos.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ObjectOp, ObjectRef, Todo};
+    use crate::test_support::temp_database;
+
+    #[test]
+    fn the_winner_keeps_its_fields_and_tags_are_combined() {
+        let primary = serde_json::json!({ "title": "A", "due": null, "tags": ["x"] });
+        let secondary = serde_json::json!({ "title": "B", "due": "2024-01-01", "tags": ["y", "x"] });
+        let merged = merge_content(&primary, &secondary, true, false);
+        assert_eq!(merged, serde_json::json!({ "title": "A", "due": "2024-01-01", "tags": ["x", "y"] }));
+        assert_eq!(merge_content(&primary, &secondary, false, false)["title"], "B");
+    }
+
+    #[tokio::test]
+    async fn concatenated_notes_combine_bodies_and_take_over_links() {
+        let (_vault, database) = temp_database("merge_notes").await;
+        let primary = database.add_note("Groceries", "milk\n", vec!["home".into()]).await.unwrap();
+        let secondary = database.add_note("Groceries 2", "eggs", vec!["shop".into(), "home".into()]).await.unwrap();
+        let other = database.add_note("Other", "x", vec![]).await.unwrap();
+        database
+            .batch_operations(&[
+                ObjectOp::Link { source: ObjectRef::Existing(secondary.id), target: ObjectRef::Existing(other.id), relation: "refs".into() },
+                ObjectOp::Link { source: ObjectRef::Existing(other.id), target: ObjectRef::Existing(secondary.id), relation: "parent".into() },
+                ObjectOp::Link { source: ObjectRef::Existing(primary.id), target: ObjectRef::Existing(secondary.id), relation: "dup".into() },
+            ])
+            .await
+            .unwrap();
+        database.set_meta(secondary.id, "color", &serde_json::json!("red")).await.unwrap();
+
+        let merged = database.merge_objects(primary.id, secondary.id, MergeStrategy::Concatenate).await.unwrap();
+        assert_eq!(merged.id, primary.id);
+        assert_eq!(merged.content["title"], "Groceries");
+        assert_eq!(merged.content["body"], "milk\n\neggs");
+        assert_eq!(merged.content["tags"], serde_json::json!(["home", "shop"]));
+        assert!(matches!(database.load_object::<Value>(secondary.id).await, Err(NexusError::ObjectNotFound(_))));
+        if let Some(file_path) = &secondary.file_path {
+            assert!(!std::path::Path::new(file_path).exists());
+        }
+
+        // The link between the two merged objects goes away with the secondary
+        let links: Vec<_> = database
+            .get_object_links(primary.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|link| (link.source_id, link.target_id, link.relation))
+            .collect();
+        assert_eq!(links, vec![(primary.id, other.id, "refs".to_string()), (other.id, primary.id, "parent".to_string())]);
+        assert_eq!(database.get_meta(primary.id, "color").await.unwrap(), Some(serde_json::json!("red")));
+    }
+
+    #[tokio::test]
+    async fn newer_objects_win_and_bad_pairs_are_refused() {
+        let (_vault, database) = temp_database("merge_newer").await;
+        let older = database.add_note("Old", "old body", vec![]).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let newer = database.add_note("New", "new body", vec![]).await.unwrap();
+        let merged = database.merge_objects(older.id, newer.id, MergeStrategy::PreferNewer).await.unwrap();
+        assert_eq!((merged.content["title"].as_str(), merged.content["body"].as_str()), (Some("New"), Some("new body")));
+
+        assert!(database.merge_objects(older.id, older.id, MergeStrategy::PreferPrimary).await.is_err());
+        let todo = database.save_object("core.todo", &Todo::new("t".into()), None, None, None).await.unwrap();
+        let mixed = database.merge_objects(older.id, todo, MergeStrategy::PreferPrimary).await;
+        assert!(matches!(mixed, Err(NexusError::InvalidInput(_))));
+    }
+}
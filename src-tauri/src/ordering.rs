@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+
+use rusqlite::{params, OptionalExtension};
+
+use crate::database::Database;
+use crate::error::{NexusError, Result};
+
+impl Database {
+    /// Puts the objects in the given order by assigning fractional order
+    /// indices. Objects already in order keep their index, so moving one item
+    /// rewrites only that row. Returns how many rows were updated.
+    pub async fn reorder_objects(&self, ordered_ids: &[i64]) -> Result<usize> {
+        let mut seen = HashSet::new();
+        if let Some(id) = ordered_ids.iter().find(|id| !seen.insert(**id)) {
+            return Err(NexusError::InvalidInput(format!("Object {} is listed more than once", id)));
+        }
+
+        let conn = self.lock_connection().await?;
+        let tx = conn.unchecked_transaction()?;
+
+        let mut current = Vec::with_capacity(ordered_ids.len());
+        for &id in ordered_ids {
+            let index: Option<f64> = tx
+                .query_row("SELECT order_index FROM data_objects WHERE id = ?1", params![id], |row| row.get(0))
+                .optional()?
+                .ok_or(NexusError::ObjectNotFound(id))?;
+            current.push(index);
+        }
+
+        let assigned = assign_order_indices(&current);
+        let mut updated = 0;
+        for ((&id, old), new) in ordered_ids.iter().zip(&current).zip(&assigned) {
+            if *old != Some(*new) {
+                tx.execute("UPDATE data_objects SET order_index = ?1 WHERE id = ?2", params![new, id])?;
+                updated += 1;
+            }
+        }
+
+        tx.commit()?;
+        Ok(updated)
+    }
+}
+
+/// Order indices for a list whose current indices are `current`. The longest
+/// run of indices that is already increasing is kept; the others are spread
+/// between their kept neighbours (a midpoint for a single item). Falls back
+/// to renumbering 1.0, 2.0, ... once the gaps get too small to split.
+pub fn assign_order_indices(current: &[Option<f64>]) -> Vec<f64> {
+    let kept = longest_increasing(current);
+
+    let mut assigned: Vec<f64> = current.iter().map(|index| index.unwrap_or(0.0)).collect();
+    let mut start = 0;
+    while start < current.len() {
+        if kept[start] {
+            start += 1;
+            continue;
+        }
+        let end = (start..current.len()).find(|&i| kept[i]).unwrap_or(current.len());
+        let low = start.checked_sub(1).map(|i| assigned[i]);
+        let high = if end < current.len() { current[end] } else { None };
+        let count = (end - start) as f64;
+
+        for (offset, i) in (start..end).enumerate() {
+            let step = offset as f64 + 1.0;
+            assigned[i] = match (low, high) {
+                (Some(low), Some(high)) => low + (high - low) * step / (count + 1.0),
+                (Some(low), None) => low + step,
+                (None, Some(high)) => high - (count + 1.0 - step),
+                (None, None) => step,
+            };
+        }
+        start = end;
+    }
+
+    if assigned.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return (1..=current.len()).map(|i| i as f64).collect();
+    }
+    assigned
+}
+
+// Marks the longest strictly increasing subsequence of the existing indices
+fn longest_increasing(current: &[Option<f64>]) -> Vec<bool> {
+    let mut length = vec![0usize; current.len()];
+    let mut previous = vec![None; current.len()];
+
+    for i in 0..current.len() {
+        let Some(index) = current[i] else { continue };
+        length[i] = 1;
+        for j in 0..i {
+            if matches!(current[j], Some(earlier) if earlier < index) && length[j] + 1 > length[i] {
+                length[i] = length[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+
+    let mut kept = vec![false; current.len()];
+    let mut next = (0..current.len()).filter(|&i| length[i] > 0).max_by_key(|&i| length[i]);
+    while let Some(i) = next {
+        kept[i] = true;
+        next = previous[i];
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AppObject, ObjectOrder, Todo};
+    use crate::test_support::temp_database;
+
+    #[test]
+    fn missing_indices_are_filled_between_their_neighbours() {
+        assert_eq!(assign_order_indices(&[Some(1.0), None, Some(2.0)]), vec![1.0, 1.5, 2.0]);
+        assert_eq!(assign_order_indices(&[None, None, None]), vec![1.0, 2.0, 3.0]);
+        assert_eq!(assign_order_indices(&[Some(1.0), Some(2.0), None, None]), vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn moving_one_item_only_changes_that_item() {
+        assert_eq!(assign_order_indices(&[Some(3.0), Some(1.0), Some(2.0)]), vec![0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn gaps_too_small_to_split_are_renumbered() {
+        let tiny = 1.0 + f64::EPSILON;
+        assert_eq!(assign_order_indices(&[Some(1.0), None, Some(tiny)]), vec![1.0, 2.0, 3.0]);
+    }
+
+    #[tokio::test]
+    async fn reordering_writes_only_the_moved_rows() {
+        let (_vault, database) = temp_database("ordering_reorder").await;
+        let mut ids = Vec::new();
+        for text in ["a", "b", "c"] {
+            ids.push(database.save_object("core.todo", &Todo::new(text.into()), None, None, None).await.unwrap());
+        }
+
+        assert_eq!(database.reorder_objects(&[ids[0], ids[1]]).await.unwrap(), 2);
+        // c goes between a and b
+        assert_eq!(database.reorder_objects(&[ids[0], ids[2], ids[1]]).await.unwrap(), 1);
+        assert_eq!(database.reorder_objects(&[ids[0], ids[2], ids[1]]).await.unwrap(), 0);
+
+        let ordered: Vec<AppObject<Todo>> = database
+            .load_objects_by_schema_ordered("core.todo", ObjectOrder::OrderIndex)
+            .await
+            .unwrap();
+        assert_eq!(ordered.iter().map(|t| t.content.text.as_str()).collect::<Vec<_>>(), vec!["a", "c", "b"]);
+    }
+
+    #[tokio::test]
+    async fn reordering_rejects_duplicate_and_unknown_ids() {
+        let (_vault, database) = temp_database("ordering_reorder_invalid").await;
+        let id = database.save_object("core.todo", &Todo::new("a".into()), None, None, None).await.unwrap();
+
+        assert!(matches!(database.reorder_objects(&[id, id]).await, Err(NexusError::InvalidInput(_))));
+        assert!(matches!(database.reorder_objects(&[id, 9999]).await, Err(NexusError::ObjectNotFound(9999))));
+        assert_eq!(database.reorder_objects(&[id]).await.unwrap(), 1);
+    }
+}
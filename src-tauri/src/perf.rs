@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use crate::models::OperationStats;
+
+#[derive(Default)]
+struct Totals {
+    calls: u64,
+    total_micros: u64,
+    max_micros: u64,
+    objects: u64,
+}
+
+// Process-wide, so timings survive switching vaults
+fn totals() -> &'static Mutex<HashMap<&'static str, Totals>> {
+    static TOTALS: OnceLock<Mutex<HashMap<&'static str, Totals>>> = OnceLock::new();
+    TOTALS.get_or_init(Default::default)
+}
+
+/// Times one call of an instrumented operation. When dropped it records the
+/// elapsed time (and object count, if set) on the current tracing span and
+/// adds them to the operation's aggregate.
+pub struct SpanTimer {
+    operation: &'static str,
+    started: Instant,
+    objects: u64,
+}
+
+impl SpanTimer {
+    pub fn start(operation: &'static str) -> Self {
+        Self {
+            operation,
+            started: Instant::now(),
+            objects: 0,
+        }
+    }
+
+    pub fn set_objects(&mut self, objects: usize) {
+        self.objects = objects as u64;
+    }
+}
+
+impl Drop for SpanTimer {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed().as_micros() as u64;
+
+        let span = tracing::Span::current();
+        span.record("elapsed_us", elapsed);
+        span.record("objects", self.objects);
+
+        if let Ok(mut totals) = totals().lock() {
+            let entry = totals.entry(self.operation).or_default();
+            entry.calls += 1;
+            entry.total_micros += elapsed;
+            entry.max_micros = entry.max_micros.max(elapsed);
+            entry.objects += self.objects;
+        }
+    }
+}
+
+/// Aggregated timings of every instrumented operation called so far.
+pub fn performance_stats() -> Vec<OperationStats> {
+    let totals = match totals().lock() {
+        Ok(totals) => totals,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut stats: Vec<OperationStats> = totals
+        .iter()
+        .map(|(operation, totals)| OperationStats {
+            operation: operation.to_string(),
+            calls: totals.calls,
+            total_ms: totals.total_micros as f64 / 1000.0,
+            mean_ms: totals.total_micros as f64 / 1000.0 / totals.calls.max(1) as f64,
+            max_ms: totals.max_micros as f64 / 1000.0,
+            objects: totals.objects,
+        })
+        .collect();
+    stats.sort_by(|a, b| a.operation.cmp(&b.operation));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{vault, write_file, TWO_TODOS};
+
+    fn stats_for(operation: &str) -> OperationStats {
+        performance_stats().into_iter().find(|stats| stats.operation == operation).unwrap()
+    }
+
+    #[test]
+    fn dropped_timers_add_to_their_operation() {
+        for objects in [3, 4] {
+            let mut timer = SpanTimer::start("perf_test_operation");
+            timer.set_objects(objects);
+        }
+
+        let stats = stats_for("perf_test_operation");
+        assert_eq!(stats.calls, 2);
+        assert_eq!(stats.objects, 7);
+        assert!(stats.max_ms <= stats.total_ms);
+    }
+
+    #[tokio::test]
+    async fn scans_and_saves_are_timed() {
+        let (vault, _database, mut sync_service) = vault("perf_scan", TWO_TODOS).await;
+        write_file(&vault, "Notes/first.md", "# First");
+        write_file(&vault, "Notes/second.md", "# Second");
+        sync_service.start().await.unwrap();
+
+        let scan = stats_for("perform_initial_scan");
+        assert!(scan.calls >= 1 && scan.total_ms > 0.0 && scan.objects >= 3);
+        assert!(stats_for("save_object").objects >= 2);
+    }
+}
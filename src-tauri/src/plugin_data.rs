@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+
+use rusqlite::params;
+
+use crate::database::{plugin_source, Database};
+use crate::error::{NexusError, Result};
+use crate::models::{ClearedPluginData, PluginStorage};
+use crate::plugins;
+use crate::vault_fs;
+
+impl Database {
+    // Plugins keep their own data out of their install folder, whose files
+    // are checksummed, under .nexus/plugin-data/<id>
+    fn plugin_data_dir(&self, plugin_id: &str) -> Result<PathBuf> {
+        if !plugins::is_valid_plugin_id(plugin_id) {
+            return Err(NexusError::InvalidInput(format!("Invalid plugin id '{}'", plugin_id)));
+        }
+        Ok(self.vault_path().join(".nexus").join("plugin-data").join(plugin_id))
+    }
+
+    /// What a plugin occupies in the vault: the size of its install folder and
+    /// data directory, and the objects attributed to it.
+    pub async fn plugin_storage(&self, plugin_id: &str) -> Result<PluginStorage> {
+        let data_dir = self.plugin_data_dir(plugin_id)?;
+        let object_ids = self.plugin_object_ids(plugin_id).await?;
+
+        Ok(PluginStorage {
+            plugin_id: plugin_id.to_string(),
+            plugin_bytes: vault_fs::directory_size(&self.vault_path().join("plugins").join(plugin_id)),
+            data_bytes: vault_fs::directory_size(&data_dir),
+            data_dir: data_dir.to_string_lossy().to_string(),
+            object_ids,
+        })
+    }
+
+    /// Deletes a plugin's data directory and, with `delete_objects`, every
+    /// object attributed to it. The plugin itself stays installed.
+    pub async fn clear_plugin_data(&self, plugin_id: &str, delete_objects: bool) -> Result<ClearedPluginData> {
+        let data_dir = self.plugin_data_dir(plugin_id)?;
+        let data_bytes = vault_fs::directory_size(&data_dir);
+        match std::fs::remove_dir_all(&data_dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut objects_deleted = 0;
+        if delete_objects {
+            let object_ids = self.plugin_object_ids(plugin_id).await?;
+            let conn = self.lock_connection().await?;
+            let tx = conn.unchecked_transaction()?;
+
+            let mut blobs = Vec::new();
+            for object_id in &object_ids {
+                let mut stmt = tx.prepare("SELECT content_hash, blob_path FROM attachments WHERE object_id = ?1")?;
+                let rows = stmt.query_map(params![object_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                for blob in rows {
+                    blobs.push(blob?);
+                }
+                objects_deleted += tx.execute("DELETE FROM data_objects WHERE id = ?1", params![object_id])?;
+            }
+            tx.commit()?;
+
+            self.remove_orphaned_blobs(&conn, &blobs)?;
+        }
+
+        log::info!(
+            "Cleared data of plugin '{}': {} bytes, {} objects",
+            plugin_id, data_bytes, objects_deleted
+        );
+        Ok(ClearedPluginData { data_bytes, objects_deleted })
+    }
+
+    async fn plugin_object_ids(&self, plugin_id: &str) -> Result<Vec<i64>> {
+        let conn = self.lock_connection().await?;
+        let mut stmt = conn.prepare("SELECT id FROM data_objects WHERE source = ?1 ORDER BY id")?;
+        let ids = stmt
+            .query_map(params![plugin_source(plugin_id)], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ObjectOp, Todo};
+    use crate::test_support::{temp_database, write_file};
+
+    fn create_todo(text: &str) -> ObjectOp {
+        serde_json::from_value(serde_json::json!({
+            "op": "create", "schema_name": "core.todo", "content": Todo::new(text.into())
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn objects_created_by_a_plugin_are_attributed_to_it() {
+        let (_vault, database) = temp_database("plugin_batch_source").await;
+        let created = database.plugin_batch_operations("habits", &[create_todo("h")]).await.unwrap();
+        database.batch_operations(&[create_todo("u")]).await.unwrap();
+
+        assert_eq!(database.plugin_storage("habits").await.unwrap().object_ids, created);
+        let invalid = database.plugin_batch_operations("../x", &[create_todo("x")]).await;
+        assert!(matches!(invalid, Err(NexusError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn storage_is_reported_and_cleared_on_request() {
+        let (vault, database) = temp_database("plugin_storage").await;
+        write_file(&vault, "plugins/habits/plugin.json", "0123456789");
+        write_file(&vault, ".nexus/plugin-data/habits/sub/a.bin", &"0".repeat(100));
+        write_file(&vault, ".nexus/plugin-data/habits/b.bin", &"0".repeat(20));
+        let mine = database.plugin_batch_operations("habits", &[create_todo("h")]).await.unwrap()[0];
+        database.plugin_batch_operations("other", &[create_todo("o")]).await.unwrap();
+        let ui = database.batch_operations(&[create_todo("u")]).await.unwrap()[0];
+
+        let storage = database.plugin_storage("habits").await.unwrap();
+        assert_eq!((storage.plugin_bytes, storage.data_bytes, storage.object_ids), (10, 120, vec![mine]));
+        assert!(matches!(database.plugin_storage("../x").await, Err(NexusError::InvalidInput(_))));
+
+        let kept = database.clear_plugin_data("habits", false).await.unwrap();
+        assert_eq!(kept, ClearedPluginData { data_bytes: 120, objects_deleted: 0 });
+        assert!(!vault.join(".nexus/plugin-data/habits").exists());
+        assert_eq!(database.plugin_storage("habits").await.unwrap().object_ids, vec![mine]);
+
+        let cleared = database.clear_plugin_data("habits", true).await.unwrap();
+        assert_eq!(cleared, ClearedPluginData { data_bytes: 0, objects_deleted: 1 });
+        assert!(database.load_object::<serde_json::Value>(mine).await.is_err());
+        database.load_object::<serde_json::Value>(ui).await.unwrap();
+        assert_eq!(database.plugin_storage("other").await.unwrap().object_ids.len(), 1);
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::models::{PermissionMismatch, PluginMetadata, PluginPermissionReport, PluginPermissions};
+use crate::vault_fs;
+
+// Every permission a manifest can declare
+const PERMISSIONS: [&str; 3] = ["network", "filesystem", "system"];
+
+// The user's per-plugin overrides; a permission without one is granted as declared
+type Overrides = HashMap<String, BTreeMap<String, bool>>;
+
+pub fn permission_overrides_path(vault_path: &Path) -> PathBuf {
+    vault_path.join(".nexus").join("plugin_permissions.json")
+}
+
+fn load_overrides(vault_path: &Path) -> Overrides {
+    match std::fs::read_to_string(permission_overrides_path(vault_path)) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("Ignoring unreadable plugin permission overrides: {}", e);
+            Overrides::default()
+        }),
+        Err(_) => Overrides::default(),
+    }
+}
+
+/// Grants or revokes one permission of a plugin, whatever its manifest asks
+/// for. Overrides are kept in the vault.
+pub fn set_plugin_permission(vault_path: &Path, plugin_id: &str, permission: &str, granted: bool) -> Result<(), String> {
+    if !PERMISSIONS.contains(&permission) {
+        return Err(format!(
+            "Unknown permission '{}', expected one of {}",
+            permission,
+            PERMISSIONS.join(", ")
+        ));
+    }
+
+    let mut overrides = load_overrides(vault_path);
+    overrides
+        .entry(plugin_id.to_string())
+        .or_default()
+        .insert(permission.to_string(), granted);
+
+    let content = serde_json::to_string_pretty(&overrides).map_err(|e| e.to_string())?;
+    vault_fs::atomic_write(&permission_overrides_path(vault_path), content.as_bytes())
+        .map_err(|e| format!("Failed to save plugin permissions: {}", e))?;
+
+    log::info!("Permission '{}' of plugin {} set to {}", permission, plugin_id, granted);
+    Ok(())
+}
+
+/// What a plugin's manifest requests next to what it is granted once the
+/// user's overrides are applied, and every permission where the two differ.
+pub fn permission_report(vault_path: &Path, metadata: &PluginMetadata) -> PluginPermissionReport {
+    let requested = metadata.permissions.clone();
+    let overrides = load_overrides(vault_path).remove(&metadata.id).unwrap_or_default();
+
+    let mut granted = requested.clone();
+    let mut mismatches = Vec::new();
+    for permission in PERMISSIONS {
+        // Still the requested value until overridden
+        let flag = permission_flag(&mut granted, permission);
+        let asked = *flag;
+        let given = overrides.get(permission).copied().unwrap_or(asked);
+        *flag = given;
+        if given != asked {
+            mismatches.push(PermissionMismatch {
+                permission: permission.to_string(),
+                requested: asked,
+                granted: given,
+            });
+        }
+    }
+
+    PluginPermissionReport {
+        plugin_id: metadata.id.clone(),
+        requested,
+        granted,
+        mismatches,
+    }
+}
+
+fn permission_flag<'a>(permissions: &'a mut PluginPermissions, permission: &str) -> &'a mut bool {
+    match permission {
+        "network" => &mut permissions.network,
+        "filesystem" => &mut permissions.filesystem,
+        _ => &mut permissions.system,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins;
+    use crate::test_support::temp_dir;
+
+    fn metadata_for(id: &str) -> PluginMetadata {
+        plugins::parse_plugin_manifest(&format!(
+            r#"{{
+                "name": "Test Plugin", "id": "{}", "version": "1.0.0", "description": "", "author": "",
+                "main": "index.ts", "permissions": {{ "network": true, "filesystem": true, "system": false }},
+                "capabilities": [], "category": "utility", "tags": []
+            }}"#,
+            id
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn overrides_reduce_the_granted_set() {
+        let vault = temp_dir("plugin_permission_overrides");
+        // An open vault always has its .nexus folder
+        std::fs::create_dir_all(vault.join(".nexus")).unwrap();
+        let metadata = metadata_for("test-plugin");
+        let report = permission_report(&vault, &metadata);
+        assert_eq!(report.granted, report.requested);
+        assert!(report.mismatches.is_empty());
+
+        set_plugin_permission(&vault, &metadata.id, "network", false).unwrap();
+        let report = permission_report(&vault, &metadata);
+        assert!(report.requested.network && !report.granted.network);
+        assert!(report.granted.filesystem && !report.granted.system);
+        let mismatch = PermissionMismatch { permission: "network".into(), requested: true, granted: false };
+        assert_eq!(report.mismatches, vec![mismatch]);
+        assert!(permission_report(&vault, &metadata_for("other")).granted.network);
+
+        // Overriding back to the requested value clears the mismatch
+        set_plugin_permission(&vault, &metadata.id, "network", true).unwrap();
+        assert!(permission_report(&vault, &metadata).mismatches.is_empty());
+    }
+
+    #[test]
+    fn unknown_permissions_are_refused() {
+        let vault = temp_dir("plugin_permission_unknown");
+        let error = set_plugin_permission(&vault, "test-plugin", "camera", true).unwrap_err();
+        assert!(error.contains("Unknown permission"));
+        assert!(!permission_overrides_path(&vault).exists());
+    }
+}
@@ -0,0 +1,783 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use sha2::{Digest, Sha256};
+
+use crate::models::{InstalledPlugin, PluginMetadata, PluginSchemaInfo, PluginStatusSummary, PluginVerification};
+use crate::scheduler;
+
+/// Where a plugin is being validated or installed from.
+pub enum PluginSource {
+    Archive(PathBuf),
+    GitHub(String),
+}
+
+impl PluginSource {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        if is_github_url(source) {
+            return Ok(PluginSource::GitHub(source.to_string()));
+        }
+
+        let path = PathBuf::from(source);
+        if !path.exists() {
+            return Err("File does not exist".to_string());
+        }
+        Ok(PluginSource::Archive(path))
+    }
+}
+
+pub fn is_github_url(url: &str) -> bool {
+    url.starts_with("https://github.com/") || url.starts_with("git@github.com:")
+}
+
+/// Scratch directory for unpacking a plugin, removed again when dropped.
+pub struct StagingDir {
+    path: PathBuf,
+}
+
+impl StagingDir {
+    pub fn new(parent: &Path) -> Result<Self, String> {
+        let unique = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        let path = parent.join(format!("plugin-{}-{}", std::process::id(), unique));
+        fs::create_dir_all(&path).map_err(|e| format!("Failed to create staging directory: {}", e))?;
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+pub fn load_plugin_metadata(plugin_json_path: &Path) -> Result<PluginMetadata, String> {
+    let content = fs::read_to_string(plugin_json_path)
+        .map_err(|e| format!("Failed to read plugin.json: {}", e))?;
+    parse_plugin_manifest(&content)
+}
+
+pub fn parse_plugin_manifest(content: &str) -> Result<PluginMetadata, String> {
+    check_plugin_manifest(content).map_err(|errors| format!("Invalid plugin.json: {}", errors.join("; ")))
+}
+
+/// Parses a plugin.json, collecting every problem found instead of stopping at the first.
+pub fn check_plugin_manifest(content: &str) -> Result<PluginMetadata, Vec<String>> {
+    let value: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| vec![format!("plugin.json is not valid JSON: {}", e)])?;
+
+    let errors = validate_manifest_fields(&value);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let metadata: PluginMetadata = serde_json::from_value(value).map_err(|e| vec![e.to_string()])?;
+
+    let errors = check_plugin_metadata(&metadata);
+    if errors.is_empty() {
+        Ok(metadata)
+    } else {
+        Err(errors)
+    }
+}
+
+fn validate_manifest_fields(value: &serde_json::Value) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    let manifest = match value.as_object() {
+        Some(manifest) => manifest,
+        None => return vec!["plugin.json must contain a JSON object".to_string()],
+    };
+
+    for field in ["name", "id", "version", "description", "author", "main", "category"] {
+        match manifest.get(field) {
+            None => errors.push(format!("missing required field '{}'", field)),
+            Some(v) if !v.is_string() => errors.push(format!("{} must be a string", field)),
+            _ => {}
+        }
+    }
+
+    match manifest.get("permissions") {
+        None => errors.push("missing required field 'permissions'".to_string()),
+        Some(serde_json::Value::Object(permissions)) => {
+            for permission in ["network", "filesystem", "system"] {
+                match permissions.get(permission) {
+                    None => errors.push(format!("missing required field 'permissions.{}'", permission)),
+                    Some(v) if !v.is_boolean() => errors.push(format!("permissions.{} must be boolean", permission)),
+                    _ => {}
+                }
+            }
+        }
+        Some(_) => errors.push("permissions must be an object".to_string()),
+    }
+
+    for field in ["capabilities", "tags"] {
+        match manifest.get(field) {
+            None => errors.push(format!("missing required field '{}'", field)),
+            Some(serde_json::Value::Array(items)) => {
+                if let Some(index) = items.iter().position(|item| !item.is_string()) {
+                    errors.push(format!("{}[{}] must be a string", field, index));
+                }
+            }
+            Some(_) => errors.push(format!("{} must be an array of strings", field)),
+        }
+    }
+
+    errors
+}
+
+/// Whether `plugin_id` can name a plugin's folder: letters, digits, '-', '_'
+/// and '.', not starting with a dot, so it never leaves the plugins directory.
+pub fn is_valid_plugin_id(plugin_id: &str) -> bool {
+    !plugin_id.is_empty()
+        && !plugin_id.starts_with('.')
+        && plugin_id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// An installed plugin's folder under `plugins_dir`. The id is checked before
+/// it is joined, so it can't point outside the plugins directory.
+pub fn installed_plugin_dir(plugins_dir: &Path, plugin_id: &str) -> Result<PathBuf, String> {
+    if !is_valid_plugin_id(plugin_id) {
+        return Err(format!("Invalid plugin id '{}'", plugin_id));
+    }
+
+    let plugin_dir = plugins_dir.join(plugin_id);
+    if !plugin_dir.join("plugin.json").exists() {
+        return Err(format!("Plugin '{}' is not installed", plugin_id));
+    }
+    Ok(plugin_dir)
+}
+
+fn check_plugin_metadata(metadata: &PluginMetadata) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if metadata.id.is_empty() {
+        problems.push("'id' must not be empty".to_string());
+    } else if !is_valid_plugin_id(&metadata.id) {
+        problems.push(format!(
+            "'id' must only contain letters, digits, '-', '_' or '.' (got '{}')",
+            metadata.id
+        ));
+    }
+
+    if metadata.name.trim().is_empty() {
+        problems.push("'name' must not be empty".to_string());
+    }
+
+    if metadata.version.trim().is_empty() {
+        problems.push("'version' must not be empty".to_string());
+    }
+
+    let main = Path::new(&metadata.main);
+    if metadata.main.trim().is_empty() {
+        problems.push("'main' must not be empty".to_string());
+    } else if main.is_absolute() || main.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir)) {
+        problems.push(format!("'main' must be a path inside the plugin (got '{}')", metadata.main));
+    }
+
+    if let Some(schedule) = &metadata.schedule {
+        if let Err(e) = scheduler::CronSchedule::parse(&schedule.cron) {
+            problems.push(format!("'schedule.cron' is invalid: {}", e));
+        }
+    }
+
+    // Capabilities that need a permission must have it requested explicitly
+    let permissions = &metadata.permissions;
+    for capability in &metadata.capabilities {
+        let granted = match capability.as_str() {
+            "network" => permissions.network,
+            "filesystem" => permissions.filesystem,
+            "system" => permissions.system,
+            _ => true,
+        };
+        if !granted {
+            problems.push(format!(
+                "capability '{}' requires permissions.{} to be true",
+                capability, capability
+            ));
+        }
+    }
+
+    problems
+}
+
+/// The file the sidecar loads for a plugin: its `main`, relative to the plugin folder.
+pub fn plugin_entry_path(plugin_dir: &Path, metadata: &PluginMetadata) -> PathBuf {
+    plugin_dir.join(&metadata.main)
+}
+
+/// Lists every plugin folder in `plugins_dir`; folders with a broken plugin.json
+/// or a missing entry file are still returned, carrying their validation errors.
+pub fn scan_plugins_dir(plugins_dir: &Path) -> Result<Vec<InstalledPlugin>, String> {
+    let mut plugins = Vec::new();
+    let entries = fs::read_dir(plugins_dir).map_err(|e| format!("Failed to read plugins directory: {}", e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+        log::info!("Checking path: {:?}", path);
+
+        let plugin_json_path = path.join("plugin.json");
+        if !path.is_dir() || !plugin_json_path.exists() {
+            continue;
+        }
+
+        let checked = fs::read_to_string(&plugin_json_path)
+            .map_err(|e| vec![format!("Failed to read plugin.json: {}", e)])
+            .and_then(|content| check_plugin_manifest(&content));
+
+        match checked {
+            Ok(metadata) => {
+                let entry = plugin_entry_path(&path, &metadata);
+                let mut plugin = InstalledPlugin::new(metadata, &path);
+                // The sidecar would fail to load it, so don't report it as enabled
+                if !entry.is_file() {
+                    log::warn!("Plugin at {:?} is missing its entry file {:?}", path, entry);
+                    plugin.errors.push(format!("entry file '{}' does not exist", plugin.metadata.main));
+                    plugin.enabled = false;
+                }
+                plugins.push(plugin);
+            }
+            Err(errors) => {
+                log::warn!("Invalid plugin manifest at {:?}: {}", plugin_json_path, errors.join("; "));
+                plugins.push(InstalledPlugin::invalid(&path, errors));
+            }
+        }
+    }
+
+    Ok(plugins)
+}
+
+/// Cross-references plugins found on disk with the ids the sidecar reports as
+/// loaded. `loaded` is `None` when the sidecar couldn't be asked.
+pub fn summarize_plugins(discovered: &[InstalledPlugin], loaded: Option<&[String]>) -> Vec<PluginStatusSummary> {
+    let mut summaries: Vec<PluginStatusSummary> = discovered
+        .iter()
+        .map(|plugin| {
+            let is_loaded = loaded.is_some_and(|ids| ids.contains(&plugin.metadata.id));
+            let status = if !plugin.errors.is_empty() {
+                "invalid"
+            } else if !plugin.enabled {
+                "disabled"
+            } else if loaded.is_none() {
+                "sidecar_unavailable"
+            } else if !is_loaded {
+                "not_loaded"
+            } else {
+                "ok"
+            };
+
+            PluginStatusSummary {
+                plugin_id: plugin.metadata.id.clone(),
+                on_disk: true,
+                loaded: is_loaded,
+                enabled: plugin.enabled,
+                status: status.to_string(),
+            }
+        })
+        .collect();
+
+    // Loaded in the sidecar but no longer installed
+    for id in loaded.unwrap_or_default() {
+        if !discovered.iter().any(|plugin| &plugin.metadata.id == id) {
+            summaries.push(PluginStatusSummary {
+                plugin_id: id.clone(),
+                on_disk: false,
+                loaded: true,
+                enabled: false,
+                status: "not_on_disk".to_string(),
+            });
+        }
+    }
+
+    summaries
+}
+
+/// Checks that `source` contains a well-formed plugin without touching the plugins directory.
+pub fn validate_plugin(source: &PluginSource) -> Result<PluginMetadata, String> {
+    parse_plugin_manifest(&read_source_manifest(source)?)
+}
+
+/// The schemas the plugin in `source` would register, without installing it.
+pub fn inspect_plugin_schemas(source: &PluginSource) -> Result<Vec<PluginSchemaInfo>, String> {
+    plugin_schema_infos(&read_source_manifest(source)?)
+}
+
+/// The schemas a plugin.json declares. Unlike installing, which treats a
+/// missing `schemas` as none, this insists on the array being there.
+pub fn plugin_schema_infos(content: &str) -> Result<Vec<PluginSchemaInfo>, String> {
+    let metadata = parse_plugin_manifest(content)?;
+    let value: serde_json::Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    if !value.get("schemas").is_some_and(|schemas| schemas.is_array()) {
+        return Err(format!("Plugin '{}' does not declare a schemas array in plugin.json", metadata.id));
+    }
+
+    Ok(metadata
+        .schemas
+        .into_iter()
+        .map(|schema| PluginSchemaInfo {
+            schema_name: format!("{}.{}", metadata.id, schema.name),
+            name: schema.name,
+            version: schema.version,
+            file_extensions: schema.file_extensions,
+        })
+        .collect())
+}
+
+// The raw plugin.json of a source; zip archives are read in place
+fn read_source_manifest(source: &PluginSource) -> Result<String, String> {
+    match source {
+        PluginSource::Archive(path) if archive_extension(path) == "zip" => read_zip_manifest(path),
+        _ => {
+            let staging = StagingDir::new(&std::env::temp_dir())?;
+            let plugin_root = stage_plugin(source, staging.path())?;
+            fs::read_to_string(plugin_root.join("plugin.json")).map_err(|e| format!("Failed to read plugin.json: {}", e))
+        }
+    }
+}
+
+/// Unpacks `source` into a staging area under `staging_parent`, validates it and
+/// only then moves it to `plugins_dir/<id>`. Nothing is left behind on failure.
+/// With `verify_checksum`, a plugin whose files don't match its declared
+/// `files_checksum` is refused.
+pub fn install_plugin(
+    source: &PluginSource,
+    plugins_dir: &Path,
+    staging_parent: &Path,
+    verify_checksum: bool,
+) -> Result<(PluginMetadata, PathBuf), String> {
+    // Archives can be checked up front; repositories are validated once cloned
+    if let PluginSource::Archive(_) = source {
+        validate_plugin(source)?;
+    }
+
+    let staging = StagingDir::new(staging_parent)?;
+    let plugin_root = stage_plugin(source, staging.path())?;
+    let metadata = load_plugin_metadata(&plugin_root.join("plugin.json"))?;
+
+    if verify_checksum {
+        let verification = verify_plugin_files(&plugin_root, &metadata)?;
+        if let (Some(expected), false) = (&verification.expected, verification.verified) {
+            return Err(format!(
+                "Plugin files do not match the declared checksum (expected {}, got {})",
+                expected, verification.actual
+            ));
+        }
+    }
+
+    let destination = plugins_dir.join(&metadata.id);
+    if destination.exists() {
+        return Err(format!("Plugin '{}' is already installed", metadata.id));
+    }
+
+    fs::create_dir_all(plugins_dir).map_err(|e| format!("Failed to create plugins directory: {}", e))?;
+    fs::rename(&plugin_root, &destination).map_err(|e| format!("Failed to move plugin into place: {}", e))?;
+
+    Ok((metadata, destination))
+}
+
+/// SHA-256 over every file in a plugin directory except its plugin.json (which
+/// holds the checksum) and any .git directory. Files are taken in order of
+/// their `/`-separated relative path, each contributing its path, a NUL byte,
+/// its length as a little-endian u64 and its contents.
+pub fn plugin_files_checksum(plugin_dir: &Path) -> Result<String, String> {
+    let mut files = Vec::new();
+    collect_plugin_files(plugin_dir, plugin_dir, &mut files)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for relative in files {
+        let contents = fs::read(plugin_dir.join(&relative))
+            .map_err(|e| format!("Failed to read plugin file {}: {}", relative, e))?;
+        hasher.update(relative.as_bytes());
+        hasher.update([0u8]);
+        hasher.update((contents.len() as u64).to_le_bytes());
+        hasher.update(&contents);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn collect_plugin_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read plugin directory: {}", e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let file_type = entry.file_type().map_err(|e| e.to_string())?;
+        if file_type.is_dir() {
+            if entry.file_name() != ".git" {
+                collect_plugin_files(root, &path, files)?;
+            }
+            continue;
+        }
+
+        let relative = path
+            .strip_prefix(root)
+            .map_err(|e| e.to_string())?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if file_type.is_file() && relative != "plugin.json" {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// Recomputes a plugin directory's checksum and compares it with the one its
+/// manifest declares.
+pub fn verify_plugin_files(plugin_dir: &Path, metadata: &PluginMetadata) -> Result<PluginVerification, String> {
+    let actual = plugin_files_checksum(plugin_dir)?;
+    let expected = metadata.files_checksum.as_ref().map(|checksum| checksum.to_lowercase());
+
+    Ok(PluginVerification {
+        plugin_id: metadata.id.clone(),
+        verified: expected.as_deref() == Some(actual.as_str()),
+        expected,
+        actual,
+    })
+}
+
+/// Fetches or extracts `source` into `staging` and returns the directory holding its plugin.json.
+fn stage_plugin(source: &PluginSource, staging: &Path) -> Result<PathBuf, String> {
+    match source {
+        PluginSource::Archive(path) => extract_plugin_archive(path, staging)?,
+        PluginSource::GitHub(url) => clone_plugin_repo(url, &staging.join(repo_name(url)))?,
+    }
+
+    find_plugin_root(staging)
+}
+
+fn find_plugin_root(dir: &Path) -> Result<PathBuf, String> {
+    if dir.join("plugin.json").exists() {
+        return Ok(dir.to_path_buf());
+    }
+
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read staging directory: {}", e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() && path.join("plugin.json").exists() {
+            return Ok(path);
+        }
+    }
+
+    Err("Invalid plugin: plugin.json not found".to_string())
+}
+
+fn repo_name(github_url: &str) -> String {
+    github_url
+        .trim_end_matches('/')
+        .split('/')
+        .next_back()
+        .unwrap_or("unknown-plugin")
+        .replace(".git", "")
+}
+
+fn clone_plugin_repo(github_url: &str, destination: &Path) -> Result<(), String> {
+    let output = Command::new("git")
+        .args(["clone", github_url, &destination.to_string_lossy()])
+        .output()
+        .map_err(|e| format!("Failed to execute git clone: {}", e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!("Git clone failed: {}", String::from_utf8_lossy(&output.stderr)))
+    }
+}
+
+fn archive_extension(archive_path: &Path) -> String {
+    archive_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+fn read_zip_manifest(archive_path: &Path) -> Result<String, String> {
+    let file = fs::File::open(archive_path).map_err(|e| format!("Failed to open archive: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Failed to read zip archive: {}", e))?;
+
+    // Accept plugin.json at the archive root or inside a single top-level folder
+    let mut manifest_index = None;
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).map_err(|e| format!("Failed to read zip entry: {}", e))?;
+        let name = entry.mangled_name();
+        let depth = name.components().count();
+        if name.file_name().and_then(|n| n.to_str()) == Some("plugin.json") && depth <= 2 {
+            if depth == 1 {
+                manifest_index = Some(i);
+                break;
+            }
+            manifest_index.get_or_insert(i);
+        }
+    }
+
+    let index = manifest_index.ok_or_else(|| "Invalid plugin: plugin.json not found in archive".to_string())?;
+    let mut entry = archive.by_index(index).map_err(|e| format!("Failed to read zip entry: {}", e))?;
+    let mut content = String::new();
+    entry
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Failed to read plugin.json from archive: {}", e))?;
+
+    Ok(content)
+}
+
+pub fn extract_plugin_archive(archive_path: &Path, destination: &Path) -> Result<(), String> {
+    let extension = archive_extension(archive_path);
+
+    match extension.as_str() {
+        "zip" => {
+            // Use built-in zip extraction
+            let file = fs::File::open(archive_path).map_err(|e| format!("Failed to open archive: {}", e))?;
+            let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Failed to read zip archive: {}", e))?;
+
+            for i in 0..archive.len() {
+                let mut file = archive.by_index(i).map_err(|e| format!("Failed to read zip entry: {}", e))?;
+                let outpath = destination.join(file.mangled_name());
+
+                if let Some(parent) = outpath.parent() {
+                    fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+                }
+
+                if !file.name().ends_with('/') {
+                    let mut outfile = fs::File::create(&outpath).map_err(|e| format!("Failed to create file: {}", e))?;
+                    std::io::copy(&mut file, &mut outfile).map_err(|e| format!("Failed to extract file: {}", e))?;
+                }
+            }
+            Ok(())
+        }
+        "rar" | "7z" => {
+            // Use 7zip for rar and 7z files
+            let output = Command::new("7z")
+                .args([
+                    "x",
+                    &archive_path.to_string_lossy(),
+                    &format!("-o{}", destination.to_string_lossy()),
+                ])
+                .output()
+                .map_err(|e| format!("Failed to extract with 7z: {}. Make sure 7-Zip is installed.", e))?;
+
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(format!("7z extraction failed: {}", String::from_utf8_lossy(&output.stderr)))
+            }
+        }
+        _ => Err(format!("Unsupported archive format: {}", extension))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::io::Write;
+
+    const MANIFEST: &str = r#"{
+        "name": "Test Plugin",
+        "id": "test-plugin",
+        "version": "1.0.0",
+        "description": "A plugin used by the tests",
+        "author": "Me-Nexus Team",
+        "main": "index.ts",
+        "permissions": { "network": false, "filesystem": false, "system": false },
+        "capabilities": ["ping"],
+        "category": "utility",
+        "tags": ["test"]
+    }"#;
+
+    fn write_plugin_zip(path: &Path, manifest: &str) -> PluginSource {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        let options = zip::write::FileOptions::default();
+        zip.add_directory("myplug/", options).unwrap();
+        zip.start_file("myplug/plugin.json", options).unwrap();
+        zip.write_all(manifest.as_bytes()).unwrap();
+        zip.start_file("myplug/index.ts", options).unwrap();
+        zip.write_all(b"export {}").unwrap();
+        zip.finish().unwrap();
+        PluginSource::parse(path.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn validates_archives_without_installing() {
+        let dir = temp_dir("plugins_validate");
+        let good = write_plugin_zip(&dir.join("good.zip"), MANIFEST);
+        let malformed = write_plugin_zip(&dir.join("bad.zip"), r#"{"name":"x","id":"../x"}"#);
+
+        assert_eq!(validate_plugin(&good).unwrap().id, "test-plugin");
+        assert!(validate_plugin(&malformed).is_err());
+        assert!(!dir.join("plugins").exists());
+    }
+
+    #[test]
+    fn failed_installs_leave_nothing_behind() {
+        let dir = temp_dir("plugins_install_failure");
+        let malformed = write_plugin_zip(&dir.join("bad.zip"), r#"{"name":"x","id":"../x"}"#);
+        fs::create_dir_all(dir.join("staging")).unwrap();
+
+        assert!(install_plugin(&malformed, &dir.join("plugins"), &dir.join("staging"), true).is_err());
+        assert!(!dir.join("plugins").exists());
+        assert_eq!(fs::read_dir(dir.join("staging")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn installing_returns_the_manifest_metadata() {
+        let dir = temp_dir("plugins_install");
+        let source = write_plugin_zip(&dir.join("good.zip"), MANIFEST);
+
+        let (metadata, path) = install_plugin(&source, &dir.join("plugins"), &dir.join("staging"), true).unwrap();
+        let installed = InstalledPlugin::new(metadata, &path);
+
+        assert_eq!(installed.metadata.id, "test-plugin");
+        assert_eq!(installed.metadata.version, "1.0.0");
+        assert_eq!(installed.metadata.main, "index.ts");
+        assert_eq!(installed.path, dir.join("plugins/test-plugin").to_string_lossy());
+        assert!(path.join("index.ts").exists());
+        assert!(install_plugin(&source, &dir.join("plugins"), &dir.join("staging"), true).is_err());
+    }
+
+    #[test]
+    fn reports_field_level_manifest_errors() {
+        let errors = check_plugin_manifest(
+            r#"{"name":"x","version":"1","description":"","author":"","main":"m.ts","category":"c",
+                "permissions":{"network":"yes","filesystem":false},"capabilities":[],"tags":[1]}"#,
+        )
+        .unwrap_err();
+        assert!(errors.contains(&"missing required field 'id'".to_string()));
+        assert!(errors.contains(&"permissions.network must be boolean".to_string()));
+        assert!(errors.contains(&"missing required field 'permissions.system'".to_string()));
+        assert!(errors.contains(&"tags[0] must be a string".to_string()));
+
+        let errors = check_plugin_manifest("[]").unwrap_err();
+        assert_eq!(errors, vec!["plugin.json must contain a JSON object".to_string()]);
+
+        let errors = check_plugin_manifest(&MANIFEST.replace(r#""index.ts""#, r#""../escape.ts""#)).unwrap_err();
+        assert!(errors[0].starts_with("'main' must be a path inside the plugin"));
+
+        let errors = check_plugin_manifest(&MANIFEST.replace(r#"["ping"]"#, r#"["network"]"#)).unwrap_err();
+        assert_eq!(errors, vec!["capability 'network' requires permissions.network to be true".to_string()]);
+    }
+
+    #[test]
+    fn invalid_plugins_are_listed_with_their_errors() {
+        let dir = temp_dir("plugins_scan_invalid");
+        fs::create_dir_all(dir.join("broken")).unwrap();
+        fs::write(dir.join("broken/plugin.json"), r#"{"name":"x"}"#).unwrap();
+
+        let plugins = scan_plugins_dir(&dir).unwrap();
+        assert_eq!(plugins.len(), 1);
+        assert!(plugins[0].errors.contains(&"missing required field 'id'".to_string()));
+    }
+
+    #[test]
+    fn plugin_ids_cannot_leave_the_plugins_directory() {
+        for id in ["test-plugin", "a.b_c", "v2"] {
+            assert!(is_valid_plugin_id(id), "{}", id);
+        }
+        for id in ["", ".", "..", "../x", "a/b", "a\\b", ".hidden", "C:x"] {
+            assert!(!is_valid_plugin_id(id), "{}", id);
+        }
+    }
+
+    #[test]
+    fn file_checksums_detect_edits() {
+        let dir = temp_dir("plugins_checksum");
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(dir.join("index.ts"), "export default 1;").unwrap();
+        fs::write(dir.join("lib/util.ts"), "export const x = 2;").unwrap();
+        let mut metadata: PluginMetadata = serde_json::from_str(MANIFEST).unwrap();
+        metadata.files_checksum = Some(plugin_files_checksum(&dir).unwrap().to_uppercase());
+        fs::write(dir.join("plugin.json"), serde_json::to_string(&metadata).unwrap()).unwrap();
+
+        // plugin.json itself is not part of the checksum, and case doesn't matter
+        assert!(verify_plugin_files(&dir, &metadata).unwrap().verified);
+
+        fs::write(dir.join("lib/util.ts"), "export const x = 3;").unwrap();
+        let edited = verify_plugin_files(&dir, &metadata).unwrap();
+        assert!(!edited.verified);
+        assert_ne!(edited.expected.unwrap(), edited.actual);
+
+        metadata.files_checksum = None;
+        assert!(!verify_plugin_files(&dir, &metadata).unwrap().verified);
+    }
+
+    #[test]
+    fn manifests_with_a_bad_schedule_are_rejected() {
+        let scheduled = MANIFEST.replace(r#""tags": ["test"]"#, r#""tags": ["test"], "schedule": { "cron": "*/5 * * * *" }"#);
+        assert_eq!(check_plugin_manifest(&scheduled).unwrap().schedule.unwrap().cron, "*/5 * * * *");
+
+        let invalid = MANIFEST.replace(r#""tags": ["test"]"#, r#""tags": ["test"], "schedule": { "cron": "61 * * * *" }"#);
+        assert!(check_plugin_manifest(&invalid).unwrap_err()[0].contains("schedule.cron"));
+    }
+
+    #[test]
+    fn plugins_without_their_entry_file_are_disabled() {
+        let plugins_dir = temp_dir("plugins_entry_files");
+        for (id, main) in [("good", "src/main.ts"), ("missing", "main.ts")] {
+            let manifest = MANIFEST.replace("test-plugin", id).replace("index.ts", main);
+            fs::create_dir_all(plugins_dir.join(id)).unwrap();
+            fs::write(plugins_dir.join(id).join("plugin.json"), manifest).unwrap();
+        }
+        fs::create_dir_all(plugins_dir.join("good/src")).unwrap();
+        fs::write(plugins_dir.join("good/src/main.ts"), "export {}").unwrap();
+
+        let mut found = scan_plugins_dir(&plugins_dir).unwrap();
+        found.sort_by(|a, b| a.metadata.id.cmp(&b.metadata.id));
+        assert!(found[0].enabled && found[0].errors.is_empty());
+        assert_eq!(plugin_entry_path(Path::new(&found[0].path), &found[0].metadata), plugins_dir.join("good/src/main.ts"));
+
+        assert!(!found[1].enabled);
+        assert_eq!(found[1].errors, vec!["entry file 'main.ts' does not exist".to_string()]);
+        // The manifest itself was fine, so its metadata is still reported
+        assert_eq!(found[1].metadata.version, "1.0.0");
+        assert_eq!(summarize_plugins(&found, Some(&[]))[1].status, "invalid");
+    }
+
+    #[test]
+    fn schemas_can_be_inspected_before_installing() {
+        let dir = temp_dir("plugins_inspect_schemas");
+        let schemas = r#""tags": ["test"], "schemas": [
+            { "name": "habit", "version": "1.0.0", "definition": { "type": "object" }, "file_extensions": ["habit"] },
+            { "name": "log", "version": "2.1.0", "definition": { "type": "object" }, "file_extensions": [] }
+        ]"#;
+        let source = write_plugin_zip(&dir.join("schemas.zip"), &MANIFEST.replace(r#""tags": ["test"]"#, schemas));
+
+        let infos = inspect_plugin_schemas(&source).unwrap();
+        assert_eq!(infos.len(), 2);
+        assert_eq!((infos[0].schema_name.as_str(), infos[0].version.as_str()), ("test-plugin.habit", "1.0.0"));
+        assert_eq!(infos[0].file_extensions, vec!["habit".to_string()]);
+        assert_eq!((infos[1].name.as_str(), infos[1].version.as_str()), ("log", "2.1.0"));
+        assert!(!dir.join("plugins").exists());
+    }
+
+    #[test]
+    fn plugins_without_a_schemas_array_are_reported() {
+        let error = plugin_schema_infos(MANIFEST).unwrap_err();
+        assert!(error.contains("does not declare a schemas array"), "{}", error);
+
+        let empty = MANIFEST.replace(r#""tags": ["test"]"#, r#""tags": ["test"], "schemas": []"#);
+        assert!(plugin_schema_infos(&empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn installed_plugin_dirs_refuse_ids_leaving_the_plugins_folder() {
+        let dir = temp_dir("plugins_installed_dir");
+        let plugins_dir = dir.join("plugins");
+        fs::create_dir_all(plugins_dir.join("test-plugin")).unwrap();
+        fs::write(plugins_dir.join("test-plugin/plugin.json"), MANIFEST).unwrap();
+        // A folder with a plugin.json just outside the plugins directory
+        fs::write(dir.join("plugin.json"), MANIFEST).unwrap();
+
+        assert_eq!(installed_plugin_dir(&plugins_dir, "test-plugin").unwrap(), plugins_dir.join("test-plugin"));
+        for id in ["..", "../..", "../plugins/test-plugin", "/etc", "", ".hidden"] {
+            let error = installed_plugin_dir(&plugins_dir, id).unwrap_err();
+            assert!(error.contains("Invalid plugin id"), "{}: {}", id, error);
+        }
+        assert!(installed_plugin_dir(&plugins_dir, "missing").unwrap_err().contains("not installed"));
+    }
+}
@@ -0,0 +1,164 @@
+use chrono::Utc;
+use rusqlite::params;
+
+use crate::database::Database;
+use crate::error::Result;
+use crate::models::QuarantinedFile;
+
+impl Database {
+    /// Counts a failed sync of `file_path` as it was at `mtime_ms`. Once the
+    /// vault's `quarantine_after_failures` consecutive failures are reached
+    /// the file is quarantined; returns whether it is. A changed file starts
+    /// counting again from one.
+    pub async fn record_sync_failure(&self, file_path: &str, mtime_ms: i64, error: &str) -> Result<bool> {
+        let threshold = self.settings().quarantine_after_failures;
+        let conn = self.lock_connection().await?;
+        let tx = conn.unchecked_transaction()?;
+
+        tx.execute(
+            "INSERT INTO sync_quarantine (file_path, failures, mtime_ms, last_error) VALUES (?1, 1, ?2, ?3)
+             ON CONFLICT(file_path) DO UPDATE SET
+                 failures = CASE WHEN mtime_ms = excluded.mtime_ms THEN failures + 1 ELSE 1 END,
+                 quarantined_at = CASE WHEN mtime_ms = excluded.mtime_ms THEN quarantined_at END,
+                 mtime_ms = excluded.mtime_ms, last_error = excluded.last_error",
+            params![file_path, mtime_ms, error],
+        )?;
+        tx.execute(
+            "UPDATE sync_quarantine SET quarantined_at = ?2
+             WHERE file_path = ?1 AND quarantined_at IS NULL AND ?3 > 0 AND failures >= ?3",
+            params![file_path, Utc::now().to_rfc3339(), threshold],
+        )?;
+        let quarantined: bool = tx.query_row(
+            "SELECT quarantined_at IS NOT NULL FROM sync_quarantine WHERE file_path = ?1",
+            params![file_path],
+            |row| row.get(0),
+        )?;
+
+        tx.commit()?;
+        Ok(quarantined)
+    }
+
+    /// Forgets the failures of a file that synced (or went away).
+    pub async fn clear_sync_failures(&self, file_path: &str) -> Result<()> {
+        let conn = self.lock_connection().await?;
+        conn.execute("DELETE FROM sync_quarantine WHERE file_path = ?1", params![file_path])?;
+        Ok(())
+    }
+
+    /// Whether scans should skip the file: it is quarantined and hasn't
+    /// changed since its last failure.
+    pub async fn is_quarantined(&self, file_path: &str, mtime_ms: i64) -> Result<bool> {
+        let conn = self.lock_connection().await?;
+        let quarantined = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sync_quarantine
+                           WHERE file_path = ?1 AND mtime_ms = ?2 AND quarantined_at IS NOT NULL)",
+            params![file_path, mtime_ms],
+            |row| row.get(0),
+        )?;
+        Ok(quarantined)
+    }
+
+    pub async fn get_quarantined_files(&self) -> Result<Vec<QuarantinedFile>> {
+        let conn = self.lock_connection().await?;
+
+        let mut stmt = conn.prepare(
+            "SELECT file_path, failures, last_error, quarantined_at FROM sync_quarantine
+             WHERE quarantined_at IS NOT NULL
+             ORDER BY quarantined_at, file_path",
+        )?;
+        let files = stmt
+            .query_map([], |row| {
+                Ok(QuarantinedFile {
+                    file_path: row.get(0)?,
+                    failures: row.get(1)?,
+                    last_error: row.get(2)?,
+                    quarantined_at: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(files)
+    }
+
+    /// Lifts every quarantine, resetting the files' failure counts, and
+    /// returns the released paths.
+    pub async fn release_quarantined(&self) -> Result<Vec<String>> {
+        let conn = self.lock_connection().await?;
+        let tx = conn.unchecked_transaction()?;
+
+        let paths = {
+            let mut stmt = tx.prepare(
+                "SELECT file_path FROM sync_quarantine WHERE quarantined_at IS NOT NULL ORDER BY file_path",
+            )?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<Vec<String>>>()?
+        };
+        tx.execute("DELETE FROM sync_quarantine WHERE quarantined_at IS NOT NULL", [])?;
+
+        tx.commit()?;
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{temp_database, vault, write_file, TWO_TODOS};
+
+    #[tokio::test]
+    async fn changed_files_start_counting_again() {
+        let (_vault, database) = temp_database("quarantine_counts").await;
+        for _ in 0..2 {
+            assert!(!database.record_sync_failure("/v/bad.json", 1, "bad").await.unwrap());
+        }
+        assert!(database.record_sync_failure("/v/bad.json", 1, "bad").await.unwrap());
+        assert!(database.is_quarantined("/v/bad.json", 1).await.unwrap());
+        assert!(!database.is_quarantined("/v/bad.json", 2).await.unwrap());
+
+        // Still broken after an edit, but the quarantine is lifted and the count restarts
+        assert!(!database.record_sync_failure("/v/bad.json", 2, "still bad").await.unwrap());
+        assert!(database.get_quarantined_files().await.unwrap().is_empty());
+        assert!(!database.record_sync_failure("/v/bad.json", 2, "still bad").await.unwrap());
+        let quarantined = database.record_sync_failure("/v/bad.json", 2, "still bad").await.unwrap();
+        assert!(quarantined);
+        assert_eq!(database.get_quarantined_files().await.unwrap()[0].failures, 3);
+    }
+
+    #[tokio::test]
+    async fn persistently_bad_files_are_quarantined_until_modified() {
+        let (vault, database, sync_service) = vault("quarantine_sync", TWO_TODOS).await;
+        database.register_schema("ext.c", r#"{"type":"object"}"#).await.unwrap();
+        database.register_schema_extensions("ext.c", &["json".into()]).await.unwrap();
+        write_file(&vault, "bad.json", "{not json");
+        let bad = vault.join("bad.json");
+        for _ in 0..2 {
+            sync_service.force_sync(None).await.unwrap();
+        }
+        assert!(database.get_quarantined_files().await.unwrap().is_empty());
+        assert_eq!(sync_service.get_errors().await.len(), 1);
+
+        sync_service.force_sync(None).await.unwrap();
+        let quarantined = database.get_quarantined_files().await.unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!((quarantined[0].failures, quarantined[0].file_path.as_str()), (3, bad.to_str().unwrap()));
+        // Skipped, so the scan no longer reports it
+        sync_service.force_sync(None).await.unwrap();
+        assert!(sync_service.get_errors().await.is_empty());
+
+        // Released by hand, it fails again and starts counting from scratch
+        assert_eq!(sync_service.retry_quarantined().await.unwrap(), 1);
+        assert!(database.get_quarantined_files().await.unwrap().is_empty());
+        assert_eq!(sync_service.get_errors().await.len(), 1);
+        for _ in 0..2 {
+            sync_service.force_sync(None).await.unwrap();
+        }
+        assert_eq!(database.get_quarantined_files().await.unwrap().len(), 1);
+
+        // Fixing the file gets it retried, and the good sync lifts the quarantine
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        write_file(&vault, "bad.json", r#"{"fixed":true}"#);
+        sync_service.force_sync(None).await.unwrap();
+        assert!(database.get_quarantined_files().await.unwrap().is_empty());
+        assert!(database.object_id_for_path(bad.to_str().unwrap()).await.unwrap().is_some());
+        assert!(sync_service.get_errors().await.is_empty());
+    }
+}
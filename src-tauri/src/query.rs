@@ -0,0 +1,146 @@
+use rusqlite::types::Value as SqlValue;
+use rusqlite::params;
+use serde_json::Value;
+
+use crate::database::Database;
+use crate::error::{NexusError, Result};
+use crate::models::AppObject;
+
+/// Turns a field path like `status`, `meta.owner` or `tags[0]` into the SQLite
+/// JSON path `$.status`. Only names of letters, digits and underscores and
+/// numeric array indices are accepted, so a path can't smuggle in SQL.
+pub fn json_path(field_path: &str) -> Result<String> {
+    let invalid = || NexusError::InvalidInput(format!("Invalid field path '{}'", field_path));
+
+    let mut path = String::from("$");
+    for segment in field_path.split('.') {
+        let (name, mut indices) = segment.find('[').map_or((segment, ""), |i| segment.split_at(i));
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(invalid());
+        }
+        path.push('.');
+        path.push_str(name);
+
+        // Any number of `[N]` may follow the name
+        while !indices.is_empty() {
+            let close = indices.find(']').filter(|_| indices.starts_with('[')).ok_or_else(invalid)?;
+            let index = &indices[1..close];
+            if index.is_empty() || !index.chars().all(|c| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            path.push_str(&indices[..=close]);
+            indices = &indices[close + 1..];
+        }
+    }
+
+    Ok(path)
+}
+
+// json_extract hands back booleans as 0/1 and arrays/objects as JSON text
+fn sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        Value::Null => SqlValue::Null,
+        Value::Array(_) | Value::Object(_) => SqlValue::Text(value.to_string()),
+    }
+}
+
+impl Database {
+    /// Objects of a schema whose content has `value` at `field_path` (see
+    /// [`json_path`]), oldest first. A `null` value matches an explicit null
+    /// but not a missing field.
+    pub async fn query_objects(
+        &self,
+        schema_name: &str,
+        field_path: &str,
+        value: &Value,
+    ) -> Result<Vec<AppObject<Value>>> {
+        let path = json_path(field_path)?;
+
+        let object_ids: Vec<i64> = {
+            let conn = self.lock_connection().await?;
+            let condition = if value.is_null() {
+                "json_type(oc.content_json, ?2) = 'null'"
+            } else {
+                "json_extract(oc.content_json, ?2) = ?3"
+            };
+            let mut stmt = conn.prepare(&format!(
+                "SELECT do.id FROM data_objects do
+                 JOIN schemas s ON do.schema_id = s.id
+                 JOIN object_content oc ON do.id = oc.object_id
+                 WHERE s.schema_name = ?1 AND {}
+                 ORDER BY do.created_at ASC, do.id ASC",
+                condition
+            ))?;
+            if value.is_null() {
+                stmt.query_map(params![schema_name, path], |row| row.get(0))?
+                    .collect::<rusqlite::Result<_>>()?
+            } else {
+                stmt.query_map(params![schema_name, path, sql_value(value)], |row| row.get(0))?
+                    .collect::<rusqlite::Result<_>>()?
+            }
+        };
+
+        let mut objects = Vec::with_capacity(object_ids.len());
+        for object_id in object_ids {
+            objects.push(self.load_object(object_id).await?);
+        }
+        Ok(objects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_database;
+
+    fn ids(objects: Vec<AppObject<Value>>) -> Vec<i64> {
+        objects.into_iter().map(|object| object.id).collect()
+    }
+
+    #[test]
+    fn field_paths_become_json_paths() {
+        assert_eq!(json_path("status").unwrap(), "$.status");
+        assert_eq!(json_path("a.b[2][10]").unwrap(), "$.a.b[2][10]");
+    }
+
+    #[tokio::test]
+    async fn objects_are_matched_on_one_content_field() {
+        let (_vault, database) = temp_database("query_objects").await;
+        let first = serde_json::json!({
+            "text": "a", "completed": false, "created_at": "2024-01-01T00:00:00Z", "status": "in_progress", "points": 3, "meta": { "owner": "kim" }, "tags": ["x", "y"], "due": null
+        });
+        let second = serde_json::json!({ "text": "b", "completed": false, "created_at": "2024-01-01T00:00:00Z", "status": "done", "points": 3.5, "tags": ["y"] });
+        let a = database.save_object("core.todo", &first, None, None, None).await.unwrap();
+        let b = database.save_object("core.todo", &second, None, None, None).await.unwrap();
+
+        let query = |field: &'static str, value: Value| {
+            let database = &database;
+            async move { ids(database.query_objects("core.todo", field, &value).await.unwrap()) }
+        };
+        assert_eq!(query("status", serde_json::json!("in_progress")).await, vec![a]);
+        assert_eq!(query("points", serde_json::json!(3)).await, vec![a]);
+        assert_eq!(query("points", serde_json::json!(3.5)).await, vec![b]);
+        assert_eq!(query("meta.owner", serde_json::json!("kim")).await, vec![a]);
+        assert_eq!(query("tags[0]", serde_json::json!("y")).await, vec![b]);
+        // Only an explicit null matches null, not a missing field
+        assert_eq!(query("due", Value::Null).await, vec![a]);
+
+        let notes = database.query_objects("core.note", "status", &serde_json::json!("done")).await.unwrap();
+        assert!(notes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn malformed_field_paths_are_rejected() {
+        let (_vault, database) = temp_database("query_invalid_paths").await;
+        for field in ["status') = 1 OR 1=1 --", "", "a..b", "tags[x]", "tags[0", "tags]0[", "$.status", "a b"] {
+            let result = database.query_objects("core.todo", field, &serde_json::json!(1)).await;
+            assert!(matches!(result, Err(NexusError::InvalidInput(_))), "{}", field);
+        }
+    }
+}
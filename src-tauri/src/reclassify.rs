@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension};
+use serde_json::Value;
+
+use crate::database::{content_hash, Database};
+use crate::error::{NexusError, Result};
+use crate::models::AppObject;
+use crate::object_merge::is_todo_entry;
+
+impl Database {
+    /// Moves an object to another schema, typically from core.todo to a
+    /// plugin's richer type. `mapping` renames content fields (old name to
+    /// new) first; the result must then validate against the new schema or
+    /// nothing changes. Entries of a todos.json are refused, since the file
+    /// would keep syncing them back as todos. Returns the reclassified object.
+    pub async fn reclassify_object(
+        &self,
+        object_id: i64,
+        new_schema_name: &str,
+        mapping: Option<&HashMap<String, String>>,
+    ) -> Result<AppObject<Value>> {
+        let object = self.load_object::<Value>(object_id).await?;
+        if object.permissions.read_only {
+            return Err(NexusError::PermissionDenied(format!("Object {} is read-only", object_id)));
+        }
+        if object.schema_name == new_schema_name {
+            return Err(NexusError::InvalidInput(format!(
+                "Object {} is already a {}",
+                object_id, new_schema_name
+            )));
+        }
+        if object.file_path.as_deref().is_some_and(is_todo_entry) {
+            return Err(NexusError::InvalidInput(format!(
+                "Object {} is an entry of a todos.json and can't change schema",
+                object_id
+            )));
+        }
+
+        let mut content = object.content;
+        if let (Some(mapping), Some(fields)) = (mapping, content.as_object_mut()) {
+            // Take every mapped field out first, so renames can swap names
+            let moved: Vec<(&String, Value)> = mapping
+                .iter()
+                .filter_map(|(from, to)| fields.remove(from).map(|value| (to, value)))
+                .collect();
+            for (to, value) in moved {
+                fields.insert(to.clone(), value);
+            }
+        }
+
+        let errors = self.validate_against_schema(new_schema_name, &content).await?;
+        if !errors.is_empty() {
+            let errors: Vec<String> = errors
+                .iter()
+                .map(|error| format!("{}: {}", if error.path.is_empty() { "/" } else { &error.path }, error.message))
+                .collect();
+            return Err(NexusError::InvalidInput(format!(
+                "Object {} doesn't satisfy {}: {}",
+                object_id,
+                new_schema_name,
+                errors.join("; ")
+            )));
+        }
+
+        let content_json = serde_json::to_string(&content)?;
+        self.check_content_size(&content_json)?;
+
+        {
+            let conn = self.lock_connection().await?;
+            let schema_id: i64 = conn
+                .query_row("SELECT id FROM schemas WHERE schema_name = ?1", params![new_schema_name], |row| row.get(0))
+                .optional()?
+                .ok_or_else(|| NexusError::SchemaNotFound(new_schema_name.to_string()))?;
+
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "UPDATE object_content SET content_json = ?1 WHERE object_id = ?2",
+                params![content_json, object_id],
+            )?;
+            tx.execute(
+                "UPDATE data_objects SET schema_id = ?1, content_hash = ?2, updated_at = ?3 WHERE id = ?4",
+                params![schema_id, content_hash(&content_json), Utc::now().to_rfc3339(), object_id],
+            )?;
+            tx.commit()?;
+        }
+        self.notify_changed(object_id);
+
+        log::info!("Reclassified object {} from {} to {}", object_id, object.schema_name, new_schema_name);
+        self.load_object(object_id).await
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{vault, TWO_TODOS};
+
+    const TASK_SCHEMA: &str = r#"{
+        "type": "object",
+        "required": ["title", "completed"],
+        "properties": { "title": { "type": "string" }, "completed": { "type": "boolean" } }
+    }"#;
+
+    fn text_to_title() -> HashMap<String, String> {
+        [("text".to_string(), "title".to_string())].into()
+    }
+
+    #[tokio::test]
+    async fn compatible_todos_move_to_the_plugin_schema() {
+        let (_vault, database, _sync_service) = vault("reclassify_compatible", TWO_TODOS).await;
+        database.register_schema("tasks.task", TASK_SCHEMA).await.unwrap();
+        let todo = serde_json::json!({ "text": "ship it", "completed": false, "created_at": "2024-01-01T00:00:00Z" });
+        let todo = database.save_object("core.todo", &todo, None, None, None).await.unwrap();
+
+        let moved = database.reclassify_object(todo, "tasks.task", Some(&text_to_title())).await.unwrap();
+        assert_eq!((moved.id, moved.schema_name.as_str()), (todo, "tasks.task"));
+        assert_eq!(moved.content, serde_json::json!({ "title": "ship it", "completed": false, "created_at": "2024-01-01T00:00:00Z" }));
+        assert_eq!(database.load_objects_by_schema::<Value>("tasks.task").await.unwrap().len(), 1);
+        let unknown = database.reclassify_object(todo, "nope", None).await;
+        assert!(matches!(unknown, Err(NexusError::SchemaNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn incompatible_todos_and_todo_entries_are_refused() {
+        let (_vault, database, sync_service) = vault("reclassify_incompatible", TWO_TODOS).await;
+        database.register_schema("tasks.task", TASK_SCHEMA).await.unwrap();
+        let todo = serde_json::json!({ "text": "ship it", "completed": false, "created_at": "2024-01-01T00:00:00Z" });
+        let todo = database.save_object("core.todo", &todo, None, None, None).await.unwrap();
+
+        // Without the mapping the required title is missing
+        let error = database.reclassify_object(todo, "tasks.task", None).await.unwrap_err();
+        assert!(matches!(error, NexusError::InvalidInput(ref message) if message.contains("title")));
+        assert_eq!(database.load_object::<Value>(todo).await.unwrap().schema_name, "core.todo");
+
+        // todos.json entries stay todos
+        sync_service.force_sync(None).await.unwrap();
+        let entry = database
+            .load_objects_by_schema::<Value>("core.todo")
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|object| object.file_path.is_some())
+            .unwrap();
+        let refused = database.reclassify_object(entry.id, "tasks.task", Some(&text_to_title())).await;
+        assert!(matches!(refused, Err(NexusError::InvalidInput(_))));
+    }
+}
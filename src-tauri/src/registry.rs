@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Registries are small JSON files; anything slower than this is treated as down
+const REGISTRY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// One installable plugin listed in a registry index.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RegistryEntry {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // GitHub repository the plugin is installed from
+    pub install_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistryIndex {
+    plugins: Vec<RegistryEntry>,
+}
+
+#[derive(Debug)]
+pub enum RegistryError {
+    // No registry URL is configured for the vault
+    NotConfigured,
+    // The registry couldn't be reached
+    Network(String),
+    // The registry answered with a non-success status
+    Status(u16),
+    // The response wasn't a registry index
+    InvalidIndex(String),
+    // The index has no plugin with this id
+    NotFound(String),
+}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::NotConfigured => write!(f, "No plugin registry is configured"),
+            RegistryError::Network(e) => write!(f, "Could not reach the plugin registry: {}", e),
+            RegistryError::Status(code) => write!(f, "Plugin registry returned HTTP {}", code),
+            RegistryError::InvalidIndex(e) => write!(f, "Plugin registry index is invalid: {}", e),
+            RegistryError::NotFound(id) => write!(f, "Plugin '{}' is not in the registry", id),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// Downloads the registry index at `url`.
+pub async fn fetch_registry(url: &str) -> Result<Vec<RegistryEntry>, RegistryError> {
+    let client = reqwest::Client::builder()
+        .timeout(REGISTRY_TIMEOUT)
+        .build()
+        .map_err(|e| RegistryError::Network(e.to_string()))?;
+
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| RegistryError::Network(e.to_string()))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(RegistryError::Status(status.as_u16()));
+    }
+
+    let body = response.text().await.map_err(|e| RegistryError::Network(e.to_string()))?;
+    let index: RegistryIndex = serde_json::from_str(&body).map_err(|e| RegistryError::InvalidIndex(e.to_string()))?;
+    Ok(index.plugins)
+}
+
+/// Entries whose id, name, description or tags contain `query`, ignoring
+/// case. An empty query matches everything.
+pub fn search_registry(entries: Vec<RegistryEntry>, query: &str) -> Vec<RegistryEntry> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return entries;
+    }
+
+    entries
+        .into_iter()
+        .filter(|entry| {
+            entry.id.to_lowercase().contains(&query)
+                || entry.name.to_lowercase().contains(&query)
+                || entry.description.to_lowercase().contains(&query)
+                || entry.tags.iter().any(|tag| tag.to_lowercase().contains(&query))
+        })
+        .collect()
+}
+
+/// Looks up the install URL of the plugin with exactly this id.
+pub async fn resolve_install_url(url: &str, plugin_id: &str) -> Result<String, RegistryError> {
+    fetch_registry(url)
+        .await?
+        .into_iter()
+        .find(|entry| entry.id == plugin_id)
+        .map(|entry| entry.install_url)
+        .ok_or_else(|| RegistryError::NotFound(plugin_id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const INDEX: &str = r#"{"plugins":[
+        {"id":"kanban","name":"Kanban","description":"Boards","tags":["todo"],"install_url":"https://github.com/x/kanban"},
+        {"id":"cal","name":"Calendar","description":"Dates","install_url":"https://github.com/x/cal"}
+    ]}"#;
+
+    // Answers a single HTTP request with `status` and `body`, returning the URL to fetch
+    async fn serve_once(status: &'static str, body: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await;
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n{}", status, body.len(), body);
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+        format!("http://{}/index.json", address)
+    }
+
+    #[tokio::test]
+    async fn registries_are_fetched_and_searched() {
+        let entries = fetch_registry(&serve_once("200 OK", INDEX).await).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].version, "");
+
+        let ids = |entries: Vec<RegistryEntry>| entries.into_iter().map(|entry| entry.id).collect::<Vec<_>>();
+        assert_eq!(ids(search_registry(entries.clone(), " TODO ")), vec!["kanban"]);
+        assert_eq!(ids(search_registry(entries.clone(), "date")), vec!["cal"]);
+        assert_eq!(search_registry(entries, "").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn install_urls_are_resolved_by_exact_id() {
+        let url = resolve_install_url(&serve_once("200 OK", INDEX).await, "cal").await.unwrap();
+        assert_eq!(url, "https://github.com/x/cal");
+        assert!(matches!(
+            resolve_install_url(&serve_once("200 OK", INDEX).await, "ca").await,
+            Err(RegistryError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn registry_failures_are_told_apart() {
+        assert!(matches!(fetch_registry(&serve_once("404 Not Found", "").await).await, Err(RegistryError::Status(404))));
+        assert!(matches!(fetch_registry(&serve_once("200 OK", "[1]").await).await, Err(RegistryError::InvalidIndex(_))));
+        assert!(matches!(fetch_registry("http://127.0.0.1:1/index.json").await, Err(RegistryError::Network(_))));
+    }
+}
@@ -0,0 +1,298 @@
+use std::fs;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+
+use rusqlite::{params, Connection};
+use sha2::{Digest, Sha256};
+
+use crate::database::db_path;
+use crate::error::{NexusError, Result};
+use crate::todo_snapshots;
+
+// Tables that keep absolute paths of vault files
+const PATH_TABLES: &[&str] = &[
+    "data_objects",
+    "sync_conflicts",
+    "file_scan_state",
+    "sync_snapshots",
+    "sync_quarantine",
+];
+
+/// Where the vault at `source` would end up when moved to `destination`,
+/// as an absolute path. Refuses a destination that exists and isn't an empty
+/// directory, and one inside the vault itself.
+pub fn check_destination(source: &Path, destination: &Path) -> Result<PathBuf> {
+    let source = source.canonicalize()?;
+
+    let destination = if destination.exists() {
+        if !destination.is_dir() || fs::read_dir(destination)?.next().is_some() {
+            return Err(NexusError::InvalidInput(format!(
+                "{} already exists and isn't empty",
+                destination.display()
+            )));
+        }
+        destination.canonicalize()?
+    } else {
+        let (Some(parent), Some(name)) = (destination.parent(), destination.file_name()) else {
+            return Err(NexusError::InvalidInput(format!("{} can't hold a vault", destination.display())));
+        };
+        let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+        if !parent.is_dir() {
+            return Err(NexusError::InvalidInput(format!("{} does not exist", parent.display())));
+        }
+        parent.canonicalize()?.join(name)
+    };
+
+    if destination.starts_with(&source) {
+        return Err(NexusError::InvalidInput(format!(
+            "{} is inside the vault being moved",
+            destination.display()
+        )));
+    }
+    Ok(destination)
+}
+
+/// Moves the vault directory to `destination`, which must not exist or be an
+/// empty directory. Where a rename isn't possible, e.g. onto another drive,
+/// the tree is copied, checked file by file against the original and only
+/// then removed from the old location.
+pub fn move_vault(source: &Path, destination: &Path) -> Result<()> {
+    // Renaming onto even an empty directory fails on Windows
+    if destination.exists() {
+        fs::remove_dir(destination)?;
+    }
+
+    match fs::rename(source, destination) {
+        Ok(()) => return Ok(()),
+        Err(e) => log::info!("Renaming the vault failed ({}); copying it instead", e),
+    }
+
+    let copied = copy_tree(source, destination).and_then(|()| verify_tree(source, destination));
+    if let Err(e) = copied {
+        if let Err(cleanup) = fs::remove_dir_all(destination) {
+            log::warn!("Failed to remove partial vault copy {}: {}", destination.display(), cleanup);
+        }
+        return Err(e);
+    }
+
+    // The vault is safely in place by now, so a leftover copy is only untidy
+    if let Err(e) = fs::remove_dir_all(source) {
+        log::warn!("Failed to remove the old vault at {}: {}", source.display(), e);
+    }
+    Ok(())
+}
+
+/// Checks the moved vault's database is intact, then rewrites the absolute
+/// file paths it and the todo snapshots keep from `old_root` to `new_root`,
+/// so the next scan finds every file already tracked. Both roots are taken
+/// as stored, without resolving them. If the snapshots can't be rewritten
+/// the database paths are put back too. Returns the number of rows rewritten.
+pub fn rebase_vault_paths(new_root: &Path, old_root: &Path) -> Result<usize> {
+    let path = db_path(new_root);
+    if !path.is_file() {
+        return Err(NexusError::InvalidInput(format!("No vault database at {}", path.display())));
+    }
+
+    let conn = Connection::open(&path)?;
+    let problems: Vec<String> = {
+        let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    if problems != ["ok"] {
+        return Err(NexusError::Sync(format!(
+            "The moved vault database is damaged: {}",
+            problems.join("; ")
+        )));
+    }
+
+    let old_prefix = with_separator(old_root);
+    let new_prefix = with_separator(new_root);
+    let rewritten = rewrite_paths(&conn, &old_prefix, &new_prefix)?;
+    if let Err(e) = todo_snapshots::rebase_snapshots(new_root, &old_prefix, &new_prefix) {
+        rewrite_paths(&conn, &new_prefix, &old_prefix)?;
+        if let Err(undo) = todo_snapshots::rebase_snapshots(new_root, &new_prefix, &old_prefix) {
+            log::warn!("Failed to restore the todo snapshot paths: {}", undo);
+        }
+        return Err(e);
+    }
+
+    log::info!("Rewrote {} file paths from {} to {}", rewritten, old_prefix, new_prefix);
+    Ok(rewritten)
+}
+
+// Moves every stored path under `from` to `to`, in one transaction
+fn rewrite_paths(conn: &Connection, from: &str, to: &str) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    let mut rewritten = 0;
+    for table in PATH_TABLES {
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            params![table],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            continue;
+        }
+        rewritten += tx.execute(
+            &format!(
+                "UPDATE {} SET file_path = ?2 || substr(file_path, length(?1) + 1)
+                 WHERE substr(file_path, 1, length(?1)) = ?1",
+                table
+            ),
+            params![from, to],
+        )?;
+    }
+    tx.commit()?;
+    Ok(rewritten)
+}
+
+// A root as the prefix of the paths below it
+fn with_separator(root: &Path) -> String {
+    let root = root.to_string_lossy();
+    format!("{}{}", root.trim_end_matches(['/', '\\']), MAIN_SEPARATOR)
+}
+
+fn copy_tree(source: &Path, destination: &Path) -> Result<()> {
+    fs::create_dir_all(destination)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target = destination.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_tree(&entry.path(), &target)?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), &target)?;
+        } else {
+            log::warn!("Not copying {}, which is neither a file nor a folder", entry.path().display());
+        }
+    }
+    Ok(())
+}
+
+// Fails unless every file under `source` exists under `copy` with the same bytes
+fn verify_tree(source: &Path, copy: &Path) -> Result<()> {
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target = copy.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            verify_tree(&entry.path(), &target)?;
+        } else if file_type.is_file() && file_digest(&entry.path())? != file_digest(&target)? {
+            return Err(NexusError::Sync(format!(
+                "The copy of {} differs from the original",
+                entry.path().display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn file_digest(path: &Path) -> Result<[u8; 32]> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::database::Database;
+    use crate::sync_service::SyncService;
+    use crate::test_support::{temp_dir, vault, write_file, TWO_TODOS};
+
+    #[test]
+    fn destinations_must_be_empty_and_outside_the_vault() {
+        let source = temp_dir("relocate_destination_source");
+        let parent = temp_dir("relocate_destination");
+        write_file(&parent, "full/x", "x");
+        let full = check_destination(&source, &parent.join("full"));
+        assert!(matches!(full, Err(NexusError::InvalidInput(_))));
+        assert!(check_destination(&source, &source.join("Todo")).is_err());
+        assert_eq!(check_destination(&source, &parent.join("vault")).unwrap(), parent.canonicalize().unwrap().join("vault"));
+    }
+
+    #[tokio::test]
+    async fn relocated_vaults_keep_their_objects() {
+        let (source, database, sync_service) = vault("relocate_source", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        let note = database.add_note("Moving", "boxes", vec![]).await.unwrap();
+        sync_service.force_sync(None).await.unwrap();
+        database.snapshot_todos("pre-move").await.unwrap();
+        let count = database.get_sync_info().await.unwrap().0;
+        let destination = check_destination(&source, &temp_dir("relocate_destination_parent").join("vault")).unwrap();
+
+        drop(sync_service);
+        database.close(Duration::from_secs(1)).await.unwrap();
+        move_vault(&source, &destination).unwrap();
+        assert!(!source.exists());
+        assert!(rebase_vault_paths(&destination, &source).unwrap() >= 3);
+
+        let database = Arc::new(Database::new(&destination).await.unwrap());
+        let sync_service = SyncService::new(database.clone(), &destination).await.unwrap();
+        sync_service.force_sync(None).await.unwrap();
+        assert_eq!(database.get_sync_info().await.unwrap().0, count);
+        let moved_note = database.load_object::<serde_json::Value>(note.id).await.unwrap();
+        assert!(moved_note.file_path.unwrap().starts_with(destination.to_str().unwrap()));
+        let todos = database.load_objects_by_schema::<serde_json::Value>("core.todo").await.unwrap();
+        assert_eq!(todos.len(), 2);
+        assert!(todos.iter().all(|todo| todo.file_path.as_deref().unwrap().starts_with(destination.to_str().unwrap())));
+        // Snapshots follow the vault
+        assert_eq!(database.restore_todo_snapshot("pre-move").await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn paths_stay_put_when_the_snapshots_can_not_be_rebased() {
+        let (source, database, sync_service) = vault("relocate_rollback", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        write_file(&source, ".nexus/snapshots/broken.json", "{not json");
+        drop(sync_service);
+        database.close(Duration::from_secs(1)).await.unwrap();
+
+        let destination = temp_dir("relocate_rollback_parent").join("vault");
+        move_vault(&source, &destination).unwrap();
+        assert!(rebase_vault_paths(&destination, &source).is_err());
+
+        let conn = Connection::open(db_path(&destination)).unwrap();
+        let moved: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM data_objects WHERE substr(file_path, 1, length(?1)) = ?1",
+                params![with_separator(&source)],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(moved, 2);
+    }
+
+    #[test]
+    fn copies_are_checked_against_the_original() {
+        let source = temp_dir("relocate_copy_source");
+        write_file(&source, "a/b/f.txt", "data");
+        let destination = temp_dir("relocate_copy").join("copy");
+        copy_tree(&source, &destination).unwrap();
+        verify_tree(&source, &destination).unwrap();
+        assert_eq!(fs::read_to_string(destination.join("a/b/f.txt")).unwrap(), "data");
+
+        fs::write(destination.join("a/b/f.txt"), "changed").unwrap();
+        assert!(verify_tree(&source, &destination).is_err());
+    }
+
+    #[tokio::test]
+    async fn closing_waits_for_the_other_handles() {
+        let (_vault, database, sync_service) = vault("relocate_close", TWO_TODOS).await;
+        let held = database.clone();
+        let task = tokio::spawn(async move {
+            let _held = held;
+            std::future::pending::<()>().await;
+        });
+        let weak = Arc::downgrade(&database);
+        drop(sync_service);
+
+        task.abort();
+        database.close(Duration::from_secs(2)).await.unwrap();
+        assert!(weak.upgrade().is_none());
+    }
+}
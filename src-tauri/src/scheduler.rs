@@ -0,0 +1,466 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::models::{InstalledPlugin, PluginStatus, ScheduledJob};
+use crate::plugins;
+use crate::sidecar::SidecarManager;
+use crate::vault_fs;
+
+// How often the scheduler wakes up to look for due jobs
+const TICK_INTERVAL: Duration = Duration::from_secs(30);
+
+// Give up looking for a matching time after this long (e.g. "0 0 31 2 *")
+const SEARCH_LIMIT_DAYS: i64 = 366 * 5;
+
+/// A five-field cron expression (minute hour day-of-month month day-of-week),
+/// with `*`, lists, ranges and `/` steps, plus the `@hourly`, `@daily`,
+/// `@weekly`, `@monthly` and `@yearly` shorthands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: Field,
+    hours: Field,
+    days_of_month: Field,
+    months: Field,
+    days_of_week: Field,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Field {
+    bits: u64,
+    // False when the field starts with `*`; matters for the day-of-month/day-of-week rule
+    restricted: bool,
+}
+
+impl Field {
+    fn parse(expr: &str, name: &str, min: u32, max: u32) -> Result<Self, String> {
+        let mut bits = 0u64;
+
+        for part in expr.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step
+                        .parse()
+                        .ok()
+                        .filter(|step| *step > 0)
+                        .ok_or_else(|| format!("invalid step '{}' in {} field", step, name))?;
+                    (range, step)
+                }
+                None => (part, 1),
+            };
+
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else if let Some((start, end)) = range.split_once('-') {
+                (parse_value(start, name, min, max)?, parse_value(end, name, min, max)?)
+            } else {
+                let value = parse_value(range, name, min, max)?;
+                // "5/15" means from 5 to the end in steps of 15
+                (value, if part.contains('/') { max } else { value })
+            };
+            if start > end {
+                return Err(format!("range '{}' in {} field runs backwards", range, name));
+            }
+
+            for value in (start..=end).step_by(step as usize) {
+                bits |= 1 << value;
+            }
+        }
+
+        Ok(Self { bits, restricted: !expr.starts_with('*') })
+    }
+
+    fn matches(&self, value: u32) -> bool {
+        self.bits & (1 << value) != 0
+    }
+}
+
+fn parse_value(value: &str, name: &str, min: u32, max: u32) -> Result<u32, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|value| (min..=max).contains(value))
+        .ok_or_else(|| format!("'{}' is not a valid {} (expected {}-{})", value, name, min, max))
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expr => expr,
+        };
+
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("expected 5 fields, got {}", fields.len()));
+        }
+
+        let mut days_of_week = Field::parse(fields[4], "day of week", 0, 7)?;
+        // Both 0 and 7 mean Sunday
+        if days_of_week.matches(7) {
+            days_of_week.bits = (days_of_week.bits | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: Field::parse(fields[0], "minute", 0, 59)?,
+            hours: Field::parse(fields[1], "hour", 0, 23)?,
+            days_of_month: Field::parse(fields[2], "day of month", 1, 31)?,
+            months: Field::parse(fields[3], "month", 1, 12)?,
+            days_of_week,
+        })
+    }
+
+    // As in cron, a day matches either field when both are restricted
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day_of_month = self.days_of_month.matches(date.day());
+        let day_of_week = self.days_of_week.matches(date.weekday().num_days_from_sunday());
+
+        if self.days_of_month.restricted && self.days_of_week.restricted {
+            day_of_month || day_of_week
+        } else {
+            day_of_month && day_of_week
+        }
+    }
+
+    /// The first matching minute strictly after `after`, in `after`'s time
+    /// zone. Wall-clock times skipped by a DST change never match.
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let timezone = after.timezone();
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let limit = start + ChronoDuration::days(SEARCH_LIMIT_DAYS);
+
+        let mut time = start;
+        while time < limit {
+            let date = time.date();
+            if !self.months.matches(date.month()) {
+                time = first_of_next_month(date)?;
+            } else if !self.matches_day(date) {
+                time = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !self.hours.matches(time.hour()) {
+                time = time.with_minute(0)? + ChronoDuration::hours(1);
+            } else if !self.minutes.matches(time.minute()) {
+                time += ChronoDuration::minutes(1);
+            } else if let Some(matched) = timezone.from_local_datetime(&time).earliest() {
+                return Some(matched);
+            } else {
+                time += ChronoDuration::minutes(1);
+            }
+        }
+
+        None
+    }
+}
+
+fn first_of_next_month(date: NaiveDate) -> Option<NaiveDateTime> {
+    let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+    NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)
+}
+
+pub fn schedule_state_path(vault_path: &Path) -> PathBuf {
+    vault_path.join(".nexus").join("plugin_schedule.json")
+}
+
+// What survives a restart: when each plugin's job last ran
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ScheduleState {
+    last_runs: HashMap<String, DateTime<Utc>>,
+}
+
+struct Job {
+    cron: String,
+    schedule: CronSchedule,
+    catch_up: bool,
+    next_run: Option<DateTime<Local>>,
+    last_status: Option<PluginStatus>,
+}
+
+/// Tracks the scheduled jobs of installed plugins and when each is next due.
+pub struct PluginScheduler {
+    state_path: PathBuf,
+    state: ScheduleState,
+    jobs: BTreeMap<String, Job>,
+}
+
+impl PluginScheduler {
+    pub fn load(state_path: PathBuf) -> Self {
+        let state = match std::fs::read_to_string(&state_path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("Ignoring unreadable plugin schedule state: {}", e);
+                ScheduleState::default()
+            }),
+            Err(_) => ScheduleState::default(),
+        };
+
+        Self {
+            state_path,
+            state,
+            jobs: BTreeMap::new(),
+        }
+    }
+
+    /// Brings the job list in line with the installed plugins. A new job whose
+    /// plugin asks to catch up, and whose last run was before a time it should
+    /// have run, is due immediately (once); otherwise it waits for its next time.
+    pub fn sync_plugins(&mut self, installed: &[InstalledPlugin], now: DateTime<Local>) {
+        let mut seen = Vec::new();
+
+        for plugin in installed.iter().filter(|plugin| plugin.enabled && plugin.errors.is_empty()) {
+            let Some(schedule) = &plugin.metadata.schedule else { continue };
+            let id = &plugin.metadata.id;
+            seen.push(id.clone());
+
+            if let Some(job) = self.jobs.get(id) {
+                if job.cron == schedule.cron && job.catch_up == schedule.catch_up {
+                    continue;
+                }
+            }
+
+            let parsed = match CronSchedule::parse(&schedule.cron) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    log::warn!("Skipping schedule of plugin {}: {}", id, e);
+                    continue;
+                }
+            };
+
+            let last_run = self.state.last_runs.get(id).map(|last| last.with_timezone(&Local));
+            let next_run = match last_run {
+                Some(last_run) if schedule.catch_up => parsed.next_after(&last_run),
+                _ => parsed.next_after(&now),
+            };
+
+            self.jobs.insert(
+                id.clone(),
+                Job {
+                    cron: schedule.cron.clone(),
+                    schedule: parsed,
+                    catch_up: schedule.catch_up,
+                    next_run,
+                    last_status: None,
+                },
+            );
+        }
+
+        self.jobs.retain(|id, _| seen.contains(id));
+    }
+
+    /// Ids of the plugins whose job is due at `now`.
+    pub fn due(&self, now: DateTime<Local>) -> Vec<String> {
+        self.jobs
+            .iter()
+            .filter(|(_, job)| job.next_run.is_some_and(|next_run| next_run <= now))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// Records a run of the plugin's job and moves it to its next time.
+    /// Plugins without a schedule are ignored.
+    pub fn record_run(&mut self, plugin_id: &str, status: PluginStatus, now: DateTime<Local>) {
+        let Some(job) = self.jobs.get_mut(plugin_id) else { return };
+        job.next_run = job.schedule.next_after(&now);
+        job.last_status = Some(status);
+
+        self.state.last_runs.insert(plugin_id.to_string(), now.with_timezone(&Utc));
+        if let Err(e) = self.save() {
+            log::warn!("Failed to save plugin schedule state: {}", e);
+        }
+    }
+
+    fn save(&self) -> crate::error::Result<()> {
+        let content = serde_json::to_string_pretty(&self.state)?;
+        vault_fs::atomic_write(&self.state_path, content.as_bytes())
+    }
+
+    pub fn jobs(&self) -> Vec<ScheduledJob> {
+        self.jobs
+            .iter()
+            .map(|(id, job)| ScheduledJob {
+                plugin_id: id.clone(),
+                cron: job.cron.clone(),
+                catch_up: job.catch_up,
+                next_run: job.next_run.map(|next_run| next_run.to_rfc3339()),
+                last_run: self.state.last_runs.get(id).map(|last_run| last_run.to_rfc3339()),
+                last_status: job.last_status.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Sends `run_scheduled` for one plugin and records the outcome against its job.
+pub async fn run_job(scheduler: &Mutex<PluginScheduler>, manager: &SidecarManager, plugin_id: &str) -> PluginStatus {
+    let status = manager.run_scheduled(plugin_id.to_string()).await;
+    if let Some(error) = &status.error_message {
+        log::warn!("Scheduled run of plugin {} failed: {}", plugin_id, error);
+    }
+
+    scheduler.lock().await.record_run(plugin_id, status.clone(), Local::now());
+    status
+}
+
+/// Runs one pass: refreshes the jobs from `plugins_dir` and runs whatever is due.
+pub async fn run_due_jobs(scheduler: &Mutex<PluginScheduler>, manager: &SidecarManager, plugins_dir: &Path) -> usize {
+    let due = {
+        let mut scheduler = scheduler.lock().await;
+        match plugins::scan_plugins_dir(plugins_dir) {
+            Ok(installed) => scheduler.sync_plugins(&installed, Local::now()),
+            Err(e) => log::debug!("Plugin scheduler could not scan plugins: {}", e),
+        }
+        scheduler.due(Local::now())
+    };
+
+    for plugin_id in &due {
+        run_job(scheduler, manager, plugin_id).await;
+    }
+    due.len()
+}
+
+pub fn spawn_plugin_scheduler(
+    scheduler: Arc<Mutex<PluginScheduler>>,
+    manager: Arc<SidecarManager>,
+    plugins_dir: PathBuf,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        log::info!("Plugin scheduler started for {}", plugins_dir.display());
+
+        let mut ticker = tokio::time::interval(TICK_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            run_due_jobs(&scheduler, &manager, &plugins_dir).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sidecar::{Connection, ResponseHandlers, RpcResponse, SidecarConnection};
+    use crate::test_support::{temp_dir, write_file};
+
+    fn at(time: &str) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap())
+    }
+
+    fn next(expr: &str, from: &str) -> DateTime<Utc> {
+        CronSchedule::parse(expr).unwrap().next_after(&at(from)).unwrap()
+    }
+
+    // Records which plugin each `run_scheduled` was for and answers it as done
+    struct RecordingConnection {
+        handlers: ResponseHandlers,
+        runs: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl SidecarConnection for RecordingConnection {
+        fn write_line(&mut self, line: &[u8]) -> Result<(), String> {
+            let request: serde_json::Value = serde_json::from_slice(line).unwrap();
+            let id = request["id"].as_u64().unwrap();
+            let plugin_id = request["params"]["plugin_id"].clone();
+            assert_eq!(request["method"], "run_scheduled");
+            self.runs.lock().unwrap().push(plugin_id.as_str().unwrap().to_string());
+
+            let handlers = self.handlers.clone();
+            tokio::spawn(async move {
+                if let Some(pending) = handlers.lock().await.remove(&id) {
+                    let result = serde_json::json!({ "plugin_id": plugin_id, "status": "active", "message": null, "timestamp": null });
+                    let _ = pending.sender.send(RpcResponse { id, result: Some(result), error: None });
+                }
+            });
+            Ok(())
+        }
+    }
+
+    fn recording_manager(runs: &Arc<std::sync::Mutex<Vec<String>>>) -> SidecarManager {
+        let handlers: ResponseHandlers = Default::default();
+        let (terminate, terminated) = tokio::sync::oneshot::channel();
+        std::mem::forget(terminate);
+        let connection = RecordingConnection { handlers: handlers.clone(), runs: runs.clone() };
+        SidecarManager::with_connection(handlers, (connection, terminated), || {
+            Err::<Connection<RecordingConnection>, _>("no reconnects in tests".to_string())
+        })
+    }
+
+    fn install_scheduled_plugin(plugins_dir: &Path, plugin_id: &str, cron: &str, catch_up: bool) {
+        let manifest = serde_json::json!({
+            "name": plugin_id, "id": plugin_id, "version": "1.0.0", "description": "", "author": "",
+            "main": "main.ts", "category": "test", "capabilities": [], "tags": [],
+            "permissions": { "network": false, "filesystem": false, "system": false },
+            "schedule": { "cron": cron, "catch_up": catch_up }
+        });
+        write_file(plugins_dir, &format!("{}/plugin.json", plugin_id), &manifest.to_string());
+        write_file(plugins_dir, &format!("{}/main.ts", plugin_id), "");
+    }
+
+    #[test]
+    fn cron_expressions_find_their_next_time() {
+        assert_eq!(next("*/15 * * * *", "2024-03-05 10:07"), at("2024-03-05 10:15"));
+        assert_eq!(next("*/15 * * * *", "2024-03-05 10:15"), at("2024-03-05 10:30"));
+        // Friday to Monday
+        assert_eq!(next("0 9 * * 1-5", "2024-03-08 09:00"), at("2024-03-11 09:00"));
+        assert_eq!(next("30 2 1 * *", "2024-12-15 00:00"), at("2025-01-01 02:30"));
+        assert_eq!(next("0 0 29 2 *", "2024-03-01 00:00"), at("2028-02-29 00:00"));
+        // 7 is Sunday too
+        assert_eq!(next("0 12 * * 7", "2024-03-05 00:00"), at("2024-03-10 12:00"));
+        // A restricted day of month and day of week match either
+        assert_eq!(next("0 0 13 * 5", "2024-03-05 00:00"), at("2024-03-08 00:00"));
+        assert_eq!(next("5,10-12/2 * * * *", "2024-03-05 00:06"), at("2024-03-05 00:10"));
+        assert_eq!(next("@daily", "2024-03-05 10:00"), at("2024-03-06 00:00"));
+    }
+
+    #[test]
+    fn malformed_cron_expressions_are_rejected() {
+        for expr in ["* * * *", "60 * * * *", "*/0 * * * *", "5-1 * * * *", "a * * * *", "* * 0 * *"] {
+            assert!(CronSchedule::parse(expr).is_err(), "{}", expr);
+        }
+        // Valid, but never matches
+        assert!(CronSchedule::parse("0 0 31 2 *").unwrap().next_after(&at("2024-01-01 00:00")).is_none());
+    }
+
+    #[tokio::test]
+    async fn due_jobs_are_sent_to_the_sidecar() {
+        let dir = temp_dir("scheduler_due_jobs");
+        let plugins_dir = dir.join("plugins");
+        install_scheduled_plugin(&plugins_dir, "every", "* * * * *", false);
+        install_scheduled_plugin(&plugins_dir, "yearly", "0 0 1 1 *", true);
+        install_scheduled_plugin(&plugins_dir, "missed", "0 0 1 1 *", false);
+
+        // Both yearly jobs last ran two years ago; only the one that catches up runs now
+        let two_years_ago = Utc::now() - ChronoDuration::days(730);
+        let state = serde_json::json!({ "last_runs": { "yearly": two_years_ago, "missed": two_years_ago } });
+        write_file(&dir, ".nexus/plugin_schedule.json", &state.to_string());
+
+        let runs = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let manager = recording_manager(&runs);
+        let scheduler = Mutex::new(PluginScheduler::load(schedule_state_path(&dir)));
+
+        assert_eq!(run_due_jobs(&scheduler, &manager, &plugins_dir).await, 1);
+        assert_eq!(*runs.lock().unwrap(), vec!["yearly"]);
+        // Caught up once, then it waits for next year
+        assert_eq!(run_due_jobs(&scheduler, &manager, &plugins_dir).await, 0);
+
+        let jobs = scheduler.lock().await.jobs();
+        assert_eq!(jobs.len(), 3);
+        let yearly = jobs.iter().find(|job| job.plugin_id == "yearly").unwrap();
+        assert_eq!(yearly.last_status.as_ref().unwrap().status, "active");
+        assert!(yearly.next_run.as_ref().unwrap() > &Local::now().to_rfc3339());
+
+        let in_two_minutes = Local::now() + ChronoDuration::minutes(2);
+        assert!(scheduler.lock().await.due(in_two_minutes).contains(&"every".to_string()));
+        assert_eq!(run_job(&scheduler, &manager, "every").await.status, "active");
+        assert_eq!(runs.lock().unwrap().last().unwrap(), "every");
+
+        // Last runs survive a restart
+        let reloaded = PluginScheduler::load(schedule_state_path(&dir));
+        assert!(reloaded.state.last_runs.contains_key("every"));
+    }
+}
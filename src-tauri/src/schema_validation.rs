@@ -0,0 +1,163 @@
+use serde_json::{Map, Value};
+
+use crate::models::ValidationError;
+
+/// Validates `document` against a JSON Schema, returning every violation found.
+/// Supports the keywords schemas in this app use: type, enum, const, required,
+/// properties, additionalProperties, items, min/maxItems, min/maxLength and
+/// minimum/maximum. Annotation-only keywords such as `format` are ignored.
+pub fn validate(schema: &Value, document: &Value) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    validate_at(schema, document, "", &mut errors);
+    errors
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, errors: &mut Vec<ValidationError>) {
+    let schema = match schema {
+        Value::Object(schema) => schema,
+        // `true` accepts anything, `false` nothing
+        Value::Bool(false) => {
+            push(errors, path, "no value is allowed here".to_string());
+            return;
+        }
+        _ => return,
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(types) => types.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| matches_type(t, value)) {
+            push(errors, path, format!("expected {}, found {}", allowed.join(" or "), type_name(value)));
+            // Further keywords would only repeat the type mismatch
+            return;
+        }
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            push(errors, path, format!("must be one of {}", Value::Array(options.clone())));
+        }
+    }
+
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            push(errors, path, format!("must be {}", expected));
+        }
+    }
+
+    match value {
+        Value::Object(object) => validate_object(schema, object, path, errors),
+        Value::Array(items) => validate_array(schema, items, path, errors),
+        Value::String(text) => {
+            let length = text.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(|v| v.as_u64()) {
+                if length < min {
+                    push(errors, path, format!("must be at least {} characters", min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(|v| v.as_u64()) {
+                if length > max {
+                    push(errors, path, format!("must be at most {} characters", max));
+                }
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(|v| v.as_f64()) {
+                if number < min {
+                    push(errors, path, format!("must be >= {}", min));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(|v| v.as_f64()) {
+                if number > max {
+                    push(errors, path, format!("must be <= {}", max));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn validate_object(schema: &Map<String, Value>, object: &Map<String, Value>, path: &str, errors: &mut Vec<ValidationError>) {
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for field in required.iter().filter_map(|f| f.as_str()) {
+            if !object.contains_key(field) {
+                push(errors, path, format!("missing required field '{}'", field));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(|p| p.as_object());
+    for (key, child) in object {
+        let child_path = format!("{}/{}", path, escape_pointer(key));
+        match properties.and_then(|p| p.get(key)) {
+            Some(child_schema) => validate_at(child_schema, child, &child_path, errors),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    push(errors, &child_path, format!("unexpected field '{}'", key));
+                }
+                Some(additional) => validate_at(additional, child, &child_path, errors),
+                None => {}
+            },
+        }
+    }
+}
+
+fn validate_array(schema: &Map<String, Value>, items: &[Value], path: &str, errors: &mut Vec<ValidationError>) {
+    let count = items.len() as u64;
+    if let Some(min) = schema.get("minItems").and_then(|v| v.as_u64()) {
+        if count < min {
+            push(errors, path, format!("must have at least {} items", min));
+        }
+    }
+    if let Some(max) = schema.get("maxItems").and_then(|v| v.as_u64()) {
+        if count > max {
+            push(errors, path, format!("must have at most {} items", max));
+        }
+    }
+
+    if let Some(item_schema) = schema.get("items") {
+        for (index, item) in items.iter().enumerate() {
+            validate_at(item_schema, item, &format!("{}/{}", path, index), errors);
+        }
+    }
+}
+
+fn matches_type(expected: &str, value: &Value) -> bool {
+    match expected {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// JSON Pointer (RFC 6901) escaping for object keys
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn push(errors: &mut Vec<ValidationError>, path: &str, message: String) {
+    errors.push(ValidationError {
+        path: path.to_string(),
+        message,
+    });
+}
@@ -0,0 +1,176 @@
+use rusqlite::{params, Connection};
+use serde_json::Value;
+
+use crate::database::Database;
+use crate::error::{NexusError, Result};
+use crate::models::{AppObject, Permissions};
+
+// Rows are object ids; the text is the object's content_json
+const CREATE_SEARCH_TABLE: &str = "CREATE VIRTUAL TABLE IF NOT EXISTS object_search USING fts5(
+    content_json,
+    content = 'object_content',
+    content_rowid = 'object_id'
+)";
+
+const CREATE_SEARCH_TRIGGERS: &str = "
+    CREATE TRIGGER IF NOT EXISTS object_search_insert AFTER INSERT ON object_content BEGIN
+        INSERT INTO object_search (rowid, content_json) VALUES (new.object_id, new.content_json);
+    END;
+    CREATE TRIGGER IF NOT EXISTS object_search_delete AFTER DELETE ON object_content BEGIN
+        INSERT INTO object_search (object_search, rowid, content_json) VALUES ('delete', old.object_id, old.content_json);
+    END;
+    CREATE TRIGGER IF NOT EXISTS object_search_update AFTER UPDATE ON object_content BEGIN
+        INSERT INTO object_search (object_search, rowid, content_json) VALUES ('delete', old.object_id, old.content_json);
+        INSERT INTO object_search (rowid, content_json) VALUES (new.object_id, new.content_json);
+    END;";
+
+/// Creates the full-text index and the triggers that maintain it, filling it
+/// from the existing content the first time.
+pub(crate) fn create_search_index(conn: &Connection) -> Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = 'object_search')",
+        [],
+        |row| row.get(0),
+    )?;
+
+    conn.execute(CREATE_SEARCH_TABLE, [])?;
+    conn.execute_batch(CREATE_SEARCH_TRIGGERS)?;
+    if !exists {
+        conn.execute("INSERT INTO object_search (object_search) VALUES ('rebuild')", [])?;
+    }
+
+    Ok(())
+}
+
+impl Database {
+    /// Objects whose content contains every word of `query`, best match
+    /// first. Words are matched as whole tokens, case-insensitively.
+    pub async fn search_objects(&self, query: &str, limit: usize) -> Result<Vec<AppObject<Value>>> {
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+            .collect();
+        if terms.is_empty() {
+            return Err(NexusError::InvalidInput("A search needs at least one word".to_string()));
+        }
+
+        let conn = self.lock_connection().await?;
+        let mut stmt = conn.prepare(
+            "SELECT
+                do.id, s.schema_name, oc.content_json, do.file_path, do.updated_at, do.created_at,
+                op.share_with_ai, op.share_with_cloud, op.read_only, op.expires_at, do.source
+             FROM object_search
+             JOIN data_objects do ON do.id = object_search.rowid
+             JOIN schemas s ON do.schema_id = s.id
+             JOIN object_content oc ON do.id = oc.object_id
+             JOIN object_permissions op ON do.id = op.object_id
+             WHERE object_search MATCH ?1
+             ORDER BY object_search.rank, do.id
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![terms.join(" "), limit as i64], |row| {
+            let content_json: String = row.get(2)?;
+            let content: Value = serde_json::from_str(&content_json).map_err(|e| {
+                rusqlite::Error::InvalidColumnType(
+                    2,
+                    format!("JSON deserialization error: {}", e),
+                    rusqlite::types::Type::Text,
+                )
+            })?;
+
+            Ok(AppObject {
+                id: row.get(0)?,
+                schema_name: row.get(1)?,
+                content,
+                file_path: row.get(3)?,
+                updated_at: row.get(4)?,
+                created_at: row.get(5)?,
+                permissions: Permissions {
+                    share_with_ai: row.get(6)?,
+                    share_with_cloud: row.get(7)?,
+                    read_only: row.get(8)?,
+                    expires_at: row.get(9)?,
+                },
+                source: row.get(10)?,
+            })
+        })?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Drops the full-text index and rebuilds it from object_content, for when
+    /// searches have drifted from the stored content. Returns the number of
+    /// objects indexed.
+    pub async fn reindex_search(&self) -> Result<usize> {
+        let conn = self.lock_connection().await?;
+        let tx = conn.unchecked_transaction()?;
+
+        tx.execute_batch(
+            "DROP TRIGGER IF EXISTS object_search_insert;
+             DROP TRIGGER IF EXISTS object_search_delete;
+             DROP TRIGGER IF EXISTS object_search_update;
+             DROP TABLE IF EXISTS object_search;",
+        )?;
+        tx.execute(CREATE_SEARCH_TABLE, [])?;
+        tx.execute_batch(CREATE_SEARCH_TRIGGERS)?;
+        tx.execute("INSERT INTO object_search (object_search) VALUES ('rebuild')", [])?;
+
+        let indexed: i64 = tx.query_row("SELECT COUNT(*) FROM object_content", [], |row| row.get(0))?;
+        tx.commit()?;
+
+        log::info!("Rebuilt the search index over {} objects", indexed);
+        Ok(indexed as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::Database;
+    use crate::models::{AppObject, NotePatch};
+    use crate::test_support::{vault, TWO_TODOS};
+
+    fn ids(objects: Vec<AppObject<serde_json::Value>>) -> Vec<i64> {
+        objects.iter().map(|object| object.id).collect()
+    }
+
+    #[tokio::test]
+    async fn the_index_follows_edits() {
+        let (_vault, database, _sync_service) = vault("search_triggers", TWO_TODOS).await;
+        let note = database.add_note("Trip", "pack the tent", vec![]).await.unwrap();
+        assert_eq!(ids(database.search_objects("TENT", 10).await.unwrap()), vec![note.id]);
+
+        let patch = NotePatch { body: Some("pack the stove".into()), ..Default::default() };
+        database.update_note(note.id, patch).await.unwrap();
+        assert!(database.search_objects("tent", 10).await.unwrap().is_empty());
+        assert_eq!(ids(database.search_objects("stove pack", 10).await.unwrap()), vec![note.id]);
+        assert!(database.search_objects("  ", 10).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn reindexing_repairs_a_drifted_index() {
+        let (vault, database, sync_service) = vault("search_reindex", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        let note = database.add_note("Trip", "pack the stove", vec![]).await.unwrap();
+
+        // Clear the index behind the triggers' back
+        database
+            .lock_connection()
+            .await
+            .unwrap()
+            .execute("INSERT INTO object_search (object_search) VALUES ('delete-all')", [])
+            .unwrap();
+        assert!(database.search_objects("stove", 10).await.unwrap().is_empty());
+
+        let total = database.get_sync_info().await.unwrap().0 as usize;
+        assert_eq!(database.reindex_search().await.unwrap(), total);
+        assert_eq!(ids(database.search_objects("stove", 10).await.unwrap()), vec![note.id]);
+        assert_eq!(database.search_objects("a", 10).await.unwrap().len(), 1);
+
+        // Reopening an existing vault keeps the index
+        drop(sync_service);
+        drop(database);
+        let database = Database::new(&vault).await.unwrap();
+        assert!(database.search_objects("\"stove AND (", 10).await.unwrap().is_empty());
+        assert_eq!(database.search_objects("stove", 10).await.unwrap().len(), 1);
+    }
+}
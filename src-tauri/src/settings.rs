@@ -0,0 +1,109 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::{NexusError, Result};
+use crate::models::VaultSettings;
+use crate::vault_fs;
+
+pub fn settings_path(vault_path: &Path) -> PathBuf {
+    vault_path.join(".nexus").join("settings.json")
+}
+
+/// Reads the vault's settings, or `None` if it has never saved any.
+pub fn load_settings(vault_path: &Path) -> Result<Option<VaultSettings>> {
+    let path = settings_path(vault_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&path)?;
+    Ok(Some(serde_json::from_str(&content)?))
+}
+
+/// Settings for a vault that predates settings.json. Earlier versions kept
+/// them in the app-level vault config under the same names; anything missing
+/// there takes its default.
+pub fn settings_from_legacy_config(config_json: &str) -> Result<VaultSettings> {
+    Ok(serde_json::from_str(config_json)?)
+}
+
+pub fn save_settings(vault_path: &Path, settings: &VaultSettings) -> Result<()> {
+    let content = serde_json::to_string_pretty(settings)?;
+    vault_fs::atomic_write(&settings_path(vault_path), content.as_bytes())
+}
+
+pub fn validate_settings(settings: &VaultSettings) -> Result<()> {
+    log_level_filter(&settings.log_level)?;
+    if settings.max_content_bytes == 0 {
+        return Err(NexusError::InvalidInput("max_content_bytes must be greater than zero".to_string()));
+    }
+    Ok(())
+}
+
+pub fn log_level_filter(level: &str) -> Result<log::LevelFilter> {
+    level
+        .parse()
+        .map_err(|_| NexusError::InvalidInput(format!("Unknown log level '{}'", level)))
+}
+
+/// Applies the log level globally. Levels above what the logger was
+/// initialized with have no effect.
+pub fn apply_log_level(settings: &VaultSettings) {
+    match log_level_filter(&settings.log_level) {
+        Ok(level) => log::set_max_level(level),
+        Err(e) => log::warn!("{}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::models::Todo;
+    use crate::test_support::{temp_database, temp_dir};
+
+    #[test]
+    fn vaults_without_settings_have_none() {
+        let vault = temp_dir("settings_missing");
+        assert_eq!(load_settings(&vault).unwrap(), None);
+
+        std::fs::create_dir_all(vault.join(".nexus")).unwrap();
+        let settings = VaultSettings { log_level: "debug".to_string(), ..Default::default() };
+        save_settings(&vault, &settings).unwrap();
+        assert_eq!(load_settings(&vault).unwrap(), Some(settings));
+    }
+
+    #[test]
+    fn legacy_configs_carry_their_settings_over() {
+        let legacy = r#"{"vault_path":"/v","created_at":"t","version":"1.0.0","encryption_enabled":false,
+            "backup_keep_count":3,"write_back_delay_ms":50,"sidecar_max_in_flight":2,"sidecar_request_timeout_secs":9}"#;
+
+        let settings = settings_from_legacy_config(legacy).unwrap();
+        assert_eq!(settings.backup_keep_count, 3);
+        assert_eq!(settings.write_back_delay_ms, 50);
+        assert_eq!(settings.sidecar_max_in_flight, 2);
+        assert_eq!(settings.sidecar_request_timeout_secs, Some(9));
+        assert_eq!(settings.log_level, VaultSettings::default().log_level);
+    }
+
+    #[tokio::test]
+    async fn updated_settings_apply_and_persist() {
+        let (vault, database) = temp_database("settings_update").await;
+        assert_eq!(database.settings(), VaultSettings::default());
+
+        let mut settings = database.settings();
+        settings.max_content_bytes = 10;
+        database.update_settings(settings.clone()).unwrap();
+        assert!(matches!(
+            database.save_object("core.todo", &Todo::new("a long todo text".to_string()), None, None, None).await,
+            Err(NexusError::ContentTooLarge(_))
+        ));
+
+        let invalid = VaultSettings { log_level: "loud".to_string(), ..settings.clone() };
+        assert!(matches!(database.update_settings(invalid), Err(NexusError::InvalidInput(_))));
+        assert_eq!(database.settings(), settings);
+
+        database.release_vault_lock();
+        drop(database);
+        assert_eq!(Database::new(&vault).await.unwrap().settings(), settings);
+    }
+}
@@ -1,8 +1,16 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tauri_plugin_shell::{ShellExt, process::CommandEvent};
-use tokio::sync::{mpsc, oneshot, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tauri_plugin_shell::{ShellExt, process::{CommandChild, CommandEvent}};
+use tokio::sync::{mpsc, oneshot, Mutex, Semaphore};
+use tokio::task::JoinSet;
+
+use crate::models::{LogStream, PluginLogLine, PluginStatus};
+use crate::sidecar_protocol::{self, CallError, SidecarMethod};
 
 #[derive(Serialize, Clone, Debug)]
 pub struct RpcRequest {
@@ -18,111 +26,372 @@ pub struct RpcResponse {
     pub error: Option<String>,
 }
 
+// A caller awaiting the response to its request
+pub struct PendingResponse {
+    pub sender: oneshot::Sender<RpcResponse>,
+    pub registered_at: Instant,
+}
+
+impl PendingResponse {
+    pub fn new(sender: oneshot::Sender<RpcResponse>) -> Self {
+        Self { sender, registered_at: Instant::now() }
+    }
+}
+
+pub type ResponseHandlers = Arc<Mutex<HashMap<u64, PendingResponse>>>;
+
+// A request whose write fails is retried once on a fresh sidecar before its caller is told
+const MAX_DELIVERY_ATTEMPTS: u32 = 2;
+// Reconnect attempts back off from the initial delay, doubling per failure up to the cap
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+// How many plugin health checks may be in flight at once
+const MAX_CONCURRENT_TESTS: usize = 4;
+
+// How many requests of any kind may await a response at once, unless configured otherwise
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 8;
+
+// Handlers still waiting after this long are failed by the periodic sweep,
+// so responses that never arrive can't pile up without a request timeout
+pub const DEFAULT_HANDLER_MAX_AGE: Duration = Duration::from_secs(300);
+const HANDLER_SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+// How many recent lines of plugin output are kept for the UI
+const PLUGIN_LOG_CAPACITY: usize = 1000;
+
+pub type LogEmitter = Arc<dyn Fn(&PluginLogLine) + Send + Sync>;
+
+/// The most recent output of the sidecar that wasn't an RPC response: stdout
+/// lines plugins printed and everything on stderr. Clones share one buffer.
+#[derive(Clone)]
+pub struct PluginLogs {
+    inner: Arc<std::sync::Mutex<LogBuffer>>,
+}
+
+struct LogBuffer {
+    lines: VecDeque<PluginLogLine>,
+    capacity: usize,
+    emitter: Option<LogEmitter>,
+}
+
+impl PluginLogs {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(std::sync::Mutex::new(LogBuffer {
+                lines: VecDeque::with_capacity(capacity),
+                capacity,
+                emitter: None,
+            })),
+        }
+    }
+
+    /// Called with every line as it's recorded, e.g. to feed a live console.
+    pub fn set_emitter(&self, emitter: LogEmitter) {
+        if let Ok(mut buffer) = self.inner.lock() {
+            buffer.emitter = Some(emitter);
+        }
+    }
+
+    pub fn record(&self, stream: LogStream, line: &str) {
+        let entry = PluginLogLine {
+            stream,
+            line: line.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+
+        let emitter = {
+            let Ok(mut buffer) = self.inner.lock() else { return };
+            if buffer.lines.len() == buffer.capacity {
+                buffer.lines.pop_front();
+            }
+            buffer.lines.push_back(entry.clone());
+            buffer.emitter.clone()
+        };
+
+        if let Some(emit) = emitter {
+            emit(&entry);
+        }
+    }
+
+    /// Up to `lines` of the latest output, oldest first.
+    pub fn recent(&self, lines: usize) -> Vec<PluginLogLine> {
+        let Ok(buffer) = self.inner.lock() else { return Vec::new() };
+        let skip = buffer.lines.len().saturating_sub(lines);
+        buffer.lines.iter().skip(skip).cloned().collect()
+    }
+}
+
 pub struct SidecarManager {
     pub tx: mpsc::Sender<RpcRequest>,
-    response_handlers: Arc<Mutex<HashMap<u64, oneshot::Sender<RpcResponse>>>>,
+    response_handlers: ResponseHandlers,
     next_id: Arc<Mutex<u64>>,
+    // Bounds the requests awaiting a response, so a single-threaded plugin
+    // process isn't flooded and `response_handlers` stays small
+    request_permits: Arc<Semaphore>,
+    request_timeout: Option<Duration>,
+    // Read by the sweep task, in milliseconds, so it can be changed after spawning
+    handler_max_age_ms: Arc<AtomicU64>,
+    plugin_logs: PluginLogs,
+    // Each plugin's most recent health check, kept for the UI
+    last_statuses: std::sync::Mutex<HashMap<String, PluginStatus>>,
+    // The vault plugins folder the sidecar was started for
+    plugins_dir: Option<PathBuf>,
 }
 
+// Where requests get written; for the real sidecar this is the child's stdin
+pub trait SidecarConnection: Send + 'static {
+    fn write_line(&mut self, line: &[u8]) -> Result<(), String>;
+
+    // Called once the manager is gone and nothing will be written any more
+    fn close(self)
+    where
+        Self: Sized,
+    {
+    }
+}
+
+impl SidecarConnection for CommandChild {
+    fn write_line(&mut self, line: &[u8]) -> Result<(), String> {
+        self.write(line).map_err(|e| e.to_string())
+    }
+
+    fn close(self) {
+        if let Err(e) = self.kill() {
+            log::debug!("Failed to stop sidecar: {}", e);
+        }
+    }
+}
+
+// A live connection plus a receiver that resolves once the process has gone away
+pub type Connection<C> = (C, oneshot::Receiver<()>);
+
 impl SidecarManager {
-    pub async fn new(app_handle: tauri::AppHandle) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let (request_tx, mut request_rx): (mpsc::Sender<RpcRequest>, mpsc::Receiver<RpcRequest>) =
-            mpsc::channel(100);
-        
-        let response_handlers: Arc<Mutex<HashMap<u64, oneshot::Sender<RpcResponse>>>> =
-            Arc::new(Mutex::new(HashMap::new()));
-        
-        let response_handlers_clone = response_handlers.clone();
-
-        // Spawn the deno process using the shell plugin
-        // Try multiple deno paths in order of preference
-        let deno_paths = [
-            "deno", // If it's in PATH
-            &format!("{}/.deno/bin/deno.exe", std::env::var("USERPROFILE").unwrap_or_default()),
-            "C:\\Users\\%USERNAME%\\.deno\\bin\\deno.exe",
-        ];
-        
-        let mut deno_command = None;
-        for deno_path in &deno_paths {
-            match app_handle.shell().command(deno_path).args(["run", "--allow-read", "--allow-net", "sidecars/plugin_manager.ts"]).spawn() {
-                Ok(result) => {
-                    deno_command = Some(result);
-                    log::info!("Found deno at: {}", deno_path);
-                    break;
-                }
-                Err(e) => {
-                    log::debug!("Failed to spawn deno at {}: {}", deno_path, e);
-                    continue;
-                }
+    /// Spawns the sidecar for the vault whose plugins live in `plugins_dir`.
+    pub async fn new(
+        app_handle: tauri::AppHandle,
+        plugins_dir: PathBuf,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let response_handlers: ResponseHandlers = Arc::new(Mutex::new(HashMap::new()));
+
+        // Each line also goes out as a `plugin-log` event for a live console
+        let plugin_logs = PluginLogs::new(PLUGIN_LOG_CAPACITY);
+        let emit_handle = app_handle.clone();
+        plugin_logs.set_emitter(Arc::new(move |line| {
+            if let Err(e) = emit_handle.emit("plugin-log", line) {
+                log::debug!("Failed to emit plugin log line: {}", e);
             }
-        }
-        
-        let (mut rx, mut child) = deno_command.ok_or("Could not find deno executable")?;
+        }));
+
+        // The first spawn must succeed; after that the writer reconnects on its own
+        let initial = spawn_sidecar(&app_handle, &plugins_dir, response_handlers.clone(), plugin_logs.clone())?;
 
-        // Task for writing to the sidecar's stdin
+        let reader_handlers = response_handlers.clone();
+        let reader_logs = plugin_logs.clone();
+        let connect_dir = plugins_dir.clone();
+        let connect = move || spawn_sidecar(&app_handle, &connect_dir, reader_handlers.clone(), reader_logs.clone());
+
+        let mut manager = Self::with_connection(response_handlers, initial, connect);
+        manager.plugin_logs = plugin_logs;
+        manager.plugins_dir = Some(plugins_dir);
+        Ok(manager)
+    }
+
+    /// Starts the writer over an already established connection. Whatever reads
+    /// the connection's responses must resolve them through `response_handlers`.
+    pub fn with_connection<C, F>(response_handlers: ResponseHandlers, initial: Connection<C>, connect: F) -> Self
+    where
+        C: SidecarConnection,
+        F: FnMut() -> Result<Connection<C>, String> + Send + 'static,
+    {
+        let (request_tx, request_rx): (mpsc::Sender<RpcRequest>, mpsc::Receiver<RpcRequest>) =
+            mpsc::channel(100);
+
+        let handlers = response_handlers.clone();
         tauri::async_runtime::spawn(async move {
-            while let Some(request) = request_rx.recv().await {
-                let json_string = serde_json::to_string(&request).unwrap();
-                let line = format!("{}\n", json_string);
-                if let Err(e) = child.write(line.as_bytes()) {
-                    log::error!("Failed to write to sidecar stdin: {}", e);
-                    break;
-                }
-            }
+            run_writer(request_rx, handlers, initial, connect).await;
         });
 
-        // Task for reading from the sidecar's events
+        // Runs until the manager, and with it the last request sender, is gone
+        let handler_max_age_ms = Arc::new(AtomicU64::new(DEFAULT_HANDLER_MAX_AGE.as_millis() as u64));
+        let sweep_handlers = response_handlers.clone();
+        let sweep_max_age = handler_max_age_ms.clone();
+        let manager_alive = request_tx.downgrade();
         tauri::async_runtime::spawn(async move {
-            while let Some(event) = rx.recv().await {
-                match event {
-                    CommandEvent::Stdout(data) => {
-                        let line = String::from_utf8_lossy(&data);
-                        for line in line.lines() {
-                            if line.trim().is_empty() {
-                                continue;
-                            }
-                            match serde_json::from_str::<RpcResponse>(&line.trim()) {
-                                Ok(response) => {
-                                    log::info!("[Deno Response]: {:?}", response);
-                                    
-                                    // Find and notify the waiting handler
-                                    let mut handlers = response_handlers_clone.lock().await;
-                                    if let Some(sender) = handlers.remove(&response.id) {
-                                        let _ = sender.send(response);
-                                    }
-                                }
-                                Err(e) => {
-                                    log::error!("Failed to parse response from sidecar: {} (line: {})", e, line);
-                                }
-                            }
-                        }
-                    }
-                    CommandEvent::Stderr(data) => {
-                        log::warn!("Sidecar stderr: {}", String::from_utf8_lossy(&data));
-                    }
-                    CommandEvent::Error(error) => {
-                        log::error!("Sidecar error: {}", error);
-                    }
-                    CommandEvent::Terminated(payload) => {
-                        log::info!("Sidecar terminated with code: {:?}", payload.code);
-                        break;
-                    }
-                    _ => {
-                        // Handle any other event types
-                    }
+            loop {
+                tokio::time::sleep(HANDLER_SWEEP_INTERVAL).await;
+                if manager_alive.upgrade().is_none() {
+                    break;
                 }
+                let max_age = Duration::from_millis(sweep_max_age.load(Ordering::Relaxed));
+                sweep_stale_handlers(&sweep_handlers, max_age).await;
             }
-            log::info!("Sidecar event handler finished");
         });
 
-        Ok(SidecarManager {
+        SidecarManager {
             tx: request_tx,
             response_handlers,
             next_id: Arc::new(Mutex::new(1)),
-        })
+            request_permits: Arc::new(Semaphore::new(DEFAULT_MAX_IN_FLIGHT)),
+            request_timeout: None,
+            handler_max_age_ms,
+            plugin_logs: PluginLogs::new(PLUGIN_LOG_CAPACITY),
+            last_statuses: std::sync::Mutex::new(HashMap::new()),
+            plugins_dir: None,
+        }
+    }
+
+    /// The plugins folder the sidecar was spawned for; None for a manager
+    /// built over an existing connection.
+    pub fn plugins_dir(&self) -> Option<&Path> {
+        self.plugins_dir.as_deref()
+    }
+
+    /// Where the connection's reader records output that isn't a response.
+    pub fn plugin_logs(&self) -> PluginLogs {
+        self.plugin_logs.clone()
+    }
+
+    /// Allows at most `max_in_flight` requests to await a response at once;
+    /// further requests wait for one to finish. Zero is treated as one.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.request_permits = Arc::new(Semaphore::new(max_in_flight.max(1)));
+        self
+    }
+
+    /// Fails requests that get no response within `timeout`, counting the
+    /// time spent waiting for a free slot.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Lets the periodic sweep fail handlers once they have waited `max_age`
+    /// for a response instead of DEFAULT_HANDLER_MAX_AGE.
+    pub fn with_handler_max_age(self, max_age: Duration) -> Self {
+        self.handler_max_age_ms.store(max_age.as_millis() as u64, Ordering::Relaxed);
+        self
+    }
+
+    /// How many requests are waiting for a response.
+    pub async fn pending_count(&self) -> usize {
+        self.response_handlers.lock().await.len()
+    }
+
+    /// Calls a sidecar method with typed params and result.
+    pub async fn call<M: SidecarMethod>(&self, params: M::Params) -> Result<M::Result, CallError> {
+        let params = serde_json::to_value(params).map_err(|e| CallError::InvalidResult(e.to_string()))?;
+
+        let response = self
+            .send_request(M::METHOD.as_str().to_string(), params)
+            .await
+            .map_err(|e| CallError::Transport(e.to_string()))?;
+
+        if let Some(error) = response.error {
+            return Err(CallError::Remote(error));
+        }
+
+        serde_json::from_value(response.result.unwrap_or_default())
+            .map_err(|e| CallError::InvalidResult(e.to_string()))
+    }
+
+    /// Runs the sidecar's health check for one plugin. Failures are reported
+    /// in the returned status rather than as an error. The status is kept as
+    /// the plugin's last status.
+    pub async fn test_plugin(&self, plugin_id: String) -> PluginStatus {
+        let status = self.plugin_status::<sidecar_protocol::TestPlugin>(plugin_id).await;
+        if let Ok(mut last_statuses) = self.last_statuses.lock() {
+            last_statuses.insert(status.plugin_id.clone(), status.clone());
+        }
+        status
+    }
+
+    /// The status of the plugin's most recent test, if it has been tested
+    /// since the sidecar started.
+    pub fn last_status(&self, plugin_id: &str) -> Option<PluginStatus> {
+        self.last_statuses.lock().ok()?.get(plugin_id).cloned()
+    }
+
+    /// Has the sidecar re-import one plugin's module so code changes take
+    /// effect without restarting the app. Failures are reported in the status.
+    pub async fn reload_plugin(&self, plugin_id: String) -> PluginStatus {
+        self.plugin_status::<sidecar_protocol::ReloadPlugin>(plugin_id).await
+    }
+
+    /// Runs a plugin's scheduled job. Failures are reported in the status.
+    pub async fn run_scheduled(&self, plugin_id: String) -> PluginStatus {
+        self.plugin_status::<sidecar_protocol::RunScheduled>(plugin_id).await
+    }
+
+    async fn plugin_status<M>(&self, plugin_id: String) -> PluginStatus
+    where
+        M: SidecarMethod<Params = sidecar_protocol::TestPluginParams, Result = sidecar_protocol::TestPluginResult>,
+    {
+        let started = Instant::now();
+        let params = sidecar_protocol::TestPluginParams { plugin_id: plugin_id.clone() };
+        let (status, error_message) = match self.call::<M>(params).await {
+            Ok(result) => (result.status, None),
+            Err(CallError::Remote(error)) => ("error".to_string(), Some(error)),
+            Err(e) => ("error".to_string(), Some(e.to_string())),
+        };
+
+        PluginStatus {
+            plugin_id,
+            status,
+            last_ping: Some(chrono::Utc::now().to_rfc3339()),
+            error_message,
+            latency_ms: Some(started.elapsed().as_millis() as u64),
+        }
+    }
+
+    /// Tests several plugins concurrently, returning their statuses in the order given.
+    pub async fn test_plugins(self: &Arc<Self>, plugin_ids: Vec<String>) -> Vec<PluginStatus> {
+        let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_TESTS));
+        let mut tasks = JoinSet::new();
+
+        for (index, plugin_id) in plugin_ids.iter().cloned().enumerate() {
+            let manager = Arc::clone(self);
+            let permits = Arc::clone(&permits);
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                (index, manager.test_plugin(plugin_id).await)
+            });
+        }
+
+        let mut statuses: Vec<Option<PluginStatus>> = vec![None; plugin_ids.len()];
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok((index, status)) => statuses[index] = Some(status),
+                Err(e) => log::error!("Plugin test task failed: {}", e),
+            }
+        }
+
+        // A test task that panicked still gets a status
+        statuses
+            .into_iter()
+            .zip(plugin_ids)
+            .map(|(status, plugin_id)| {
+                status.unwrap_or_else(|| PluginStatus {
+                    plugin_id,
+                    status: "error".to_string(),
+                    last_ping: Some(chrono::Utc::now().to_rfc3339()),
+                    error_message: Some("Plugin test did not complete".to_string()),
+                    latency_ms: None,
+                })
+            })
+            .collect()
     }
 
     pub async fn send_request(&self, method: String, params: serde_json::Value) -> Result<RpcResponse, Box<dyn std::error::Error + Send + Sync>> {
+        // The timeout covers waiting for a free slot as well as for the response
+        let deadline = self.request_timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+        let _permit = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, self.request_permits.acquire())
+                .await
+                .map_err(|_| "Request timeout or sidecar disconnected")??,
+            None => self.request_permits.acquire().await?,
+        };
+
         let id = {
             let mut next_id = self.next_id.lock().await;
             let current_id = *next_id;
@@ -131,22 +400,699 @@ impl SidecarManager {
         };
 
         let request = RpcRequest { id, method, params };
-        
+
         let (response_tx, response_rx) = oneshot::channel();
-        
+
         // Register the response handler
         {
             let mut handlers = self.response_handlers.lock().await;
-            handlers.insert(id, response_tx);
+            handlers.insert(id, PendingResponse::new(response_tx));
         }
 
         // Send the request
         self.tx.send(request).await?;
 
         // Wait for the response
-        match response_rx.await {
+        let response = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, response_rx).await {
+                Ok(response) => response,
+                Err(_) => {
+                    // Nobody is waiting any more, so a late response has nowhere to go
+                    self.response_handlers.lock().await.remove(&id);
+                    return Err("Request timeout or sidecar disconnected".into());
+                }
+            },
+            None => response_rx.await,
+        };
+
+        match response {
             Ok(response) => Ok(response),
             Err(_) => Err("Request timeout or sidecar disconnected".into()),
         }
     }
 }
+
+/// The plugins folder a sidecar should be started for: none without a vault,
+/// or when `current` already serves the vault's plugins.
+pub fn plugins_dir_to_spawn(current: Option<&SidecarManager>, vault_path: Option<&Path>) -> Option<PathBuf> {
+    let plugins_dir = vault_path?.join("plugins");
+    match current {
+        Some(manager) if manager.plugins_dir() == Some(plugins_dir.as_path()) => None,
+        _ => Some(plugins_dir),
+    }
+}
+
+/// Arguments the deno process is run with; the plugin manager script finds
+/// the vault's plugins through `--plugins-dir`.
+pub fn sidecar_args(plugins_dir: &Path) -> Vec<String> {
+    vec![
+        "run".to_string(),
+        "--allow-read".to_string(),
+        "--allow-net".to_string(),
+        "sidecars/plugin_manager.ts".to_string(),
+        "--plugins-dir".to_string(),
+        plugins_dir.to_string_lossy().to_string(),
+    ]
+}
+
+// Spawns the deno process and the task reading its responses
+fn spawn_sidecar(
+    app_handle: &tauri::AppHandle,
+    plugins_dir: &Path,
+    response_handlers: ResponseHandlers,
+    plugin_logs: PluginLogs,
+) -> Result<Connection<CommandChild>, String> {
+    // Try multiple deno paths in order of preference
+    let deno_paths = [
+        "deno", // If it's in PATH
+        &format!("{}/.deno/bin/deno.exe", std::env::var("USERPROFILE").unwrap_or_default()),
+        "C:\\Users\\%USERNAME%\\.deno\\bin\\deno.exe",
+    ];
+
+    let mut deno_command = None;
+    for deno_path in &deno_paths {
+        match app_handle.shell().command(deno_path).args(sidecar_args(plugins_dir)).spawn() {
+            Ok(result) => {
+                deno_command = Some(result);
+                log::info!("Found deno at: {}", deno_path);
+                break;
+            }
+            Err(e) => {
+                log::debug!("Failed to spawn deno at {}: {}", deno_path, e);
+                continue;
+            }
+        }
+    }
+
+    let (mut rx, child) = deno_command.ok_or("Could not find deno executable")?;
+    let (terminated_tx, terminated_rx) = oneshot::channel();
+
+    // Task for reading from the sidecar's events
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(data) => {
+                    handle_stdout(&data, &response_handlers, &plugin_logs).await;
+                }
+                CommandEvent::Stderr(data) => {
+                    handle_stderr(&data, &plugin_logs);
+                }
+                CommandEvent::Error(error) => {
+                    log::error!("Sidecar error: {}", error);
+                }
+                CommandEvent::Terminated(payload) => {
+                    log::info!("Sidecar terminated with code: {:?}", payload.code);
+                    break;
+                }
+                _ => {
+                    // Handle any other event types
+                }
+            }
+        }
+        log::info!("Sidecar event handler finished");
+        let _ = terminated_tx.send(());
+    });
+
+    Ok((child, terminated_rx))
+}
+
+/// Routes the sidecar's stdout: RPC responses go to their waiting handler,
+/// anything else (e.g. a plugin's `console.log`) is kept as plugin output.
+pub async fn handle_stdout(data: &[u8], response_handlers: &ResponseHandlers, plugin_logs: &PluginLogs) {
+    let output = String::from_utf8_lossy(data);
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<RpcResponse>(line.trim()) {
+            Ok(response) => {
+                log::info!("[Deno Response]: {:?}", response);
+
+                // Find and notify the waiting handler
+                let mut handlers = response_handlers.lock().await;
+                if let Some(pending) = handlers.remove(&response.id) {
+                    let _ = pending.sender.send(response);
+                }
+            }
+            Err(_) => plugin_logs.record(LogStream::Stdout, line),
+        }
+    }
+}
+
+pub fn handle_stderr(data: &[u8], plugin_logs: &PluginLogs) {
+    let output = String::from_utf8_lossy(data);
+    log::warn!("Sidecar stderr: {}", output);
+    for line in output.lines().filter(|line| !line.trim().is_empty()) {
+        plugin_logs.record(LogStream::Stderr, line);
+    }
+}
+
+/// Writes queued requests to the sidecar. When a write fails or the process
+/// exits, the writer reconnects and retries the failed request; a request that
+/// still can't be delivered fails its handler instead of hanging.
+pub async fn run_writer<C, F>(
+    mut request_rx: mpsc::Receiver<RpcRequest>,
+    response_handlers: ResponseHandlers,
+    initial: Connection<C>,
+    mut connect: F,
+) where
+    C: SidecarConnection,
+    F: FnMut() -> Result<Connection<C>, String>,
+{
+    let mut connection = Some(initial);
+    let mut pending: Option<(RpcRequest, u32)> = None;
+    let mut backoff = ReconnectBackoff::new();
+
+    loop {
+        let (mut child, mut terminated) = match connection.take() {
+            Some(connection) => connection,
+            None => {
+                tokio::time::sleep(backoff.delay()).await;
+                match connect() {
+                    Ok(connection) => {
+                        log::info!("Sidecar reconnected");
+                        backoff.reset();
+                        connection
+                    }
+                    Err(e) => {
+                        backoff.failed();
+                        log::error!("Failed to restart sidecar, next attempt in {:?}: {}", backoff.delay(), e);
+                        if let Some((request, _)) = pending.take() {
+                            fail_request(&response_handlers, request.id, &e).await;
+                        }
+                        continue;
+                    }
+                }
+            }
+        };
+
+        loop {
+            let (request, attempts) = match pending.take() {
+                Some(pending) => pending,
+                None => tokio::select! {
+                    request = request_rx.recv() => match request {
+                        Some(request) => (request, 0),
+                        None => {
+                            child.close();
+                            return;
+                        }
+                    },
+                    _ = &mut terminated => {
+                        log::warn!("Sidecar exited, reconnecting");
+                        break;
+                    }
+                },
+            };
+
+            let json_string = serde_json::to_string(&request).unwrap();
+            let line = format!("{}\n", json_string);
+            if let Err(e) = child.write_line(line.as_bytes()) {
+                log::error!("Failed to write to sidecar stdin: {}", e);
+                if attempts + 1 >= MAX_DELIVERY_ATTEMPTS {
+                    fail_request(&response_handlers, request.id, &e).await;
+                } else {
+                    pending = Some((request, attempts + 1));
+                }
+                break;
+            }
+        }
+    }
+}
+
+/// How long the writer waits before its next reconnect attempt.
+#[derive(Debug)]
+struct ReconnectBackoff {
+    delay: Duration,
+}
+
+impl ReconnectBackoff {
+    fn new() -> Self {
+        Self { delay: RECONNECT_INITIAL_DELAY }
+    }
+
+    fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    fn failed(&mut self) {
+        self.delay = (self.delay * 2).min(RECONNECT_MAX_DELAY);
+    }
+
+    fn reset(&mut self) {
+        self.delay = RECONNECT_INITIAL_DELAY;
+    }
+}
+
+/// Removes the handlers that have waited longer than `max_age`, resolving
+/// each with an error. Returns how many were removed.
+pub async fn sweep_stale_handlers(response_handlers: &ResponseHandlers, max_age: Duration) -> usize {
+    let stale: Vec<(u64, PendingResponse)> = {
+        let mut handlers = response_handlers.lock().await;
+        let ids: Vec<u64> = handlers
+            .iter()
+            .filter(|(_, pending)| pending.registered_at.elapsed() > max_age)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.into_iter().filter_map(|id| handlers.remove(&id).map(|pending| (id, pending))).collect()
+    };
+
+    if !stale.is_empty() {
+        log::warn!("Reaped {} sidecar response handlers older than {:?}", stale.len(), max_age);
+    }
+    let reaped = stale.len();
+    for (id, pending) in stale {
+        let _ = pending.sender.send(RpcResponse {
+            id,
+            result: None,
+            error: Some(format!("No response from sidecar within {:?}", max_age)),
+        });
+    }
+    reaped
+}
+
+async fn fail_request(response_handlers: &ResponseHandlers, id: u64, reason: &str) {
+    if let Some(pending) = response_handlers.lock().await.remove(&id) {
+        let _ = pending.sender.send(RpcResponse {
+            id,
+            result: None,
+            error: Some(format!("Could not deliver request to sidecar: {}", reason)),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    struct FakeConnection {
+        fail: bool,
+        sent: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl SidecarConnection for FakeConnection {
+        fn write_line(&mut self, line: &[u8]) -> Result<(), String> {
+            if self.fail {
+                return Err("broken pipe".to_string());
+            }
+            self.sent.lock().unwrap().push(String::from_utf8_lossy(line).to_string());
+            Ok(())
+        }
+    }
+
+    fn fake_connection(fail: bool, sent: &Arc<std::sync::Mutex<Vec<String>>>) -> Connection<FakeConnection> {
+        // Leaking the sender keeps the connection from looking terminated
+        let (terminate, terminated) = oneshot::channel();
+        std::mem::forget(terminate);
+        (FakeConnection { fail, sent: sent.clone() }, terminated)
+    }
+
+    // Answers requests in-process the way the Deno plugin manager would
+    struct EchoConnection(ResponseHandlers);
+
+    impl SidecarConnection for EchoConnection {
+        fn write_line(&mut self, line: &[u8]) -> Result<(), String> {
+            let request: serde_json::Value = serde_json::from_slice(line).unwrap();
+            let id = request["id"].as_u64().unwrap();
+            let plugin_id = &request["params"]["plugin_id"];
+            // Every plugin except "bad" counts as loaded
+            let (result, error) = match request["method"].as_str().unwrap() {
+                "ping" => (serde_json::json!("pong"), None),
+                "get_info" => (serde_json::json!({
+                    "version": "1.0.0", "runtime": "Deno", "denoVersion": "1", "typescriptVersion": "5",
+                    "v8Version": "12", "capabilities": ["ping"], "timestamp": "t"
+                }), None),
+                "list_plugins" => (serde_json::json!(["a", "c"]), None),
+                "test_plugin" | "reload_plugin" | "run_scheduled" if plugin_id == "bad" => {
+                    (serde_json::Value::Null, Some("Plugin 'bad' not found".to_string()))
+                }
+                "test_plugin" | "reload_plugin" | "run_scheduled" => (serde_json::json!({
+                    "plugin_id": plugin_id, "status": "active", "message": null, "timestamp": null
+                }), None),
+                method => (serde_json::Value::Null, Some(format!("Unknown method: {}", method))),
+            };
+
+            let handlers = self.0.clone();
+            tokio::spawn(async move {
+                if let Some(pending) = handlers.lock().await.remove(&id) {
+                    let response = serde_json::json!({ "id": id, "result": result, "error": error });
+                    let _ = pending.sender.send(serde_json::from_value(response).unwrap());
+                }
+            });
+            Ok(())
+        }
+    }
+
+    // Fails the first request for each plugin, then answers like EchoConnection
+    struct FlakyConnection {
+        handlers: ResponseHandlers,
+        failed: std::collections::HashSet<String>,
+    }
+
+    impl SidecarConnection for FlakyConnection {
+        fn write_line(&mut self, line: &[u8]) -> Result<(), String> {
+            let request: serde_json::Value = serde_json::from_slice(line).unwrap();
+            let plugin_id = request["params"]["plugin_id"].as_str().unwrap_or_default().to_string();
+            if !self.failed.insert(plugin_id) {
+                return EchoConnection(self.handlers.clone()).write_line(line);
+            }
+
+            let id = request["id"].as_u64().unwrap();
+            let handlers = self.handlers.clone();
+            tokio::spawn(async move {
+                if let Some(pending) = handlers.lock().await.remove(&id) {
+                    let _ = pending.sender.send(RpcResponse { id, result: None, error: Some("plugin crashed".to_string()) });
+                }
+            });
+            Ok(())
+        }
+    }
+
+    fn echo_manager() -> SidecarManager {
+        let handlers: ResponseHandlers = Default::default();
+        let (terminate, terminated) = oneshot::channel();
+        std::mem::forget(terminate);
+        SidecarManager::with_connection(handlers.clone(), (EchoConnection(handlers), terminated), || {
+            Err::<Connection<EchoConnection>, _>("no reconnects in tests".to_string())
+        })
+    }
+
+    // Answers every request with "pong" after `delay`, counting how many are outstanding
+    struct SlowConnection {
+        handlers: ResponseHandlers,
+        in_flight: Arc<AtomicUsize>,
+        most_in_flight: Arc<AtomicUsize>,
+        delay: Duration,
+    }
+
+    impl SidecarConnection for SlowConnection {
+        fn write_line(&mut self, line: &[u8]) -> Result<(), String> {
+            let request: serde_json::Value = serde_json::from_slice(line).unwrap();
+            let id = request["id"].as_u64().unwrap();
+            let outstanding = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most_in_flight.fetch_max(outstanding, Ordering::SeqCst);
+
+            let (handlers, in_flight, delay) = (self.handlers.clone(), self.in_flight.clone(), self.delay);
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                if let Some(pending) = handlers.lock().await.remove(&id) {
+                    let _ = pending.sender.send(RpcResponse { id, result: Some(serde_json::json!("pong")), error: None });
+                }
+            });
+            Ok(())
+        }
+    }
+
+    // The manager, its handler map and the most requests it ever had outstanding
+    fn slow_manager(delay: Duration, max_in_flight: usize) -> (SidecarManager, ResponseHandlers, Arc<AtomicUsize>) {
+        let handlers: ResponseHandlers = Default::default();
+        let most_in_flight = Arc::new(AtomicUsize::new(0));
+        let connection = SlowConnection {
+            handlers: handlers.clone(),
+            in_flight: Default::default(),
+            most_in_flight: most_in_flight.clone(),
+            delay,
+        };
+        let (terminate, terminated) = oneshot::channel();
+        std::mem::forget(terminate);
+        let manager = SidecarManager::with_connection(handlers.clone(), (connection, terminated), || {
+            Err::<Connection<SlowConnection>, _>("no reconnects in tests".to_string())
+        })
+        .with_max_in_flight(max_in_flight);
+        (manager, handlers, most_in_flight)
+    }
+
+    fn spawn_ping(manager: &Arc<SidecarManager>) -> tokio::task::JoinHandle<Result<String, CallError>> {
+        let manager = manager.clone();
+        tokio::spawn(async move { manager.call::<sidecar_protocol::Ping>(()).await })
+    }
+
+    fn ping(id: u64) -> RpcRequest {
+        RpcRequest { id, method: "ping".to_string(), params: serde_json::Value::Null }
+    }
+
+    #[test]
+    fn reconnect_delay_doubles_up_to_the_cap_and_resets() {
+        let mut backoff = ReconnectBackoff::new();
+        let mut delays = Vec::new();
+        for _ in 0..7 {
+            delays.push(backoff.delay().as_secs());
+            backoff.failed();
+        }
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 30, 30]);
+
+        backoff.reset();
+        assert_eq!(backoff.delay(), RECONNECT_INITIAL_DELAY);
+    }
+
+    #[tokio::test]
+    async fn failed_writes_are_retried_on_a_fresh_connection() {
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let handlers: ResponseHandlers = Default::default();
+        let (request_tx, request_rx) = mpsc::channel(10);
+        let fresh = sent.clone();
+        let broken = fake_connection(true, &Default::default());
+        tokio::spawn(run_writer(request_rx, handlers, broken, move || Ok(fake_connection(false, &fresh))));
+
+        request_tx.send(ping(1)).await.unwrap();
+        tokio::time::sleep(RECONNECT_INITIAL_DELAY + Duration::from_millis(500)).await;
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].contains(r#""id":1"#));
+    }
+
+    #[tokio::test]
+    async fn undeliverable_requests_fail_their_handler() {
+        let handlers: ResponseHandlers = Default::default();
+        let (response_tx, response_rx) = oneshot::channel();
+        handlers.lock().await.insert(7, PendingResponse::new(response_tx));
+        let (request_tx, request_rx) = mpsc::channel(10);
+        let broken = fake_connection(true, &Default::default());
+        tokio::spawn(run_writer(request_rx, handlers.clone(), broken, || Ok(fake_connection(true, &Default::default()))));
+
+        request_tx.send(ping(7)).await.unwrap();
+        let response = tokio::time::timeout(Duration::from_secs(3), response_rx).await.unwrap().unwrap();
+
+        assert!(response.error.unwrap().contains("broken pipe"));
+    }
+
+    #[tokio::test]
+    async fn typed_calls_round_trip_through_the_sidecar() {
+        let manager = echo_manager();
+
+        assert_eq!(manager.call::<sidecar_protocol::Ping>(()).await.unwrap(), "pong");
+        let info = manager.call::<sidecar_protocol::GetInfo>(()).await.unwrap();
+        assert_eq!(info.runtime, "Deno");
+        assert_eq!(info.deno_version, "1");
+
+        let params = sidecar_protocol::TestPluginParams { plugin_id: "bad".to_string() };
+        let error = manager.call::<sidecar_protocol::TestPlugin>(params).await.unwrap_err();
+        assert!(matches!(error, CallError::Remote(_)));
+    }
+
+    #[tokio::test]
+    async fn one_failing_plugin_does_not_stop_the_others() {
+        let manager = Arc::new(echo_manager());
+        let plugin_ids: Vec<String> = ["a", "bad", "c", "d", "e", "f"].iter().map(|id| id.to_string()).collect();
+
+        let statuses = manager.test_plugins(plugin_ids.clone()).await;
+
+        assert_eq!(statuses.iter().map(|s| s.plugin_id.clone()).collect::<Vec<_>>(), plugin_ids);
+        assert_eq!(statuses.iter().filter(|s| s.status == "active").count(), 5);
+        assert_eq!(statuses[1].status, "error");
+        assert!(statuses[1].error_message.is_some());
+        assert!(statuses.iter().all(|s| s.latency_ms.is_some()));
+    }
+
+    #[tokio::test]
+    async fn listing_plugins_returns_the_loaded_ids() {
+        let manager = echo_manager();
+
+        let loaded = manager.call::<sidecar_protocol::ListPlugins>(()).await.unwrap();
+        assert_eq!(loaded, vec!["a", "c"]);
+    }
+
+    #[tokio::test]
+    async fn reload_failures_are_reported_in_the_status() {
+        let manager = echo_manager();
+
+        let reloaded = manager.reload_plugin("a".to_string()).await;
+        assert_eq!((reloaded.plugin_id.as_str(), reloaded.status.as_str()), ("a", "active"));
+        assert!(reloaded.error_message.is_none());
+
+        let failed = manager.reload_plugin("bad".to_string()).await;
+        assert_eq!(failed.status, "error");
+        assert!(failed.error_message.unwrap().contains("Plugin 'bad' not found"));
+        // A reload is not a test, so it leaves the last test status alone
+        assert!(manager.last_status("bad").is_none());
+    }
+
+    #[tokio::test]
+    async fn scheduled_run_failures_are_reported_in_the_status() {
+        let manager = echo_manager();
+
+        assert_eq!(manager.run_scheduled("a".to_string()).await.status, "active");
+
+        let failed = manager.run_scheduled("bad".to_string()).await;
+        assert_eq!(failed.status, "error");
+        assert!(failed.error_message.unwrap().contains("Plugin 'bad' not found"));
+    }
+
+    #[tokio::test]
+    async fn requests_beyond_the_limit_wait_for_a_slot() {
+        let (manager, handlers, most_in_flight) = slow_manager(Duration::from_millis(20), 2);
+        let manager = Arc::new(manager);
+
+        let calls: Vec<_> = (0..10).map(|_| spawn_ping(&manager)).collect();
+        for call in calls {
+            assert_eq!(call.await.unwrap().unwrap(), "pong");
+        }
+
+        assert_eq!(most_in_flight.load(Ordering::SeqCst), 2);
+        assert!(handlers.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn waiting_for_a_slot_counts_towards_the_timeout() {
+        let (manager, handlers, most_in_flight) = slow_manager(Duration::from_millis(60), 1);
+        let manager = Arc::new(manager.with_request_timeout(Duration::from_millis(100)));
+
+        let first = spawn_ping(&manager);
+        let second = spawn_ping(&manager);
+        assert!(first.await.unwrap().is_ok());
+        assert!(second.await.unwrap().is_err());
+        assert_eq!(most_in_flight.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        assert!(handlers.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn plugin_output_is_kept_apart_from_responses() {
+        let handlers: ResponseHandlers = Default::default();
+        let logs = PluginLogs::new(PLUGIN_LOG_CAPACITY);
+        let emitted = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = emitted.clone();
+        logs.set_emitter(Arc::new(move |line: &PluginLogLine| sink.lock().unwrap().push(line.line.clone())));
+
+        handle_stderr(b"[DEBUG] one\n\n[ERROR] two\n", &logs);
+        let (response_tx, response_rx) = oneshot::channel();
+        handlers.lock().await.insert(7, PendingResponse::new(response_tx));
+        handle_stdout(b"hello from plugin\n{\"id\":7,\"result\":\"pong\",\"error\":null}\n", &handlers, &logs).await;
+
+        assert_eq!(response_rx.await.unwrap().result, Some(serde_json::json!("pong")));
+        let lines: Vec<(LogStream, String)> = logs.recent(usize::MAX).into_iter().map(|l| (l.stream, l.line)).collect();
+        assert_eq!(lines, vec![
+            (LogStream::Stderr, "[DEBUG] one".to_string()),
+            (LogStream::Stderr, "[ERROR] two".to_string()),
+            (LogStream::Stdout, "hello from plugin".to_string()),
+        ]);
+        assert_eq!(logs.recent(1)[0].line, "hello from plugin");
+        assert_eq!(emitted.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn plugin_logs_keep_the_newest_lines() {
+        let logs = PluginLogs::new(2);
+        for line in ["1", "2", "3", "4"] {
+            logs.record(LogStream::Stdout, line);
+        }
+        assert_eq!(logs.recent(10).into_iter().map(|l| l.line).collect::<Vec<_>>(), vec!["3", "4"]);
+    }
+
+    #[tokio::test]
+    async fn the_latest_test_result_is_kept_per_plugin() {
+        let handlers: ResponseHandlers = Default::default();
+        let (terminate, terminated) = oneshot::channel();
+        std::mem::forget(terminate);
+        let connection = FlakyConnection { handlers: handlers.clone(), failed: Default::default() };
+        let manager = SidecarManager::with_connection(handlers, (connection, terminated), || {
+            Err::<Connection<FlakyConnection>, _>("no reconnects in tests".to_string())
+        });
+        assert!(manager.last_status("a").is_none());
+
+        assert_eq!(manager.test_plugin("a".to_string()).await.status, "error");
+        let last = manager.last_status("a").unwrap();
+        assert_eq!((last.status.as_str(), last.error_message.as_deref()), ("error", Some("plugin crashed")));
+        assert!(manager.last_status("b").is_none());
+
+        manager.test_plugin("a".to_string()).await;
+        let last = manager.last_status("a").unwrap();
+        assert_eq!(last.status, "active");
+        assert!(last.error_message.is_none());
+    }
+
+    // Records being closed, which is how a manager stops its process
+    struct ClosingConnection(Arc<std::sync::atomic::AtomicBool>);
+
+    impl SidecarConnection for ClosingConnection {
+        fn write_line(&mut self, _line: &[u8]) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn close(self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn sidecars_spawn_for_the_vault_plugins_folder() {
+        let args = sidecar_args(Path::new("/vault/plugins"));
+        assert_eq!(&args[3..], ["sidecars/plugin_manager.ts", "--plugins-dir", "/vault/plugins"]);
+    }
+
+    #[tokio::test]
+    async fn sidecars_only_start_once_a_vault_is_open() {
+        let vault = Path::new("/vault");
+        assert_eq!(plugins_dir_to_spawn(None, None), None);
+        assert_eq!(plugins_dir_to_spawn(None, Some(vault)), Some(vault.join("plugins")));
+
+        let mut manager = echo_manager();
+        manager.plugins_dir = Some(vault.join("plugins"));
+        assert_eq!(plugins_dir_to_spawn(Some(&manager), Some(vault)), None);
+        let other = Path::new("/other");
+        assert_eq!(plugins_dir_to_spawn(Some(&manager), Some(other)), Some(other.join("plugins")));
+    }
+
+    #[tokio::test]
+    async fn replaced_managers_stop_their_process() {
+        let closed = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let (_terminate, terminated) = oneshot::channel();
+        let manager = SidecarManager::with_connection(Default::default(), (ClosingConnection(closed.clone()), terminated), || {
+            Err::<Connection<ClosingConnection>, _>("no reconnects in tests".to_string())
+        });
+        assert!(manager.plugins_dir().is_none());
+
+        drop(manager);
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while !closed.load(Ordering::SeqCst) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_sweep_fails_only_stale_handlers() {
+        let manager = echo_manager();
+        let handlers = manager.response_handlers.clone();
+        let (stale_tx, stale_rx) = oneshot::channel();
+        let mut stale = PendingResponse::new(stale_tx);
+        stale.registered_at = Instant::now() - Duration::from_secs(600);
+        handlers.lock().await.insert(1, stale);
+        let (fresh_tx, _fresh_rx) = oneshot::channel();
+        handlers.lock().await.insert(2, PendingResponse::new(fresh_tx));
+        assert_eq!(manager.pending_count().await, 2);
+
+        assert_eq!(sweep_stale_handlers(&handlers, Duration::from_secs(300)).await, 1);
+        assert_eq!(manager.pending_count().await, 1);
+        assert!(handlers.lock().await.contains_key(&2));
+        let response = stale_rx.await.unwrap();
+        assert_eq!(response.id, 1);
+        assert!(response.error.unwrap().contains("No response from sidecar"));
+        assert_eq!(sweep_stale_handlers(&handlers, Duration::from_secs(300)).await, 0);
+    }
+}
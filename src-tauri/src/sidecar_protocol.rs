@@ -0,0 +1,126 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+// Methods the host calls on the Deno plugin manager (see sidecars/plugin_manager.ts)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Ping,
+    GetInfo,
+    ListPlugins,
+    TestPlugin,
+    ReloadPlugin,
+    RunScheduled,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Ping => "ping",
+            Method::GetInfo => "get_info",
+            Method::ListPlugins => "list_plugins",
+            Method::TestPlugin => "test_plugin",
+            Method::ReloadPlugin => "reload_plugin",
+            Method::RunScheduled => "run_scheduled",
+        }
+    }
+}
+
+/// Ties a method name to the shapes of its params and result.
+pub trait SidecarMethod {
+    const METHOD: Method;
+    type Params: Serialize + Send;
+    type Result: DeserializeOwned;
+}
+
+pub struct Ping;
+pub struct GetInfo;
+pub struct ListPlugins;
+pub struct TestPlugin;
+pub struct ReloadPlugin;
+pub struct RunScheduled;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginManagerInfo {
+    pub version: String,
+    pub runtime: String,
+    pub deno_version: String,
+    pub typescript_version: String,
+    pub v8_version: String,
+    pub capabilities: Vec<String>,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TestPluginParams {
+    pub plugin_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TestPluginResult {
+    pub plugin_id: String,
+    pub status: String,
+    pub message: Option<String>,
+    pub timestamp: Option<String>,
+}
+
+impl SidecarMethod for Ping {
+    const METHOD: Method = Method::Ping;
+    type Params = ();
+    type Result = String;
+}
+
+impl SidecarMethod for GetInfo {
+    const METHOD: Method = Method::GetInfo;
+    type Params = ();
+    type Result = PluginManagerInfo;
+}
+
+// Ids of the plugins the sidecar currently has loaded
+impl SidecarMethod for ListPlugins {
+    const METHOD: Method = Method::ListPlugins;
+    type Params = ();
+    type Result = Vec<String>;
+}
+
+impl SidecarMethod for TestPlugin {
+    const METHOD: Method = Method::TestPlugin;
+    type Params = TestPluginParams;
+    type Result = TestPluginResult;
+}
+
+// Re-imports the plugin's module, answering like a test of the fresh copy
+impl SidecarMethod for ReloadPlugin {
+    const METHOD: Method = Method::ReloadPlugin;
+    type Params = TestPluginParams;
+    type Result = TestPluginResult;
+}
+
+// Runs the periodic work a plugin declared with its manifest's schedule
+impl SidecarMethod for RunScheduled {
+    const METHOD: Method = Method::RunScheduled;
+    type Params = TestPluginParams;
+    type Result = TestPluginResult;
+}
+
+#[derive(Debug)]
+pub enum CallError {
+    // The request never got a response
+    Transport(String),
+    // The sidecar answered with an error
+    Remote(String),
+    // The response didn't match the method's result type
+    InvalidResult(String),
+}
+
+impl std::fmt::Display for CallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallError::Transport(e) => write!(f, "Failed to communicate with plugins: {}", e),
+            CallError::Remote(e) => write!(f, "Sidecar error: {}", e),
+            CallError::InvalidResult(e) => write!(f, "Unexpected sidecar response: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
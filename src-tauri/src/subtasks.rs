@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+
+use rusqlite::params;
+
+use crate::database::Database;
+use crate::error::{NexusError, Result};
+use crate::models::{AppObject, Todo};
+
+impl Database {
+    /// Direct children of a todo, i.e. todos whose `parent_id` is the parent's todo `id`.
+    pub async fn get_subtasks(&self, parent_object_id: i64) -> Result<Vec<AppObject<Todo>>> {
+        let parent: AppObject<Todo> = self.load_object(parent_object_id).await?;
+        let parent_id = match parent.content.id {
+            Some(id) => id,
+            None => return Ok(Vec::new()),
+        };
+
+        let child_ids: Vec<i64> = {
+            let conn = self.lock_connection().await?;
+            let mut stmt = conn.prepare(
+                "SELECT do.id FROM data_objects do
+                 JOIN schemas s ON do.schema_id = s.id
+                 JOIN object_content oc ON do.id = oc.object_id
+                 WHERE s.schema_name = 'core.todo' AND json_extract(oc.content_json, '$.parent_id') = ?1
+                 ORDER BY do.created_at ASC"
+            )?;
+            let rows = stmt.query_map(params![parent_id], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        let mut subtasks = Vec::new();
+        for child_id in child_ids {
+            // A todo listed as its own parent isn't a subtask
+            if child_id != parent_object_id {
+                subtasks.push(self.load_object(child_id).await?);
+            }
+        }
+
+        Ok(subtasks)
+    }
+
+    /// Fraction of a todo's direct subtasks that are completed, or `None` if it has none.
+    pub async fn subtask_progress(&self, parent_object_id: i64) -> Result<Option<f64>> {
+        let subtasks = self.get_subtasks(parent_object_id).await?;
+        if subtasks.is_empty() {
+            return Ok(None);
+        }
+
+        let completed = subtasks.iter().filter(|t| t.content.completed).count();
+        Ok(Some(completed as f64 / subtasks.len() as f64))
+    }
+
+    /// Marks a todo completed or not. With `with_subtasks`, every descendant
+    /// follows; read-only descendants are left alone.
+    pub async fn set_todo_completed(
+        &self,
+        object_id: i64,
+        completed: bool,
+        with_subtasks: bool,
+    ) -> Result<AppObject<Todo>> {
+        let root: AppObject<Todo> = self.load_object(object_id).await?;
+        if root.permissions.read_only {
+            return Err(NexusError::PermissionDenied(format!("Todo {} is read-only", object_id)));
+        }
+
+        let mut pending = vec![root];
+        let mut visited = HashSet::new();
+
+        while let Some(mut todo) = pending.pop() {
+            // Guard against parent_id cycles
+            if !visited.insert(todo.id) {
+                continue;
+            }
+
+            if with_subtasks {
+                pending.extend(self.get_subtasks(todo.id).await?);
+            }
+
+            if todo.permissions.read_only {
+                log::info!("Skipping read-only subtask {}", todo.id);
+                continue;
+            }
+
+            if todo.content.completed != completed {
+                todo.content.completed = completed;
+                todo.content.mark_updated();
+                self.update_object(todo.id, &todo.content).await?;
+            }
+        }
+
+        self.load_object(object_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_database;
+
+    fn todo(id: u32, parent_id: Option<u32>) -> Todo {
+        let mut todo = Todo::new(format!("task {}", id));
+        todo.id = Some(id);
+        todo.parent_id = parent_id;
+        todo
+    }
+
+    #[tokio::test]
+    async fn parent_progress_follows_its_children() {
+        let (_vault, database) = temp_database("subtasks_tree").await;
+        let parent = database.save_object("core.todo", &todo(1, None), None, None, None).await.unwrap();
+        let first = database.save_object("core.todo", &todo(2, Some(1)), None, None, None).await.unwrap();
+        let second = database.save_object("core.todo", &todo(3, Some(1)), None, None, None).await.unwrap();
+        let grandchild = database.save_object("core.todo", &todo(4, Some(2)), None, None, None).await.unwrap();
+
+        assert_eq!(database.get_subtasks(parent).await.unwrap().len(), 2);
+        assert_eq!(database.subtask_progress(parent).await.unwrap(), Some(0.0));
+        assert_eq!(database.subtask_progress(grandchild).await.unwrap(), None);
+
+        database.set_todo_completed(first, true, false).await.unwrap();
+        assert_eq!(database.subtask_progress(parent).await.unwrap(), Some(0.5));
+        database.set_todo_completed(second, true, false).await.unwrap();
+        assert_eq!(database.subtask_progress(parent).await.unwrap(), Some(1.0));
+    }
+
+    #[tokio::test]
+    async fn completing_with_subtasks_completes_the_whole_tree() {
+        let (_vault, database) = temp_database("subtasks_cascade").await;
+        let parent = database.save_object("core.todo", &todo(1, None), None, None, None).await.unwrap();
+        database.save_object("core.todo", &todo(2, Some(1)), None, None, None).await.unwrap();
+        let grandchild = database.save_object("core.todo", &todo(3, Some(2)), None, None, None).await.unwrap();
+
+        database.set_todo_completed(parent, true, true).await.unwrap();
+
+        assert_eq!(database.subtask_progress(parent).await.unwrap(), Some(1.0));
+        assert!(database.load_object::<Todo>(grandchild).await.unwrap().content.completed);
+    }
+
+    #[test]
+    fn todos_without_a_parent_serialize_unchanged() {
+        let legacy = r#"{"id":1,"text":"a","completed":false,"created_at":"x","updated_at":null,"due_date":null,"priority":null,"tags":null}"#;
+        let todo: Todo = serde_json::from_str(legacy).unwrap();
+        assert_eq!(serde_json::to_string(&todo).unwrap(), legacy);
+    }
+}
@@ -1,21 +1,81 @@
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, Mutex, RwLock};
 use notify::{RecommendedWatcher, Watcher};
 use notify_debouncer_full::{new_debouncer, DebouncedEvent, Debouncer, FileIdMap};
 
 use crate::error::{NexusError, Result};
-use crate::database::Database;
-use crate::models::{SyncStatus, Todo};
+use crate::database::{content_hash, Database, SOURCE_SYNC};
+use crate::models::{AppObject, ChangeKind, Keep, Note, ObjectChange, ScanProgress, SyncError, SyncStatus, Todo};
+use crate::formats::FileFormat;
+use crate::merge;
+use crate::notes;
+use crate::perf::SpanTimer;
+use crate::vault_fs;
+use crate::write_back::WriteBack;
 
 pub struct SyncService {
     database: Arc<Database>,
     vault_path: PathBuf,
     status: Arc<RwLock<SyncStatus>>,
-    _watcher: Option<Debouncer<RecommendedWatcher, FileIdMap>>,
+    // File events taken off the queue but not yet handled; kept outside the
+    // status lock so bursts of events don't contend on it
+    pending: Arc<AtomicUsize>,
+    watcher: Arc<Mutex<Option<VaultWatcher>>>,
+    event_tx: Option<mpsc::Sender<DebouncedEvent>>,
+    progress: Option<ProgressEmitter>,
+    write_back: Option<WriteBack>,
+    write_back_delay: Duration,
+    // Object changes made while handling file events
+    changes: broadcast::Sender<ObjectChange>,
 }
 
+/// The change feed narrowed to the objects of one schema.
+pub struct SchemaChanges {
+    schema_name: String,
+    changes: broadcast::Receiver<ObjectChange>,
+}
+
+impl SchemaChanges {
+    pub fn schema_name(&self) -> &str {
+        &self.schema_name
+    }
+
+    /// The next change to an object of the schema; other schemas' changes
+    /// are skipped.
+    pub async fn recv(&mut self) -> std::result::Result<ObjectChange, broadcast::error::RecvError> {
+        loop {
+            let change = self.changes.recv().await?;
+            if change.schema_name == self.schema_name {
+                return Ok(change);
+            }
+        }
+    }
+}
+
+/// Name of the event a schema's changes are emitted as, e.g.
+/// "schema-change-core_todo"; characters event names can't hold become '_'.
+pub fn schema_change_event(schema_name: &str) -> String {
+    let name: String = schema_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '/' | ':') { c } else { '_' })
+        .collect();
+    format!("schema-change-{}", name)
+}
+
+// Receives a progress update after each file of a vault scan
+pub type ProgressEmitter = Arc<dyn Fn(ScanProgress) + Send + Sync>;
+
+type VaultWatcher = Debouncer<RecommendedWatcher, FileIdMap>;
+
+// Changes a slow subscriber can fall behind by before it misses some
+const CHANGE_FEED_CAPACITY: usize = 256;
+
+// How often the watchdog checks that the watcher (and the vault) are still there
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
+
 impl SyncService {
     pub async fn new(database: Arc<Database>, vault_path: &Path) -> Result<Self> {
         let status = Arc::new(RwLock::new(SyncStatus {
@@ -23,68 +83,131 @@ impl SyncService {
             last_sync: None,
             pending_changes: 0,
             errors: Vec::new(),
+            watcher_healthy: true,
+            watcher_error: None,
+            vault_available: true,
+            paused: false,
         }));
 
+        let write_back_delay = Duration::from_millis(database.settings().write_back_delay_ms);
+
         let service = Self {
             database,
             vault_path: vault_path.to_path_buf(),
             status,
-            _watcher: None,
+            pending: Arc::new(AtomicUsize::new(0)),
+            watcher: Arc::new(Mutex::new(None)),
+            event_tx: None,
+            progress: None,
+            write_back: None,
+            write_back_delay,
+            changes: broadcast::channel(CHANGE_FEED_CAPACITY).0,
         };
 
         Ok(service)
     }
 
+    /// Subscribes to the objects created, updated or deleted by file events
+    /// from now on.
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<ObjectChange> {
+        self.changes.subscribe()
+    }
+
+    /// Like `subscribe_changes`, but only for objects of `schema_name`.
+    pub fn subscribe_schema(&self, schema_name: &str) -> SchemaChanges {
+        SchemaChanges {
+            schema_name: schema_name.to_string(),
+            changes: self.changes.subscribe(),
+        }
+    }
+
+    pub fn set_progress_emitter(&mut self, emitter: ProgressEmitter) {
+        self.progress = Some(emitter);
+    }
+
+    /// How long edits must be quiet before they are written back to the vault.
+    /// A running write-back is flushed and restarted with the new delay.
+    pub async fn set_write_back_delay(&mut self, delay: Duration) {
+        if delay == self.write_back_delay {
+            return;
+        }
+        self.write_back_delay = delay;
+
+        if let Some(previous) = self.write_back.take() {
+            previous.flush().await;
+            self.write_back = Some(WriteBack::spawn(&self.database, delay));
+        }
+    }
+
     pub async fn start(&mut self) -> Result<()> {
         log::info!("Starting sync service for vault: {:?}", self.vault_path);
 
         // Perform initial scan
         self.perform_initial_scan().await?;
 
+        // Edits made in the app are written back to the vault after a quiet period
+        self.write_back = Some(WriteBack::spawn(&self.database, self.write_back_delay));
+
         // Set up file watcher
         let (tx, mut rx) = mpsc::channel(100);
-        let database = Arc::clone(&self.database);
+        // Only a weak handle, so a closed vault's database isn't kept open
+        let database = Arc::downgrade(&self.database);
         let status = Arc::clone(&self.status);
+        let pending = Arc::clone(&self.pending);
         let vault_path = self.vault_path.clone();
+        let changes = self.changes.clone();
 
-        let mut debouncer = new_debouncer(
-            Duration::from_millis(250),
-            None,
-            move |result: notify_debouncer_full::DebounceEventResult| {
-                let tx = tx.clone();
-                tokio::spawn(async move {
-                    match result {
-                        Ok(events) => {
-                            for event in events {
-                                if let Err(e) = tx.send(event).await {
-                                    log::error!("Failed to send file event: {}", e);
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            log::error!("File watcher error: {:?}", e);
-                        }
-                    }
-                });
-            },
-        ).map_err(NexusError::from)?;
-
-        // Watch the vault directory recursively
-        debouncer
-            .watcher()
-            .watch(&self.vault_path, notify::RecursiveMode::Recursive)
-            .map_err(NexusError::from)?;
-
-        self._watcher = Some(debouncer);
+        let watch_paths = self.database.settings().watch_paths;
+        let debouncer = create_watcher(&self.vault_path, &watch_paths, tx.clone(), Arc::clone(&self.status))?;
+        *self.watcher.lock().await = Some(debouncer);
+        self.event_tx = Some(tx.clone());
 
         // Spawn background task to handle file events
         tokio::spawn(async move {
             while let Some(event) = rx.recv().await {
-                if let Err(e) = Self::handle_file_event(&database, &status, &vault_path, event).await {
-                    log::error!("Error handling file event: {}", e);
-                    let mut status_guard = status.write().await;
-                    status_guard.errors.push(e.to_string());
+                // Take the whole burst at once so the status is updated once for it
+                let mut events = vec![event];
+                while let Ok(event) = rx.try_recv() {
+                    events.push(event);
                 }
+                let Some(database) = database.upgrade() else {
+                    break;
+                };
+
+                // Reconnecting or resuming rescans the vault, so nothing is lost by dropping these
+                if !database.is_available() || status.read().await.paused {
+                    continue;
+                }
+                pending.fetch_add(events.len(), Ordering::SeqCst);
+
+                let mut errors = Vec::new();
+                for event in events {
+                    errors.extend(Self::handle_file_event(&database, &vault_path, &changes, event).await);
+                    pending.fetch_sub(1, Ordering::SeqCst);
+                }
+
+                let max_errors = database.settings().max_sync_errors;
+                let mut status_guard = status.write().await;
+                status_guard.record_errors(errors, max_errors);
+                status_guard.last_sync = Some(chrono::Utc::now().to_rfc3339());
+            }
+        });
+
+        // Watchdog that notices a dead watcher and re-arms it once the vault is back
+        // Holds the watcher weakly so it stops once the service is dropped
+        let watcher = Arc::downgrade(&self.watcher);
+        let database = Arc::downgrade(&self.database);
+        let status = Arc::clone(&self.status);
+        let vault_path = self.vault_path.clone();
+        let event_tx = tx;
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(WATCHDOG_INTERVAL).await;
+                let (watcher, database) = match (watcher.upgrade(), database.upgrade()) {
+                    (Some(watcher), Some(database)) => (watcher, database),
+                    _ => break,
+                };
+                check_watcher(&watcher, &database, &status, &vault_path, &event_tx).await;
             }
         });
 
@@ -92,22 +215,36 @@ impl SyncService {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, fields(elapsed_us = tracing::field::Empty, objects = tracing::field::Empty))]
     async fn perform_initial_scan(&self) -> Result<()> {
+        let mut timer = SpanTimer::start("perform_initial_scan");
         log::info!("Performing initial vault scan...");
         
         let mut status = self.status.write().await;
         status.is_syncing = true;
-        status.pending_changes = 0;
         status.errors.clear();
         drop(status);
 
-        // Scan for todos
-        let todos_path = self.vault_path.join("Todo").join("todos.json");
-        if todos_path.exists() {
-            if let Err(e) = self.sync_todos_file(&todos_path).await {
-                log::error!("Failed to sync todos file during initial scan: {}", e);
-                let mut status = self.status.write().await;
-                status.errors.push(format!("Initial todos sync failed: {}", e));
+        // Scan every supported file, skipping those unchanged since the last scan
+        let extensions = self.database.get_registered_extensions().await?;
+        let settings = self.database.settings();
+        let files = collect_supported_files(&self.vault_path, &extensions, &settings.ignore_paths, &settings.watch_paths);
+        let total = files.len();
+        timer.set_objects(total);
+        for (index, file_path) in files.iter().enumerate() {
+            if let Err(e) = self.sync_file_if_modified(file_path).await {
+                log::error!("Failed to sync {:?} during initial scan: {}", file_path, e);
+                let error = SyncError::new(Some(file_path), format!("Initial sync failed: {}", e));
+                let max_errors = self.database.settings().max_sync_errors;
+                self.status.write().await.record_errors([error], max_errors);
+            }
+
+            if let Some(emit) = &self.progress {
+                emit(ScanProgress {
+                    processed: index + 1,
+                    total,
+                    current_path: file_path.to_string_lossy().to_string(),
+                });
             }
         }
 
@@ -120,22 +257,69 @@ impl SyncService {
         Ok(())
     }
 
+    async fn sync_file_if_modified(&self, file_path: &Path) -> Result<()> {
+        let path_str = file_path.to_string_lossy().to_string();
+        let mtime = file_mtime_ms(file_path)?;
+
+        if self.database.get_file_mtime(&path_str).await? == Some(mtime) {
+            log::debug!("Skipping unchanged file: {}", path_str);
+            return Ok(());
+        }
+        if self.database.is_quarantined(&path_str, mtime).await? {
+            log::debug!("Skipping quarantined file: {}", path_str);
+            return Ok(());
+        }
+
+        if let Err(e) = self.sync_file(file_path).await {
+            Self::record_failure(&self.database, file_path, &e).await;
+            return Err(e);
+        }
+        self.database.clear_sync_failures(&path_str).await?;
+        self.database.set_file_mtime(&path_str, mtime).await
+    }
+
+    // Counts a failed sync towards the file's quarantine
+    async fn record_failure(database: &Database, file_path: &Path, error: &NexusError) {
+        let path_str = file_path.to_string_lossy();
+        let mtime = file_mtime_ms(file_path).unwrap_or_default();
+        match database.record_sync_failure(&path_str, mtime, &error.to_string()).await {
+            Ok(true) => log::warn!("Quarantined {} until it changes: {}", path_str, error),
+            Ok(false) => {}
+            Err(e) => log::warn!("Failed to record sync failure of {}: {}", path_str, e),
+        }
+    }
+
+    async fn sync_file(&self, file_path: &Path) -> Result<()> {
+        if file_path.file_name().and_then(|n| n.to_str()) == Some("todos.json") {
+            Self::sync_todos_file(&self.database, file_path).await.map(|_| ())
+        } else {
+            Self::handle_structured_file(&self.database, file_path).await.map(|_| ())
+        }
+    }
+
+    // Syncs every path of one event and broadcasts the resulting changes,
+    // returning the failures instead of recording them so a burst of events
+    // can be recorded in one go
+    #[tracing::instrument(skip_all, fields(elapsed_us = tracing::field::Empty, objects = tracing::field::Empty))]
     async fn handle_file_event(
         database: &Arc<Database>,
-        status: &Arc<RwLock<SyncStatus>>,
         vault_path: &Path,
+        changes: &broadcast::Sender<ObjectChange>,
         event: DebouncedEvent,
-    ) -> Result<()> {
+    ) -> Vec<SyncError> {
         use notify::EventKind;
 
-        let mut status_guard = status.write().await;
-        status_guard.is_syncing = true;
-        status_guard.pending_changes += 1;
-        drop(status_guard);
+        let mut timer = SpanTimer::start("handle_file_event");
+        timer.set_objects(event.paths.len());
 
+        let settings = database.settings();
+        let mut errors = Vec::new();
         for path in &event.paths {
-            // Skip .nexus directory to avoid infinite loops
-            if path.starts_with(vault_path.join(".nexus")) {
+            // Skip .nexus (our own writes would loop), plugins, ignored paths
+            // and anything outside the watched folders
+            if is_excluded_path(vault_path, path, &settings.ignore_paths)
+                || !is_watched_path(vault_path, path, &settings.watch_paths)
+            {
                 continue;
             }
 
@@ -146,107 +330,822 @@ impl SyncService {
                 }
             }
 
-            match event.kind {
-                EventKind::Create(_) | EventKind::Modify(_) => {
-                    if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                        Self::handle_json_file_change(database, path).await?;
+            let result = match event.kind {
+                EventKind::Create(_) | EventKind::Modify(_) if path.is_file() => {
+                    Self::handle_structured_file(database, path).await
+                }
+                EventKind::Remove(_) => Self::handle_file_deletion(database, path).await,
+                _ => Ok(Vec::new()),
+            };
+
+            match result {
+                Ok(object_changes) => {
+                    if let Err(e) = database.clear_sync_failures(&path.to_string_lossy()).await {
+                        log::warn!("Failed to clear sync failures of {:?}: {}", path, e);
+                    }
+                    for change in object_changes {
+                        // Nobody listening is fine
+                        let _ = changes.send(change);
                     }
                 }
-                EventKind::Remove(_) => {
-                    Self::handle_file_deletion(database, path).await?;
+                // One bad file shouldn't stop the rest of the event from syncing
+                Err(e) => {
+                    log::error!("Failed to sync {:?}: {}", path, e);
+                    Self::record_failure(database, path, &e).await;
+                    errors.push(SyncError::new(Some(path), e.to_string()));
                 }
-                _ => {}
             }
         }
 
-        let mut status_guard = status.write().await;
-        status_guard.is_syncing = false;
-        status_guard.pending_changes = status_guard.pending_changes.saturating_sub(1);
-        status_guard.last_sync = Some(chrono::Utc::now().to_rfc3339());
-
-        Ok(())
+        errors
     }
 
-    async fn handle_json_file_change(database: &Arc<Database>, file_path: &Path) -> Result<()> {
+    // Returns the objects the file's content created or changed
+    async fn handle_structured_file(database: &Arc<Database>, file_path: &Path) -> Result<Vec<ObjectChange>> {
         let path_str = file_path.to_string_lossy().to_string();
-        log::info!("Handling JSON file change: {}", path_str);
+        log::info!("Handling structured file change: {}", path_str);
 
         // Check if this is a todos file
         if file_path.file_name().and_then(|n| n.to_str()) == Some("todos.json") {
-            Self::sync_todos_file_from_db(database, file_path).await?;
+            return Self::sync_todos_file(database, file_path).await;
         }
 
-        // Update the database timestamp for this file
-        database.update_object_from_file_path(&path_str).await?;
+        if notes::is_note_file(database.vault_path(), file_path) {
+            return Self::handle_note_file_change(database, file_path).await;
+        }
 
-        Ok(())
+        // Files with a registered extension belong to that schema; other
+        // files are only ours to parse in a format we know. Custom
+        // extensions are read as JSON.
+        let extension = file_path.extension().and_then(|s| s.to_str()).unwrap_or_default();
+        let schema_name = database.get_schema_for_extension(extension).await?;
+        let format = match (FileFormat::from_extension(extension), &schema_name) {
+            (Some(format), _) => format,
+            (None, Some(_)) => FileFormat::Json,
+            (None, None) => {
+                log::debug!("Ignoring file with unregistered extension: {}", path_str);
+                return Ok(Vec::new());
+            }
+        };
+
+        // Update the tracked object for this file; unchanged content is skipped
+        let content = tokio::fs::read_to_string(file_path).await?;
+        let value = format.parse(&content)?;
+        if let Some(tracked) = database.find_object_by_path(&path_str).await? {
+            return update_tracked_object(database, tracked, &path_str, &value.to_string()).await;
+        }
+
+        let schema_name = match schema_name {
+            Some(schema_name) => schema_name,
+            None => return Ok(Vec::new()),
+        };
+        // Content carrying its own uuid keeps its identity across paths
+        let (object_id, kind) = match value.get("uuid").and_then(|uuid| uuid.as_str()) {
+            Some(uuid) => {
+                let external_id = format!("{}:{}", schema_name, uuid);
+                let kind = match database.object_id_for_external_id(&external_id).await? {
+                    Some(_) => ChangeKind::Updated,
+                    None => ChangeKind::Created,
+                };
+                let object_id = database.save_object_with_external_id(&schema_name, &value, Some(&path_str), &external_id, SOURCE_SYNC).await?;
+                (object_id, kind)
+            }
+            None => (database.save_object(&schema_name, &value, Some(&path_str), None, Some(SOURCE_SYNC)).await?, ChangeKind::Created),
+        };
+
+        Ok(vec![object_change(database, kind, object_id, &path_str).await?])
     }
 
-    async fn handle_file_deletion(database: &Arc<Database>, file_path: &Path) -> Result<()> {
+    async fn handle_note_file_change(database: &Arc<Database>, file_path: &Path) -> Result<Vec<ObjectChange>> {
         let path_str = file_path.to_string_lossy().to_string();
-        log::info!("Handling file deletion: {}", path_str);
+        let content = tokio::fs::read_to_string(file_path).await?;
+        let title = file_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let note = notes::parse_note(&content, &title);
+
+        if let Some(tracked) = database.find_object_by_path(&path_str).await? {
+            return update_tracked_object(database, tracked, &path_str, &serde_json::to_string(&note)?).await;
+        }
 
-        // For now, we'll just log the deletion
-        // In a full implementation, we might mark objects as deleted or remove them
-        database.update_object_from_file_path(&path_str).await?;
+        let object_id = database.save_object("core.note", &note, Some(&path_str), None, Some(SOURCE_SYNC)).await?;
+        Ok(vec![object_change(database, ChangeKind::Created, object_id, &path_str).await?])
+    }
 
-        Ok(())
+    async fn handle_file_deletion(database: &Arc<Database>, file_path: &Path) -> Result<Vec<ObjectChange>> {
+        let path_str = file_path.to_string_lossy().to_string();
+        log::info!("Handling file deletion: {}", path_str);
+
+        // The database row outlives its file, but the object listeners knew
+        // at that path is gone
+        match database.touch_object_by_path(&path_str).await? {
+            Some(object_id) => Ok(vec![object_change(database, ChangeKind::Deleted, object_id, &path_str).await?]),
+            None => Ok(Vec::new()),
+        }
     }
 
-    async fn sync_todos_file(&self, todos_path: &Path) -> Result<()> {
+    // Merges a todos.json with the database against the snapshot of its last
+    // sync, for scans and watcher events alike. Entries whose content hash is
+    // unchanged are left alone so our own writes don't loop.
+    async fn sync_todos_file(database: &Arc<Database>, todos_path: &Path) -> Result<Vec<ObjectChange>> {
         if !todos_path.exists() {
-            return Ok(());
+            return Ok(Vec::new());
         }
 
         let content = tokio::fs::read_to_string(todos_path).await?;
         let todo_list: serde_json::Value = serde_json::from_str(&content)?;
-        
+        let snapshot_path = todos_path.to_string_lossy();
+        let base = database.get_sync_snapshot(&snapshot_path).await?;
+
+        let mut changes = Vec::new();
         if let Some(todos_array) = todo_list.get("todos").and_then(|v| v.as_array()) {
-            for todo_value in todos_array {
+            let mut conflicted = Vec::new();
+            for (index, todo_value) in todos_array.iter().enumerate() {
                 let todo: Todo = serde_json::from_value(todo_value.clone())?;
-                
-                // Save to database
-                self.database.save_object(
-                    "core.todo",
-                    &todo,
-                    Some(&todos_path.to_string_lossy()),
-                    None,
-                ).await?;
+                let entry_path = todo_entry_path(todos_path, &todo, index);
+                let file_content_json = serde_json::to_string(&todo)?;
+
+                match database.find_object_by_path(&entry_path).await? {
+                    None => {
+                        let object_id = insert_todo(database, todos_path, &todo, &entry_path).await?;
+                        changes.push(object_change(database, ChangeKind::Created, object_id, &entry_path).await?);
+                    }
+                    Some((_, Some(hash))) if hash == content_hash(&file_content_json) => {
+                        // Already in sync
+                    }
+                    Some((object_id, _)) => {
+                        let db_object: AppObject<serde_json::Value> = database.load_object(object_id).await?;
+                        let base_entry = match (&base, merge::todo_key(todo_value)) {
+                            (Some(base), Some(key)) => merge::find_todo(base, &key),
+                            _ => None,
+                        };
+
+                        if base_entry.is_some_and(|base_entry| merge::same_todo(&db_object.content, base_entry)) {
+                            // Only the file changed since the last sync
+                            database.update_object_from_file_path(&entry_path, &file_content_json).await?;
+                            changes.push(object_change(database, ChangeKind::Updated, object_id, &entry_path).await?);
+                        } else if base_entry.is_some_and(|base_entry| merge::same_todo(todo_value, base_entry)) {
+                            // Only the app changed it; write-back will catch the file up
+                        } else {
+                            // Edited on both sides since the last sync; keep both versions for the user to pick
+                            let db_content_json = serde_json::to_string(&db_object.content)?;
+                            database
+                                .record_conflict(object_id, &entry_path, &db_content_json, &file_content_json)
+                                .await?;
+                            if let Some(key) = merge::todo_key(todo_value) {
+                                conflicted.push(key);
+                            }
+                        }
+                    }
+                }
             }
+
+            let conflicted: Vec<&str> = conflicted.iter().map(String::as_str).collect();
+            let snapshot = merge::rebase(base.as_deref(), todos_array, &conflicted);
+            database.set_sync_snapshot(&snapshot_path, &snapshot).await?;
         }
 
         log::info!("Synced todos file: {:?}", todos_path);
-        Ok(())
+        Ok(changes)
     }
 
-    async fn sync_todos_file_from_db(database: &Arc<Database>, _file_path: &Path) -> Result<()> {
-        // Load todos from database
-        let todos: Vec<crate::models::AppObject<Todo>> = database
-            .load_objects_by_schema("core.todo")
-            .await?;
+    /// Imports the vault's legacy Todo/todos.json as core.todo objects. Todos
+    /// already tracked, or matching an existing todo's content, are skipped.
+    /// Returns the number imported.
+    pub async fn import_legacy_todos(&self) -> Result<usize> {
+        let todos_path = self.vault_path.join("Todo").join("todos.json");
+        if !todos_path.exists() {
+            return Ok(0);
+        }
 
-        log::info!("Loaded {} todos from database for sync", todos.len());
-        
-        // In a full implementation, we would update the file here
-        // For now, we just log the sync operation
-        
+        let content = tokio::fs::read_to_string(&todos_path).await?;
+        let todo_list: serde_json::Value = serde_json::from_str(&content)?;
+        let todos_array = match todo_list.get("todos").and_then(|v| v.as_array()) {
+            Some(todos_array) => todos_array,
+            None => return Ok(0),
+        };
+
+        let mut imported = 0;
+        for (index, todo_value) in todos_array.iter().enumerate() {
+            let todo: Todo = serde_json::from_value(todo_value.clone())?;
+            let entry_path = todo_entry_path(&todos_path, &todo, index);
+            let hash = content_hash(&serde_json::to_string(&todo)?);
+
+            if self.database.object_exists_for_path(&entry_path).await?
+                || self.database.find_object_by_hash("core.todo", &hash).await?.is_some()
+            {
+                continue;
+            }
+
+            insert_todo(&self.database, &todos_path, &todo, &entry_path).await?;
+            imported += 1;
+        }
+
+        log::info!("Imported {} legacy todos from {:?}", imported, todos_path);
+        Ok(imported)
+    }
+
+    pub async fn resolve_conflict(&self, conflict_id: i64, keep: Keep) -> Result<()> {
+        let conflict = self
+            .database
+            .get_conflict(conflict_id)
+            .await?
+            .ok_or_else(|| NexusError::Sync(format!("Conflict {} not found", conflict_id)))?;
+
+        match keep {
+            Keep::File => {
+                self.database.update_object(conflict.object_id, &conflict.file_content).await?;
+            }
+            Keep::Database => {
+                write_entry_to_file(&self.database, &conflict.file_path, &conflict.db_content).await?;
+            }
+        }
+
+        self.database.delete_conflict(conflict_id).await?;
+        log::info!("Conflict {} resolved keeping the {:?} version", conflict_id, keep);
         Ok(())
     }
 
+    /// Runs one watchdog pass immediately instead of waiting for the next tick.
+    pub async fn check_watcher_health(&self) {
+        if let Some(event_tx) = &self.event_tx {
+            check_watcher(&self.watcher, &self.database, &self.status, &self.vault_path, event_tx).await;
+        }
+    }
+
     pub async fn get_status(&self) -> SyncStatus {
-        self.status.read().await.clone()
+        let mut status = self.status.read().await.clone();
+        status.pending_changes = self.pending.load(Ordering::SeqCst);
+        status.is_syncing |= status.pending_changes > 0;
+        status
+    }
+
+    /// Stops syncing file events until `resume`, e.g. during a large external
+    /// change to the vault. Events arriving meanwhile are dropped.
+    pub async fn pause(&self) {
+        self.status.write().await.paused = true;
+        log::info!("Sync paused");
+    }
+
+    /// Resumes syncing after `pause`, catching up with one scan of the files
+    /// changed in the meantime.
+    pub async fn resume(&self) -> Result<()> {
+        let was_paused = std::mem::replace(&mut self.status.write().await.paused, false);
+        if !was_paused {
+            return Ok(());
+        }
+
+        log::info!("Sync resumed");
+        self.perform_initial_scan().await
     }
 
-    pub async fn force_sync(&self) -> Result<()> {
+    /// Writes out any edits still waiting for their quiet period.
+    pub async fn flush_write_back(&self) {
+        if let Some(write_back) = &self.write_back {
+            write_back.flush().await;
+        }
+    }
+
+    pub async fn get_errors(&self) -> Vec<SyncError> {
+        self.status.read().await.errors.clone()
+    }
+
+    pub async fn clear_errors(&self) {
+        self.status.write().await.errors.clear();
+    }
+
+    /// Re-applies the error cap and duplicate folding to the errors already
+    /// kept, e.g. after `max_sync_errors` was lowered.
+    pub async fn compact_errors(&self) -> Vec<SyncError> {
+        let max_errors = self.database.settings().max_sync_errors;
+        let mut status = self.status.write().await;
+        let errors = std::mem::take(&mut status.errors);
+        status.record_errors(errors, max_errors);
+        status.errors.clone()
+    }
+
+    /// Lifts every quarantine and syncs the released files again, recording
+    /// any that still fail as sync errors. Returns the number released.
+    pub async fn retry_quarantined(&self) -> Result<usize> {
+        let paths = self.database.release_quarantined().await?;
+
+        for path in &paths {
+            let file_path = Path::new(path);
+            if !file_path.is_file() {
+                continue;
+            }
+            if let Err(e) = self.sync_file_if_modified(file_path).await {
+                let error = SyncError::new(Some(file_path), format!("Retry failed: {}", e));
+                let max_errors = self.database.settings().max_sync_errors;
+                self.status.write().await.record_errors([error], max_errors);
+            }
+        }
+
+        log::info!("Retried {} quarantined files", paths.len());
+        Ok(paths.len())
+    }
+
+    /// Re-syncs the given vault-relative paths, or every file modified since
+    /// the last scan when `paths` is `None`.
+    pub async fn force_sync(&self, paths: Option<&[String]>) -> Result<()> {
         log::info!("Force sync requested");
+
+        let paths = match paths {
+            Some(paths) => paths,
+            None => return self.perform_initial_scan().await,
+        };
+
+        let watch_paths = self.database.settings().watch_paths;
+        for relative_path in paths {
+            let file_path = vault_fs::resolve_vault_path(&self.vault_path, relative_path)?;
+            if !file_path.is_file() || !is_watched_path(&self.vault_path, &file_path, &watch_paths) {
+                continue;
+            }
+
+            self.sync_file(&file_path).await?;
+            self.database
+                .set_file_mtime(&file_path.to_string_lossy(), file_mtime_ms(&file_path)?)
+                .await?;
+        }
+
+        self.status.write().await.last_sync = Some(chrono::Utc::now().to_rfc3339());
+        Ok(())
+    }
+
+    /// Limits syncing to the given vault-relative folders (all of the vault
+    /// when empty), re-arming the watcher and scanning the newly watched files.
+    /// Objects already synced from outside the folders are kept.
+    pub async fn set_watch_paths(&self, watch_paths: Vec<String>) -> Result<()> {
+        for watch_path in &watch_paths {
+            if !vault_fs::resolve_vault_path(&self.vault_path, watch_path)?.is_dir() {
+                return Err(NexusError::InvalidInput(format!("'{}' is not a folder in the vault", watch_path)));
+            }
+        }
+
+        let mut settings = self.database.settings();
+        if settings.watch_paths != watch_paths {
+            settings.watch_paths = watch_paths;
+            self.database.update_settings(settings)?;
+        }
+        self.rewatch().await
+    }
+
+    /// Re-arms a running watcher for the current `watch_paths` and rescans.
+    pub async fn rewatch(&self) -> Result<()> {
+        if let Some(event_tx) = &self.event_tx {
+            let watch_paths = self.database.settings().watch_paths;
+            let debouncer = create_watcher(&self.vault_path, &watch_paths, event_tx.clone(), Arc::clone(&self.status))?;
+            *self.watcher.lock().await = Some(debouncer);
+            log::info!("File watcher now covers {:?}", watch_roots(&self.vault_path, &watch_paths));
+        }
         self.perform_initial_scan().await
     }
 
+    /// Recreates the database and repopulates it from the vault's files.
+    /// Returns the number of objects reindexed.
+    pub async fn rebuild_index(&self) -> Result<usize> {
+        self.database.rebuild().await?;
+        self.perform_initial_scan().await?;
+        self.reindex_plain_json_files().await?;
+
+        let (object_count, _) = self.database.get_sync_info().await?;
+        log::info!("Reindexed {} objects", object_count);
+        Ok(object_count)
+    }
+
+    // Standalone .json files, such as moved or materialized objects, don't say
+    // which schema they belong to, so a rebuild gives each the first registered
+    // schema its content validates against. Files that fit none are skipped.
+    async fn reindex_plain_json_files(&self) -> Result<()> {
+        let settings = self.database.settings();
+        let schemas = self.database.list_schemas().await?;
+        let json = ["json".to_string()];
+
+        for file_path in collect_supported_files(&self.vault_path, &json, &settings.ignore_paths, &settings.watch_paths) {
+            let path_str = file_path.to_string_lossy().to_string();
+            if file_path.file_name().and_then(|n| n.to_str()) == Some("todos.json")
+                || self.database.find_object_by_path(&path_str).await?.is_some()
+            {
+                continue;
+            }
+
+            let value = match FileFormat::Json.parse(&tokio::fs::read_to_string(&file_path).await?) {
+                Ok(value) => value,
+                Err(e) => {
+                    log::warn!("Not reindexing {}: {}", path_str, e);
+                    continue;
+                }
+            };
+
+            let mut schema_name = None;
+            for schema in &schemas {
+                if self.database.validate_against_schema(&schema.schema_name, &value).await?.is_empty() {
+                    schema_name = Some(&schema.schema_name);
+                    break;
+                }
+            }
+
+            match schema_name {
+                Some(schema_name) => {
+                    self.database.save_object(schema_name, &value, Some(&path_str), None, Some(SOURCE_SYNC)).await?;
+                    self.database.set_file_mtime(&path_str, file_mtime_ms(&file_path)?).await?;
+                }
+                None => log::warn!("Not reindexing {}: its content matches no registered schema", path_str),
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn get_vault_stats(&self) -> Result<(usize, String)> {
         self.database.get_sync_info().await
     }
 }
 
+fn create_watcher(
+    vault_path: &Path,
+    watch_paths: &[String],
+    tx: mpsc::Sender<DebouncedEvent>,
+    status: Arc<RwLock<SyncStatus>>,
+) -> Result<VaultWatcher> {
+    // The callback runs on the debouncer's own thread, outside the runtime
+    let runtime = tokio::runtime::Handle::current();
+    let mut debouncer = new_debouncer(
+        Duration::from_millis(250),
+        None,
+        move |result: notify_debouncer_full::DebounceEventResult| {
+            let tx = tx.clone();
+            let status = Arc::clone(&status);
+            runtime.spawn(async move {
+                match result {
+                    Ok(events) => {
+                        for event in events {
+                            if let Err(e) = tx.send(event).await {
+                                log::error!("Failed to send file event: {}", e);
+                            }
+                        }
+                    }
+                    Err(errors) => {
+                        log::error!("File watcher error: {:?}", errors);
+                        let message = errors
+                            .iter()
+                            .map(|e| e.to_string())
+                            .collect::<Vec<_>>()
+                            .join("; ");
+                        mark_watcher_unhealthy(&status, message).await;
+                    }
+                }
+            });
+        },
+    ).map_err(NexusError::from)?;
+
+    // Watch the vault, or just its watched folders, recursively
+    for root in watch_roots(vault_path, watch_paths) {
+        debouncer
+            .watcher()
+            .watch(&root, notify::RecursiveMode::Recursive)
+            .map_err(NexusError::from)?;
+    }
+
+    Ok(debouncer)
+}
+
+async fn mark_watcher_unhealthy(status: &Arc<RwLock<SyncStatus>>, message: String) {
+    let mut status = status.write().await;
+    status.watcher_healthy = false;
+    status.watcher_error = Some(message);
+}
+
+// Marks the watcher unhealthy if the vault disappeared, and re-arms it once the vault is back
+async fn check_watcher(
+    watcher: &Arc<Mutex<Option<VaultWatcher>>>,
+    database: &Database,
+    status: &Arc<RwLock<SyncStatus>>,
+    vault_path: &Path,
+    event_tx: &mpsc::Sender<DebouncedEvent>,
+) {
+    let healthy = status.read().await.watcher_healthy;
+
+    if !vault_path.is_dir() {
+        if database.is_available() {
+            log::error!("Vault directory disappeared: {:?}", vault_path);
+            database.mark_unavailable();
+            status.write().await.vault_available = false;
+        }
+        if healthy {
+            mark_watcher_unhealthy(
+                status,
+                format!("Vault directory {} no longer exists. Reconnect the vault once it is back.", vault_path.display()),
+            ).await;
+        }
+        // Drop the dead watcher so it can be recreated cleanly
+        watcher.lock().await.take();
+        return;
+    }
+
+    if healthy {
+        return;
+    }
+
+    let watch_paths = database.settings().watch_paths;
+    match create_watcher(vault_path, &watch_paths, event_tx.clone(), Arc::clone(status)) {
+        Ok(debouncer) => {
+            *watcher.lock().await = Some(debouncer);
+            let mut status = status.write().await;
+            status.watcher_healthy = true;
+            status.watcher_error = None;
+            log::info!("File watcher re-established for {:?}", vault_path);
+        }
+        Err(e) => {
+            log::warn!("Failed to re-arm file watcher: {}", e);
+            mark_watcher_unhealthy(status, e.to_string()).await;
+        }
+    }
+}
+
+// Files the scanner knows how to turn into objects
+// todos.json collections, markdown notes, plus any file whose extension a schema has claimed
+fn is_supported_file(vault_path: &Path, file_path: &Path, extensions: &[String]) -> bool {
+    if file_path.file_name().and_then(|n| n.to_str()) == Some("todos.json") || notes::is_note_file(vault_path, file_path) {
+        return true;
+    }
+
+    file_path
+        .extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|extension| extensions.contains(&extension.to_lowercase()))
+}
+
+// Directories the sync service never treats as vault content
+const EXCLUDED_DIRS: &[&str] = &[".nexus", "plugins"];
+
+// Whether a path lies in .nexus, plugins or one of the ignored vault-relative
+// paths. Both sides are canonicalized and compared case-insensitively, so
+// case-insensitive filesystems and symlinked vault paths can't slip through.
+pub(crate) fn is_excluded_path(vault_path: &Path, path: &Path, ignore_paths: &[String]) -> bool {
+    let components = match relative_components(vault_path, path) {
+        Some(components) => components,
+        None => return false,
+    };
+
+    match components.first() {
+        Some(first) if EXCLUDED_DIRS.contains(&first.as_str()) => true,
+        _ => ignore_paths.iter().any(|ignored| {
+            let ignored = lowercase_components(Path::new(ignored));
+            !ignored.is_empty() && components.starts_with(&ignored)
+        }),
+    }
+}
+
+// Whether a path lies in one of the watched vault-relative folders; with none
+// configured the whole vault is watched. Compared like is_excluded_path.
+pub(crate) fn is_watched_path(vault_path: &Path, path: &Path, watch_paths: &[String]) -> bool {
+    if watch_paths.is_empty() {
+        return true;
+    }
+    let components = match relative_components(vault_path, path) {
+        Some(components) => components,
+        None => return false,
+    };
+
+    watch_paths.iter().any(|watched| {
+        let watched = lowercase_components(Path::new(watched));
+        components.starts_with(&watched)
+    })
+}
+
+// The directories to watch and scan: the vault itself, or each watched folder
+// that currently exists
+fn watch_roots(vault_path: &Path, watch_paths: &[String]) -> Vec<PathBuf> {
+    if watch_paths.is_empty() {
+        return vec![vault_path.to_path_buf()];
+    }
+
+    watch_paths
+        .iter()
+        .filter_map(|watch_path| match vault_fs::resolve_vault_path(vault_path, watch_path) {
+            // Keep the vault's own spelling so object paths match a full scan
+            Ok(root) if root.is_dir() => Some(vault_path.join(watch_path)),
+            Ok(_) => {
+                log::warn!("Watched folder {:?} does not exist", watch_path);
+                None
+            }
+            Err(e) => {
+                log::warn!("Ignoring watched folder {:?}: {}", watch_path, e);
+                None
+            }
+        })
+        .collect()
+}
+
+// A path's components relative to the vault, lowercased; None outside the vault
+fn relative_components(vault_path: &Path, path: &Path) -> Option<Vec<String>> {
+    let vault = vault_path.canonicalize().unwrap_or_else(|_| vault_path.to_path_buf());
+    let path = canonicalize_lenient(path);
+    let relative = path.strip_prefix(&vault).or_else(|_| path.strip_prefix(vault_path)).ok()?;
+    Some(lowercase_components(relative))
+}
+
+// Canonicalizes a path that may no longer exist (a deleted file) via its parent
+fn canonicalize_lenient(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    match (path.parent().and_then(|p| p.canonicalize().ok()), path.file_name()) {
+        (Some(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn lowercase_components(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(name) => Some(name.to_string_lossy().to_lowercase()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn collect_supported_files(
+    vault_path: &Path,
+    extensions: &[String],
+    ignore_paths: &[String],
+    watch_paths: &[String],
+) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = watch_roots(vault_path, watch_paths);
+
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Failed to read directory {:?}: {}", dir, e);
+                continue;
+            }
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            // Hidden entries include .nexus and editor temp files
+            if entry.file_name().to_string_lossy().starts_with('.') || is_excluded_path(vault_path, &path, ignore_paths) {
+                continue;
+            }
+
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => pending.push(path),
+                Ok(file_type) if file_type.is_file() && is_supported_file(vault_path, &path, extensions) => files.push(path),
+                _ => {}
+            }
+        }
+    }
+
+    files.sort();
+    // Overlapping watched folders would otherwise list files twice
+    files.dedup();
+    files
+}
+
+fn file_mtime_ms(file_path: &Path) -> Result<i64> {
+    let modified = std::fs::metadata(file_path)?.modified()?;
+    let mtime = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    Ok(mtime)
+}
+
+// Each todo in a todos.json is tracked as "<file>#<todo id>" (or "#idx<n>" without an id)
+fn todo_entry_path(todos_path: &Path, todo: &Todo, index: usize) -> String {
+    let entry = match todo.id {
+        Some(id) => id.to_string(),
+        None => format!("idx{}", index),
+    };
+    format!("{}#{}", todos_path.to_string_lossy(), entry)
+}
+
+// Splits a tracked path into its file and, for todos, the entry within it.
+// Only a '#' following a todos.json counts, so '#' elsewhere in a path is kept.
+pub(crate) fn split_entry_path(file_path: &str) -> (&str, Option<&str>) {
+    match file_path.rsplit_once('#') {
+        Some((path, entry)) if Path::new(path).file_name().and_then(|n| n.to_str()) == Some("todos.json") => {
+            (path, Some(entry))
+        }
+        _ => (file_path, None),
+    }
+}
+
+// A todo's identity is its id within its todos.json, named relative to the
+// vault so it's the same on every device. Todos without an id have none.
+fn todo_external_id(vault_path: &Path, todos_path: &Path, todo: &Todo) -> Option<String> {
+    let id = todo.id?;
+    let relative = todos_path.strip_prefix(vault_path).unwrap_or(todos_path);
+    let relative = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Some(format!("core.todo:{}#{}", relative, id))
+}
+
+// Applies a file's content to the object already tracked at `path`; only
+// content that actually differs counts as a change
+async fn update_tracked_object(
+    database: &Database,
+    (object_id, stored_hash): (i64, Option<String>),
+    path: &str,
+    content_json: &str,
+) -> Result<Vec<ObjectChange>> {
+    if stored_hash.as_deref() == Some(content_hash(content_json).as_str()) {
+        return Ok(Vec::new());
+    }
+
+    database.update_object_from_file_path(path, content_json).await?;
+    Ok(vec![object_change(database, ChangeKind::Updated, object_id, path).await?])
+}
+
+async fn object_change(database: &Database, kind: ChangeKind, object_id: i64, path: &str) -> Result<ObjectChange> {
+    let object = database.load_object::<serde_json::Value>(object_id).await?;
+    Ok(ObjectChange {
+        kind,
+        object_id,
+        schema_name: object.schema_name,
+        path: path.to_string(),
+    })
+}
+
+// Saves a todo that isn't tracked at its entry path yet, reusing the object
+// that already holds the same logical todo if there is one
+async fn insert_todo(database: &Database, todos_path: &Path, todo: &Todo, entry_path: &str) -> Result<i64> {
+    match todo_external_id(database.vault_path(), todos_path, todo) {
+        Some(external_id) => {
+            database.save_object_with_external_id("core.todo", todo, Some(entry_path), &external_id, SOURCE_SYNC).await
+        }
+        None => database.save_object("core.todo", todo, Some(entry_path), None, Some(SOURCE_SYNC)).await,
+    }
+}
+
+// Writes the chosen version of a conflicted object back to its backing file
+// as is, without merging, replacing just the matching entry when the path
+// points into a todos.json collection.
+async fn write_entry_to_file(database: &Database, file_path: &str, content: &serde_json::Value) -> Result<()> {
+    let (path, entry) = split_entry_path(file_path);
+    write_entries_to_file(database, Path::new(path), &[(entry, content)], false).await
+}
+
+// Applies several entries to one file in a single write. An entry of `None`
+// replaces the whole file; the last one given wins. With `merge_edits`, todos also
+// edited on disk since the last sync are three-way merged (see merge::merge_todos)
+// and true conflicts are recorded instead of overwriting the disk version.
+/// `content` serialized as the file at `path` stores it. Notes live on disk as
+/// markdown; other files keep the format they were read in.
+pub(crate) fn render_file(path: &Path, content: &serde_json::Value) -> Result<String> {
+    if path.extension().and_then(|s| s.to_str()) == Some("md") {
+        let note: Note = serde_json::from_value(content.clone())?;
+        return Ok(notes::render_note(&note));
+    }
+    FileFormat::from_path(path).unwrap_or(FileFormat::Json).render(content)
+}
+
+pub(crate) async fn write_entries_to_file(
+    database: &Database,
+    path: &Path,
+    entries: &[(Option<&str>, &serde_json::Value)],
+    merge_edits: bool,
+) -> Result<()> {
+    if let Some((_, content)) = entries.iter().rev().find(|(entry, _)| entry.is_none()) {
+        return vault_fs::atomic_write(path, render_file(path, content)?.as_bytes());
+    }
+
+    let existing = tokio::fs::read_to_string(path).await?;
+    let mut todo_list: serde_json::Value = serde_json::from_str(&existing)?;
+    let todos = todo_list
+        .get_mut("todos")
+        .and_then(|v| v.as_array_mut())
+        .ok_or_else(|| NexusError::Sync(format!("{} has no todos array", path.display())))?;
+
+    let snapshot_path = path.to_string_lossy();
+    let base = if merge_edits { database.get_sync_snapshot(&snapshot_path).await? } else { None };
+    let merged = merge::merge_todos(base.as_deref(), todos, entries);
+    *todos = merged.todos;
+
+    vault_fs::atomic_write(path, serde_json::to_string_pretty(&todo_list)?.as_bytes())?;
+    database.set_sync_snapshot(&snapshot_path, &merged.base).await?;
+
+    for conflict in merged.conflicts {
+        let entry_path = format!("{}#{}", snapshot_path, conflict.entry);
+        if let Some(object_id) = database.object_id_for_path(&entry_path).await? {
+            // Serialized the way the sync does, so it recognises the conflict as already recorded
+            let file_content_json = match serde_json::from_value::<Todo>(conflict.theirs.clone()) {
+                Ok(todo) => serde_json::to_string(&todo)?,
+                Err(_) => serde_json::to_string(&conflict.theirs)?,
+            };
+            database
+                .record_conflict(object_id, &entry_path, &serde_json::to_string(&conflict.ours)?, &file_content_json)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
 // Helper function for manual sync operations
 pub async fn sync_vault_to_database(_database: &Database, vault_path: &Path) -> Result<()> {
     log::info!("Performing manual vault to database sync");
@@ -267,6 +1166,515 @@ impl Clone for SyncStatus {
             last_sync: self.last_sync.clone(),
             pending_changes: self.pending_changes,
             errors: self.errors.clone(),
+            watcher_healthy: self.watcher_healthy,
+            watcher_error: self.watcher_error.clone(),
+            vault_available: self.vault_available,
+            paused: self.paused,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database;
+    use crate::test_support::{todo_path, vault, write_file, TWO_TODOS};
+
+    async fn rename_todo(database: &Database, object_id: i64, text: &str) {
+        let mut todo = database.load_object::<Todo>(object_id).await.unwrap().content;
+        todo.text = text.to_string();
+        database.update_object(object_id, &todo).await.unwrap();
+    }
+
+    #[test]
+    fn only_todo_entries_are_split_on_their_last_hash() {
+        assert_eq!(split_entry_path("/v/Todo/todos.json#3"), ("/v/Todo/todos.json", Some("3")));
+        assert_eq!(split_entry_path("/v/C#/todos.json#idx0"), ("/v/C#/todos.json", Some("idx0")));
+        assert_eq!(split_entry_path("/v/notes/C#.md"), ("/v/notes/C#.md", None));
+        assert_eq!(split_entry_path("/v/notes/a#b/c.md"), ("/v/notes/a#b/c.md", None));
+    }
+
+    #[tokio::test]
+    async fn diverging_edits_are_recorded_as_conflicts() {
+        let (vault, database, sync_service) = vault("sync_conflicts", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        sync_service.force_sync(None).await.unwrap();
+        assert!(database.get_conflicts().await.unwrap().is_empty());
+
+        let (first, _) = database.find_object_by_path(&todo_path(&vault, 1)).await.unwrap().unwrap();
+        rename_todo(&database, first, "from app").await;
+        std::fs::write(vault.join("Todo/todos.json"), TWO_TODOS.replace(r#""text":"a""#, r#""text":"from file""#)).unwrap();
+        sync_service.force_sync(None).await.unwrap();
+
+        let conflicts = database.get_conflicts().await.unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].object_id, first);
+
+        sync_service.resolve_conflict(conflicts[0].id, Keep::File).await.unwrap();
+        sync_service.force_sync(None).await.unwrap();
+        assert!(database.get_conflicts().await.unwrap().is_empty());
+        assert_eq!(database.load_object::<Todo>(first).await.unwrap().content.text, "from file");
+    }
+
+    #[tokio::test]
+    async fn keeping_the_database_version_rewrites_the_file() {
+        let (vault, database, sync_service) = vault("sync_conflicts_keep_db", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+
+        let (second, _) = database.find_object_by_path(&todo_path(&vault, 2)).await.unwrap().unwrap();
+        rename_todo(&database, second, "b2").await;
+        std::fs::write(vault.join("Todo/todos.json"), TWO_TODOS.replace(r#""text":"b""#, r#""text":"B""#)).unwrap();
+        sync_service.force_sync(None).await.unwrap();
+
+        let conflicts = database.get_conflicts().await.unwrap();
+        assert_eq!(conflicts.len(), 1);
+        sync_service.resolve_conflict(conflicts[0].id, Keep::Database).await.unwrap();
+        sync_service.force_sync(None).await.unwrap();
+
+        assert!(database.get_conflicts().await.unwrap().is_empty());
+        let file = std::fs::read_to_string(vault.join("Todo/todos.json")).unwrap();
+        assert!(file.contains(r#""text": "b2""#), "{}", file);
+    }
+
+    #[tokio::test]
+    async fn targeted_sync_leaves_other_objects_alone() {
+        let (vault, database, sync_service) = vault("sync_targeted", TWO_TODOS).await;
+        let other = vault.join("other.json");
+        std::fs::write(&other, r#"{"a":1}"#).unwrap();
+        sync_service.force_sync(None).await.unwrap();
+        database.register_schema("ext.c", r#"{"type":"object"}"#).await.unwrap();
+        let other_id = database
+            .save_object("ext.c", &serde_json::json!({"a": 1}), Some(&other.to_string_lossy()), None, None)
+            .await
+            .unwrap();
+        let (todo_id, _) = database.find_object_by_path(&todo_path(&vault, 1)).await.unwrap().unwrap();
+        let todo_before = database.load_object::<serde_json::Value>(todo_id).await.unwrap().updated_at;
+
+        std::fs::write(&other, r#"{"a":2}"#).unwrap();
+        std::fs::write(vault.join("Todo/todos.json"), TWO_TODOS.replace(r#""text":"a""#, r#""text":"A""#)).unwrap();
+        sync_service.force_sync(Some(&["other.json".to_string()])).await.unwrap();
+
+        assert_eq!(database.load_object::<serde_json::Value>(todo_id).await.unwrap().updated_at, todo_before);
+        assert_eq!(database.load_object::<serde_json::Value>(other_id).await.unwrap().content["a"], 2);
+        assert!(sync_service.force_sync(Some(&["../x.json".to_string()])).await.is_err());
+
+        // An untargeted sync still picks up the modified todos.json
+        sync_service.force_sync(None).await.unwrap();
+        assert!(database.get_conflicts().await.unwrap().is_empty());
+        assert_eq!(database.load_object::<Todo>(todo_id).await.unwrap().content.text, "A");
+    }
+
+    #[tokio::test]
+    async fn rebuilding_reindexes_every_file() {
+        let (vault, database, sync_service) = vault("sync_rebuild", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        write_file(
+            &vault,
+            "Work/todos.json",
+            r#"{"todos":[{"id":5,"text":"w","completed":false,"created_at":"2024-01-03T00:00:00Z","updated_at":null,"due_date":null,"priority":null,"tags":null}]}"#,
+        );
+        // A todo given a file of its own, and a note stored as plain JSON
+        let standalone = database.save_object("core.todo", &Todo::new("standalone".into()), None, None, None).await.unwrap();
+        database.materialize_object(standalone, "Archive/standalone.json").await.unwrap();
+        write_file(&vault, "Archive/note.json", r#"{"title":"t","body":"b"}"#);
+        write_file(&vault, "Archive/unknown.json", r#"{"anything":true}"#);
+
+        assert_eq!(sync_service.rebuild_index().await.unwrap(), 5);
+
+        let todos: Vec<AppObject<Todo>> = database.load_objects_by_schema("core.todo").await.unwrap();
+        assert_eq!(todos.len(), 4);
+        assert!(todos.iter().any(|todo| todo.content.text == "standalone"));
+        let notes: Vec<AppObject<Note>> = database.load_objects_by_schema("core.note").await.unwrap();
+        assert_eq!(notes.len(), 1);
+        assert!(database::db_path(&vault).exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn watcher_failures_show_in_the_status_until_rearmed() {
+        let (vault, _database, mut sync_service) = vault("sync_watcher_health", TWO_TODOS).await;
+        sync_service.start().await.unwrap();
+        assert!(sync_service.get_status().await.watcher_healthy);
+
+        std::fs::remove_dir_all(&vault).unwrap();
+        sync_service.check_watcher_health().await;
+        let status = sync_service.get_status().await;
+        assert!(!status.watcher_healthy);
+        assert!(status.watcher_error.unwrap().contains("no longer exists"));
+        sync_service.check_watcher_health().await;
+        assert!(!sync_service.get_status().await.watcher_healthy);
+
+        std::fs::create_dir_all(&vault).unwrap();
+        sync_service.check_watcher_health().await;
+        let status = sync_service.get_status().await;
+        assert!(status.watcher_healthy);
+        assert!(status.watcher_error.is_none());
+    }
+
+    #[tokio::test]
+    async fn scan_progress_counts_up_to_the_total() {
+        let (vault, _database, mut sync_service) = vault("sync_scan_progress", TWO_TODOS).await;
+        write_file(&vault, "Work/todos.json", TWO_TODOS);
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = seen.clone();
+        sync_service.set_progress_emitter(Arc::new(move |progress: ScanProgress| recorder.lock().unwrap().push(progress)));
+
+        sync_service.force_sync(None).await.unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.iter().map(|p| p.processed).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(seen.iter().all(|p| p.total == 2));
+    }
+
+    #[tokio::test]
+    async fn files_are_routed_to_the_schema_registered_for_their_extension() {
+        let (vault, database, sync_service) = vault("sync_extension_routing", TWO_TODOS).await;
+        let schema: crate::models::PluginSchema = serde_json::from_value(serde_json::json!({
+            "name": "task", "version": "1", "definition": {"type": "object"}, "file_extensions": [".TASK"]
+        }))
+        .unwrap();
+        database.register_plugin_schemas("tasks", &[schema]).await.unwrap();
+        assert_eq!(database.get_schema_for_extension("task").await.unwrap().as_deref(), Some("tasks.task"));
+        write_file(&vault, "Work/a.task", r#"{"title":"x"}"#);
+        write_file(&vault, "Work/b.unknown", "not json");
+
+        sync_service.force_sync(None).await.unwrap();
+        let tasks: Vec<AppObject<serde_json::Value>> = database.load_objects_by_schema("tasks.task").await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].content["title"], "x");
+        assert!(sync_service.get_errors().await.is_empty());
+
+        write_file(&vault, "Work/a.task", r#"{"title":"y"}"#);
+        sync_service.force_sync(Some(&["Work/a.task".to_string(), "Work/b.unknown".to_string()])).await.unwrap();
+        let tasks: Vec<AppObject<serde_json::Value>> = database.load_objects_by_schema("tasks.task").await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].content["title"], "y");
+    }
+
+    #[tokio::test]
+    async fn sync_errors_are_listed_and_cleared() {
+        let (vault, _database, sync_service) = vault("sync_errors", TWO_TODOS).await;
+        write_file(&vault, "Bad/todos.json", "{oops");
+
+        sync_service.force_sync(None).await.unwrap();
+
+        let errors = sync_service.get_errors().await;
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].path.as_deref().unwrap().ends_with("Bad/todos.json"));
+        sync_service.clear_errors().await;
+        assert!(sync_service.get_errors().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn legacy_todos_are_imported_once() {
+        let (vault, database, sync_service) = vault("sync_legacy_import", TWO_TODOS).await;
+
+        assert_eq!(sync_service.import_legacy_todos().await.unwrap(), 2);
+        assert!(database.object_exists_for_path(&todo_path(&vault, 1)).await.unwrap());
+        assert_eq!(database.load_objects_by_schema::<Todo>("core.todo").await.unwrap().len(), 2);
+
+        assert_eq!(sync_service.import_legacy_todos().await.unwrap(), 0);
+        assert_eq!(database.load_objects_by_schema::<Todo>("core.todo").await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn legacy_import_skips_todos_matching_existing_content() {
+        let (_vault, database, sync_service) = vault("sync_legacy_duplicate", TWO_TODOS).await;
+        let todos: serde_json::Value = serde_json::from_str(TWO_TODOS).unwrap();
+        let first: Todo = serde_json::from_value(todos["todos"][0].clone()).unwrap();
+        database.save_object("core.todo", &first, None, None, None).await.unwrap();
+
+        assert_eq!(sync_service.import_legacy_todos().await.unwrap(), 1);
+        assert_eq!(database.load_objects_by_schema::<Todo>("core.todo").await.unwrap().len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn a_burst_of_file_events_drains_the_pending_count() {
+        let (vault, database, mut sync_service) = vault("sync_event_burst", TWO_TODOS).await;
+        // Files written into a folder made after the watch started can be missed
+        std::fs::create_dir_all(vault.join(notes::NOTES_DIR)).unwrap();
+        sync_service.start().await.unwrap();
+        let first_sync = sync_service.get_status().await.last_sync;
+
+        for index in 0..30 {
+            write_file(&vault, &format!("Notes/n{}.md", index), &format!("note {}", index));
+        }
+
+        // Events may arrive in several bursts; wait for the last file
+        let last = vault.join("Notes/n29.md").to_string_lossy().to_string();
+        let mut status = sync_service.get_status().await;
+        for _ in 0..200 {
+            tokio::time::sleep(Duration::from_millis(25)).await;
+            status = sync_service.get_status().await;
+            if status.pending_changes == 0 && database.object_exists_for_path(&last).await.unwrap() {
+                break;
+            }
+        }
+        assert!(database.object_exists_for_path(&last).await.unwrap());
+        assert_eq!(status.pending_changes, 0);
+        assert!(!status.is_syncing);
+        assert_ne!(status.last_sync, first_sync);
+    }
+
+    #[tokio::test]
+    async fn toml_and_yaml_files_sync_in_their_own_format() {
+        let (vault, database, sync_service) = vault("sync_toml_yaml", TWO_TODOS).await;
+        database.register_schema("ext.cfg", r#"{"type":"object"}"#).await.unwrap();
+        database.register_schema_extensions("ext.cfg", &["toml".to_string(), "yaml".to_string()]).await.unwrap();
+        write_file(&vault, "a.toml", "name = \"alpha\"\ncount = 3\n");
+        write_file(&vault, "b.yaml", "name: beta\ncount: 4\n");
+
+        sync_service.force_sync(None).await.unwrap();
+        let objects: Vec<AppObject<serde_json::Value>> = database.load_objects_by_schema("ext.cfg").await.unwrap();
+        let toml_path = vault.join("a.toml");
+        let toml_object = objects
+            .iter()
+            .find(|object| object.file_path.as_deref() == Some(toml_path.to_string_lossy().as_ref()))
+            .unwrap();
+        assert_eq!(toml_object.content, serde_json::json!({"name": "alpha", "count": 3}));
+        assert!(objects.iter().any(|object| object.content == serde_json::json!({"name": "beta", "count": 4})));
+
+        let edited = serde_json::json!({"name": "gamma", "count": 5});
+        write_entries_to_file(&database, &toml_path, &[(None, &edited)], true).await.unwrap();
+        let text = std::fs::read_to_string(&toml_path).unwrap();
+        assert!(text.contains("name = \"gamma\""), "{}", text);
+    }
+
+    #[test]
+    fn internal_and_ignored_paths_are_excluded_case_insensitively() {
+        let vault = crate::test_support::temp_dir("sync_excluded_paths");
+        std::fs::create_dir_all(vault.join("Sub/deep")).unwrap();
+        let excluded = |path: &str, ignore: &[&str]| {
+            let ignore: Vec<String> = ignore.iter().map(|path| path.to_string()).collect();
+            is_excluded_path(&vault, &vault.join(path), &ignore)
+        };
+
+        assert!(excluded(".NEXUS/vault.sqlite-wal", &[]));
+        assert!(excluded(".nexus/vault.sqlite-shm", &[]));
+        assert!(excluded("Plugins/p/plugin.json", &[]));
+        assert!(!excluded("Todo/todos.json", &[]));
+        assert!(excluded("sub/deep/x.json", &["Sub"]));
+        assert!(!excluded("Subway/x.json", &["Sub"]));
+        // A non-canonical spelling of the vault path is still recognized
+        assert!(is_excluded_path(&vault.join("Sub/.."), &vault.join(".nexus/vault.sqlite"), &[]));
+    }
+
+    #[tokio::test]
+    async fn ignored_paths_are_left_out_of_a_sync() {
+        let (vault, database, sync_service) = vault("sync_ignore_paths", TWO_TODOS).await;
+        let mut settings = database.settings();
+        settings.ignore_paths = vec!["Private".to_string()];
+        database.update_settings(settings).unwrap();
+        write_file(&vault, "Notes/kept.md", "kept");
+        write_file(&vault, "private/Notes/hidden.md", "hidden");
+
+        sync_service.force_sync(None).await.unwrap();
+        assert!(database.object_exists_for_path(&vault.join("Notes/kept.md").to_string_lossy()).await.unwrap());
+        let objects: Vec<AppObject<Note>> = database.load_objects_by_schema("core.note").await.unwrap();
+        assert_eq!(objects.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn a_vanished_vault_shuts_the_database_until_reconnected() {
+        let (vault, database, mut sync_service) = vault("sync_vault_unavailable", TWO_TODOS).await;
+        sync_service.start().await.unwrap();
+
+        std::fs::remove_dir_all(&vault).unwrap();
+        sync_service.check_watcher_health().await;
+        assert!(!sync_service.get_status().await.vault_available);
+        assert!(!database.is_available());
+        assert!(matches!(
+            database.load_objects_by_schema::<Todo>("core.todo").await,
+            Err(NexusError::VaultUnavailable(_))
+        ));
+
+        // The folder coming back doesn't revive the stale database
+        write_file(&vault, "Todo/todos.json", TWO_TODOS);
+        sync_service.check_watcher_health().await;
+        assert!(!sync_service.get_status().await.vault_available);
+
+        // Reconnecting opens a fresh backend for the same path
+        let reopened = Arc::new(Database::new(&vault).await.unwrap());
+        let resynced = SyncService::new(reopened.clone(), &vault).await.unwrap();
+        resynced.force_sync(None).await.unwrap();
+        assert!(resynced.get_status().await.vault_available);
+        assert_eq!(reopened.load_objects_by_schema::<Todo>("core.todo").await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn watch_paths_must_be_folders_inside_the_vault() {
+        let (_vault, database, sync_service) = vault("sync_watch_paths_invalid", TWO_TODOS).await;
+
+        assert!(matches!(sync_service.set_watch_paths(vec!["Missing".into()]).await, Err(NexusError::InvalidInput(_))));
+        assert!(sync_service.set_watch_paths(vec!["../outside".into()]).await.is_err());
+        assert!(database.settings().watch_paths.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn only_watch_paths_are_synced_and_watched() {
+        let (vault, database, mut sync_service) = vault("sync_watch_paths", TWO_TODOS).await;
+        write_file(&vault, "Notes/Work/deep/a.md", "a");
+        write_file(&vault, "Notes/Other/b.md", "b");
+        let note_paths = || async {
+            let notes: Vec<AppObject<Note>> = database.load_objects_by_schema("core.note").await.unwrap();
+            let mut paths: Vec<String> = notes.into_iter().filter_map(|note| note.file_path).collect();
+            paths.sort();
+            paths
+        };
+        let path = |relative: &str| vault.join(relative).to_string_lossy().to_string();
+
+        sync_service.set_watch_paths(vec!["Notes/Work".into()]).await.unwrap();
+        assert_eq!(database.settings().watch_paths, vec!["Notes/Work".to_string()]);
+        assert_eq!(note_paths().await, vec![path("Notes/Work/deep/a.md")]);
+        assert!(database.load_objects_by_schema::<Todo>("core.todo").await.unwrap().is_empty());
+
+        sync_service.start().await.unwrap();
+        write_file(&vault, "Notes/Other/c.md", "c");
+        write_file(&vault, "Notes/Work/d.md", "d");
+        for _ in 0..100 {
+            if note_paths().await.len() > 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        // Give a stray event for Notes/Other time to show up
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(note_paths().await, vec![path("Notes/Work/d.md"), path("Notes/Work/deep/a.md")]);
+        sync_service.force_sync(Some(&["Notes/Other/c.md".to_string()])).await.unwrap();
+        assert_eq!(note_paths().await.len(), 2);
+
+        // Widening back to the whole vault picks up what was skipped
+        sync_service.set_watch_paths(Vec::new()).await.unwrap();
+        assert_eq!(note_paths().await.len(), 4);
+        assert_eq!(database.load_objects_by_schema::<Todo>("core.todo").await.unwrap().len(), 2);
+    }
+
+    async fn next_change(changes: &mut broadcast::Receiver<ObjectChange>) -> ObjectChange {
+        tokio::time::timeout(Duration::from_secs(5), changes.recv()).await.unwrap().unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn file_events_are_broadcast_as_object_changes() {
+        let (vault, database, mut sync_service) = vault("sync_change_feed", TWO_TODOS).await;
+        database.register_schema("ext.c", r#"{"type":"object"}"#).await.unwrap();
+        database.register_schema_extensions("ext.c", &["json".into()]).await.unwrap();
+        let mut changes = sync_service.subscribe_changes();
+        sync_service.start().await.unwrap();
+        assert!(changes.try_recv().is_err());
+
+        let path = vault.join("new.json");
+        std::fs::write(&path, r#"{"n":1}"#).unwrap();
+        let created = next_change(&mut changes).await;
+        assert_eq!((created.kind, created.schema_name.as_str()), (ChangeKind::Created, "ext.c"));
+        assert_eq!(created.path, path.to_string_lossy());
+        assert_eq!(Some(created.object_id), database.object_id_for_path(&created.path).await.unwrap());
+
+        std::fs::write(&path, r#"{"n":2}"#).unwrap();
+        let updated = next_change(&mut changes).await;
+        assert_eq!((updated.kind, updated.object_id), (ChangeKind::Updated, created.object_id));
+
+        std::fs::write(vault.join("Todo/todos.json"), TWO_TODOS.replacen(r#""a""#, r#""A""#, 1)).unwrap();
+        let todo = next_change(&mut changes).await;
+        assert_eq!((todo.kind, todo.schema_name.as_str()), (ChangeKind::Updated, "core.todo"));
+        assert_eq!(todo.path, todo_path(&vault, 1));
+        // Only the todo that changed is reported
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(changes.try_recv().is_err());
+
+        // Removing the file is reported as a deletion, though the row is kept
+        std::fs::remove_file(&path).unwrap();
+        let removed = next_change(&mut changes).await;
+        assert_eq!((removed.kind, removed.object_id), (ChangeKind::Deleted, created.object_id));
+        assert_eq!(removed.path, path.to_string_lossy());
+        assert!(database.load_object::<serde_json::Value>(created.object_id).await.is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn paused_changes_are_picked_up_on_resume() {
+        let (vault, database, mut sync_service) = vault("sync_pause", TWO_TODOS).await;
+        database.register_schema("ext.c", r#"{"type":"object"}"#).await.unwrap();
+        database.register_schema_extensions("ext.c", &["json".into()]).await.unwrap();
+        write_file(&vault, "kept.json", r#"{"n":1}"#);
+        sync_service.start().await.unwrap();
+        let mut changes = sync_service.subscribe_changes();
+        let path = |relative: &str| vault.join(relative).to_string_lossy().to_string();
+        let n = |object_id: i64| {
+            let database = database.clone();
+            async move { database.load_object::<serde_json::Value>(object_id).await.unwrap().content["n"].clone() }
+        };
+
+        sync_service.pause().await;
+        assert!(sync_service.get_status().await.paused);
+        write_file(&vault, "new.json", r#"{"n":1}"#);
+        write_file(&vault, "new.json", r#"{"n":2}"#);
+        write_file(&vault, "kept.json", r#"{"n":5}"#);
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        assert!(changes.try_recv().is_err());
+        assert!(database.object_id_for_path(&path("new.json")).await.unwrap().is_none());
+        let kept = database.object_id_for_path(&path("kept.json")).await.unwrap().unwrap();
+        assert_eq!(n(kept).await, 1);
+
+        sync_service.resume().await.unwrap();
+        assert!(!sync_service.get_status().await.paused);
+        let new = database.object_id_for_path(&path("new.json")).await.unwrap().unwrap();
+        assert_eq!(n(new).await, 2);
+        assert_eq!(n(kept).await, 5);
+
+        // Resuming again is a no-op, and file events flow as before
+        sync_service.resume().await.unwrap();
+        write_file(&vault, "after.json", r#"{"n":3}"#);
+        loop {
+            let change = next_change(&mut changes).await;
+            if change.path == path("after.json") {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn schema_change_events_have_event_safe_names() {
+        assert_eq!(schema_change_event("core.todo"), "schema-change-core_todo");
+        assert_eq!(schema_change_event("my-plugin/x:y z"), "schema-change-my-plugin/x:y_z");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn schema_subscribers_only_see_their_schema() {
+        let (vault, database, mut sync_service) = vault("sync_schema_changes", TWO_TODOS).await;
+        database.register_schema("ext.c", r#"{"type":"object"}"#).await.unwrap();
+        database.register_schema_extensions("ext.c", &["json".into()]).await.unwrap();
+        let mut todos = sync_service.subscribe_schema("core.todo");
+        let mut custom = sync_service.subscribe_schema("ext.c");
+        sync_service.start().await.unwrap();
+
+        write_file(&vault, "new.json", r#"{"n":1}"#);
+        let change = tokio::time::timeout(Duration::from_secs(5), custom.recv()).await.unwrap().unwrap();
+        assert_eq!((change.kind, change.schema_name.as_str()), (ChangeKind::Created, "ext.c"));
+        assert!(tokio::time::timeout(Duration::from_millis(800), todos.recv()).await.is_err());
+
+        write_file(&vault, "Todo/todos.json", &TWO_TODOS.replacen("\"a\"", "\"A\"", 1));
+        let change = tokio::time::timeout(Duration::from_secs(5), todos.recv()).await.unwrap().unwrap();
+        assert_eq!((change.kind, change.schema_name.as_str()), (ChangeKind::Updated, "core.todo"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn watched_todos_files_merge_with_pending_app_edits() {
+        let (vault, database, mut sync_service) = vault("sync_watch_merge", TWO_TODOS).await;
+        sync_service.start().await.unwrap();
+        let mut changes = sync_service.subscribe_changes();
+        let (first, _) = database.find_object_by_path(&todo_path(&vault, 1)).await.unwrap().unwrap();
+        let (second, _) = database.find_object_by_path(&todo_path(&vault, 2)).await.unwrap().unwrap();
+        let mut edited = database.load_object::<Todo>(first).await.unwrap().content;
+        edited.text = "app".into();
+        database.update_object(first, &edited).await.unwrap();
+
+        // The first todo changes on both sides, the second only in the file
+        let external = TWO_TODOS.replace(r#""text":"a""#, r#""text":"file a""#).replace(r#""text":"b""#, r#""text":"file b""#);
+        write_file(&vault, "Todo/todos.json", &external);
+        while next_change(&mut changes).await.object_id != second {}
+
+        assert_eq!(database.load_object::<Todo>(second).await.unwrap().content.text, "file b");
+        assert_eq!(database.load_object::<Todo>(first).await.unwrap().content.text, "app");
+        let conflicts = database.get_conflicts().await.unwrap();
+        assert_eq!(conflicts.iter().map(|conflict| conflict.object_id).collect::<Vec<_>>(), vec![first]);
+        assert_eq!(conflicts[0].file_content["text"], "file a");
+    }
+}
@@ -0,0 +1,210 @@
+use std::path::Path;
+
+use chrono::Utc;
+use rusqlite::params;
+use serde_json::Value;
+
+use crate::database::{content_hash, Database};
+use crate::error::{NexusError, Result};
+use crate::models::TagFilter;
+
+impl Database {
+    /// Adds and removes tags on every object matching `filter`, in one
+    /// transaction, by rewriting each object's `tags` content field. Read-only
+    /// objects are left alone. Returns the number of objects whose tags changed.
+    pub async fn tag_objects_matching(
+        &self,
+        filter: &TagFilter,
+        add_tags: &[String],
+        remove_tags: &[String],
+    ) -> Result<usize> {
+        if filter.schema_name.is_none() && filter.path_glob.is_none() && filter.tag.is_none() {
+            return Err(NexusError::InvalidInput(
+                "A tag filter needs a schema, a path glob or a tag".to_string(),
+            ));
+        }
+
+        let conn = self.lock_connection().await?;
+        let tx = conn.unchecked_transaction()?;
+
+        let candidates: Vec<(i64, Option<String>, String)> = {
+            let mut stmt = tx.prepare(
+                "SELECT do.id, do.file_path, oc.content_json
+                 FROM data_objects do
+                 JOIN schemas s ON do.schema_id = s.id
+                 JOIN object_content oc ON do.id = oc.object_id
+                 JOIN object_permissions op ON do.id = op.object_id
+                 WHERE op.read_only = 0 AND (?1 IS NULL OR s.schema_name = ?1)
+                 ORDER BY do.id",
+            )?;
+            let rows = stmt.query_map(params![filter.schema_name], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        let now = Utc::now().to_rfc3339();
+        let mut modified = Vec::new();
+        for (object_id, file_path, content_json) in candidates {
+            if let Some(glob) = &filter.path_glob {
+                let matches = file_path
+                    .as_deref()
+                    .and_then(|file_path| Path::new(file_path).strip_prefix(self.vault_path()).ok())
+                    .is_some_and(|relative| glob_match(glob, &relative.to_string_lossy().replace('\\', "/")));
+                if !matches {
+                    continue;
+                }
+            }
+
+            let mut content: Value = serde_json::from_str(&content_json)?;
+            let tags = content_tags(&content);
+            if filter.tag.as_ref().is_some_and(|tag| !tags.contains(tag)) {
+                continue;
+            }
+
+            let mut new_tags: Vec<String> = tags.iter().filter(|tag| !remove_tags.contains(tag)).cloned().collect();
+            for tag in add_tags {
+                if !new_tags.contains(tag) {
+                    new_tags.push(tag.clone());
+                }
+            }
+            if new_tags == tags {
+                continue;
+            }
+
+            match content.as_object_mut() {
+                Some(fields) => fields.insert("tags".to_string(), Value::from(new_tags)),
+                None => continue,
+            };
+
+            let content_json = serde_json::to_string(&content)?;
+            self.check_content_size(&content_json)?;
+            tx.execute(
+                "UPDATE object_content SET content_json = ?1 WHERE object_id = ?2",
+                params![content_json, object_id],
+            )?;
+            tx.execute(
+                "UPDATE data_objects SET content_hash = ?1, updated_at = ?2 WHERE id = ?3",
+                params![content_hash(&content_json), now, object_id],
+            )?;
+            modified.push(object_id);
+        }
+        tx.commit()?;
+        drop(conn);
+
+        for object_id in &modified {
+            self.notify_changed(*object_id);
+        }
+
+        log::info!("Retagged {} objects", modified.len());
+        Ok(modified.len())
+    }
+}
+
+// The string tags of an object's content; a missing or null field has none
+fn content_tags(content: &Value) -> Vec<String> {
+    content
+        .get("tags")
+        .and_then(|tags| tags.as_array())
+        .map(|tags| tags.iter().filter_map(|tag| tag.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+/// Matches a vault-relative path against a glob: `*` matches within one path
+/// segment, `**` across any number of segments and `?` a single character.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches_from(&pattern, &path)
+}
+
+fn matches_from(pattern: &[char], path: &[char]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            // "**/" may also match no directories at all
+            let rest = &pattern[2..];
+            let rest_after_slash = rest.strip_prefix(&['/']).unwrap_or(rest);
+            (0..=path.len()).any(|i| {
+                matches_from(rest, &path[i..])
+                    || ((i == 0 || path[i - 1] == '/') && matches_from(rest_after_slash, &path[i..]))
+            })
+        }
+        Some('*') => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != '/')
+            .any(|i| matches_from(&pattern[1..], &path[i..])),
+        Some('?') => path.first().is_some_and(|c| *c != '/') && matches_from(&pattern[1..], &path[1..]),
+        Some(c) => path.first() == Some(c) && matches_from(&pattern[1..], &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::models::AppObject;
+    use crate::test_support::{vault, write_file, TWO_TODOS};
+
+    #[test]
+    fn globs_match_whole_path_segments() {
+        assert!(glob_match("a/**/b", "a/b"));
+        assert!(glob_match("a/**/b", "a/x/y/b"));
+        assert!(!glob_match("a/**/b", "a/xb"));
+        assert!(glob_match("a/?.md", "a/x.md"));
+        assert!(!glob_match("a/*.md", "a/b/x.md"));
+    }
+
+    #[tokio::test]
+    async fn tags_are_changed_on_every_matching_object() {
+        let (vault, database, sync_service) = vault("tags_bulk", TWO_TODOS).await;
+        database.register_schema("ext.c", r#"{"type":"object"}"#).await.unwrap();
+        database.register_schema_extensions("ext.c", &["json".into()]).await.unwrap();
+        write_file(&vault, "Projects/a/x.json", r#"{"tags":["old"]}"#);
+        write_file(&vault, "Projects/y.json", r#"{"n":1}"#);
+        write_file(&vault, "Other/z.json", r#"{"tags":["old","keep"]}"#);
+        sync_service.force_sync(None).await.unwrap();
+        let object = |relative: &str| {
+            let path = vault.join(relative).to_string_lossy().to_string();
+            let database = database.clone();
+            async move {
+                let object_id = database.object_id_for_path(&path).await.unwrap().unwrap();
+                database.load_object::<Value>(object_id).await.unwrap()
+            }
+        };
+
+        // Refuses to tag everything
+        assert!(database.tag_objects_matching(&TagFilter::default(), &["x".into()], &[]).await.is_err());
+
+        let projects = TagFilter { path_glob: Some("Projects/**/*.json".into()), ..Default::default() };
+        assert_eq!(database.tag_objects_matching(&projects, &["proj".into()], &[]).await.unwrap(), 2);
+        assert_eq!(object("Projects/a/x.json").await.content["tags"], json!(["old", "proj"]));
+        assert_eq!(object("Projects/y.json").await.content["tags"], json!(["proj"]));
+        assert_eq!(object("Other/z.json").await.content["tags"], json!(["old", "keep"]));
+        // Already tagged, so nothing changes
+        assert_eq!(database.tag_objects_matching(&projects, &["proj".into()], &[]).await.unwrap(), 0);
+        let top_level = TagFilter { path_glob: Some("*.json".into()), ..Default::default() };
+        assert_eq!(database.tag_objects_matching(&top_level, &["t".into()], &[]).await.unwrap(), 0);
+
+        let todos = TagFilter { schema_name: Some("core.todo".into()), ..Default::default() };
+        assert_eq!(database.tag_objects_matching(&todos, &["todo".into()], &[]).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn read_only_objects_keep_their_tags() {
+        let (vault, database, sync_service) = vault("tags_read_only", TWO_TODOS).await;
+        database.register_schema("ext.c", r#"{"type":"object"}"#).await.unwrap();
+        database.register_schema_extensions("ext.c", &["json".into()]).await.unwrap();
+        write_file(&vault, "a.json", r#"{"tags":["old"]}"#);
+        write_file(&vault, "b.json", r#"{"tags":["old","keep"]}"#);
+        sync_service.force_sync(None).await.unwrap();
+        let mut objects: Vec<AppObject<Value>> = database.load_objects_by_schema("ext.c").await.unwrap();
+        objects.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+
+        let mut permissions = objects[0].permissions.clone();
+        permissions.read_only = true;
+        database.update_object_permissions(objects[0].id, &permissions).await.unwrap();
+
+        let tagged_old = TagFilter { tag: Some("old".into()), ..Default::default() };
+        assert_eq!(database.tag_objects_matching(&tagged_old, &[], &["old".into()]).await.unwrap(), 1);
+        assert_eq!(database.load_object::<Value>(objects[0].id).await.unwrap().content["tags"], json!(["old"]));
+        assert_eq!(database.load_object::<Value>(objects[1].id).await.unwrap().content["tags"], json!(["keep"]));
+    }
+}
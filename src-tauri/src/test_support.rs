@@ -0,0 +1,50 @@
+//! Fixtures shared by the per-module test suites. Every helper works in its
+//! own directory under the system temp dir so tests can run in parallel.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::database::Database;
+use crate::sync_service::SyncService;
+
+/// A todos.json with one open todo (id 1, "a") and one completed todo (id 2, "b").
+pub const TWO_TODOS: &str = r#"{"todos":[{"id":1,"text":"a","completed":false,"created_at":"2024-01-01T00:00:00Z","updated_at":null,"due_date":null,"priority":null,"tags":null},{"id":2,"text":"b","completed":true,"created_at":"2024-01-02T00:00:00Z","updated_at":null,"due_date":null,"priority":null,"tags":null}]}"#;
+
+/// Returns an empty directory dedicated to the named test.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join(format!("me-nexus-tests-{}", std::process::id()))
+        .join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Opens a fresh database in an empty vault directory.
+pub async fn temp_database(name: &str) -> (PathBuf, Database) {
+    let dir = temp_dir(name);
+    let database = Database::new(&dir).await.unwrap();
+    (dir, database)
+}
+
+/// Creates a vault whose `Todo/todos.json` holds `todos` and opens a database
+/// and sync service over it. Nothing is scanned until the test syncs.
+pub async fn vault(name: &str, todos: &str) -> (PathBuf, Arc<Database>, SyncService) {
+    let dir = temp_dir(name);
+    write_file(&dir, "Todo/todos.json", todos);
+    let database = Arc::new(Database::new(&dir).await.unwrap());
+    let sync_service = SyncService::new(database.clone(), &dir).await.unwrap();
+    (dir, database, sync_service)
+}
+
+/// The path a todo with `id` in the vault's `Todo/todos.json` is tracked under.
+pub fn todo_path(vault: &Path, id: i64) -> String {
+    format!("{}#{}", vault.join("Todo").join("todos.json").display(), id)
+}
+
+/// Writes `contents` to a vault-relative path, creating parent directories.
+pub fn write_file(root: &Path, relative_path: &str, contents: &str) {
+    let path = root.join(relative_path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, contents).unwrap();
+}
@@ -0,0 +1,485 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::database::{content_hash, Database};
+use crate::error::{NexusError, Result};
+use crate::models::{Permissions, TodoSnapshotInfo};
+use crate::vault_fs;
+
+const TODO_SCHEMA: &str = "core.todo";
+
+#[derive(Serialize, Deserialize)]
+struct TodoSnapshot {
+    label: String,
+    created_at: String,
+    todos: Vec<SnapshotTodo>,
+    // Every link with a todo at either end
+    #[serde(default)]
+    links: Vec<SnapshotLink>,
+    // Raw contents of each todos.json the todos came from, keyed by path
+    files: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotTodo {
+    id: i64,
+    file_path: Option<String>,
+    external_id: Option<String>,
+    source: String,
+    content: Value,
+    permissions: Permissions,
+    created_at: String,
+    updated_at: String,
+    #[serde(default)]
+    meta: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotLink {
+    source_id: i64,
+    target_id: i64,
+    relation: String,
+    created_at: String,
+}
+
+pub fn snapshots_dir(vault_path: &Path) -> PathBuf {
+    vault_path.join(".nexus").join("snapshots")
+}
+
+impl Database {
+    /// Saves every core.todo object, and the todos.json files they come from,
+    /// to `.nexus/snapshots/<label>.json`. An existing snapshot with the same
+    /// label is replaced.
+    pub async fn snapshot_todos(&self, label: &str) -> Result<TodoSnapshotInfo> {
+        let path = snapshot_path(self.vault_path(), label)?;
+
+        let (todos, links) = {
+            let conn = self.lock_connection().await?;
+            let mut stmt = conn.prepare(
+                "SELECT do.id, do.file_path, do.external_id, do.source, oc.content_json,
+                        op.share_with_ai, op.share_with_cloud, op.read_only, op.expires_at,
+                        do.created_at, do.updated_at
+                 FROM data_objects do
+                 JOIN schemas s ON do.schema_id = s.id
+                 JOIN object_content oc ON do.id = oc.object_id
+                 JOIN object_permissions op ON do.id = op.object_id
+                 WHERE s.schema_name = ?1
+                 ORDER BY do.id",
+            )?;
+            let rows = stmt.query_map(params![TODO_SCHEMA], |row| {
+                let content_json: String = row.get(4)?;
+                Ok(SnapshotTodo {
+                    id: row.get(0)?,
+                    file_path: row.get(1)?,
+                    external_id: row.get(2)?,
+                    source: row.get(3)?,
+                    content: serde_json::from_str(&content_json).map_err(|e| {
+                        rusqlite::Error::InvalidColumnType(
+                            4,
+                            format!("JSON deserialization error: {}", e),
+                            rusqlite::types::Type::Text,
+                        )
+                    })?,
+                    permissions: Permissions {
+                        share_with_ai: row.get(5)?,
+                        share_with_cloud: row.get(6)?,
+                        read_only: row.get(7)?,
+                        expires_at: row.get(8)?,
+                    },
+                    created_at: row.get(9)?,
+                    updated_at: row.get(10)?,
+                    meta: BTreeMap::new(),
+                })
+            })?;
+            let mut todos: Vec<SnapshotTodo> = rows.collect::<rusqlite::Result<_>>()?;
+
+            let mut stmt = conn.prepare(
+                "SELECT om.object_id, om.key, om.value_json FROM object_meta om
+                 JOIN data_objects do ON do.id = om.object_id
+                 JOIN schemas s ON do.schema_id = s.id
+                 WHERE s.schema_name = ?1",
+            )?;
+            let rows = stmt.query_map(params![TODO_SCHEMA], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            let mut meta: HashMap<i64, BTreeMap<String, Value>> = HashMap::new();
+            for row in rows {
+                let (object_id, key, value_json): (i64, String, String) = row?;
+                meta.entry(object_id).or_default().insert(key, serde_json::from_str(&value_json)?);
+            }
+            for todo in &mut todos {
+                todo.meta = meta.remove(&todo.id).unwrap_or_default();
+            }
+
+            let mut stmt = conn.prepare(
+                "SELECT ol.source_id, ol.target_id, ol.relation, ol.created_at FROM object_links ol
+                 WHERE EXISTS (SELECT 1 FROM data_objects do JOIN schemas s ON do.schema_id = s.id
+                               WHERE s.schema_name = ?1 AND do.id IN (ol.source_id, ol.target_id))
+                 ORDER BY ol.source_id, ol.target_id, ol.relation",
+            )?;
+            let rows = stmt.query_map(params![TODO_SCHEMA], |row| {
+                Ok(SnapshotLink {
+                    source_id: row.get(0)?,
+                    target_id: row.get(1)?,
+                    relation: row.get(2)?,
+                    created_at: row.get(3)?,
+                })
+            })?;
+            let links: Vec<SnapshotLink> = rows.collect::<rusqlite::Result<_>>()?;
+            (todos, links)
+        };
+
+        let mut files = BTreeMap::new();
+        for todos_path in todos.iter().filter_map(|todo| todo.file_path.as_deref().and_then(todos_file)) {
+            if files.contains_key(todos_path) {
+                continue;
+            }
+            match tokio::fs::read_to_string(todos_path).await {
+                Ok(content) => {
+                    files.insert(todos_path.to_string(), content);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let snapshot = TodoSnapshot {
+            label: label.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            todos,
+            links,
+            files,
+        };
+        tokio::fs::create_dir_all(snapshots_dir(self.vault_path())).await?;
+        vault_fs::atomic_write(&path, serde_json::to_string_pretty(&snapshot)?.as_bytes())?;
+
+        log::info!("Saved todo snapshot '{}' with {} todos", label, snapshot.todos.len());
+        Ok(info(&snapshot))
+    }
+
+    /// The saved todo snapshots, newest first.
+    pub async fn list_todo_snapshots(&self) -> Result<Vec<TodoSnapshotInfo>> {
+        let mut entries = match tokio::fs::read_dir(snapshots_dir(self.vault_path())).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut snapshots = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match read_snapshot(&path).await {
+                Ok(snapshot) => snapshots.push(info(&snapshot)),
+                Err(e) => log::warn!("Skipping unreadable todo snapshot {}: {}", path.display(), e),
+            }
+        }
+
+        snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(snapshots)
+    }
+
+    /// Replaces the current todos with those of snapshot `label` in one
+    /// transaction, then rewrites the snapshotted todos.json files so the next
+    /// sync doesn't undo the restore. Returns the number of restored todos.
+    ///
+    /// Restored todos get new ids, as their old ones may have been taken by
+    /// then. Their meta and links come back from the snapshot, pointed at the
+    /// new ids; links to objects that have since been deleted are dropped.
+    pub async fn restore_todo_snapshot(&self, label: &str) -> Result<usize> {
+        let path = snapshot_path(self.vault_path(), label)?;
+        if !path.exists() {
+            return Err(NexusError::InvalidInput(format!("No todo snapshot named '{}'", label)));
+        }
+        let snapshot = read_snapshot(&path).await?;
+
+        let conn = self.lock_connection().await?;
+        let tx = conn.unchecked_transaction()?;
+
+        let schema_id: i64 = tx
+            .query_row("SELECT id FROM schemas WHERE schema_name = ?1", params![TODO_SCHEMA], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| NexusError::SchemaNotFound(TODO_SCHEMA.to_string()))?;
+
+        let deleted_blobs: Vec<(String, String)> = {
+            let mut stmt = tx.prepare(
+                "SELECT a.content_hash, a.blob_path FROM attachments a
+                 JOIN data_objects do ON a.object_id = do.id
+                 WHERE do.schema_id = ?1",
+            )?;
+            let rows = stmt.query_map(params![schema_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        tx.execute("DELETE FROM data_objects WHERE schema_id = ?1", params![schema_id])?;
+
+        // Snapshotted id -> restored id
+        let mut restored_ids = HashMap::new();
+        for todo in &snapshot.todos {
+            let content_json = serde_json::to_string(&todo.content)?;
+            tx.execute(
+                "INSERT INTO data_objects
+                 (schema_id, file_path, content_hash, external_id, source, updated_at, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    schema_id,
+                    todo.file_path,
+                    content_hash(&content_json),
+                    todo.external_id,
+                    todo.source,
+                    todo.updated_at,
+                    todo.created_at
+                ],
+            )?;
+            let object_id = tx.last_insert_rowid();
+            restored_ids.insert(todo.id, object_id);
+
+            tx.execute(
+                "INSERT INTO object_content (object_id, content_json) VALUES (?1, ?2)",
+                params![object_id, content_json],
+            )?;
+            tx.execute(
+                "INSERT INTO object_permissions
+                 (object_id, share_with_ai, share_with_cloud, read_only, expires_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    object_id,
+                    todo.permissions.share_with_ai,
+                    todo.permissions.share_with_cloud,
+                    todo.permissions.read_only,
+                    todo.permissions.expires_at
+                ],
+            )?;
+            for (key, value) in &todo.meta {
+                tx.execute(
+                    "INSERT INTO object_meta (object_id, key, value_json) VALUES (?1, ?2, ?3)",
+                    params![object_id, key, serde_json::to_string(value)?],
+                )?;
+            }
+        }
+
+        let mut links_restored = 0;
+        for link in &snapshot.links {
+            let endpoint = |id: i64| -> Result<Option<i64>> {
+                if let Some(restored) = restored_ids.get(&id) {
+                    return Ok(Some(*restored));
+                }
+                let exists: bool = tx.query_row(
+                    "SELECT EXISTS(SELECT 1 FROM data_objects WHERE id = ?1)",
+                    params![id],
+                    |row| row.get(0),
+                )?;
+                Ok(exists.then_some(id))
+            };
+            if let (Some(source_id), Some(target_id)) = (endpoint(link.source_id)?, endpoint(link.target_id)?) {
+                links_restored += tx.execute(
+                    "INSERT OR IGNORE INTO object_links (source_id, target_id, relation, created_at)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![source_id, target_id, link.relation, link.created_at],
+                )?;
+            }
+        }
+        tx.commit()?;
+
+        self.remove_orphaned_blobs(&conn, &deleted_blobs)?;
+        drop(conn);
+
+        for (todos_path, content) in &snapshot.files {
+            vault_fs::atomic_write(Path::new(todos_path), content.as_bytes())?;
+            let todos = serde_json::from_str::<Value>(content)
+                .ok()
+                .and_then(|todo_list| todo_list.get("todos").and_then(|v| v.as_array()).cloned())
+                .unwrap_or_default();
+            self.set_sync_snapshot(todos_path, &todos).await?;
+        }
+
+        log::info!(
+            "Restored todo snapshot '{}' with {} todos and {} of {} links",
+            label, snapshot.todos.len(), links_restored, snapshot.links.len()
+        );
+        Ok(snapshot.todos.len())
+    }
+}
+
+/// Rewrites the file paths saved in every snapshot from `old_prefix` to
+/// `new_prefix`, after the vault has moved. Returns the number of snapshots
+/// changed.
+pub(crate) fn rebase_snapshots(vault_path: &Path, old_prefix: &str, new_prefix: &str) -> Result<usize> {
+    let entries = match std::fs::read_dir(snapshots_dir(vault_path)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+
+    let rebase = |path: &str| path.strip_prefix(old_prefix).map(|rest| format!("{}{}", new_prefix, rest));
+    let mut changed = 0;
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let mut snapshot: TodoSnapshot = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+
+        let mut rebased = false;
+        for todo in &mut snapshot.todos {
+            if let Some(file_path) = todo.file_path.as_deref().and_then(rebase) {
+                todo.file_path = Some(file_path);
+                rebased = true;
+            }
+        }
+        let files = std::mem::take(&mut snapshot.files);
+        for (todos_path, content) in files {
+            let todos_path = match rebase(&todos_path) {
+                Some(rebased_path) => {
+                    rebased = true;
+                    rebased_path
+                }
+                None => todos_path,
+            };
+            snapshot.files.insert(todos_path, content);
+        }
+
+        if rebased {
+            vault_fs::atomic_write(&path, serde_json::to_string_pretty(&snapshot)?.as_bytes())?;
+            changed += 1;
+        }
+    }
+
+    Ok(changed)
+}
+
+fn snapshot_path(vault_path: &Path, label: &str) -> Result<PathBuf> {
+    let valid = !label.is_empty()
+        && label.len() <= 64
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && !label.starts_with('.');
+    if !valid {
+        return Err(NexusError::InvalidInput(format!(
+            "'{}' is not a valid snapshot label; use letters, digits, '-', '_' and '.'",
+            label
+        )));
+    }
+    Ok(snapshots_dir(vault_path).join(format!("{}.json", label)))
+}
+
+// The todos.json a todo's "<todos.json>#<entry>" path points into
+fn todos_file(file_path: &str) -> Option<&str> {
+    file_path
+        .rsplit_once('#')
+        .map(|(path, _)| path)
+        .filter(|path| path.ends_with("todos.json"))
+}
+
+async fn read_snapshot(path: &Path) -> Result<TodoSnapshot> {
+    let content = tokio::fs::read_to_string(path).await?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn info(snapshot: &TodoSnapshot) -> TodoSnapshotInfo {
+    TodoSnapshotInfo {
+        label: snapshot.label.clone(),
+        created_at: snapshot.created_at.clone(),
+        todo_count: snapshot.todos.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ObjectOp, ObjectRef, Todo};
+    use crate::test_support::{temp_database, vault, write_file, TWO_TODOS};
+
+    async fn todo_contents(database: &Database) -> Vec<Value> {
+        let mut todos: Vec<Value> = database
+            .load_objects_by_schema::<Value>(TODO_SCHEMA)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|object| object.content)
+            .collect();
+        todos.sort_by_key(|todo| todo["text"].to_string());
+        todos
+    }
+
+    #[tokio::test]
+    async fn restoring_returns_the_snapshotted_todos() {
+        let (vault, database, sync_service) = vault("todo_snapshot_roundtrip", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        let before = todo_contents(&database).await;
+        assert_eq!(before.len(), 2);
+        assert_eq!(database.snapshot_todos("before-edit").await.unwrap().todo_count, 2);
+
+        let ids: Vec<i64> = database.load_objects_by_schema::<Value>(TODO_SCHEMA).await.unwrap().iter().map(|o| o.id).collect();
+        database.update_object(ids[0], &serde_json::json!({ "text": "changed" })).await.unwrap();
+        database.delete_object(ids[1]).await.unwrap();
+        database.save_object(TODO_SCHEMA, &Todo::new("new".into()), None, None, None).await.unwrap();
+        write_file(&vault, "Todo/todos.json", r#"{"todos":[]}"#);
+
+        assert_eq!(database.restore_todo_snapshot("before-edit").await.unwrap(), 2);
+        assert_eq!(todo_contents(&database).await, before);
+        assert_eq!(std::fs::read_to_string(vault.join("Todo/todos.json")).unwrap(), TWO_TODOS);
+        // A rescan of the restored file changes nothing
+        sync_service.force_sync(None).await.unwrap();
+        assert_eq!(todo_contents(&database).await, before);
+
+        let snapshots = database.list_todo_snapshots().await.unwrap();
+        assert_eq!(snapshots.iter().map(|s| (s.label.as_str(), s.todo_count)).collect::<Vec<_>>(), vec![("before-edit", 2)]);
+        assert!(matches!(database.snapshot_todos("../x").await, Err(NexusError::InvalidInput(_))));
+        assert!(matches!(database.restore_todo_snapshot("missing").await, Err(NexusError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn links_and_meta_follow_the_restored_todos() {
+        let (_vault, database) = temp_database("todo_snapshot_links").await;
+        let first = database.save_object(TODO_SCHEMA, &Todo::new("first".into()), None, None, None).await.unwrap();
+        let second = database.save_object(TODO_SCHEMA, &Todo::new("second".into()), None, None, None).await.unwrap();
+        let note = database.add_note("Project", "", vec![]).await.unwrap();
+        let gone = database.add_note("Gone", "", vec![]).await.unwrap();
+        database
+            .batch_operations(&[
+                ObjectOp::Link { source: ObjectRef::Existing(first), target: ObjectRef::Existing(second), relation: "blocks".into() },
+                ObjectOp::Link { source: ObjectRef::Existing(note.id), target: ObjectRef::Existing(first), relation: "task".into() },
+                ObjectOp::Link { source: ObjectRef::Existing(second), target: ObjectRef::Existing(gone.id), relation: "refs".into() },
+            ])
+            .await
+            .unwrap();
+        database.set_meta(first, "color", &serde_json::json!("red")).await.unwrap();
+        database.snapshot_todos("linked").await.unwrap();
+
+        // The first todo's id now belongs to another schema's object, so it can't be reused
+        database.register_schema("ext.c", r#"{"type":"object"}"#).await.unwrap();
+        database.reclassify_object(first, "ext.c", None).await.unwrap();
+        database.delete_object(gone.id).await.unwrap();
+
+        assert_eq!(database.restore_todo_snapshot("linked").await.unwrap(), 2);
+        let restored: BTreeMap<String, i64> = database
+            .load_objects_by_schema::<Value>(TODO_SCHEMA)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|object| (object.content["text"].as_str().unwrap().to_string(), object.id))
+            .collect();
+        assert_eq!(restored.len(), 2);
+        let (new_first, new_second) = (restored["first"], restored["second"]);
+        assert!(new_first != first && new_second != second);
+        database.load_object::<Value>(first).await.unwrap();
+
+        assert_eq!(database.get_meta(new_first, "color").await.unwrap(), Some(serde_json::json!("red")));
+        let mut links: Vec<_> = database
+            .get_object_links(new_first)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|link| (link.source_id, link.target_id, link.relation))
+            .collect();
+        links.sort();
+        let mut expected = vec![(new_first, new_second, "blocks".to_string()), (note.id, new_first, "task".to_string())];
+        expected.sort();
+        assert_eq!(links, expected);
+        assert_eq!(database.get_object_links(new_second).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,340 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, Offset, SecondsFormat, TimeZone, Utc};
+use rusqlite::params;
+
+use crate::database::Database;
+use crate::error::{NexusError, Result};
+use crate::models::{AppObject, DueTodos, PatchOp, Permissions, Todo, TodoPatch};
+
+/// Normalizes a due date to UTC RFC 3339. Accepts RFC 3339 timestamps, or a
+/// `YYYY-MM-DD` date (taken as midnight) when `tz` is given as `UTC`,
+/// `local` or a fixed offset such as `+02:00`.
+pub fn normalize_due_date(due_date: &str, tz: Option<&str>) -> Result<String> {
+    let due_date = due_date.trim();
+
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(due_date) {
+        return Ok(timestamp.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+
+    let date = NaiveDate::parse_from_str(due_date, "%Y-%m-%d").map_err(|_| {
+        NexusError::InvalidInput(format!(
+            "Invalid due date '{}': expected RFC 3339 or YYYY-MM-DD",
+            due_date
+        ))
+    })?;
+    let tz = tz.ok_or_else(|| {
+        NexusError::InvalidInput(format!("Due date '{}' has no time zone; pass one with it", due_date))
+    })?;
+    let offset = parse_offset(tz, date)?;
+
+    let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+    let local = offset
+        .from_local_datetime(&midnight)
+        .single()
+        .ok_or_else(|| NexusError::InvalidInput(format!("Due date '{}' does not exist in {}", due_date, tz)))?;
+    Ok(local.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+fn parse_offset(tz: &str, date: NaiveDate) -> Result<FixedOffset> {
+    let invalid = || NexusError::InvalidInput(format!("Invalid time zone '{}': expected UTC, local or an offset like +02:00", tz));
+
+    match tz.trim() {
+        "Z" | "z" | "UTC" | "utc" => return Ok(Utc.fix()),
+        "local" => {
+            let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+            return chrono::Local
+                .from_local_datetime(&midnight)
+                .earliest()
+                .map(|local| local.offset().fix())
+                .ok_or_else(invalid);
+        }
+        _ => {}
+    }
+
+    // +HH, +HHMM or +HH:MM, optionally prefixed with UTC
+    let offset = tz.trim().trim_start_matches("UTC");
+    let (sign, digits) = match offset.chars().next() {
+        Some('+') => (1, &offset[1..]),
+        Some('-') => (-1, &offset[1..]),
+        _ => return Err(invalid()),
+    };
+    let digits = digits.replace(':', "");
+    if !digits.chars().all(|c| c.is_ascii_digit()) || !matches!(digits.len(), 2 | 4) {
+        return Err(invalid());
+    }
+    let hours: i32 = digits[..2].parse().map_err(|_| invalid())?;
+    let minutes: i32 = if digits.len() == 4 { digits[2..].parse().map_err(|_| invalid())? } else { 0 };
+    if minutes >= 60 {
+        return Err(invalid());
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+impl Database {
+    /// Merges the fields set in `patch` into a todo. Read-only todos are refused.
+    pub async fn update_todo(&self, object_id: i64, patch: TodoPatch) -> Result<AppObject<Todo>> {
+        let mut object: AppObject<Todo> = self.load_object(object_id).await?;
+        if object.permissions.read_only {
+            return Err(NexusError::PermissionDenied(format!("Todo {} is read-only", object_id)));
+        }
+
+        object.content.apply_patch(patch);
+        self.update_object(object_id, &object.content).await?;
+        self.load_object(object_id).await
+    }
+
+    /// Completes and archives a todo in a single transaction.
+    pub async fn archive_todo(&self, object_id: i64) -> Result<AppObject<Todo>> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let ops = [
+            PatchOp::Replace { path: "/completed".to_string(), value: serde_json::json!(true) },
+            PatchOp::Add { path: "/archived".to_string(), value: serde_json::json!(true) },
+            PatchOp::Replace { path: "/updated_at".to_string(), value: serde_json::json!(now) },
+        ];
+
+        self.patch_object(object_id, &ops).await?;
+        self.load_object(object_id).await
+    }
+
+    /// Validates and normalizes `due_date` (see [`normalize_due_date`]) before
+    /// storing it on the todo.
+    pub async fn set_todo_due_date(
+        &self,
+        object_id: i64,
+        due_date: &str,
+        tz: Option<&str>,
+    ) -> Result<AppObject<Todo>> {
+        let due_date = normalize_due_date(due_date, tz)?;
+        let now = chrono::Utc::now().to_rfc3339();
+        let ops = [
+            PatchOp::Add { path: "/due_date".to_string(), value: serde_json::json!(due_date) },
+            PatchOp::Replace { path: "/updated_at".to_string(), value: serde_json::json!(now) },
+        ];
+
+        self.patch_object(object_id, &ops).await?;
+        self.load_object(object_id).await
+    }
+
+    /// Open todos that are overdue or fall due within the next `within_hours`.
+    /// Relies on due dates being normalized, so they compare as strings.
+    pub async fn get_due_todos(&self, within_hours: i64) -> Result<DueTodos> {
+        if within_hours < 0 {
+            return Err(NexusError::InvalidInput("within_hours must not be negative".to_string()));
+        }
+
+        let now = Utc::now();
+        let until = now + chrono::Duration::hours(within_hours);
+        let now = now.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let until = until.to_rfc3339_opts(SecondsFormat::Secs, true);
+
+        let conn = self.lock_connection().await?;
+        let mut stmt = conn.prepare(
+            "SELECT
+                do.id, s.schema_name, oc.content_json, do.file_path, do.updated_at, do.created_at,
+                op.share_with_ai, op.share_with_cloud, op.read_only, op.expires_at, do.source,
+                json_extract(oc.content_json, '$.due_date') < ?1
+             FROM data_objects do
+             JOIN schemas s ON do.schema_id = s.id
+             JOIN object_content oc ON do.id = oc.object_id
+             JOIN object_permissions op ON do.id = op.object_id
+             WHERE s.schema_name = 'core.todo'
+               AND json_extract(oc.content_json, '$.completed') = 0
+               AND COALESCE(json_extract(oc.content_json, '$.archived'), 0) = 0
+               AND json_extract(oc.content_json, '$.due_date') <= ?2
+             ORDER BY json_extract(oc.content_json, '$.due_date')"
+        )?;
+
+        let rows = stmt.query_map(params![now, until], |row| {
+            let content_json: String = row.get(2)?;
+            let content: Todo = serde_json::from_str(&content_json)
+                .map_err(|e| rusqlite::Error::InvalidColumnType(
+                    2,
+                    format!("JSON deserialization error: {}", e),
+                    rusqlite::types::Type::Text
+                ))?;
+
+            let todo = AppObject {
+                id: row.get(0)?,
+                schema_name: row.get(1)?,
+                content,
+                file_path: row.get(3)?,
+                updated_at: row.get(4)?,
+                created_at: row.get(5)?,
+                permissions: Permissions {
+                    share_with_ai: row.get(6)?,
+                    share_with_cloud: row.get(7)?,
+                    read_only: row.get(8)?,
+                    expires_at: row.get(9)?,
+                },
+                source: row.get(10)?,
+            };
+            Ok((todo, row.get::<_, bool>(11)?))
+        })?;
+
+        let mut due = DueTodos { overdue: Vec::new(), upcoming: Vec::new() };
+        for row in rows {
+            let (todo, overdue) = row?;
+            if overdue {
+                due.overdue.push(todo);
+            } else {
+                due.upcoming.push(todo);
+            }
+        }
+
+        Ok(due)
+    }
+
+    /// Todos that are (or, with `archived` false, are not) archived.
+    pub async fn load_todos_by_archived(&self, archived: bool) -> Result<Vec<AppObject<Todo>>> {
+        let mut todos: Vec<AppObject<Todo>> = self.load_objects_by_schema("core.todo").await?;
+        todos.retain(|todo| todo.content.archived == archived);
+        Ok(todos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_database;
+
+    #[tokio::test]
+    async fn partial_patches_only_touch_the_given_fields() {
+        let (_vault, database) = temp_database("todos_partial_patch").await;
+        let id = database.save_object("core.todo", &Todo::new("a".into()), None, None, None).await.unwrap();
+
+        let patch = TodoPatch { due_date: Some("2024-05-01".into()), ..Default::default() };
+        let updated = database.update_todo(id, patch).await.unwrap();
+
+        assert_eq!(updated.content.text, "a");
+        assert!(!updated.content.completed);
+        assert_eq!(updated.content.due_date.as_deref(), Some("2024-05-01"));
+        assert!(updated.content.updated_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn full_patches_replace_every_field() {
+        let (_vault, database) = temp_database("todos_full_patch").await;
+        let id = database.save_object("core.todo", &Todo::new("a".into()), None, None, None).await.unwrap();
+
+        let patch = TodoPatch {
+            text: Some("b".into()),
+            completed: Some(true),
+            due_date: Some("2024-05-01".into()),
+            priority: Some("high".into()),
+            tags: Some(vec!["x".into()]),
+            parent_id: None,
+        };
+        let updated = database.update_todo(id, patch).await.unwrap().content;
+
+        assert_eq!(updated.text, "b");
+        assert!(updated.completed);
+        assert_eq!(updated.priority.as_deref(), Some("high"));
+        assert_eq!(updated.tags, Some(vec!["x".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn read_only_todos_cannot_be_patched() {
+        let (_vault, database) = temp_database("todos_read_only_patch").await;
+        let permissions = Permissions { read_only: true, ..Default::default() };
+        let id = database
+            .save_object("core.todo", &Todo::new("a".into()), None, Some(&permissions), None)
+            .await
+            .unwrap();
+
+        let patch = TodoPatch { text: Some("b".into()), ..Default::default() };
+        assert!(matches!(database.update_todo(id, patch).await, Err(NexusError::PermissionDenied(_))));
+        assert_eq!(database.load_object::<Todo>(id).await.unwrap().content.text, "a");
+    }
+
+    #[tokio::test]
+    async fn archived_todos_leave_the_active_list() {
+        let (_vault, database) = temp_database("todos_archive").await;
+        let first = database.save_object("core.todo", &Todo::new("a".into()), None, None, None).await.unwrap();
+        database.save_object("core.todo", &Todo::new("b".into()), None, None, None).await.unwrap();
+
+        let archived = database.archive_todo(first).await.unwrap().content;
+        assert!(archived.completed && archived.archived);
+        assert!(archived.updated_at.is_some());
+
+        let active = database.load_todos_by_archived(false).await.unwrap();
+        assert_eq!(active.iter().map(|t| t.content.text.as_str()).collect::<Vec<_>>(), vec!["b"]);
+        // Unarchived todos keep serializing without the flag
+        assert!(!serde_json::to_string(&active[0].content).unwrap().contains("archived"));
+        let archived = database.load_todos_by_archived(true).await.unwrap();
+        assert_eq!(archived.iter().map(|t| t.id).collect::<Vec<_>>(), vec![first]);
+    }
+
+    #[tokio::test]
+    async fn archiving_passes_schema_validation() {
+        let (_vault, database) = temp_database("todos_archive_validated").await;
+        let mut settings = database.settings();
+        settings.validate_patches = true;
+        database.update_settings(settings).unwrap();
+        let id = database.save_object("core.todo", &Todo::new("a".into()), None, None, None).await.unwrap();
+
+        assert!(database.archive_todo(id).await.unwrap().content.archived);
+    }
+
+    #[test]
+    fn due_dates_are_normalized_to_utc() {
+        assert_eq!(normalize_due_date("2024-03-05", Some("+02:00")).unwrap(), "2024-03-04T22:00:00Z");
+        assert_eq!(normalize_due_date("2024-03-05", Some("UTC")).unwrap(), "2024-03-05T00:00:00Z");
+        assert_eq!(normalize_due_date("2024-03-05T10:00:00-05:00", None).unwrap(), "2024-03-05T15:00:00Z");
+    }
+
+    #[test]
+    fn unparseable_due_dates_are_rejected() {
+        for due_date in ["tomorrow", "2024-13-40"] {
+            let error = normalize_due_date(due_date, Some("UTC")).unwrap_err();
+            assert!(error.to_string().contains("Invalid due date"));
+        }
+        // Bare dates need a timezone, and it has to be a known one
+        assert!(normalize_due_date("2024-03-05", None).is_err());
+        assert!(normalize_due_date("2024-03-05", Some("Mars/Olympus")).is_err());
+    }
+
+    #[tokio::test]
+    async fn setting_a_due_date_stores_it_normalized() {
+        let (_vault, database) = temp_database("todos_set_due_date").await;
+        let id = database.save_object("core.todo", &Todo::new("a".into()), None, None, None).await.unwrap();
+
+        let updated = database.set_todo_due_date(id, "2024-03-05", Some("-0130")).await.unwrap();
+        assert_eq!(updated.content.due_date.as_deref(), Some("2024-03-05T01:30:00Z"));
+
+        assert!(database.set_todo_due_date(id, "nope", None).await.is_err());
+        let stored = database.load_object::<Todo>(id).await.unwrap().content;
+        assert_eq!(stored.due_date.as_deref(), Some("2024-03-05T01:30:00Z"));
+    }
+
+    #[tokio::test]
+    async fn due_todos_are_split_into_overdue_and_upcoming() {
+        let (_vault, database) = temp_database("todos_due").await;
+        let in_hours = |hours: i64| {
+            (Utc::now() + chrono::Duration::hours(hours)).to_rfc3339_opts(SecondsFormat::Secs, true)
+        };
+        let todos = [
+            ("late", Some(in_hours(-1)), false, false),
+            ("later", Some(in_hours(-48)), false, false),
+            ("done", Some(in_hours(-1)), true, false),
+            ("archived", Some(in_hours(-1)), false, true),
+            ("soon", Some(in_hours(2)), false, false),
+            ("far", Some(in_hours(30)), false, false),
+            ("none", None, false, false),
+            ("junk", Some("tomorrow".to_string()), false, false),
+        ];
+        for (text, due_date, completed, archived) in todos {
+            let mut todo = Todo::new(text.into());
+            todo.due_date = due_date;
+            todo.completed = completed;
+            todo.archived = archived;
+            database.save_object("core.todo", &todo, None, None, None).await.unwrap();
+        }
+
+        let texts = |todos: &[AppObject<Todo>]| todos.iter().map(|t| t.content.text.clone()).collect::<Vec<_>>();
+        let due = database.get_due_todos(24).await.unwrap();
+        assert_eq!(texts(&due.overdue), vec!["later", "late"]);
+        assert_eq!(texts(&due.upcoming), vec!["soon"]);
+        assert!(database.get_due_todos(0).await.unwrap().upcoming.is_empty());
+        assert!(matches!(database.get_due_todos(-1).await, Err(NexusError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,369 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{NexusError, Result};
+use crate::models::VaultFileContents;
+
+/// Resolves a vault-relative path to an absolute path, refusing anything that
+/// would land outside the vault root (including via `..` or symlinks).
+pub fn resolve_vault_path(vault_root: &Path, relative_path: &str) -> Result<PathBuf> {
+    let root = vault_root.canonicalize()?;
+    let relative = Path::new(relative_path);
+
+    if relative.is_absolute()
+        || relative
+            .components()
+            .any(|c| matches!(c, Component::Prefix(_) | Component::RootDir))
+    {
+        return Err(NexusError::PermissionDenied(format!(
+            "'{}' is not a vault-relative path",
+            relative_path
+        )));
+    }
+
+    // Canonicalize the deepest existing ancestor so symlinks are resolved
+    // before the containment check; the rest of the path must not exist yet.
+    let candidate = root.join(relative);
+    let mut existing = candidate.as_path();
+    let mut remainder = Vec::new();
+    while existing.symlink_metadata().is_err() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                remainder.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => {
+                return Err(NexusError::PermissionDenied(format!(
+                    "'{}' escapes the vault root",
+                    relative_path
+                )))
+            }
+        }
+    }
+
+    let mut resolved = existing.canonicalize()?;
+    if !resolved.starts_with(&root) {
+        return Err(NexusError::PermissionDenied(format!(
+            "'{}' escapes the vault root",
+            relative_path
+        )));
+    }
+
+    for name in remainder.iter().rev() {
+        resolved.push(name);
+    }
+
+    Ok(resolved)
+}
+
+/// Vaults holding more files than this are refused unless forced.
+pub const MAX_VAULT_FILES: usize = 50_000;
+
+// Directories that are never a sensible vault. Compared by their components,
+// case-insensitively and ignoring any drive prefix, and by where they resolve
+// to, since e.g. /bin links to /usr/bin on merged-/usr systems.
+#[cfg(target_os = "linux")]
+const SYSTEM_DIRS: &[&str] = &[
+    "/bin", "/boot", "/dev", "/etc", "/home", "/lib", "/lib64", "/opt", "/proc", "/root",
+    "/sbin", "/sys", "/tmp", "/usr", "/var",
+];
+
+#[cfg(target_os = "macos")]
+const SYSTEM_DIRS: &[&str] = &[
+    "/Applications", "/Library", "/System", "/Users", "/Volumes", "/bin", "/etc", "/opt",
+    "/private", "/sbin", "/tmp", "/usr", "/var",
+];
+
+#[cfg(windows)]
+const SYSTEM_DIRS: &[&str] = &[
+    r"C:\Windows", r"C:\Program Files", r"C:\Program Files (x86)", r"C:\ProgramData", r"C:\Users",
+];
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+const SYSTEM_DIRS: &[&str] = &[];
+
+// Pseudo filesystems that must not be scanned anywhere below their root.
+const SYSTEM_TREES: &[&str] = &["dev", "proc", "sys"];
+
+/// Rejects vault locations whose scan would sweep up the whole machine: drive
+/// and filesystem roots, the home directory itself, system directories, and
+/// directories with more than [`MAX_VAULT_FILES`] files.
+pub fn check_vault_location(path: &Path, home: Option<&Path>) -> Result<()> {
+    let path = path.canonicalize()?;
+    let components = normal_components(&path);
+
+    if components.is_empty() {
+        return Err(NexusError::UnsafeVaultPath(format!(
+            "{} is a drive or filesystem root",
+            path.display()
+        )));
+    }
+
+    if let Some(home) = home.and_then(|home| home.canonicalize().ok()) {
+        if path == home {
+            return Err(NexusError::UnsafeVaultPath(format!(
+                "{} is your home directory; pick a folder inside it",
+                path.display()
+            )));
+        }
+    }
+
+    let is_system_dir = |dir: &&str| {
+        let dir = Path::new(dir);
+        normal_components(dir) == components || dir.canonicalize().is_ok_and(|dir| dir == path)
+    };
+    if SYSTEM_DIRS.iter().any(is_system_dir)
+        || SYSTEM_TREES.contains(&components[0].as_str())
+    {
+        return Err(NexusError::UnsafeVaultPath(format!(
+            "{} is a system directory",
+            path.display()
+        )));
+    }
+
+    if count_files(&path, MAX_VAULT_FILES) > MAX_VAULT_FILES {
+        return Err(NexusError::UnsafeVaultPath(format!(
+            "{} contains more than {} files",
+            path.display(),
+            MAX_VAULT_FILES
+        )));
+    }
+
+    Ok(())
+}
+
+// The path's named components, lowercased, without root or drive prefix
+fn normal_components(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().to_lowercase()),
+            _ => None,
+        })
+        .collect()
+}
+
+// Counts regular files below `dir`, giving up once `limit` is exceeded.
+// Unreadable directories and symlinks are skipped.
+fn count_files(dir: &Path, limit: usize) -> usize {
+    let mut count = 0;
+    let mut stack = vec![dir.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => stack.push(entry.path()),
+                Ok(file_type) if file_type.is_file() => {
+                    count += 1;
+                    if count > limit {
+                        return count;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    count
+}
+
+/// Total size of the regular files below `dir`. Symlinks are not followed.
+pub fn directory_size(dir: &Path) -> u64 {
+    let mut total = 0;
+    let mut stack = vec![dir.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => stack.push(entry.path()),
+                Ok(file_type) if file_type.is_file() => {
+                    total += entry.metadata().map(|m| m.len()).unwrap_or_default();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    total
+}
+
+pub fn read_vault_file(vault_root: &Path, relative_path: &str) -> Result<VaultFileContents> {
+    let path = resolve_vault_path(vault_root, relative_path)?;
+
+    if !path.is_file() {
+        return Err(NexusError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("'{}' is not a file", relative_path),
+        )));
+    }
+
+    let bytes = fs::read(&path)?;
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(VaultFileContents::Utf8(text)),
+        Err(e) => Ok(VaultFileContents::Binary(e.into_bytes())),
+    }
+}
+
+pub fn write_vault_file(vault_root: &Path, relative_path: &str, contents: &[u8]) -> Result<PathBuf> {
+    let path = resolve_vault_path(vault_root, relative_path)?;
+
+    if path.is_dir() {
+        return Err(NexusError::PermissionDenied(format!(
+            "'{}' is a directory",
+            relative_path
+        )));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    atomic_write(&path, contents)?;
+    Ok(path)
+}
+
+/// Writes `contents` to a hidden temp file next to `path`, fsyncs it and
+/// renames it over the target so readers never observe a partial file.
+pub fn atomic_write(path: &Path, contents: &[u8]) -> Result<()> {
+    let parent = path.parent().ok_or_else(|| {
+        NexusError::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("'{}' has no parent directory", path.display()),
+        ))
+    })?;
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    // Leading dot and .tmp suffix keep the sync watcher from picking this up
+    let temp_path = parent.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let result = (|| -> std::io::Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&temp_path, path)?;
+
+        // Persist the rename itself; directories can't be opened on Windows
+        #[cfg(unix)]
+        fs::File::open(parent)?.sync_all()?;
+
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    result.map_err(NexusError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn reads_files_inside_the_vault() {
+        let vault = temp_dir("vault_fs_read");
+        fs::create_dir_all(vault.join("notes")).unwrap();
+        fs::write(vault.join("notes/a.md"), "hello").unwrap();
+        fs::write(vault.join("notes/b.bin"), [0xff, 0xfe]).unwrap();
+
+        match read_vault_file(&vault, "notes/a.md").unwrap() {
+            VaultFileContents::Utf8(text) => assert_eq!(text, "hello"),
+            other => panic!("expected text, got {:?}", other),
+        }
+        match read_vault_file(&vault, "notes/../notes/b.bin").unwrap() {
+            VaultFileContents::Binary(bytes) => assert_eq!(bytes, vec![0xff, 0xfe]),
+            other => panic!("expected bytes, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_paths_that_escape_the_vault() {
+        let vault = temp_dir("vault_fs_escape");
+        fs::write(vault.parent().unwrap().join("outside.md"), "secret").unwrap();
+
+        for path in ["../outside.md", "notes/../../outside.md", "/etc/passwd"] {
+            assert!(
+                matches!(read_vault_file(&vault, path), Err(NexusError::PermissionDenied(_))),
+                "{} should be refused",
+                path
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlinks_leading_outside() {
+        let vault = temp_dir("vault_fs_symlink");
+        std::os::unix::fs::symlink("/etc", vault.join("evil")).unwrap();
+
+        assert!(matches!(
+            read_vault_file(&vault, "evil/passwd"),
+            Err(NexusError::PermissionDenied(_))
+        ));
+        assert!(resolve_vault_path(&vault, "new/dir/file.md").is_ok());
+    }
+
+    #[test]
+    fn writes_atomically_and_cleans_up() {
+        let vault = temp_dir("vault_fs_write");
+
+        write_vault_file(&vault, "deep/x/y.md", b"first").unwrap();
+        write_vault_file(&vault, "deep/x/y.md", b"second").unwrap();
+
+        assert_eq!(fs::read_to_string(vault.join("deep/x/y.md")).unwrap(), "second");
+        let entries: Vec<_> = fs::read_dir(vault.join("deep/x")).unwrap().collect();
+        assert_eq!(entries.len(), 1, "temp file left behind");
+        assert!(matches!(
+            write_vault_file(&vault, "../escaped.md", b"x"),
+            Err(NexusError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn roots_and_system_directories_are_not_vaults() {
+        assert!(matches!(check_vault_location(Path::new("/"), None), Err(NexusError::UnsafeVaultPath(_))));
+        for dir in SYSTEM_DIRS.iter().filter(|dir| Path::new(dir).is_dir()) {
+            assert!(check_vault_location(Path::new(dir), None).is_err(), "{} was accepted", dir);
+        }
+        #[cfg(target_os = "linux")]
+        assert!(check_vault_location(Path::new("/proc/self"), None).is_err());
+    }
+
+    #[test]
+    fn the_home_directory_itself_is_refused_but_folders_inside_it_are_not() {
+        let home = temp_dir("vault_fs_home");
+        let vault = home.join("Notes Vault");
+        fs::create_dir_all(&vault).unwrap();
+
+        assert!(matches!(check_vault_location(&home, Some(&home)), Err(NexusError::UnsafeVaultPath(_))));
+        check_vault_location(&vault, Some(&home)).unwrap();
+    }
+
+    #[test]
+    fn file_counting_stops_past_the_limit() {
+        let dir = temp_dir("vault_fs_count");
+        for name in ["a", "b", "sub/c", "sub/deeper/d"] {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "x").unwrap();
+        }
+
+        assert_eq!(count_files(&dir, 10), 4);
+        assert_eq!(count_files(&dir, 2), 3);
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::error::{NexusError, Result};
+
+/// Advisory lock on a vault, held for the lifetime of its `Database`.
+/// The lock file holds `<pid> <token>`; the token tells apart two
+/// initializations of the same vault within one process.
+#[derive(Debug)]
+pub struct VaultLock {
+    path: PathBuf,
+    contents: String,
+}
+
+pub fn lock_path(vault_path: &Path) -> PathBuf {
+    vault_path.join(".nexus").join("vault.lock")
+}
+
+impl VaultLock {
+    pub fn acquire(vault_path: &Path) -> Result<Self> {
+        let path = lock_path(vault_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let pid = std::process::id();
+        let token = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        let contents = format!("{} {}", pid, token);
+
+        loop {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(contents.as_bytes())?;
+                    file.sync_all()?;
+                    log::info!("Acquired vault lock: {:?}", path);
+                    return Ok(Self { path, contents });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    match read_lock_pid(&path) {
+                        // Our own process re-opening the vault takes the lock over
+                        Some(holder) if holder != pid && process_is_alive(holder) => {
+                            return Err(NexusError::VaultLocked(holder));
+                        }
+                        holder => {
+                            log::warn!("Reclaiming stale vault lock held by PID {:?}", holder);
+                            match fs::remove_file(&path) {
+                                Ok(()) => continue,
+                                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                                Err(e) => return Err(e.into()),
+                            }
+                        }
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Removes the lock file if it is still ours.
+    pub fn release(&self) {
+        match fs::read_to_string(&self.path) {
+            Ok(current) if current == self.contents => {
+                if let Err(e) = fs::remove_file(&self.path) {
+                    log::warn!("Failed to release vault lock {:?}: {}", self.path, e);
+                } else {
+                    log::info!("Released vault lock: {:?}", self.path);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for VaultLock {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Deletes the vault lock regardless of who holds it. Returns whether a lock existed.
+pub fn force_unlock(vault_path: &Path) -> Result<bool> {
+    let path = lock_path(vault_path);
+    match fs::remove_file(&path) {
+        Ok(()) => {
+            log::warn!("Vault lock forcibly removed: {:?}", path);
+            Ok(true)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn read_lock_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path)
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(unix)]
+fn process_is_alive(pid: u32) -> bool {
+    // Signal 0 only checks for existence; EPERM means it exists under another user
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(windows)]
+fn process_is_alive(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)))
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    // PID lock files in these tests name a process that is always alive (init)
+    // or one that cannot exist on any default pid_max.
+    const LIVE_PID: u32 = 1;
+    const DEAD_PID: u32 = 4_000_000;
+
+    #[test]
+    fn acquiring_writes_and_releasing_removes_the_lock() {
+        let vault = temp_dir("vault_lock_acquire");
+
+        let lock = VaultLock::acquire(&vault).unwrap();
+        let contents = fs::read_to_string(lock_path(&vault)).unwrap();
+        assert!(contents.starts_with(&format!("{} ", std::process::id())));
+
+        drop(lock);
+        assert!(!lock_path(&vault).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_lock_held_by_a_live_process_is_refused() {
+        let vault = temp_dir("vault_lock_held");
+        fs::create_dir_all(vault.join(".nexus")).unwrap();
+        fs::write(lock_path(&vault), format!("{} 5", LIVE_PID)).unwrap();
+
+        assert!(matches!(VaultLock::acquire(&vault), Err(NexusError::VaultLocked(LIVE_PID))));
+        assert!(force_unlock(&vault).unwrap());
+        assert!(!force_unlock(&vault).unwrap());
+        assert!(VaultLock::acquire(&vault).is_ok());
+    }
+
+    #[test]
+    fn stale_locks_are_reclaimed() {
+        let vault = temp_dir("vault_lock_stale");
+        fs::create_dir_all(vault.join(".nexus")).unwrap();
+        fs::write(lock_path(&vault), format!("{} 5", DEAD_PID)).unwrap();
+
+        let lock = VaultLock::acquire(&vault).unwrap();
+        let contents = fs::read_to_string(lock_path(&vault)).unwrap();
+        assert!(contents.starts_with(&std::process::id().to_string()));
+
+        // Re-opening in the same process takes over; the old handle must not release it
+        let reopened = VaultLock::acquire(&vault).unwrap();
+        drop(lock);
+        assert!(lock_path(&vault).exists());
+        drop(reopened);
+        assert!(!lock_path(&vault).exists());
+    }
+}
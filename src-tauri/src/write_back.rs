@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::database::Database;
+use crate::error::Result;
+use crate::models::AppObject;
+use crate::sync_service::{split_entry_path, write_entries_to_file};
+
+/// Writes edited objects back to their vault files once edits have been quiet
+/// for `delay`, so a burst of changes to one file costs a single write.
+pub struct WriteBack {
+    flush_tx: mpsc::UnboundedSender<oneshot::Sender<usize>>,
+}
+
+impl WriteBack {
+    pub fn spawn(database: &Arc<Database>, delay: Duration) -> Self {
+        let (change_tx, change_rx) = mpsc::unbounded_channel();
+        let (flush_tx, flush_rx) = mpsc::unbounded_channel();
+        database.set_change_listener(change_tx);
+
+        // Only a weak handle, so the task ends once the database is dropped
+        let database = Arc::downgrade(database);
+        tokio::spawn(run(database, delay, change_rx, flush_rx));
+
+        Self { flush_tx }
+    }
+
+    /// Writes out pending changes now. Returns the number of files written.
+    pub async fn flush(&self) -> usize {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if self.flush_tx.send(reply_tx).is_err() {
+            return 0;
+        }
+        reply_rx.await.unwrap_or_default()
+    }
+}
+
+async fn run(
+    database: Weak<Database>,
+    delay: Duration,
+    mut change_rx: mpsc::UnboundedReceiver<i64>,
+    mut flush_rx: mpsc::UnboundedReceiver<oneshot::Sender<usize>>,
+) {
+    let mut pending: HashSet<i64> = HashSet::new();
+
+    loop {
+        // The sleep is recreated on every pass, so each change restarts the quiet period
+        let quiet = tokio::time::sleep(delay);
+
+        tokio::select! {
+            changed = change_rx.recv() => match changed {
+                Some(object_id) => {
+                    pending.insert(object_id);
+                }
+                None => break,
+            },
+            reply = flush_rx.recv() => {
+                // Changes sent before the flush was requested belong to it
+                while let Ok(object_id) = change_rx.try_recv() {
+                    pending.insert(object_id);
+                }
+                let written = flush(&database, &mut pending).await;
+                match reply {
+                    Some(reply) => {
+                        let _ = reply.send(written);
+                    }
+                    None => break,
+                }
+            }
+            _ = quiet, if !pending.is_empty() => {
+                flush(&database, &mut pending).await;
+            }
+        }
+    }
+}
+
+async fn flush(database: &Weak<Database>, pending: &mut HashSet<i64>) -> usize {
+    if pending.is_empty() {
+        return 0;
+    }
+
+    let database = match database.upgrade() {
+        Some(database) => database,
+        None => return 0,
+    };
+
+    // Group the changed entries by the file they live in
+    let mut files: BTreeMap<PathBuf, Vec<(Option<String>, serde_json::Value)>> = BTreeMap::new();
+    let mut file_backed: HashMap<String, bool> = HashMap::new();
+    for object_id in pending.drain() {
+        let object: AppObject<serde_json::Value> = match database.load_object(object_id).await {
+            Ok(object) => object,
+            Err(e) => {
+                log::warn!("Skipping write-back of object {}: {}", object_id, e);
+                continue;
+            }
+        };
+
+        let file_path = match object.file_path {
+            Some(file_path) => file_path,
+            None => continue,
+        };
+
+        // DB-only schemas keep their edits out of the vault
+        if !file_backed.contains_key(&object.schema_name) {
+            let backed = database.is_schema_file_backed(&object.schema_name).await.unwrap_or(true);
+            file_backed.insert(object.schema_name.clone(), backed);
+        }
+        if !file_backed[&object.schema_name] {
+            log::debug!("Not writing back object {} of DB-only schema {}", object_id, object.schema_name);
+            continue;
+        }
+
+        let (path, entry) = split_entry_path(&file_path);
+        files
+            .entry(PathBuf::from(path))
+            .or_default()
+            .push((entry.map(str::to_string), object.content));
+    }
+
+    let mut written = 0;
+    for (path, entries) in files {
+        match write_file(&database, &path, &entries).await {
+            Ok(()) => written += 1,
+            Err(e) => log::error!("Failed to write back {:?}: {}", path, e),
+        }
+    }
+
+    log::info!("Wrote back {} file(s)", written);
+    written
+}
+
+async fn write_file(database: &Database, path: &std::path::Path, entries: &[(Option<String>, serde_json::Value)]) -> Result<()> {
+    let entries: Vec<(Option<&str>, &serde_json::Value)> = entries
+        .iter()
+        .map(|(entry, content)| (entry.as_deref(), content))
+        .collect();
+    write_entries_to_file(database, path, &entries, true).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Todo;
+    use crate::test_support::{vault, TWO_TODOS};
+
+    async fn rename(database: &Database, todo: &AppObject<Todo>, text: &str) {
+        let mut content = todo.content.clone();
+        content.text = text.to_string();
+        database.update_object(todo.id, &content).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn edits_are_written_once_they_go_quiet() {
+        let (vault, database, sync_service) = vault("write_back_debounce", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        let file = vault.join("Todo/todos.json");
+        let write_back = WriteBack::spawn(&database, Duration::from_millis(200));
+        let todos: Vec<AppObject<Todo>> = database.load_objects_by_schema("core.todo").await.unwrap();
+        let before = std::fs::read_to_string(&file).unwrap();
+
+        for (index, text) in ["x", "y", "z"].iter().enumerate() {
+            rename(&database, &todos[index % 2], text).await;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), before);
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        let after = std::fs::read_to_string(&file).unwrap();
+        assert!(after.contains(r#""z""#) && after.contains(r#""y""#), "{}", after);
+        assert_eq!(write_back.flush().await, 0);
+    }
+
+    #[tokio::test]
+    async fn flushing_coalesces_edits_into_one_write() {
+        let (vault, database, sync_service) = vault("write_back_flush", TWO_TODOS).await;
+        sync_service.force_sync(None).await.unwrap();
+        let write_back = WriteBack::spawn(&database, Duration::from_secs(60));
+        let todos: Vec<AppObject<Todo>> = database.load_objects_by_schema("core.todo").await.unwrap();
+
+        for text in ["p", "q", "r"] {
+            rename(&database, &todos[0], text).await;
+        }
+
+        assert_eq!(write_back.flush().await, 1);
+        let file = std::fs::read_to_string(vault.join("Todo/todos.json")).unwrap();
+        assert!(file.contains(r#""r""#), "{}", file);
+    }
+
+    #[tokio::test]
+    async fn database_only_schemas_are_not_written_back() {
+        let (vault, database, sync_service) = vault("write_back_db_only", TWO_TODOS).await;
+        database.register_schema("ext.c", r#"{"type":"object"}"#).await.unwrap();
+        database.register_schema_extensions("ext.c", &["json".into()]).await.unwrap();
+        std::fs::write(vault.join("c.json"), r#"{"n":1}"#).unwrap();
+        sync_service.force_sync(None).await.unwrap();
+        let write_back = WriteBack::spawn(&database, Duration::from_secs(60));
+        let config = database.object_id_for_path(&vault.join("c.json").to_string_lossy()).await.unwrap().unwrap();
+        let todos: Vec<AppObject<Todo>> = database.load_objects_by_schema("core.todo").await.unwrap();
+
+        database.set_schema_file_backed("ext.c", false).await.unwrap();
+        database.update_object(config, &serde_json::json!({ "n": 2 })).await.unwrap();
+        rename(&database, &todos[0], "edited").await;
+        assert_eq!(write_back.flush().await, 1);
+        assert_eq!(std::fs::read_to_string(vault.join("c.json")).unwrap(), r#"{"n":1}"#);
+        assert!(std::fs::read_to_string(vault.join("Todo/todos.json")).unwrap().contains("edited"));
+
+        database.set_schema_file_backed("ext.c", true).await.unwrap();
+        database.update_object(config, &serde_json::json!({ "n": 3 })).await.unwrap();
+        assert_eq!(write_back.flush().await, 1);
+        assert!(std::fs::read_to_string(vault.join("c.json")).unwrap().contains('3'));
+    }
+}